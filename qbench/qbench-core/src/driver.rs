@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::sink::Sink;
+use crate::source::Source;
+
+/// The requests sent to a sink and not completed yet, to keep a bounded number
+/// of them in flight.
+pub struct InFlightRequests<F: Future> {
+    requests: FuturesUnordered<F>,
+}
+
+impl<F: Future> Default for InFlightRequests<F> {
+    fn default() -> Self {
+        Self {
+            requests: FuturesUnordered::new(),
+        }
+    }
+}
+
+impl<F: Future> InFlightRequests<F> {
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn push(&mut self, request: F) {
+        self.requests.push(request);
+    }
+
+    /// Waits for the next request to complete, None if none is in flight.
+    pub async fn next(&mut self) -> Option<F::Output> {
+        self.requests.next().await
+    }
+
+    /// Waits for the next request to complete if `concurrency` or more are in
+    /// flight, to make room for another one. Called until it returns None, it
+    /// also catches up with a concurrency that decreased.
+    pub async fn make_room(&mut self, concurrency: usize) -> Option<F::Output> {
+        if self.requests.len() < concurrency.max(1) {
+            return None;
+        }
+        self.requests.next().await
+    }
+}
+
+/// What `ingest` sent to the sink.
+#[derive(Debug, Default)]
+pub struct IngestSummary {
+    pub num_batches: u64,
+    pub num_bytes: u64,
+    /// From the first batch read to the commit.
+    pub duration: Duration,
+}
+
+/// Sends all the documents of `source` to `sink`, in batches of the sink's
+/// batch size with at most `concurrency` requests in flight, and commits.
+///
+/// This is the core of `qbench index`, without its rate limiting, budget and
+/// reporting, for harnesses embedding the ingestion. Needs a multi-threaded
+/// tokio runtime: `UriSource` reads the dataset in a task blocking on its
+/// channel.
+pub async fn ingest(
+    source: &dyn Source,
+    sink: &dyn Sink,
+    concurrency: usize,
+) -> anyhow::Result<IngestSummary> {
+    let batches = source.batch_stream(sink.batch_size()).await?;
+    let start = Instant::now();
+    let mut summary = IngestSummary::default();
+    let mut in_flight = InFlightRequests::default();
+    while let Ok(batch_res) = batches.recv_async().await {
+        let batch = batch_res?;
+        if batch.bytes.is_empty() {
+            continue;
+        }
+        while let Some(send_res) = in_flight.make_room(concurrency).await {
+            send_res?;
+        }
+        summary.num_batches += 1;
+        summary.num_bytes += batch.bytes.len() as u64;
+        in_flight.push(async move {
+            sink.send(&batch)
+                .await
+                .context("Failed to send the batch to the sink")
+        });
+    }
+    while let Some(send_res) = in_flight.next().await {
+        send_res?;
+    }
+    sink.commit().await?;
+    summary.duration = start.elapsed();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::sink::{BuildInfo, IndexInfo, NullSink};
+    use crate::source::{DocumentBatch, UriSource};
+
+    #[derive(Default)]
+    struct CollectingSink {
+        docs: Mutex<Vec<u8>>,
+        committed: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl Sink for CollectingSink {
+        fn batch_size(&self) -> usize {
+            64
+        }
+
+        async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+            self.docs
+                .lock()
+                .unwrap()
+                .extend_from_slice(&document_batch.bytes);
+            Ok(())
+        }
+
+        async fn commit(&self) -> anyhow::Result<()> {
+            *self.committed.lock().unwrap() = true;
+            Ok(())
+        }
+
+        async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+            NullSink.index_info().await
+        }
+
+        async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+            NullSink.build_info().await
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ingest() {
+        let dataset_path = std::env::temp_dir()
+            .join(format!("qbench-ingest-{}.json", std::process::id()));
+        let dataset: String = (0..100)
+            .map(|idx| format!("{{\"id\": {idx}, \"message\": \"hello\"}}\n"))
+            .collect();
+        std::fs::write(&dataset_path, &dataset).unwrap();
+        let source = UriSource::new(dataset_path.to_str().unwrap()).unwrap();
+        let sink = CollectingSink::default();
+        let summary = ingest(&source, &sink, 4).await.unwrap();
+        std::fs::remove_file(&dataset_path).unwrap();
+
+        assert!(summary.num_batches > 1);
+        assert_eq!(summary.num_bytes, dataset.len() as u64);
+        assert!(*sink.committed.lock().unwrap());
+        let mut lines: Vec<String> = String::from_utf8(sink.docs.into_inner().unwrap())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+        assert_eq!(lines.len(), 100);
+    }
+}
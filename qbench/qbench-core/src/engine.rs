@@ -0,0 +1,111 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+pub const QUICKWIT_DEFAULT_HOST: &str = "127.0.0.1:7280";
+pub const ELASTICSEARCH_DEFAULT_HOST: &str = "127.0.0.1:9200";
+pub const ES_COMPATIBLE_DEFAULT_HOST: &str = "127.0.0.1:9200";
+/// OpenSearch listens on 9200 too by default, but our docker setup
+/// (`engines/opensearch`) moves it to 9301 so that both can run side by side.
+pub const OPENSEARCH_DEFAULT_HOST: &str = "127.0.0.1:9301";
+pub const LOKI_DEFAULT_HOST: &str = "127.0.0.1:3100";
+/// The Kusto emulator, real clusters are reached over https.
+pub const KUSTO_DEFAULT_HOST: &str = "http://127.0.0.1:8080";
+/// Unused, BigQuery is only reachable through Google's API endpoint.
+pub const BIGQUERY_DEFAULT_HOST: &str = "bigquery.googleapis.com";
+/// Unused, tantivy runs in-process.
+pub const TANTIVY_DEFAULT_HOST: &str = "";
+pub const PARSEABLE_DEFAULT_HOST: &str = "127.0.0.1:8000";
+pub const SIGNOZ_DEFAULT_HOST: &str = "127.0.0.1:3301";
+pub const ZINCOBSERVE_DEFAULT_HOST: &str = "127.0.0.1:5080";
+/// Unused, the exec sink is a local command.
+pub const EXEC_DEFAULT_HOST: &str = "";
+/// Only used as the `{host}` of the HTTP sink spec's URLs.
+pub const HTTP_DEFAULT_HOST: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Engine {
+    Quickwit,
+    Elasticsearch,
+    Opensearch,
+    EsCompatible,
+    Loki,
+    Kusto,
+    Bigquery,
+    Tantivy,
+    Parseable,
+    Signoz,
+    ZincObserve,
+    Exec,
+    Http,
+}
+
+impl Engine {
+    pub fn default_host(&self) -> &'static str {
+        match self {
+            Engine::Quickwit => QUICKWIT_DEFAULT_HOST,
+            Engine::Elasticsearch => ELASTICSEARCH_DEFAULT_HOST,
+            Engine::Opensearch => OPENSEARCH_DEFAULT_HOST,
+            Engine::EsCompatible => ES_COMPATIBLE_DEFAULT_HOST,
+            Engine::Loki => LOKI_DEFAULT_HOST,
+            Engine::Kusto => KUSTO_DEFAULT_HOST,
+            Engine::Bigquery => BIGQUERY_DEFAULT_HOST,
+            Engine::Tantivy => TANTIVY_DEFAULT_HOST,
+            Engine::Parseable => PARSEABLE_DEFAULT_HOST,
+            Engine::Signoz => SIGNOZ_DEFAULT_HOST,
+            Engine::ZincObserve => ZINCOBSERVE_DEFAULT_HOST,
+            Engine::Exec => EXEC_DEFAULT_HOST,
+            Engine::Http => HTTP_DEFAULT_HOST,
+        }
+    }
+}
+
+impl Display for Engine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let engine = match s {
+            "quickwit" => Engine::Quickwit,
+            "elasticsearch" => Engine::Elasticsearch,
+            "opensearch" => Engine::Opensearch,
+            "es-compatible" => Engine::EsCompatible,
+            "loki" => Engine::Loki,
+            "kusto" => Engine::Kusto,
+            "bigquery" => Engine::Bigquery,
+            "tantivy" => Engine::Tantivy,
+            "parseable" => Engine::Parseable,
+            "signoz" => Engine::Signoz,
+            "zincobserve" => Engine::ZincObserve,
+            "exec" => Engine::Exec,
+            "http" => Engine::Http,
+            _ => return Err(format!("Unknown engine {s:?}")),
+        };
+
+        Ok(engine)
+    }
+}
+
+impl AsRef<str> for Engine {
+    fn as_ref(&self) -> &str {
+        match self {
+            Engine::Quickwit => "quickwit",
+            Engine::Elasticsearch => "elasticsearch",
+            Engine::Opensearch => "opensearch",
+            Engine::EsCompatible => "es-compatible",
+            Engine::Loki => "loki",
+            Engine::Kusto => "kusto",
+            Engine::Bigquery => "bigquery",
+            Engine::Tantivy => "tantivy",
+            Engine::Parseable => "parseable",
+            Engine::Signoz => "signoz",
+            Engine::ZincObserve => "zincobserve",
+            Engine::Exec => "exec",
+            Engine::Http => "http",
+        }
+    }
+}
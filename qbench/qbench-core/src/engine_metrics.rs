@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use reqwest::{Client, Url};
+
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A snapshot of the cumulative counters of the engine, e.g. its GC time or
+/// number of merges, by name.
+pub type EngineStats = BTreeMap<String, f64>;
+
+/// Scrapes a subset of the metrics exposed by the engine in the Prometheus
+/// text format.
+pub struct EngineMetricsScraper {
+    url: Url,
+    client: Client,
+    metric_names: Vec<String>,
+}
+
+impl EngineMetricsScraper {
+    pub fn new(url: &str, metric_names: Vec<String>) -> Self {
+        Self {
+            url: Url::parse(url).expect("Invalid engine metrics URL"),
+            client: Client::new(),
+            metric_names,
+        }
+    }
+
+    /// Returns the current value of the watched metrics, summed over all
+    /// their label sets.
+    pub async fn scrape(&self) -> anyhow::Result<BTreeMap<String, f64>> {
+        let response = self
+            .client
+            .get(self.url.clone())
+            .timeout(SCRAPE_TIMEOUT)
+            .send()
+            .await
+            .context("Engine metrics request error")?;
+        if !response.status().is_success() {
+            error!(resp=?response, "Engine metrics API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let text = response.text().await?;
+        Ok(sum_metrics(&text, &self.metric_names))
+    }
+}
+
+/// Fetches the metrics of the engine in the Prometheus text format, and sums
+/// its counters.
+pub async fn fetch_counters(client: &Client, url: Url) -> anyhow::Result<EngineStats> {
+    let response = client
+        .get(url)
+        .timeout(SCRAPE_TIMEOUT)
+        .send()
+        .await
+        .context("Engine metrics request error")?;
+    if !response.status().is_success() {
+        bail!(
+            "http error with status code {}: {:?}",
+            response.status(),
+            response.text().await?
+        );
+    }
+    Ok(sum_counters(&response.text().await?))
+}
+
+/// Sums the samples of each of the metrics over their label sets. Metrics
+/// without any sample are left out.
+fn sum_metrics(text: &str, metric_names: &[String]) -> BTreeMap<String, f64> {
+    let mut sums = BTreeMap::new();
+    for (name, value) in text.lines().filter_map(parse_sample) {
+        if metric_names.iter().any(|metric_name| metric_name == name) {
+            *sums.entry(name.to_string()).or_insert(0.0) += value;
+        }
+    }
+    sums
+}
+
+/// Sums the samples of the counters, and the sums and counts of the summaries
+/// and histograms (e.g. `go_gc_duration_seconds_sum`), over their label sets.
+pub fn sum_counters(text: &str) -> EngineStats {
+    let mut cumulative_names = BTreeSet::new();
+    for line in text.lines() {
+        let mut type_line = line.split_whitespace();
+        if type_line.next() != Some("#") || type_line.next() != Some("TYPE") {
+            continue;
+        }
+        let (Some(family), Some(metric_type)) = (type_line.next(), type_line.next())
+        else {
+            continue;
+        };
+        let suffixes: &[&str] = match metric_type {
+            "counter" => &["", "_total"],
+            "summary" | "histogram" => &["_sum", "_count"],
+            _ => continue,
+        };
+        for suffix in suffixes {
+            cumulative_names.insert(format!("{family}{suffix}"));
+        }
+    }
+    let mut sums = EngineStats::new();
+    for (name, value) in text.lines().filter_map(parse_sample) {
+        if cumulative_names.contains(name) {
+            *sums.entry(name.to_string()).or_insert(0.0) += value;
+        }
+    }
+    sums
+}
+
+/// The name and value of a sample line, None for comments and NaN values.
+fn parse_sample(line: &str) -> Option<(&str, f64)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    // Label values may contain spaces, the value comes after the labels.
+    let (name, rest) = match line.find('{') {
+        Some(labels_start) => {
+            let labels_end = line.rfind('}')?;
+            (&line[..labels_start], &line[labels_end + 1..])
+        },
+        None => line.split_once(char::is_whitespace)?,
+    };
+    let value = rest
+        .split_whitespace()
+        .next()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())?;
+    Some((name, value))
+}
+
+/// The CPU time of the engine in its stats: `process_cpu_seconds_total` of the
+/// Prometheus exporters, `process.cpu.total_in_millis` of `_nodes/stats`.
+pub fn process_cpu_seconds(engine_stats: &EngineStats) -> Option<f64> {
+    engine_stats
+        .get("process_cpu_seconds_total")
+        .copied()
+        .or_else(|| {
+            engine_stats
+                .get("process.cpu.total_in_millis")
+                .map(|cpu_millis| cpu_millis / 1000.0)
+        })
+}
+
+/// How much each stat grew between the two snapshots, leaving out the ones
+/// that did not change. The stats missing from `before`, such as the counters
+/// an engine only creates once it receives documents, grew from 0.
+pub fn stats_delta(before: &EngineStats, after: &EngineStats) -> EngineStats {
+    after
+        .iter()
+        .filter_map(|(name, after_value)| {
+            let delta = after_value - before.get(name).copied().unwrap_or_default();
+            (delta != 0.0).then(|| (name.clone(), delta))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum_metrics() {
+        let text = "# HELP quickwit_ingest_docs_total Number of docs.\n\
+                    # TYPE quickwit_ingest_docs_total counter\n\
+                    quickwit_ingest_docs_total{index=\"a b\",validity=\"valid\"} 10\n\
+                    quickwit_ingest_docs_total{index=\"c\",validity=\"valid\"} 2.5e1 1700000000\n\
+                    quickwit_memory_bytes 1024\n\
+                    quickwit_memory_bytes_limit 4096\n\
+                    quickwit_pending_merges NaN\n";
+        let metric_names = vec![
+            "quickwit_ingest_docs_total".to_string(),
+            "quickwit_memory_bytes".to_string(),
+            "quickwit_pending_merges".to_string(),
+        ];
+        let sums = sum_metrics(text, &metric_names);
+        assert_eq!(
+            sums,
+            BTreeMap::from([
+                ("quickwit_ingest_docs_total".to_string(), 35.0),
+                ("quickwit_memory_bytes".to_string(), 1024.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_stats_delta() {
+        let scrape = |gc_secs: f64, num_chunks: u64| {
+            sum_counters(&format!(
+                "# TYPE go_gc_duration_seconds summary\n\
+                 go_gc_duration_seconds{{quantile=\"0.5\"}} 0.001\n\
+                 go_gc_duration_seconds_sum {gc_secs}\n\
+                 go_gc_duration_seconds_count 10\n\
+                 # TYPE loki_ingester_chunks_flushed_total counter\n\
+                 loki_ingester_chunks_flushed_total{{reason=\"full\"}} {num_chunks}\n\
+                 loki_ingester_chunks_flushed_total{{reason=\"idle\"}} 1\n\
+                 # TYPE loki_ingester_memory_streams gauge\n\
+                 loki_ingester_memory_streams 3\n"
+            ))
+        };
+        let before = scrape(1.5, 4);
+        assert_eq!(
+            before,
+            EngineStats::from([
+                ("go_gc_duration_seconds_count".to_string(), 10.0),
+                ("go_gc_duration_seconds_sum".to_string(), 1.5),
+                ("loki_ingester_chunks_flushed_total".to_string(), 5.0),
+            ])
+        );
+        let after = scrape(2.0, 6);
+        assert_eq!(
+            stats_delta(&before, &after),
+            EngineStats::from([
+                ("go_gc_duration_seconds_sum".to_string(), 0.5),
+                ("loki_ingester_chunks_flushed_total".to_string(), 2.0),
+            ])
+        );
+        let mut after_first_flush = after.clone();
+        after_first_flush.insert("loki_ingester_flush_failures_total".to_string(), 3.0);
+        assert_eq!(
+            stats_delta(&before, &after_first_flush)
+                ["loki_ingester_flush_failures_total"],
+            3.0
+        );
+        assert_eq!(process_cpu_seconds(&after), None);
+        let es_stats =
+            EngineStats::from([("process.cpu.total_in_millis".to_string(), 1500.0)]);
+        assert_eq!(process_cpu_seconds(&es_stats), Some(1.5));
+    }
+}
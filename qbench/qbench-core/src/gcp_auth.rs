@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use http::{header, StatusCode};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::utils::CachedToken;
+
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const METADATA_TOKEN_URL: &str = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CredentialsFile {
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        #[serde(default)]
+        token_uri: Option<String>,
+    },
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+}
+
+enum GcpCredentials {
+    AccessToken(String),
+    File(CredentialsFile),
+    MetadataServer,
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+/// Google Cloud OAuth2 authentication for a given scope.
+///
+/// Credentials are resolved like Google's client libraries do with
+/// "application default credentials": the file pointed to by
+/// `GOOGLE_APPLICATION_CREDENTIALS` (service account key or `gcloud` user
+/// credentials), then gcloud's well-known credentials file, then the GCE
+/// metadata server.
+pub struct GcpAuth {
+    credentials: GcpCredentials,
+    scope: String,
+    token: Mutex<Option<CachedToken>>,
+    client: Client,
+}
+
+impl GcpAuth {
+    /// Uses a pre-obtained access token, e.g. from `gcloud auth print-access-token`.
+    pub fn from_access_token(access_token: &str) -> Self {
+        Self::new(GcpCredentials::AccessToken(access_token.to_string()), "")
+    }
+
+    /// Uses the credentials stored in a service account key or gcloud user
+    /// credentials JSON file.
+    pub fn from_credentials_file(path: &Path, scope: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read GCP credentials file {path:?}"))?;
+        let credentials_file: CredentialsFile = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid GCP credentials file {path:?}"))?;
+        Ok(Self::new(GcpCredentials::File(credentials_file), scope))
+    }
+
+    pub fn application_default(scope: &str) -> anyhow::Result<Self> {
+        if let Some(path) = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+            return Self::from_credentials_file(Path::new(&path), scope);
+        }
+        if let Some(home_dir) = std::env::var_os("HOME") {
+            let well_known_path = PathBuf::from(home_dir)
+                .join(".config/gcloud/application_default_credentials.json");
+            if well_known_path.exists() {
+                return Self::from_credentials_file(&well_known_path, scope);
+            }
+        }
+        Ok(Self::new(GcpCredentials::MetadataServer, scope))
+    }
+
+    fn new(credentials: GcpCredentials, scope: &str) -> Self {
+        Self {
+            credentials,
+            scope: scope.to_string(),
+            token: Mutex::new(None),
+            client: Client::new(),
+        }
+    }
+
+    /// Returns the `Authorization` header value, fetching a new access token if
+    /// the cached one is about to expire.
+    pub async fn authorization(&self) -> anyhow::Result<String> {
+        if let GcpCredentials::AccessToken(access_token) = &self.credentials {
+            return Ok(format!("Bearer {access_token}"));
+        }
+        let mut cached_token = self.token.lock().await;
+        if let Some(token) = cached_token.as_ref().and_then(CachedToken::get) {
+            return Ok(format!("Bearer {token}"));
+        }
+        info!("Fetching GCP access token...");
+        let request = match &self.credentials {
+            GcpCredentials::AccessToken(_) => unreachable!(),
+            GcpCredentials::File(CredentialsFile::ServiceAccount {
+                client_email,
+                private_key,
+                token_uri,
+            }) => {
+                let token_uri = token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+                let assertion = self.sign_jwt(client_email, private_key, token_uri)?;
+                self.client.post(token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", &assertion),
+                ])
+            },
+            GcpCredentials::File(CredentialsFile::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            }) => self.client.post(DEFAULT_TOKEN_URI).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+                ("refresh_token", refresh_token),
+            ]),
+            GcpCredentials::MetadataServer => self
+                .client
+                .get(METADATA_TOKEN_URL)
+                .header("Metadata-Flavor", "Google"),
+        };
+        let response = request
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await
+            .with_context(|| "GCP token request error")?;
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Failed to fetch GCP access token, got status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let token = data["access_token"]
+            .as_str()
+            .context("access_token field must be a string")?
+            .to_string();
+        let expires_in = data["expires_in"].as_u64().unwrap_or(3600);
+        let authorization = format!("Bearer {token}");
+        *cached_token = Some(CachedToken::new(token, Duration::from_secs(expires_in)));
+        Ok(authorization)
+    }
+
+    fn sign_jwt(
+        &self,
+        client_email: &str,
+        private_key: &str,
+        token_uri: &str,
+    ) -> anyhow::Result<String> {
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = JwtClaims {
+            iss: client_email,
+            scope: &self.scope,
+            aud: token_uri,
+            iat,
+            exp: iat + 3600,
+        };
+        let key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())
+            .context("Invalid service account private key")?;
+        let jwt = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+            &claims,
+            &key,
+        )?;
+        Ok(jwt)
+    }
+}
@@ -0,0 +1,45 @@
+//! The building blocks of qbench's ingestion driver, to embed it in
+//! integration tests and custom harnesses without running the binary:
+//!
+//! - [`source`]: reads the dataset's URIs (local files, http(s) URLs, `gs://`
+//!   objects; NDJSON, CSV/TSV or Avro) and cuts them into batches of documents,
+//!   see [`source::UriSource`] and [`source::Source::batch_stream`].
+//! - [`sink`]: sends the batches to an engine, see [`sink::Sink`] and its
+//!   implementations, one module per engine.
+//! - [`driver::ingest`]: the loop between them. `qbench index` adds its
+//!   pacing, budgets and reports on top of [`driver::InFlightRequests`].
+//! - [`run_results`]: the results of a `qbench index` run, and [`results`]
+//!   writes and reads the results files.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use qbench_core::sink::quickwit::QuickwitSink;
+//! use qbench_core::source::UriSource;
+//!
+//! let source = UriSource::new("datasets/generated-logs-v1-0001.ndjson.gz")?;
+//! let sink = QuickwitSink::new(
+//!     "127.0.0.1:7280",
+//!     "generated-logs",
+//!     false,
+//!     reqwest::Client::new(),
+//! );
+//! let summary = qbench_core::driver::ingest(&source, &sink, 8).await?;
+//! println!("{} bytes sent in {:?}", summary.num_bytes, summary.duration);
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+extern crate tracing;
+
+pub mod aws_sigv4;
+pub mod driver;
+pub mod engine;
+pub mod engine_metrics;
+pub mod gcp_auth;
+pub mod query;
+pub mod results;
+pub mod run_results;
+pub mod sink;
+pub mod source;
+pub mod utils;
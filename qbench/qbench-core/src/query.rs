@@ -0,0 +1,119 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A query in the dialect of the engine it is sent to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum EngineQuery {
+    /// An Elasticsearch or OpenSearch search request body.
+    Elasticsearch(Value),
+    /// A Quickwit search request body.
+    Quickwit(Value),
+    /// The parameters of a Loki `query_range` request.
+    Loki(Value),
+}
+
+impl EngineQuery {
+    /// The request body or parameters, whatever the engine.
+    pub fn into_body(self) -> Value {
+        match self {
+            EngineQuery::Elasticsearch(body)
+            | EngineQuery::Quickwit(body)
+            | EngineQuery::Loki(body) => body,
+        }
+    }
+}
+
+/// The latency distribution of query executions.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    pub fn from_latencies_ms(mut latencies_ms: Vec<f64>) -> Option<Self> {
+        if latencies_ms.is_empty() {
+            return None;
+        }
+        latencies_ms.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let rank = (p * (latencies_ms.len() - 1) as f64).round() as usize;
+            latencies_ms[rank]
+        };
+        Some(Self {
+            min_ms: latencies_ms[0],
+            mean_ms: latencies_ms.iter().sum::<f64>() / latencies_ms.len() as f64,
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: latencies_ms[latencies_ms.len() - 1],
+        })
+    }
+}
+
+/// The executions of one query of a suite.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The query as sent to the engine.
+    pub query: Value,
+    pub num_hits: u64,
+    pub num_errors: usize,
+    /// The latency of the first run of the query, after dropping the caches.
+    /// None if it failed, or if there was no cold run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cold_latency_ms: Option<f64>,
+    pub hot_latency: Option<LatencyStats>,
+    /// Whether all the executions returned the expected results, if the query
+    /// has any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correct: Option<bool>,
+    /// The distinct differences with the expected results.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub mismatches: BTreeSet<String>,
+}
+
+/// The throughput, latencies and correctness of the queries run by the load
+/// generator.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoadReport {
+    pub num_clients: usize,
+    pub target_qps: Option<f64>,
+    /// The successful executions per second.
+    pub achieved_qps: f64,
+    pub duration_secs: f64,
+    /// The latencies of the successful executions.
+    pub latency: Option<LatencyStats>,
+    /// The number of failed executions.
+    #[serde(default)]
+    pub num_errors: usize,
+    /// The share of the executions that failed.
+    #[serde(default)]
+    pub error_rate: f64,
+    pub num_incorrect_queries: usize,
+    pub queries: Vec<QueryResult>,
+}
+
+/// The time until the sentinel documents became searchable, from the
+/// acknowledgment of their ingestion.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VisibilityReport {
+    pub interval_secs: u64,
+    pub timeout_secs: u64,
+    pub field: String,
+    pub num_probes: usize,
+    /// The sentinels still not visible after `timeout_secs`.
+    pub num_timed_out: usize,
+    /// The sentinels that failed to be sent or searched for.
+    pub num_errors: usize,
+    pub visibility_latency: Option<LatencyStats>,
+}
@@ -0,0 +1,302 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    MessagePack,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::MessagePack => "msgpack",
+        }
+    }
+
+    /// Guesses the format from the extension of a results file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(OutputFormat::Json),
+            "yaml" | "yml" => Some(OutputFormat::Yaml),
+            "msgpack" => Some(OutputFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    pub fn writer(&self) -> Box<dyn ResultsWriter> {
+        match self {
+            OutputFormat::Json => Box::new(JsonWriter),
+            OutputFormat::Yaml => Box::new(YamlWriter),
+            OutputFormat::MessagePack => Box::new(MessagePackWriter),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "msgpack" => Ok(OutputFormat::MessagePack),
+            _ => Err(format!("Unknown output format {s:?}")),
+        }
+    }
+}
+
+/// Serializes the results of a run.
+pub trait ResultsWriter {
+    /// Writes the results as a standalone document.
+    fn write(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()>;
+
+    /// Writes the results as one record of a stream, after the ones of
+    /// previous runs.
+    fn append(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()>;
+}
+
+/// Pretty-printed JSON, or one JSON object per line when appending.
+pub struct JsonWriter;
+
+impl ResultsWriter for JsonWriter {
+    fn write(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(writer, results)?;
+        Ok(())
+    }
+
+    fn append(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut *writer, results)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// YAML, with one document per run when appending.
+pub struct YamlWriter;
+
+impl ResultsWriter for YamlWriter {
+    fn write(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_yaml::to_writer(writer, results)?;
+        Ok(())
+    }
+
+    fn append(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        writer.write_all(b"---\n")?;
+        self.write(results, writer)
+    }
+}
+
+/// MessagePack maps, which are self-delimiting: appended results are simply
+/// concatenated.
+pub struct MessagePackWriter;
+
+impl ResultsWriter for MessagePackWriter {
+    fn write(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        rmp_serde::encode::write_named(writer, results)?;
+        Ok(())
+    }
+
+    fn append(&self, results: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        self.write(results, writer)
+    }
+}
+
+/// Writes the results to `path`, replacing its content, or after the results
+/// already in it if `append` is set.
+pub fn write_results(
+    path: &Path,
+    output_format: OutputFormat,
+    append: bool,
+    results: &Value,
+) -> anyhow::Result<()> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .with_context(|| format!("Failed to open results file {path:?}"))?;
+    let mut file = std::io::BufWriter::new(file);
+    let results_writer = output_format.writer();
+    if append {
+        results_writer.append(results, &mut file)?;
+    } else {
+        results_writer.write(results, &mut file)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// The results file of a run in progress, rewritten as the run goes. Each
+/// write replaces the previous one atomically (temp file and rename), so that
+/// a crash leaves the last complete write behind, after the results of the
+/// previous runs when appending.
+pub struct RunResultsFile {
+    path: PathBuf,
+    output_format: OutputFormat,
+    append: bool,
+    previous_runs: Vec<u8>,
+}
+
+impl RunResultsFile {
+    pub fn open(
+        path: &Path,
+        output_format: OutputFormat,
+        append: bool,
+    ) -> anyhow::Result<Self> {
+        let previous_runs = if append {
+            match std::fs::read(path) {
+                Ok(previous_runs) => previous_runs,
+                Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(error) => {
+                    return Err(error).with_context(|| {
+                        format!("Failed to read results file {path:?}")
+                    })
+                },
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            output_format,
+            append,
+            previous_runs,
+        })
+    }
+
+    /// Replaces the results of the run.
+    pub fn write(&self, results: &Value) -> anyhow::Result<()> {
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create results file {tmp_path:?}"))?;
+        let mut file = std::io::BufWriter::new(file);
+        file.write_all(&self.previous_runs)?;
+        let results_writer = self.output_format.writer();
+        if self.append {
+            results_writer.append(results, &mut file)?;
+        } else {
+            results_writer.write(results, &mut file)?;
+        }
+        file.into_inner()?.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path).with_context(|| {
+            format!("Failed to replace results file {:?}", self.path)
+        })?;
+        Ok(())
+    }
+}
+
+/// Returns `format`, or guesses it from the extension of the results file.
+pub fn results_format(
+    path: &Path,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<OutputFormat> {
+    match format {
+        Some(format) => Ok(format),
+        None => OutputFormat::from_path(path).with_context(|| {
+            format!("Cannot guess the format of {path:?}, use --format")
+        }),
+    }
+}
+
+/// Reads all the results of a file, whether it holds a standalone document or
+/// a stream of appended results.
+pub fn read_results(
+    path: &Path,
+    output_format: OutputFormat,
+) -> anyhow::Result<Vec<Value>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read results file {path:?}"))?;
+    let results = match output_format {
+        OutputFormat::Json => serde_json::Deserializer::from_slice(&bytes)
+            .into_iter()
+            .collect::<Result<_, _>>()?,
+        OutputFormat::Yaml => serde_yaml::Deserializer::from_slice(&bytes)
+            .map(Value::deserialize)
+            .collect::<Result<_, _>>()?,
+        OutputFormat::MessagePack => {
+            let mut reader = &bytes[..];
+            let mut results = Vec::new();
+            while !reader.is_empty() {
+                results.push(rmp_serde::from_read(&mut reader)?);
+            }
+            results
+        },
+    };
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_results_writers_append() {
+        let runs = [json!({"run": 1}), json!({"run": 2, "engine": "quickwit"})];
+
+        let mut json_stream = Vec::new();
+        let mut yaml_stream = Vec::new();
+        let mut msgpack_stream = Vec::new();
+        for run in &runs {
+            JsonWriter.append(run, &mut json_stream).unwrap();
+            YamlWriter.append(run, &mut yaml_stream).unwrap();
+            MessagePackWriter.append(run, &mut msgpack_stream).unwrap();
+        }
+
+        let json_runs: Vec<Value> = serde_json::Deserializer::from_slice(&json_stream)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(json_runs, runs);
+        let yaml_runs: Vec<Value> = serde_yaml::Deserializer::from_slice(&yaml_stream)
+            .map(|document| serde::Deserialize::deserialize(document).unwrap())
+            .collect();
+        assert_eq!(yaml_runs, runs);
+        let mut msgpack_reader = &msgpack_stream[..];
+        let mut msgpack_runs: Vec<Value> = Vec::new();
+        while !msgpack_reader.is_empty() {
+            msgpack_runs.push(rmp_serde::from_read(&mut msgpack_reader).unwrap());
+        }
+        assert_eq!(msgpack_runs, runs);
+    }
+
+    #[test]
+    fn test_run_results_file() {
+        let path = std::env::temp_dir()
+            .join(format!("qbench-run-results-{}.json", std::process::id()));
+        write_results(&path, OutputFormat::Json, true, &json!({"run": 1})).unwrap();
+
+        let run_results_file =
+            RunResultsFile::open(&path, OutputFormat::Json, true).unwrap();
+        run_results_file
+            .write(&json!({"run": 2, "partial": true}))
+            .unwrap();
+        run_results_file.write(&json!({"run": 2})).unwrap();
+        assert_eq!(
+            read_results(&path, OutputFormat::Json).unwrap(),
+            [json!({"run": 1}), json!({"run": 2})]
+        );
+
+        let run_results_file =
+            RunResultsFile::open(&path, OutputFormat::Json, false).unwrap();
+        run_results_file.write(&json!({"run": 3})).unwrap();
+        assert_eq!(
+            read_results(&path, OutputFormat::Json).unwrap(),
+            [json!({"run": 3})]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,542 @@
+//! The results of an indexing run, as written to the results file by `qbench
+//! index` and read back by its reports.
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use self::reports::{
+    AdaptiveConcurrencyReport,
+    BatchSizeReport,
+    DocSizeReport,
+    DriverUsageReport,
+    EngineContainer,
+    EngineMemoryReport,
+    PacingReport,
+    ReorderReport,
+    SchemaDriftReport,
+    SourceErrorsReport,
+    TcpStatsReport,
+    TimeSlicesReport,
+    TransformSpec,
+};
+use crate::engine_metrics::EngineStats;
+use crate::query::{LoadReport, VisibilityReport};
+use crate::sink::failure_injection::FailureInjectionReport;
+use crate::sink::quickwit::QuickwitCommit;
+use crate::sink::{BuildInfo, RetentionTimings, SplitBreakdown};
+use crate::source::{ShardInfo, UriSummary};
+
+pub mod reports;
+
+/// The version of the `RunResults` layout. Bump it whenever a field is
+/// renamed, removed or changes type.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The default number of indexing requests in flight.
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
+/// The results of an indexing run, as written to the results file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunResults {
+    pub schema_version: u32,
+    pub engine: String,
+    pub host: String,
+    pub index: String,
+    /// The `--es-shards` and `--es-replicas` the index was created with. The
+    /// actual counts are in `index_stats`.
+    #[serde(default)]
+    pub es_shards: Option<u32>,
+    #[serde(default)]
+    pub es_replicas: Option<u32>,
+    /// The ingest pipeline the documents went through.
+    #[serde(default)]
+    pub es_pipeline: Option<String>,
+    /// The index settings tuned for the run, e.g. `index.refresh_interval`,
+    /// absent if left untouched.
+    #[serde(default)]
+    pub es_index_settings: Option<Map<String, Value>>,
+    /// The VRL script of the Quickwit ingest source.
+    #[serde(default)]
+    pub qw_vrl_transform: Option<String>,
+    /// The `--qw-commit` of the ingest requests.
+    #[serde(default)]
+    pub qw_commit: Option<QuickwitCommit>,
+    /// Whether the ingest requests were streamed with chunked transfer
+    /// encoding.
+    #[serde(default)]
+    pub chunked_transfer: bool,
+    /// The `--http-version` of the HTTP client.
+    #[serde(default)]
+    pub http_version: Option<String>,
+    /// The HTTP version the engine answered with.
+    #[serde(default)]
+    pub negotiated_http_version: Option<String>,
+    pub dataset_uri: String,
+    pub dataset_fingerprint: String,
+    /// How `dataset_fingerprint` was computed, see
+    /// `DATASET_FINGERPRINT_VERSION`. Only the fingerprints of a same version
+    /// compare.
+    #[serde(default = "legacy_dataset_fingerprint_version")]
+    pub dataset_fingerprint_version: u32,
+    pub dataset_format: String,
+    pub csv_infer_types: bool,
+    pub on_uri_error: String,
+    pub uri_summary: Vec<UriSummary>,
+    pub repeat_dataset: usize,
+    pub mutate_ids: bool,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Absent from the results of older runs.
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+    #[serde(default)]
+    pub adaptive_concurrency: Option<AdaptiveConcurrencyReport>,
+    /// The labels of the run.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The `key=value` tags of the run, by key.
+    #[serde(default)]
+    pub key_value_tags: BTreeMap<String, String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// The indexing phase, from the first batch to the index being committed.
+    pub time_range: TimeRange,
+    /// The whole run, including setup and retention measurement.
+    pub run_time_range: TimeRange,
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub engine_container: Option<EngineContainer>,
+    pub forwarded_to: Option<String>,
+    pub num_ingested_bytes: u64,
+    /// The ingest requests failed on a connect or request timeout, retried
+    /// or not.
+    #[serde(default)]
+    pub num_timed_out_requests: u64,
+    /// The documents rejected individually by the engine, e.g. on mapping
+    /// errors.
+    #[serde(default)]
+    pub num_rejected_docs: u64,
+    /// The ingest requests rejected by the engine's backpressure (Quickwit
+    /// 429s and unavailable shards) and retried, and the time waited before
+    /// retrying them, summed over the concurrent requests.
+    #[serde(default)]
+    pub num_throttled_requests: u64,
+    #[serde(default)]
+    pub throttled_secs: f64,
+    pub num_indexed_docs: u64,
+    pub num_indexed_bytes: u64,
+    pub num_splits: u64,
+    pub indexing_duration_secs: f64,
+    // The phases of the indexing duration. The reporters are stopped between
+    // the ingestion and the commit, and the index info fetched after the force
+    // merge, which the phases don't account for.
+    pub time_to_first_batch_secs: f64,
+    pub ingest_duration_secs: f64,
+    pub commit_duration_secs: f64,
+    /// Until the merge task completed, excluding the commit.
+    #[serde(alias = "force_merge_duration_secs")]
+    pub merge_duration_secs: Option<f64>,
+    #[serde(default)]
+    pub num_segments_before_merge: Option<u64>,
+    #[serde(default)]
+    pub num_segments_after_merge: Option<u64>,
+    #[serde(default)]
+    pub wait_for_merges_secs: Option<u64>,
+    /// From the end of the indexing to the last background merge, when
+    /// `wait_for_merges_secs` is set. `num_splits` is then the split count
+    /// once the merges settled.
+    #[serde(default)]
+    pub merges_settled_secs: Option<f64>,
+    #[serde(default)]
+    pub num_splits_before_merges: Option<u64>,
+    /// The published splits by maturity and merge level at the end of the
+    /// ingestion, for Quickwit.
+    #[serde(default)]
+    pub split_breakdown: Option<SplitBreakdown>,
+    /// The engine-specific stats of the index at the end of the ingestion
+    /// (segments per shard, translog size, refresh and merge totals), for
+    /// Elasticsearch and OpenSearch.
+    #[serde(default)]
+    pub index_stats: Option<EngineStats>,
+    pub doc_per_second: f64,
+    pub megabytes_per_second: f64,
+    pub build_info: BuildInfo,
+    pub num_billed_bytes: u64,
+    pub estimated_cost_usd: Option<f64>,
+    pub budget_exceeded: bool,
+    /// Whether the run was interrupted (SIGINT or SIGTERM), the results
+    /// covering the documents sent until then.
+    #[serde(default)]
+    pub aborted: bool,
+    #[serde(default)]
+    pub max_duration_secs: Option<u64>,
+    /// Whether the ingestion was stopped by `max_duration_secs`, the dataset
+    /// progress being in `uri_summary`.
+    #[serde(default)]
+    pub deadline_reached: bool,
+    #[serde(default)]
+    pub target_num_docs: Option<u64>,
+    /// Whether the ingestion was stopped by `target_num_docs`. The documents
+    /// sent until the engine reported them are indexed too: `num_indexed_docs`
+    /// can exceed the target.
+    #[serde(default)]
+    pub target_num_docs_reached: bool,
+    /// The transforms applied to the documents.
+    #[serde(default)]
+    pub transforms: Option<Vec<TransformSpec>>,
+    pub tcp_stats: Option<TcpStatsReport>,
+    #[serde(default)]
+    pub engine_memory: Option<EngineMemoryReport>,
+    /// Absent from the results of older runs.
+    #[serde(default)]
+    pub driver_usage: Option<DriverUsageReport>,
+    pub schema_drift: Option<SchemaDriftReport>,
+    pub retention: Option<RetentionTimings>,
+    pub source_errors: Option<SourceErrorsReport>,
+    /// The `--reorder-window` sorting of the documents by timestamp.
+    #[serde(default)]
+    pub reorder: Option<ReorderReport>,
+    pub doc_size_histogram: Option<DocSizeReport>,
+    /// Absent from the results of older runs.
+    #[serde(default)]
+    pub batch_sizes: Option<BatchSizeReport>,
+    pub time_slices: Option<TimeSlicesReport>,
+    pub ingest_rate_mbps: Option<f64>,
+    /// The `--profile` shaping the rate around `ingest_rate_mbps`.
+    #[serde(default)]
+    pub pacing: Option<PacingReport>,
+    #[serde(default)]
+    pub simulated_latency_ms: Option<u64>,
+    #[serde(default)]
+    pub simulated_bandwidth_mbps: Option<f64>,
+    /// The `--seed` of the randomized behaviors of the run.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub shuffle_uris: bool,
+    #[serde(default)]
+    pub interleave_uris: bool,
+    /// The `--inject-failures` spec, and the requests it failed or delayed.
+    #[serde(default)]
+    pub failure_injection: Option<FailureInjectionReport>,
+    pub mixed_workload: Option<LoadReport>,
+    #[serde(default)]
+    pub visibility: Option<VisibilityReport>,
+    /// How much the counters of the engine (GC time, merges, flushes, network
+    /// bytes...) grew during the run, for the whole engine.
+    #[serde(default)]
+    pub engine_stats_delta: Option<EngineStats>,
+    /// The CPU time of the engine during the run, from `engine_stats_delta`.
+    #[serde(default)]
+    pub engine_cpu_seconds: Option<f64>,
+    #[serde(default)]
+    pub cpu_seconds_per_gb_ingested: Option<f64>,
+    #[serde(default)]
+    pub cpu_seconds_per_million_docs: Option<f64>,
+    pub input_shard_info: Vec<ShardInfo>,
+}
+
+/// The concurrency of the runs recorded before it was configurable.
+fn default_concurrency() -> usize {
+    DEFAULT_CONCURRENCY
+}
+
+/// The version of `dataset_fingerprint`:
+/// - 1 hashed the raw bytes of the local files, compressed or not, and the
+///   URIs of the remote ones.
+/// - 2 hashes the decompressed content of every URI, local or remote.
+pub const DATASET_FINGERPRINT_VERSION: u32 = 2;
+
+/// The fingerprint version of the runs recorded before it was versioned.
+fn legacy_dataset_fingerprint_version() -> u32 {
+    1
+}
+
+/// RFC 3339 UTC timestamps.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// Checks that the results of a run, as read from a results file, match the
+/// current schema.
+pub fn check_run(run: &Value) -> anyhow::Result<()> {
+    if run["partial"] == true {
+        bail!("partial results of a run that didn't finish");
+    }
+    match run.get("schema_version").and_then(Value::as_u64) {
+        Some(schema_version) if schema_version == SCHEMA_VERSION as u64 => {},
+        Some(schema_version) => {
+            bail!("schema version {schema_version}, expected {SCHEMA_VERSION}")
+        },
+        None => bail!("missing schema version"),
+    }
+    RunResults::deserialize(run)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn run_results_json() -> Value {
+        let mut run_results_json = json!({
+            "schema_version": SCHEMA_VERSION,
+            "engine": "elasticsearch",
+            "host": "127.0.0.1:9200",
+            "index": "logs",
+            "dataset_uri": "file:///data/logs.json",
+            "dataset_fingerprint": "af1349b9",
+            "dataset_fingerprint_version": 2,
+            "dataset_format": "ndjson",
+            "csv_infer_types": false,
+            "on_uri_error": "abort",
+            "uri_summary": [{
+                "uri": "file:///data/logs.json",
+                "state": "done",
+                "num_read_bytes": 1000,
+                "num_docs": 10,
+                "num_errors": 0,
+                "skipped": false,
+            }],
+            "repeat_dataset": 1,
+            "mutate_ids": false,
+            "time_range": {
+                "start": "2024-01-01T00:00:00.000000Z",
+                "end": "2024-01-01T00:01:00.000000Z",
+            },
+            "run_time_range": {
+                "start": "2024-01-01T00:00:00.000000Z",
+                "end": "2024-01-01T00:01:05.000000Z",
+            },
+            "alias": null,
+            "forwarded_to": null,
+            "num_ingested_bytes": 1000,
+            "num_indexed_docs": 10,
+            "num_indexed_bytes": 800,
+            "num_splits": 1,
+            "indexing_duration_secs": 60.0,
+            "time_to_first_batch_secs": 0.5,
+            "ingest_duration_secs": 58.0,
+            "commit_duration_secs": 1.5,
+            "merge_duration_secs": null,
+            "doc_per_second": 0.5,
+            "megabytes_per_second": 0.25,
+            "build_info": {
+                "version": "8.12.0",
+                "commit_date": "2024-01-01",
+                "commit_hash": "abcdef",
+                "build_target": "",
+            },
+            "num_billed_bytes": 1000,
+            "estimated_cost_usd": 0.5,
+            "budget_exceeded": false,
+            "tcp_stats": null,
+            "schema_drift": null,
+            "retention": null,
+            "source_errors": null,
+            "doc_size_histogram": null,
+            "time_slices": null,
+            "ingest_rate_mbps": null,
+            "mixed_workload": null,
+            "input_shard_info": [],
+        });
+        run_results_json["concurrency"] = json!(2);
+        run_results_json["worker_threads"] = json!(16);
+        run_results_json["pacing"] =
+            json!({"profile": "diurnal", "period_secs": 3600.0});
+        run_results_json["adaptive_concurrency"] = json!({
+            "initial_concurrency": 2,
+            "max_concurrency": 64,
+            "final_concurrency": 6,
+            "peak_concurrency": 12,
+            "num_increases": 10,
+            "num_decreases": 1,
+            "trajectory": [
+                {"elapsed_secs": 0.0, "concurrency": 2},
+                {"elapsed_secs": 1.5, "concurrency": 12},
+                {"elapsed_secs": 3.0, "concurrency": 6},
+            ],
+        });
+        run_results_json["tags"] = json!(["nightly"]);
+        run_results_json["key_value_tags"] = json!({"instance": "c6i.2xlarge"});
+        run_results_json["comment"] = json!("gp3 volume");
+        run_results_json["num_timed_out_requests"] = json!(0);
+        run_results_json["num_rejected_docs"] = json!(0);
+        run_results_json["num_throttled_requests"] = json!(3);
+        run_results_json["throttled_secs"] = json!(1.5);
+        run_results_json["aborted"] = json!(false);
+        run_results_json["batch_sizes"] = json!({
+            "num_batches": 3,
+            "sink_batch_num_bytes": 5_000_000,
+            "num_small_batches": 1,
+            "num_bytes": {"min": 1200, "mean": 3_400_400.0, "p50": 5_000_000, "p90": 5_000_000, "p99": 5_000_000, "max": 5_000_000},
+            "num_docs": {"min": 2, "mean": 6668.0, "p50": 10_000, "p90": 10_001, "p99": 10_001, "max": 10_001},
+            "buckets": [
+                {"max_num_bytes": 2047, "num_batches": 1},
+                {"max_num_bytes": 8_388_607, "num_batches": 2},
+            ],
+        });
+        run_results_json["simulated_latency_ms"] = json!(80);
+        run_results_json["simulated_bandwidth_mbps"] = json!(12.5);
+        run_results_json["seed"] = json!(42);
+        run_results_json["shuffle_uris"] = json!(true);
+        run_results_json["interleave_uris"] = json!(true);
+        run_results_json["reorder"] = json!({
+            "timestamp_field": "timestamp",
+            "window_num_docs": 10000,
+            "num_docs": 1000,
+            "num_docs_without_timestamp": 0,
+            "num_out_of_order_docs_read": 420,
+            "num_out_of_order_docs_sent": 3,
+            "num_flushed_docs": 2,
+        });
+        run_results_json["failure_injection"] = json!({
+            "rate": 0.01,
+            "kind": "500",
+            "delay_ms": 1000,
+            "seed": 0,
+            "num_requests": 400,
+            "num_injected_failures": 5,
+            "num_injected_delays": 0,
+        });
+        run_results_json["max_duration_secs"] = json!(3600);
+        run_results_json["deadline_reached"] = json!(true);
+        run_results_json["target_num_docs"] = json!(null);
+        run_results_json["target_num_docs_reached"] = json!(false);
+        run_results_json["num_segments_before_merge"] = json!(null);
+        run_results_json["num_segments_after_merge"] = json!(null);
+        run_results_json["wait_for_merges_secs"] = json!(null);
+        run_results_json["merges_settled_secs"] = json!(null);
+        run_results_json["num_splits_before_merges"] = json!(null);
+        run_results_json["es_shards"] = json!(3);
+        run_results_json["es_replicas"] = json!(0);
+        run_results_json["es_pipeline"] = json!("parse-logs");
+        run_results_json["es_index_settings"] = json!({
+            "index.refresh_interval": "-1",
+            "index.translog.durability": "async",
+            "index.number_of_replicas": "0",
+        });
+        run_results_json["qw_vrl_transform"] = json!(".severity = upcase!(.severity)");
+        run_results_json["qw_commit"] = json!("wait_for");
+        run_results_json["chunked_transfer"] = json!(true);
+        run_results_json["http_version"] = json!("auto");
+        run_results_json["negotiated_http_version"] = json!("HTTP/2.0");
+        run_results_json["index_stats"] = json!({
+            "segments.count": 7.0,
+            "shards.0.segments.count": 7.0,
+            "translog.size_in_bytes": 2048.0,
+        });
+        run_results_json["split_breakdown"] = json!({
+            "num_immature_splits": 3,
+            "immature_num_bytes": 3000,
+            "num_mature_splits": 1,
+            "mature_num_bytes": 9000,
+            "merge_levels": [
+                {"num_merge_ops": 0, "num_splits": 3, "num_bytes": 3000},
+                {"num_merge_ops": 1, "num_splits": 1, "num_bytes": 9000},
+            ],
+        });
+        run_results_json["engine_stats_delta"] = json!({
+            "indices.merges.total": 12.0,
+            "jvm.gc.collectors.young.collection_time_in_millis": 830.0,
+        });
+        run_results_json["engine_memory"] = json!({
+            "pid": 4242,
+            "num_samples": 60,
+            "max_rss_bytes": 4_200_000_000u64,
+            "avg_rss_bytes": 3_900_000_000u64,
+            "cgroup": "/sys/fs/cgroup/system.slice/docker-3f4e2a.scope",
+            "max_cgroup_memory_bytes": 6_100_000_000u64,
+            "avg_cgroup_memory_bytes": 5_800_000_000u64,
+            "cgroup_memory_peak_bytes": null,
+        });
+        run_results_json["transforms"] = json!([
+            {"type": "rename", "fields": {"ts": "timestamp"}},
+            {"type": "lowercase_keys"},
+        ]);
+        run_results_json["driver_usage"] = json!({
+            "cpu_user_secs": 12.5,
+            "cpu_system_secs": 3.5,
+            "cpu_utilization": 0.4,
+            "peak_rss_bytes": 120_000_000,
+        });
+        run_results_json["engine_cpu_seconds"] = json!(42.0);
+        run_results_json["cpu_seconds_per_gb_ingested"] = json!(21.0);
+        run_results_json["cpu_seconds_per_million_docs"] = json!(4.2);
+        run_results_json["visibility"] = json!({
+            "interval_secs": 10,
+            "timeout_secs": 120,
+            "field": "message",
+            "num_probes": 2,
+            "num_timed_out": 0,
+            "num_errors": 0,
+            "visibility_latency": {
+                "min_ms": 980.0,
+                "mean_ms": 1010.0,
+                "p50_ms": 1040.0,
+                "p90_ms": 1040.0,
+                "p99_ms": 1040.0,
+                "max_ms": 1040.0,
+            },
+        });
+        run_results_json["engine_container"] = json!({
+            "container_name": "qbench-elasticsearch",
+            "container_id": "3f4e2a",
+            "image": "docker.elastic.co/elasticsearch/elasticsearch:8.13.4",
+            "heap_size": "4g",
+            "data_dir": null,
+            "memory": null,
+            "cpus": "4",
+            "env": ["discovery.type=single-node"],
+        });
+        run_results_json
+    }
+
+    #[test]
+    fn test_run_results_round_trip() {
+        let run_results_json = run_results_json();
+        let run_results: RunResults =
+            serde_json::from_value(run_results_json.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&run_results).unwrap(),
+            run_results_json
+        );
+
+        let mut legacy_run_results_json = run_results_json.clone();
+        let legacy_fields = legacy_run_results_json.as_object_mut().unwrap();
+        legacy_fields.remove("dataset_fingerprint_version");
+        legacy_fields.remove("merge_duration_secs");
+        legacy_fields.insert("force_merge_duration_secs".to_string(), json!(2.5));
+        let legacy_run_results: RunResults =
+            serde_json::from_value(legacy_run_results_json).unwrap();
+        assert_eq!(legacy_run_results.dataset_fingerprint_version, 1);
+        assert_eq!(legacy_run_results.merge_duration_secs, Some(2.5));
+
+        let run_results_yaml = serde_yaml::to_string(&run_results).unwrap();
+        let run_results: RunResults = serde_yaml::from_str(&run_results_yaml).unwrap();
+        assert_eq!(
+            serde_json::to_value(&run_results).unwrap(),
+            run_results_json
+        );
+    }
+
+    #[test]
+    fn test_check_run() {
+        let mut run = run_results_json();
+        check_run(&run).unwrap();
+        run["schema_version"] = json!(SCHEMA_VERSION + 1);
+        assert!(check_run(&run).is_err());
+        run["schema_version"] = json!(SCHEMA_VERSION);
+        run["num_docs"] = json!(10);
+        assert!(check_run(&run).is_err());
+    }
+}
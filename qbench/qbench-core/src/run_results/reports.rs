@@ -0,0 +1,571 @@
+//! The reports of the features of `qbench index` and the settings they ran
+//! with, as recorded in the run results.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Below this many batches, the dataset is too small to tell anything from
+/// small batches.
+const MIN_NUM_BATCHES: u64 = 10;
+
+/// The fraction of the cores the driver may use before it is suspected to be
+/// the bottleneck of the run.
+const MAX_CPU_UTILIZATION: f64 = 0.8;
+
+const PROC_NET_SNMP_PATH: &str = "/proc/net/snmp";
+
+/// The share of a `burst` period spent bursting.
+const BURST_RATIO: f64 = 1.0 / 6.0;
+/// The rate of a `burst` profile outside of the bursts, relative to the
+/// average rate.
+const BURST_LOW_MULTIPLIER: f64 = 0.2;
+/// How far the `ramp` and `diurnal` profiles swing around the average rate.
+const SWING: f64 = 0.8;
+
+/// The concurrency from `elapsed_secs` after the start of the ingestion.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConcurrencyStep {
+    pub elapsed_secs: f64,
+    pub concurrency: usize,
+}
+
+/// The trajectory of `--adaptive-concurrency`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdaptiveConcurrencyReport {
+    pub initial_concurrency: usize,
+    pub max_concurrency: usize,
+    pub final_concurrency: usize,
+    /// The highest concurrency reached.
+    pub peak_concurrency: usize,
+    pub num_increases: u64,
+    pub num_decreases: u64,
+    pub trajectory: Vec<ConcurrencyStep>,
+}
+
+/// The container an engine was started in by `qbench engine up`, recorded in
+/// the results to tell how the engine was run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EngineContainer {
+    pub container_name: String,
+    pub container_id: String,
+    pub image: String,
+    pub heap_size: Option<String>,
+    pub data_dir: Option<String>,
+    pub memory: Option<String>,
+    pub cpus: Option<String>,
+    pub env: Vec<String>,
+}
+
+impl EngineContainer {
+    /// Reads the container config written by `qbench engine up`.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let container_json = std::fs::read(path).with_context(|| {
+            format!("Failed to read engine container config {path:?}")
+        })?;
+        serde_json::from_slice(&container_json)
+            .with_context(|| format!("Invalid engine container config {path:?}"))
+    }
+}
+
+/// The memory of the engine over the run. The RSS covers the engine's process
+/// and its descendants, e.g. the engine under a container's init process.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EngineMemoryReport {
+    pub pid: u32,
+    pub num_samples: usize,
+    pub max_rss_bytes: u64,
+    pub avg_rss_bytes: u64,
+    /// The cgroup (v2) of the engine, whose `memory.current` includes the page
+    /// cache of the engine's files.
+    pub cgroup: Option<String>,
+    pub max_cgroup_memory_bytes: Option<u64>,
+    pub avg_cgroup_memory_bytes: Option<u64>,
+    /// The `memory.peak` of the cgroup, since the cgroup was created rather
+    /// than since the start of the run. Needs Linux 5.19+.
+    pub cgroup_memory_peak_bytes: Option<u64>,
+}
+
+/// Host-wide TCP counters, as exposed by the kernel in `/proc/net/snmp`.
+///
+/// These are not scoped to qbench's own sockets: when the engine runs on the
+/// same host, its server-side retransmissions and resets are included too.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TcpStats {
+    pub active_opens: u64,
+    pub attempt_fails: u64,
+    pub estab_resets: u64,
+    pub in_segs: u64,
+    pub out_segs: u64,
+    pub retrans_segs: u64,
+    pub in_errs: u64,
+    pub out_rsts: u64,
+}
+
+impl TcpStats {
+    pub fn read() -> anyhow::Result<Self> {
+        let snmp = std::fs::read_to_string(PROC_NET_SNMP_PATH)
+            .with_context(|| format!("Failed to read {PROC_NET_SNMP_PATH}"))?;
+        Self::parse(&snmp)
+    }
+
+    /// Parses the `Tcp:` header and value lines of `/proc/net/snmp`.
+    fn parse(snmp: &str) -> anyhow::Result<Self> {
+        let mut tcp_lines = snmp.lines().filter(|line| line.starts_with("Tcp:"));
+        let (Some(header), Some(values)) = (tcp_lines.next(), tcp_lines.next()) else {
+            bail!("No TCP counters found in {PROC_NET_SNMP_PATH}");
+        };
+        let mut stats = TcpStats::default();
+        for (name, value) in header
+            .split_whitespace()
+            .zip(values.split_whitespace())
+            .skip(1)
+        {
+            let counter = match name {
+                "ActiveOpens" => &mut stats.active_opens,
+                "AttemptFails" => &mut stats.attempt_fails,
+                "EstabResets" => &mut stats.estab_resets,
+                "InSegs" => &mut stats.in_segs,
+                "OutSegs" => &mut stats.out_segs,
+                "RetransSegs" => &mut stats.retrans_segs,
+                "InErrs" => &mut stats.in_errs,
+                "OutRsts" => &mut stats.out_rsts,
+                _ => continue,
+            };
+            *counter = value.parse().with_context(|| {
+                format!("Invalid value {value:?} for TCP counter {name}")
+            })?;
+        }
+        Ok(stats)
+    }
+
+    /// Returns the counters accumulated since `start`.
+    pub fn since(&self, start: &TcpStats) -> TcpStats {
+        TcpStats {
+            active_opens: self.active_opens.saturating_sub(start.active_opens),
+            attempt_fails: self.attempt_fails.saturating_sub(start.attempt_fails),
+            estab_resets: self.estab_resets.saturating_sub(start.estab_resets),
+            in_segs: self.in_segs.saturating_sub(start.in_segs),
+            out_segs: self.out_segs.saturating_sub(start.out_segs),
+            retrans_segs: self.retrans_segs.saturating_sub(start.retrans_segs),
+            in_errs: self.in_errs.saturating_sub(start.in_errs),
+            out_rsts: self.out_rsts.saturating_sub(start.out_rsts),
+        }
+    }
+
+    /// The fraction of sent segments that were retransmissions.
+    pub fn retransmission_rate(&self) -> f64 {
+        if self.out_segs == 0 {
+            return 0.0;
+        }
+        self.retrans_segs as f64 / self.out_segs as f64
+    }
+}
+
+/// The host TCP counters over the run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcpStatsReport {
+    /// The counters accumulated over the whole run.
+    pub delta: TcpStats,
+    pub retransmission_rate: f64,
+    /// The highest retransmission rate observed over a single sampling
+    /// interval, which surfaces short bursts of network flakiness.
+    pub max_interval_retransmission_rate: f64,
+}
+
+/// How the send rate is shaped over time around `--ingest-rate-mbps`, the
+/// average rate over a period of the profile, to observe the merges and
+/// compactions of the engine under realistic traffic rather than a flat rate.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PacingProfile {
+    /// A constant rate.
+    Steady,
+    /// A burst at 5x the rate for the first sixth of each period, then 0.2x.
+    Burst,
+    /// A sawtooth, going from 0.2x to 1.8x the rate over each period.
+    Ramp,
+    /// A sine wave from 0.2x the rate at the start of each period (the night)
+    /// to 1.8x in its middle, each period being a compressed day.
+    Diurnal,
+}
+
+impl FromStr for PacingProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "steady" => Ok(PacingProfile::Steady),
+            "burst" => Ok(PacingProfile::Burst),
+            "ramp" => Ok(PacingProfile::Ramp),
+            "diurnal" => Ok(PacingProfile::Diurnal),
+            _ => Err(format!("Unknown pacing profile {s:?}")),
+        }
+    }
+}
+
+impl PacingProfile {
+    pub fn default_period(&self) -> Duration {
+        match self {
+            PacingProfile::Steady | PacingProfile::Burst => Duration::from_secs(60),
+            PacingProfile::Ramp => Duration::from_secs(600),
+            PacingProfile::Diurnal => Duration::from_secs(3600),
+        }
+    }
+
+    /// The rate at `elapsed`, relative to the average rate. Never zero, for
+    /// the ingestion to always move forward.
+    pub fn rate_multiplier(&self, elapsed: Duration, period: Duration) -> f64 {
+        let phase = (elapsed.as_secs_f64() / period.as_secs_f64()).fract();
+        match self {
+            PacingProfile::Steady => 1.0,
+            PacingProfile::Burst => {
+                if phase < BURST_RATIO {
+                    (1.0 - (1.0 - BURST_RATIO) * BURST_LOW_MULTIPLIER) / BURST_RATIO
+                } else {
+                    BURST_LOW_MULTIPLIER
+                }
+            },
+            PacingProfile::Ramp => 1.0 - SWING + 2.0 * SWING * phase,
+            PacingProfile::Diurnal => 1.0 - SWING * (2.0 * PI * phase).cos(),
+        }
+    }
+}
+
+/// The pacing of a run, as recorded in the results.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PacingReport {
+    pub profile: PacingProfile,
+    pub period_secs: f64,
+}
+
+/// The reordering of `--reorder-window`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderReport {
+    pub timestamp_field: String,
+    pub window_num_docs: usize,
+    pub num_docs: u64,
+    pub num_docs_without_timestamp: u64,
+    /// The documents older than a document read before them.
+    pub num_out_of_order_docs_read: u64,
+    /// The documents older than a document sent before them, the window
+    /// being too small to reorder them.
+    pub num_out_of_order_docs_sent: u64,
+    /// The documents not sent yet when the ingestion stopped before the end
+    /// of the dataset, sent at once as far as the budget allows.
+    #[serde(default)]
+    pub num_flushed_docs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DocSizeBucket {
+    /// Inclusive upper bound of the bucket.
+    pub max_num_bytes: u64,
+    pub num_docs: u64,
+}
+
+/// The distribution of the size of the documents read from the dataset.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocSizeReport {
+    pub num_docs: u64,
+    pub num_empty_lines: u64,
+    pub min_num_bytes: u64,
+    pub max_num_bytes: u64,
+    pub mean_num_bytes: f64,
+    /// Upper bounds of the buckets containing the percentiles.
+    pub p50_num_bytes: u64,
+    pub p90_num_bytes: u64,
+    pub p99_num_bytes: u64,
+    /// The non-empty buckets, in increasing size order.
+    pub buckets: Vec<DocSizeBucket>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchSizeBucket {
+    /// Inclusive upper bound of the bucket, a power of two minus one.
+    pub max_num_bytes: u64,
+    pub num_batches: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BatchSizeDistribution {
+    pub min: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// The distribution of the size of the batches sent.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSizeReport {
+    pub num_batches: u64,
+    /// The `batch_size()` of the sink the batches were cut for.
+    pub sink_batch_num_bytes: u64,
+    /// The batches under a tenth of `sink_batch_num_bytes`.
+    pub num_small_batches: u64,
+    pub num_bytes: BatchSizeDistribution,
+    pub num_docs: BatchSizeDistribution,
+    /// The non-empty power-of-two buckets of the batch sizes in bytes, in
+    /// increasing size order.
+    pub buckets: Vec<BatchSizeBucket>,
+}
+
+impl BatchSizeReport {
+    /// Whether most batches were small, on a dataset larger than a few
+    /// batches.
+    pub fn is_mostly_small(&self) -> bool {
+        self.num_batches >= MIN_NUM_BATCHES
+            && self.num_small_batches * 2 > self.num_batches
+    }
+}
+
+/// The client-side throughput and the engine metrics over one interval of
+/// the run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSlice {
+    pub start: String,
+    pub end: String,
+    pub num_ingested_bytes: u64,
+    pub num_ingested_docs: u64,
+    pub num_failed_batches: u64,
+    #[serde(default)]
+    pub num_timed_out_requests: u64,
+    pub megabytes_per_second: f64,
+    pub docs_per_second: f64,
+    /// The value of the watched engine metrics at the end of the slice, if
+    /// they could be scraped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine_metrics: Option<BTreeMap<String, f64>>,
+}
+
+/// The run cut in slices of `--time-slice-interval-secs`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeSlicesReport {
+    pub interval_secs: u64,
+    /// The value of the watched engine metrics when the run started.
+    pub start_engine_metrics: Option<BTreeMap<String, f64>>,
+    pub slices: Vec<TimeSlice>,
+}
+
+/// The resources used by qbench itself, to check that the driver wasn't the
+/// bottleneck.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DriverUsageReport {
+    pub cpu_user_secs: f64,
+    pub cpu_system_secs: f64,
+    /// The CPU time over the run duration, in cores.
+    pub cpu_utilization: f64,
+    /// The peak RSS of the qbench process, since it started rather than since
+    /// the start of the run.
+    pub peak_rss_bytes: u64,
+}
+
+impl DriverUsageReport {
+    /// Whether the driver used more than `MAX_CPU_UTILIZATION` of the cores it
+    /// could use: one per in-flight request at most, and no more than the
+    /// host has.
+    pub fn is_cpu_bound(&self, concurrency: usize) -> bool {
+        let num_cores = std::thread::available_parallelism()
+            .map(|num_cores| num_cores.get())
+            .unwrap_or(1);
+        self.cpu_utilization > MAX_CPU_UTILIZATION * concurrency.min(num_cores) as f64
+    }
+}
+
+/// How the documents drift with `--schema-drift-after-bytes`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchemaDriftKind {
+    /// Adds fields that were never seen before.
+    NewFields,
+    /// Changes the type of the existing top-level scalar fields.
+    TypeChanges,
+    Both,
+}
+
+impl FromStr for SchemaDriftKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "new-fields" => Ok(SchemaDriftKind::NewFields),
+            "type-changes" => Ok(SchemaDriftKind::TypeChanges),
+            "both" => Ok(SchemaDriftKind::Both),
+            _ => Err(format!("Unknown schema drift kind {s:?}")),
+        }
+    }
+}
+
+/// Throughput and errors measured on one side of the drift point.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PhaseStats {
+    pub duration_secs: f64,
+    pub num_ingested_bytes: u64,
+    pub num_ingestion_error_bytes: u64,
+    pub megabytes_per_second: f64,
+}
+
+impl PhaseStats {
+    pub fn new(
+        duration: Duration,
+        num_ingested_bytes: u64,
+        num_ingestion_error_bytes: u64,
+    ) -> Self {
+        let duration_secs = duration.as_secs_f64();
+        Self {
+            duration_secs,
+            num_ingested_bytes,
+            num_ingestion_error_bytes,
+            megabytes_per_second: num_ingested_bytes as f64
+                / 1_000_000.0
+                / duration_secs,
+        }
+    }
+}
+
+/// The documents drifted by `--schema-drift-after-bytes`, and the throughput
+/// on each side of the drift point.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaDriftReport {
+    pub after_bytes: u64,
+    pub ratio: f64,
+    pub kind: SchemaDriftKind,
+    pub num_drifted_docs: u64,
+    pub before: PhaseStats,
+    pub after: PhaseStats,
+}
+
+/// The documents broken by `--inject-source-errors`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SourceErrorsReport {
+    pub rate: f64,
+    pub seed: u64,
+    pub num_docs: u64,
+    pub num_corrupted_docs: u64,
+    pub num_truncated_docs: u64,
+}
+
+/// A transform as written in the `--transforms` file, e.g.
+///
+/// ```json
+/// [
+///   {"type": "rename", "fields": {"ts": "timestamp"}},
+///   {"type": "drop", "fields": ["tenant_id"]},
+///   {"type": "add", "fields": {"source": "qbench"}},
+///   {"type": "lowercase_keys"},
+///   {"type": "log_schema", "schema": "otel"}
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TransformSpec {
+    /// Renames top-level fields, from their current name to their new name.
+    Rename { fields: BTreeMap<String, String> },
+    /// Removes top-level fields.
+    Drop { fields: Vec<String> },
+    /// Sets top-level fields to static values, overwriting existing ones.
+    Add { fields: Map<String, Value> },
+    /// Lowercases the keys of the document, nested objects included.
+    LowercaseKeys,
+    /// Remaps a log document to a canonical log schema, for engines expecting
+    /// ECS or OpenTelemetry logs. Without a `*_field`, the first of the usual
+    /// field names found in the document is used, e.g. `ts` or `time` for the
+    /// timestamp.
+    LogSchema {
+        schema: LogSchema,
+        #[serde(default)]
+        timestamp_field: Option<String>,
+        #[serde(default)]
+        message_field: Option<String>,
+        #[serde(default)]
+        severity_field: Option<String>,
+        /// The fields describing the source of the logs, from their name in
+        /// the document to their resource attribute name.
+        #[serde(default = "default_resource_fields")]
+        resource_fields: BTreeMap<String, String>,
+    },
+    /// Flattens the GitHub Archive events, for the engines to index the same
+    /// fields: `actor`, `repo` and `org` become `actor_id`, `actor_login`,
+    /// `repo_id`, `repo_name`, `org_id` and `org_login`, and the `payload`
+    /// fields larger than `max_payload_field_bytes` once serialized (commit
+    /// lists, full pull requests and issues) are dropped.
+    Gharchive {
+        /// Keeps only the events of these types, e.g. `PushEvent`. All the
+        /// events are kept if empty.
+        #[serde(default)]
+        event_types: Vec<String>,
+        #[serde(default = "default_max_payload_field_bytes")]
+        max_payload_field_bytes: usize,
+    },
+}
+
+/// The `max_payload_field_bytes` of the `gharchive` transform.
+pub fn default_max_payload_field_bytes() -> usize {
+    1024
+}
+
+/// The log schema of the `log_schema` transform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogSchema {
+    /// Elastic Common Schema: `@timestamp`, `message` and `log.level`, the
+    /// resource attributes as nested objects (e.g. `service.name`), and the
+    /// other fields left as is.
+    Ecs,
+    /// The OpenTelemetry log data model, as indexed by Quickwit's OTel logs
+    /// index: `timestamp`, `severity_text`, `severity_number`, `body`, the
+    /// other fields under `attributes` and the resource attributes under
+    /// `resource_attributes`.
+    Otel,
+}
+
+pub fn default_resource_fields() -> BTreeMap<String, String> {
+    [
+        ("service", "service.name"),
+        ("service_name", "service.name"),
+        ("host", "host.name"),
+        ("hostname", "host.name"),
+    ]
+    .into_iter()
+    .map(|(field, attribute)| (field.to_string(), attribute.to_string()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tcp_stats() {
+        let snmp = "Ip: Forwarding DefaultTTL\n\
+                    Ip: 1 64\n\
+                    Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens PassiveOpens \
+                    AttemptFails EstabResets CurrEstab InSegs OutSegs RetransSegs InErrs \
+                    OutRsts InCsumErrors\n\
+                    Tcp: 1 200 120000 -1 7 4 2 1 2 1152 1000 50 3 5 0\n";
+        let stats = TcpStats::parse(snmp).unwrap();
+        assert_eq!(
+            stats,
+            TcpStats {
+                active_opens: 7,
+                attempt_fails: 2,
+                estab_resets: 1,
+                in_segs: 1152,
+                out_segs: 1000,
+                retrans_segs: 50,
+                in_errs: 3,
+                out_rsts: 5,
+            }
+        );
+        assert_eq!(stats.retransmission_rate(), 0.05);
+        assert_eq!(stats.since(&stats), TcpStats::default());
+    }
+}
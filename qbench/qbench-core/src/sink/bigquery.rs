@@ -0,0 +1,159 @@
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use http::{header, StatusCode};
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+
+use super::{BuildInfo, IndexInfo, Sink};
+use crate::gcp_auth::GcpAuth;
+use crate::source::DocumentBatch;
+
+pub const BIGQUERY_SCOPE: &str = "https://www.googleapis.com/auth/bigquery";
+
+/// `insertAll` requests are limited to 10MB, so we keep a safe margin for the
+/// row envelopes.
+const MAX_INSERT_ALL_SIZE: usize = 8_000_000;
+
+/// The maximum number of rows per `insertAll` request recommended by Google.
+const MAX_ROWS_PER_REQUEST: usize = 10_000;
+
+/// Sink for Google BigQuery, using the streaming `insertAll` JSON API.
+///
+/// The target table must already exist with a schema matching the documents.
+pub struct BigQuerySink {
+    table_url: Url,
+    insert_all_url: Url,
+    auth: GcpAuth,
+    client: Client,
+}
+
+impl BigQuerySink {
+    pub fn new(
+        project: &str,
+        dataset: &str,
+        table: &str,
+        auth: GcpAuth,
+        client: Client,
+    ) -> Self {
+        debug!(project=?project, dataset=?dataset, table=?table, "bigquery client");
+        let table_url = Url::parse(&format!(
+            "https://bigquery.googleapis.com/bigquery/v2/projects/{project}/datasets/{dataset}/tables/{table}"
+        ))
+        .expect("Invalid bigquery URL");
+        let insert_all_url =
+            Url::parse(&format!("{table_url}/insertAll")).expect("Invalid bigquery URL");
+        Self {
+            table_url,
+            insert_all_url,
+            auth,
+            client,
+        }
+    }
+
+    async fn insert_all(&self, rows: &[Value]) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(self.insert_all_url.clone())
+            .header(header::AUTHORIZATION, self.auth.authorization().await?)
+            .json(&json!({ "rows": rows }))
+            .send()
+            .await
+            .with_context(|| "BigQuery request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "BigQuery API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: Value = response.json().await?;
+        if let Some(insert_errors) = data.get("insertErrors") {
+            error!(errors=?insert_errors, "Errors contained in insertAll response");
+            bail!("Error on insertAll request");
+        }
+        Ok(())
+    }
+}
+
+/// Parses a JSON string field holding an integer, as BigQuery returns int64
+/// values as strings.
+fn parse_u64_field(value: &Value) -> Option<u64> {
+    value.as_str().and_then(|value| value.parse().ok())
+}
+
+#[async_trait]
+impl Sink for BigQuerySink {
+    fn batch_size(&self) -> usize {
+        MAX_INSERT_ALL_SIZE
+    }
+
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let mut rows = Vec::new();
+        for line in document_batch.bytes.split(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let doc: Value = serde_json::from_slice(line)
+                .context("Failed to parse document line as JSON")?;
+            rows.push(json!({ "json": doc }));
+        }
+        for chunk in rows.chunks(MAX_ROWS_PER_REQUEST) {
+            self.insert_all(chunk).await?;
+        }
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        // Streamed rows are available for queries right after being inserted.
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let response = self
+            .client
+            .get(self.table_url.clone())
+            .header(header::AUTHORIZATION, self.auth.authorization().await?)
+            .send()
+            .await
+            .with_context(|| "BigQuery request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "BigQuery API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: Value = response.json().await?;
+        // Rows still in the streaming buffer are not accounted in `numRows`.
+        let num_docs = parse_u64_field(&data["numRows"]).unwrap_or(0)
+            + parse_u64_field(&data["streamingBuffer"]["estimatedRows"]).unwrap_or(0);
+        let num_logical_bytes = parse_u64_field(&data["numTotalLogicalBytes"])
+            .or_else(|| parse_u64_field(&data["numBytes"]))
+            .unwrap_or(0);
+        let num_physical_bytes =
+            parse_u64_field(&data["numTotalPhysicalBytes"]).unwrap_or(num_logical_bytes);
+        info!(
+            num_logical_bytes,
+            num_physical_bytes, "BigQuery table storage"
+        );
+        Ok(IndexInfo {
+            num_docs,
+            num_splits: 0,
+            num_bytes: num_physical_bytes,
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        // BigQuery is a managed service without any version information.
+        Ok(BuildInfo {
+            version: "".to_string(),
+            commit_date: "".to_string(),
+            commit_hash: "".to_string(),
+            build_target: "bigquery".to_string(),
+        })
+    }
+}
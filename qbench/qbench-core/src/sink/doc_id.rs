@@ -0,0 +1,104 @@
+use anyhow::Context;
+use serde_json::{Map, Value};
+
+/// The field Quickwit documents get their hash-based ID in, Quickwit having no
+/// document ID of its own.
+pub const HASH_DOC_ID_FIELD: &str = "doc_id";
+
+/// Where the stable ID of a document comes from, so that a document sent
+/// twice (e.g. by a retried batch) is only indexed once by the engines
+/// deduplicating on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocId {
+    /// The value of a top-level field of the document.
+    Field(String),
+    /// The blake3 hash of the document's line.
+    Hash,
+}
+
+impl DocId {
+    /// The ID of the document, None if it lacks the ID field.
+    pub fn of(&self, doc: &[u8]) -> anyhow::Result<Option<String>> {
+        match self {
+            DocId::Field(field_name) => field_value(doc, field_name),
+            DocId::Hash => Ok(Some(hash_doc_id(doc))),
+        }
+    }
+}
+
+/// The string or number value of a top-level field of the document, e.g. its
+/// ID or its routing key.
+pub fn field_value(doc: &[u8], field_name: &str) -> anyhow::Result<Option<String>> {
+    let doc: Map<String, Value> =
+        serde_json::from_slice(doc).context("Failed to parse document line as JSON")?;
+    let value = match doc.get(field_name) {
+        Some(Value::String(value)) => Some(value.clone()),
+        Some(Value::Number(value)) => Some(value.to_string()),
+        _ => None,
+    };
+    Ok(value)
+}
+
+fn hash_doc_id(doc: &[u8]) -> String {
+    let mut doc_id = blake3::hash(doc.trim_ascii()).to_hex().to_string();
+    // 128 bits are plenty to tell documents apart.
+    doc_id.truncate(32);
+    doc_id
+}
+
+/// Adds the hash-based ID of each document of the batch in its
+/// `HASH_DOC_ID_FIELD` field.
+pub fn inject_hash_doc_ids(batch: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut injected = Vec::with_capacity(batch.len() + batch.len() / 10);
+    for line in batch.split_inclusive(|byte| *byte == b'\n') {
+        if line.trim_ascii().is_empty() {
+            injected.extend_from_slice(line);
+            continue;
+        }
+        let mut doc: Map<String, Value> = serde_json::from_slice(line)
+            .context("Failed to parse document line as JSON")?;
+        doc.insert(
+            HASH_DOC_ID_FIELD.to_string(),
+            Value::String(hash_doc_id(line)),
+        );
+        serde_json::to_writer(&mut injected, &doc)?;
+        injected.push(b'\n');
+    }
+    Ok(injected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_id() {
+        let doc = br#"{"id": 12, "trace_id": "abc", "message": "hello"}"#;
+        let id_field = DocId::Field("id".to_string());
+        assert_eq!(id_field.of(doc).unwrap().as_deref(), Some("12"));
+        let trace_id_field = DocId::Field("trace_id".to_string());
+        assert_eq!(trace_id_field.of(doc).unwrap().as_deref(), Some("abc"));
+        let missing_field = DocId::Field("span_id".to_string());
+        assert_eq!(missing_field.of(doc).unwrap(), None);
+
+        let hash = DocId::Hash.of(doc).unwrap().unwrap();
+        assert_eq!(hash.len(), 32);
+        assert_eq!(
+            DocId::Hash
+                .of(&[doc.as_slice(), b"\n"].concat())
+                .unwrap()
+                .unwrap(),
+            hash
+        );
+        assert_ne!(DocId::Hash.of(br#"{"id": 13}"#).unwrap().unwrap(), hash);
+
+        let injected = inject_hash_doc_ids(&[doc.as_slice(), b"\n\n"].concat()).unwrap();
+        let injected_doc: Value = serde_json::from_slice(
+            injected.split(|byte| *byte == b'\n').next().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(injected_doc["doc_id"], hash);
+        assert_eq!(injected_doc["message"], "hello");
+        assert!(injected.ends_with(b"\n\n"));
+    }
+}
@@ -0,0 +1,1445 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use http::{header, StatusCode};
+use reqwest::{Client, RequestBuilder, Response, Url};
+use serde_json::{json, Map, Value};
+use tokio::sync::OnceCell;
+
+use super::doc_id::{field_value, DocId};
+use super::{
+    base_url,
+    encode_blocking,
+    ingest_body,
+    BuildInfo,
+    IndexInfo,
+    RejectedDoc,
+    RetentionTimings,
+    SearchResponse,
+    Sink,
+    TRANSFER_CHUNK_SIZE,
+};
+use crate::aws_sigv4::AwsSigV4;
+use crate::engine_metrics::EngineStats;
+use crate::query::EngineQuery;
+use crate::source::DocumentBatch;
+use crate::utils::wait_until;
+
+/// The ILM/ISM policy deleting the index right away, used to measure retention.
+const RETENTION_POLICY_NAME: &str = "qbench-retention";
+
+/// The interval at which the index state is polled while waiting for ILM.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum number of times the documents rejected with a retryable status
+/// are resent, see `with_retry_rejected_docs`.
+const MAX_REJECTED_DOCS_RETRIES: usize = 3;
+/// The maximum number of rejected documents kept for `take_rejected_docs`.
+const MAX_KEPT_REJECTED_DOCS: usize = 1000;
+
+/// Merging a large index into one segment outlasts the request timeout.
+const FORCE_MERGE_TIMEOUT: Duration = Duration::from_secs(3 * 3600);
+
+/// The Elasticsearch-compatible distribution the sink talks to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Distribution {
+    Elasticsearch,
+    Opensearch,
+}
+
+/// The distribution and version reported by the cluster, used to gate the
+/// behaviors differing between distributions or versions.
+#[derive(Debug, Copy, Clone)]
+struct Flavor {
+    distribution: Distribution,
+    major_version: u64,
+    minor_version: u64,
+}
+
+impl Flavor {
+    /// Parses the `version` object returned by the root endpoint. OpenSearch
+    /// sets `distribution`, Elasticsearch does not.
+    fn parse(version: &serde_json::Value) -> anyhow::Result<Self> {
+        let distribution = match version["distribution"].as_str() {
+            Some("opensearch") => Distribution::Opensearch,
+            _ => Distribution::Elasticsearch,
+        };
+        let number = version["number"]
+            .as_str()
+            .context("version field must be a string")?;
+        let mut version_parts = number.split('.').map(|part| part.parse::<u64>());
+        let (Some(Ok(major_version)), Some(Ok(minor_version))) =
+            (version_parts.next(), version_parts.next())
+        else {
+            bail!("Invalid version number {number:?}");
+        };
+        Ok(Flavor {
+            distribution,
+            major_version,
+            minor_version,
+        })
+    }
+
+    fn is_at_least(&self, major_version: u64, minor_version: u64) -> bool {
+        (self.major_version, self.minor_version) >= (major_version, minor_version)
+    }
+}
+
+#[derive(Clone)]
+pub struct ElasticsearchSink {
+    api_root_url: Url,
+    index_url: Url,
+    ingest_url: Url,
+    client: Client,
+    index_id: String,
+    expected_distribution: Distribution,
+    flavor: OnceCell<Flavor>,
+    retry_rejected_docs: bool,
+    doc_id: Option<DocId>,
+    routing_field: Option<String>,
+    num_shards: Option<u32>,
+    num_replicas: Option<u32>,
+    pipeline: Option<String>,
+    chunked_transfer: bool,
+    aws_sigv4: Option<Arc<AwsSigV4>>,
+    num_rejected_docs: Arc<AtomicU64>,
+    /// Up to `MAX_KEPT_REJECTED_DOCS`, until taken.
+    rejected_docs: Arc<Mutex<Vec<RejectedDoc>>>,
+}
+
+impl ElasticsearchSink {
+    pub fn new(
+        host: &str,
+        index_id: &str,
+        alias: Option<&str>,
+        expected_distribution: Distribution,
+        client: Client,
+    ) -> Self {
+        debug!(host=?host, index_id=?index_id, "elasticsearch client");
+        let base_url = base_url(host);
+        let api_root_url =
+            Url::parse(&format!("{base_url}/")).expect("Invalid elastic URL");
+        let index_url =
+            Url::parse(&format!("{base_url}/{index_id}/")).expect("Invalid elastic URL");
+        // When an alias is given, documents are written through it so that it
+        // has to be pointing to the index, see `switch_alias`.
+        let write_target = alias.unwrap_or(index_id);
+        let ingest_url = Url::parse(&format!("{base_url}/{write_target}/_bulk"))
+            .expect("Invalid elastic URL");
+        Self {
+            api_root_url,
+            index_url,
+            ingest_url,
+            client,
+            index_id: index_id.to_string(),
+            expected_distribution,
+            flavor: OnceCell::new(),
+            retry_rejected_docs: false,
+            doc_id: None,
+            routing_field: None,
+            num_shards: None,
+            num_replicas: None,
+            pipeline: None,
+            chunked_transfer: false,
+            aws_sigv4: None,
+            num_rejected_docs: Arc::default(),
+            rejected_docs: Arc::default(),
+        }
+    }
+
+    /// Resends the documents of a bulk request rejected with a retryable
+    /// status (e.g. 429 when the write queue is full), rather than counting
+    /// them as rejected right away.
+    pub fn with_retry_rejected_docs(mut self, retry_rejected_docs: bool) -> Self {
+        self.retry_rejected_docs = retry_rejected_docs;
+        self
+    }
+
+    /// Sets the `_id` of the documents. A document already indexed is not
+    /// indexed again, nor counted as rejected.
+    pub fn with_doc_id(mut self, doc_id: Option<DocId>) -> Self {
+        self.doc_id = doc_id;
+        self
+    }
+
+    /// Routes the documents to the shard given by the value of this field.
+    pub fn with_routing_field(mut self, routing_field: Option<String>) -> Self {
+        self.routing_field = routing_field;
+        self
+    }
+
+    /// Creates the indexes with this many primary shards and replicas,
+    /// overriding the settings of the index config.
+    pub fn with_shards(
+        mut self,
+        num_shards: Option<u32>,
+        num_replicas: Option<u32>,
+    ) -> Self {
+        self.num_shards = num_shards;
+        self.num_replicas = num_replicas;
+        self
+    }
+
+    /// Runs the documents through this ingest pipeline, given as the
+    /// `pipeline` of the bulk requests.
+    pub fn with_pipeline(mut self, pipeline: Option<String>) -> Self {
+        if let Some(pipeline) = &pipeline {
+            self.ingest_url
+                .query_pairs_mut()
+                .append_pair("pipeline", pipeline);
+        }
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Streams the bulk requests with chunked transfer encoding rather than
+    /// sending them with a `Content-Length`.
+    pub fn with_chunked_transfer(mut self, chunked_transfer: bool) -> Self {
+        self.chunked_transfer = chunked_transfer;
+        self
+    }
+
+    /// Signs the requests with AWS SigV4, for Amazon OpenSearch Service and
+    /// OpenSearch Serverless.
+    pub fn with_aws_sigv4(mut self, aws_sigv4: Option<Arc<AwsSigV4>>) -> Self {
+        self.aws_sigv4 = aws_sigv4;
+        self
+    }
+
+    /// Sends the request, signed if `with_aws_sigv4` is set.
+    async fn execute(
+        &self,
+        request_builder: RequestBuilder,
+    ) -> anyhow::Result<Response> {
+        let Some(aws_sigv4) = &self.aws_sigv4 else {
+            return Ok(request_builder.send().await?);
+        };
+        let mut request = request_builder.build()?;
+        aws_sigv4.sign(&mut request, Utc::now())?;
+        Ok(self.client.execute(request).await?)
+    }
+
+    /// Creates or replaces the ingest pipeline of `with_pipeline` with this
+    /// definition, in YAML or JSON.
+    pub async fn put_pipeline(&self, pipeline_definition: &str) -> anyhow::Result<()> {
+        let Some(pipeline) = &self.pipeline else {
+            bail!("No ingest pipeline to put");
+        };
+        let pipeline_definition: Value = serde_yaml::from_str(pipeline_definition)
+            .context("Invalid ingest pipeline definition")?;
+        let response = self
+            .execute(
+                self.client
+                    .put(
+                        self.api_root_url
+                            .join(&format!("_ingest/pipeline/{pipeline}"))
+                            .expect("Invalid elastic URL"),
+                    )
+                    .json(&pipeline_definition),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            let status = response.status();
+            let response_body = response.text().await.unwrap_or_default();
+            error!(status=?status, body=response_body, "Elasticsearch API error");
+            bail!("Failed to put ingest pipeline {pipeline:?}, got status code {status}: {response_body}");
+        }
+        Ok(())
+    }
+
+    /// Reads these settings of the index (e.g. `index.refresh_interval`),
+    /// `null` for the ones left to their defaults.
+    pub async fn index_settings(
+        &self,
+        settings: &[&str],
+    ) -> anyhow::Result<Map<String, Value>> {
+        let mut settings_url = self
+            .index_url
+            .join(&format!("_settings/{}", settings.join(",")))
+            .expect("Invalid settings URL");
+        settings_url.set_query(Some("flat_settings=true"));
+        let response = self
+            .execute(self.client.get(settings_url))
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            let status = response.status();
+            let response_body = response.text().await.unwrap_or_default();
+            bail!("Failed to read the index settings, got status code {status}: {response_body}");
+        }
+        let response_json: Value = response.json().await?;
+        // Keyed by the index name, whatever its alias.
+        let index_settings = response_json
+            .as_object()
+            .and_then(|indexes| indexes.values().next())
+            .and_then(|index| index["settings"].as_object());
+        Ok(settings
+            .iter()
+            .map(|setting| {
+                let value = index_settings
+                    .and_then(|index_settings| index_settings.get(*setting))
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                (setting.to_string(), value)
+            })
+            .collect())
+    }
+
+    /// Updates the dynamic settings of the index, `null` resetting them to
+    /// their defaults.
+    pub async fn put_index_settings(
+        &self,
+        settings: &Map<String, Value>,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .execute(
+                self.client
+                    .put(
+                        self.index_url
+                            .join("_settings")
+                            .expect("Invalid settings URL"),
+                    )
+                    .json(settings),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            let status = response.status();
+            let response_body = response.text().await.unwrap_or_default();
+            error!(status=?status, body=response_body, "Elasticsearch API error");
+            bail!("Failed to update the index settings, got status code {status}: {response_body}");
+        }
+        Ok(())
+    }
+
+    /// Sends the documents in a bulk request, and returns the items rejected.
+    /// With chunked transfer, the payload is encoded chunk by chunk as it is
+    /// sent rather than held in memory next to the documents.
+    async fn bulk(&self, docs: &[Bytes]) -> anyhow::Result<Vec<RejectedItem>> {
+        let docs = docs.to_vec();
+        let doc_id = self.doc_id.clone();
+        let routing_field = self.routing_field.clone();
+        let request = self
+            .client
+            .post(self.ingest_url.clone())
+            .header(header::CONTENT_TYPE, "application/json");
+        let request = if self.chunked_transfer {
+            let chunks =
+                bulk_payload_chunks(docs, doc_id, routing_field, TRANSFER_CHUNK_SIZE)
+                    .map(|chunk_res| chunk_res.map_err(std::io::Error::other));
+            request.body(reqwest::Body::wrap_stream(futures::stream::iter(chunks)))
+        } else {
+            let payload = encode_blocking(move || {
+                bulk_payload(&docs, doc_id.as_ref(), routing_field.as_deref())
+            })
+            .await?;
+            request
+                .header(header::CONTENT_LENGTH, payload.len().to_string())
+                .body(ingest_body(payload.into(), false))
+        };
+        let response = self
+            .execute(request)
+            .await
+            .with_context(|| "elasticsearch request error")?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            let response_body = response.text().await.unwrap_or_default();
+            error!(status=?status, body=response_body, "Elasticsearch bulk request error");
+            bail!("Error on bulk request, got status code {status}: {response_body}");
+        }
+        let data: Value = response.json().await?;
+        rejected_items(&data)
+    }
+}
+
+impl ElasticsearchSink {
+    /// Keeps the documents rejected by a request that succeeded until they
+    /// are taken.
+    fn keep_rejected_docs(&self, request_rejected_docs: Vec<RejectedDoc>) {
+        let mut rejected_docs = self.rejected_docs.lock().unwrap();
+        let num_kept_docs = MAX_KEPT_REJECTED_DOCS.saturating_sub(rejected_docs.len());
+        rejected_docs.extend(request_rejected_docs.into_iter().take(num_kept_docs));
+    }
+}
+
+/// The documents of `rejected_items`, along with their errors.
+fn rejected_docs<'a>(
+    docs: &'a [Bytes],
+    rejected_items: &'a [RejectedItem],
+) -> impl Iterator<Item = RejectedDoc> + 'a {
+    rejected_items.iter().map(|item| RejectedDoc {
+        doc: docs[item.position].clone(),
+        error: item.error.to_string(),
+    })
+}
+
+/// The body of a bulk request creating the documents.
+fn bulk_payload(
+    docs: &[Bytes],
+    doc_id: Option<&DocId>,
+    routing_field: Option<&str>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut payload = Vec::new();
+    for doc in docs {
+        write_bulk_item(&mut payload, doc, doc_id, routing_field)?;
+    }
+    Ok(payload)
+}
+
+/// The body of a bulk request creating the documents, encoded lazily in
+/// chunks of at least `chunk_size` bytes, but the last one.
+fn bulk_payload_chunks(
+    docs: Vec<Bytes>,
+    doc_id: Option<DocId>,
+    routing_field: Option<String>,
+    chunk_size: usize,
+) -> impl Iterator<Item = anyhow::Result<Bytes>> {
+    let mut docs = docs.into_iter();
+    std::iter::from_fn(move || {
+        let mut chunk = Vec::new();
+        while chunk.len() < chunk_size {
+            let Some(doc) = docs.next() else {
+                break;
+            };
+            if let Err(error) = write_bulk_item(
+                &mut chunk,
+                &doc,
+                doc_id.as_ref(),
+                routing_field.as_deref(),
+            ) {
+                return Some(Err(error));
+            }
+        }
+        (!chunk.is_empty()).then(|| Ok(chunk.into()))
+    })
+}
+
+/// Appends the action creating the document, then the document, to the bulk
+/// request payload.
+fn write_bulk_item(
+    payload: &mut Vec<u8>,
+    doc: &Bytes,
+    doc_id: Option<&DocId>,
+    routing_field: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut action = Map::new();
+    if let Some(doc_id) = doc_id {
+        if let Some(doc_id) = doc_id.of(doc)? {
+            action.insert("_id".to_string(), Value::String(doc_id));
+        }
+    }
+    if let Some(routing_field) = routing_field {
+        if let Some(routing) = field_value(doc, routing_field)? {
+            action.insert("routing".to_string(), Value::String(routing));
+        }
+    }
+    if action.is_empty() {
+        writeln!(payload, r#"{{"create": {{  }}}}"#,)?;
+    } else {
+        writeln!(payload, "{}", json!({ "create": action }))?;
+    }
+    payload.extend_from_slice(doc);
+    payload.extend_from_slice(b"\n");
+    Ok(())
+}
+
+/// An item of a bulk request that failed.
+#[derive(Debug, PartialEq)]
+struct RejectedItem {
+    /// The position of the document in the request.
+    position: usize,
+    status: u16,
+    error: Value,
+}
+
+impl RejectedItem {
+    /// Whether sending the document again may succeed, as opposed to e.g. a
+    /// mapping error.
+    fn is_retryable(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS.as_u16() || self.status >= 500
+    }
+}
+
+/// The items of a bulk response that failed, if `errors` is set.
+fn rejected_items(response: &Value) -> anyhow::Result<Vec<RejectedItem>> {
+    if !response
+        .get("errors")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return Ok(Vec::new());
+    }
+    let items = response
+        .get("items")
+        .and_then(Value::as_array)
+        .context("Missing items in bulk response")?;
+    let mut rejected_items = Vec::new();
+    for (position, item) in items.iter().enumerate() {
+        // Each item is keyed by its action, e.g. `{"create": {...}}`.
+        let result = item
+            .as_object()
+            .and_then(|actions| actions.values().next())
+            .with_context(|| format!("Invalid bulk response item {item}"))?;
+        let Some(error) = result.get("error") else {
+            continue;
+        };
+        let status = result.get("status").and_then(Value::as_u64).unwrap_or(0) as u16;
+        // A document whose ID already exists was indexed by an earlier
+        // attempt.
+        if status == StatusCode::CONFLICT.as_u16() {
+            continue;
+        }
+        rejected_items.push(RejectedItem {
+            position,
+            status,
+            error: error.clone(),
+        });
+    }
+    Ok(rejected_items)
+}
+
+#[async_trait]
+impl Sink for ElasticsearchSink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let docs: Vec<Bytes> = document_batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .map(|line| document_batch.bytes.slice_ref(line))
+            .collect();
+        let num_docs = docs.len();
+        let mut pending_docs = docs;
+        let mut num_rejected_docs = 0;
+        // Only kept once the request succeeded: the whole batch of a failed
+        // request is dumped instead.
+        let mut request_rejected_docs = Vec::new();
+        let mut num_retries = 0;
+        let mut last_error = None;
+        loop {
+            let rejected_items = self.bulk(&pending_docs).await?;
+            let Some(first_rejected_item) = rejected_items.first() else {
+                break;
+            };
+            let retry = self.retry_rejected_docs
+                && num_retries < MAX_REJECTED_DOCS_RETRIES
+                && rejected_items.iter().any(RejectedItem::is_retryable);
+            if !retry {
+                warn!(
+                    num_rejected_docs = rejected_items.len(),
+                    num_docs = pending_docs.len(),
+                    first_error = %first_rejected_item.error,
+                    "Documents rejected by the bulk request"
+                );
+                last_error = Some(first_rejected_item.error.clone());
+                num_rejected_docs += rejected_items.len();
+                request_rejected_docs
+                    .extend(rejected_docs(&pending_docs, &rejected_items));
+                break;
+            }
+            num_retries += 1;
+            let (retryable_items, rejected_items): (Vec<_>, Vec<_>) = rejected_items
+                .into_iter()
+                .partition(RejectedItem::is_retryable);
+            num_rejected_docs += rejected_items.len();
+            request_rejected_docs.extend(rejected_docs(&pending_docs, &rejected_items));
+            info!(
+                num_retryable_docs = retryable_items.len(),
+                num_retries, "Retrying the documents rejected by the bulk request"
+            );
+            pending_docs = retryable_items
+                .iter()
+                .map(|item| pending_docs[item.position].clone())
+                .collect();
+            tokio::time::sleep(Duration::from_millis(300 * num_retries as u64)).await;
+        }
+        self.num_rejected_docs
+            .fetch_add(num_rejected_docs as u64, Ordering::Relaxed);
+        if num_docs > 0 && num_rejected_docs == num_docs {
+            bail!(
+                "All the {num_docs} documents of the bulk request were rejected: {}",
+                last_error.unwrap_or_default()
+            );
+        }
+        self.keep_rejected_docs(request_rejected_docs);
+        Ok(())
+    }
+
+    fn num_rejected_docs(&self) -> u64 {
+        self.num_rejected_docs.load(Ordering::Relaxed)
+    }
+
+    fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+        std::mem::take(&mut *self.rejected_docs.lock().unwrap())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        info!("Forcing commit to elasticsearch...");
+        let refresh_url = self
+            .index_url
+            .join("_refresh")
+            .expect("Invalid refresh URL");
+        let response = self
+            .execute(
+                self.client
+                    .post(refresh_url)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Vec::new()),
+            )
+            .await
+            .with_context(|| "elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Error on refresh, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+
+    async fn create_index(&self, index_config: &str) -> anyhow::Result<()> {
+        let mut index_config: serde_json::Value =
+            serde_yaml::from_str(index_config).context("Invalid index config")?;
+        let shard_settings = [
+            ("number_of_shards", self.num_shards),
+            ("number_of_replicas", self.num_replicas),
+        ];
+        for (setting, value) in shard_settings {
+            if let Some(value) = value {
+                set_index_setting(&mut index_config, setting, json!(value));
+            }
+        }
+        let response = self
+            .execute(
+                self.client
+                    .put(
+                        self.api_root_url
+                            .join(&self.index_id)
+                            .expect("Invalid elastic URL"),
+                    )
+                    .json(&index_config),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+
+    async fn delete_index(&self) -> anyhow::Result<bool> {
+        let response = self
+            .execute(
+                self.client.delete(
+                    self.api_root_url
+                        .join(&self.index_id)
+                        .expect("Invalid elastic URL"),
+                ),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => {
+                error!(resp=?response, "Elasticsearch API error");
+                bail!("http error with status code {}: {:?}", status, response);
+            },
+        }
+    }
+
+    async fn force_merge(&self) -> anyhow::Result<()> {
+        info!("Force merge segments into one...");
+        let force_merge_url = self
+            .index_url
+            .join("_forcemerge")
+            .expect("Invalid force merge URL");
+        // Elasticsearch 7.7+ runs the merge as a task, polled below. The other
+        // versions reject `wait_for_completion` and answer once the merge is
+        // done.
+        let mut query = vec![("max_num_segments", "1")];
+        if self.flavor().await?.is_at_least(7, 7) {
+            query.push(("wait_for_completion", "false"));
+        }
+        let response = self
+            .execute(
+                self.client
+                    .post(force_merge_url)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Vec::new())
+                    .query(&query)
+                    .timeout(FORCE_MERGE_TIMEOUT),
+            )
+            .await
+            .with_context(|| "elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Error on force merge, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let data: Value = response.json().await?;
+        // The blocking force merge is done already.
+        let Some(task_id) = data["task"].as_str() else {
+            return Ok(());
+        };
+        let task_url = self
+            .api_root_url
+            .join(&format!("_tasks/{task_id}"))
+            .expect("Invalid task URL");
+        wait_until(FORCE_MERGE_TIMEOUT, POLL_INTERVAL, || async {
+            let response = self
+                .execute(self.client.get(task_url.clone()))
+                .await
+                .with_context(|| "elasticsearch request error")?;
+            if response.status() != StatusCode::OK {
+                bail!(
+                    "Error polling the force merge task, got status code {}",
+                    response.status()
+                );
+            }
+            let task: Value = response.json().await?;
+            if !task["error"].is_null() {
+                bail!("Force merge failed: {}", task["error"]);
+            }
+            let num_failed_shards = task["response"]["_shards"]["failed"]
+                .as_u64()
+                .unwrap_or_default();
+            if num_failed_shards > 0 {
+                bail!("Force merge failed on {num_failed_shards} shards");
+            }
+            Ok(task["completed"] == true)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        info!("Fetching index info from elasticsearch  commit to elasticsearch...");
+        let mut describe_url = self.index_url.join("_stats").unwrap();
+        describe_url.set_query(Some("level=shards"));
+        let response = self
+            .execute(
+                self.client
+                    .get(describe_url)
+                    .header(header::CONTENT_TYPE, "application/json"),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let total = &data["_all"]["total"];
+        let num_docs = total["docs"]["count"]
+            .as_u64()
+            .expect("docs count field must be a u64");
+        let num_splits = total["segments"]["count"]
+            .as_u64()
+            .expect("segments count field must be a u64");
+        let num_bytes = total["store"]["size_in_bytes"]
+            .as_u64()
+            .expect("segments memory_in_bytes field must be a u64");
+
+        Ok(IndexInfo {
+            num_docs,
+            num_bytes,
+            num_splits,
+            split_breakdown: None,
+            engine_specific: index_stats(&data),
+        })
+    }
+
+    async fn check_health(&self) -> anyhow::Result<()> {
+        let response = self
+            .execute(
+                self.client.get(
+                    self.api_root_url
+                        .join("_cluster/health")
+                        .expect("Invalid elastic URL"),
+                ),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            bail!("http error with status code {}", response.status());
+        }
+        let health: serde_json::Value = response.json().await?;
+        // Yellow clusters, e.g. single nodes with replicas configured, index
+        // fine.
+        if health["status"] == "red" {
+            bail!("cluster health is red");
+        }
+        Ok(())
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let data = self.fetch_root().await?;
+        let version = data["version"]["number"]
+            .as_str()
+            .expect("version field must be a string")
+            .to_string();
+        let build_hash = data["version"]["build_hash"]
+            .as_str()
+            .expect("build_hash field must be a string")
+            .to_string();
+        let build_type = data["version"]["build_type"]
+            .as_str()
+            .expect("build_type field must be a string")
+            .to_string();
+        let build_date = data["version"]["build_date"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let flavor = Flavor::parse(&data["version"])?;
+        if flavor.distribution != self.expected_distribution {
+            warn!(
+                distribution=?flavor.distribution,
+                expected_distribution=?self.expected_distribution,
+                "The cluster distribution does not match the benchmarked engine"
+            );
+        }
+        let _ = self.flavor.set(flavor);
+        Ok(BuildInfo {
+            version,
+            commit_date: build_date,
+            commit_hash: build_hash,
+            build_target: build_type,
+        })
+    }
+
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        let EngineQuery::Elasticsearch(search_request) = query else {
+            bail!("Elasticsearch only runs queries in the Elasticsearch DSL");
+        };
+        let response = self
+            .execute(
+                self.client
+                    .post(self.index_url.join("_search").expect("Invalid search URL"))
+                    .json(search_request),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let mut data: serde_json::Value = response.json().await?;
+        let hits = match data["hits"]["hits"].take() {
+            serde_json::Value::Array(hits) => hits,
+            _ => Vec::new(),
+        };
+        // The total is left out when `track_total_hits` is false.
+        let num_hits = data["hits"]["total"]["value"]
+            .as_u64()
+            .unwrap_or(hits.len() as u64);
+        let doc_ids = hits
+            .iter()
+            .map(|hit| hit["_id"].as_str().unwrap_or_default().to_string())
+            .collect();
+        let docs = hits
+            .into_iter()
+            .map(|mut hit| hit["_source"].take())
+            .collect();
+        Ok(SearchResponse {
+            num_hits,
+            doc_ids,
+            docs,
+            aggregations: data.get_mut("aggregations").map(serde_json::Value::take),
+        })
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        let response = self
+            .execute(
+                self.client.get(
+                    self.api_root_url
+                        .join("_nodes/stats/indices,jvm,process,transport,http")
+                        .expect("Invalid nodes stats URL"),
+                ),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        Ok(sum_node_stats(&data))
+    }
+
+    async fn clear_caches(&self) -> anyhow::Result<()> {
+        // Clears the request, query and fielddata caches, not the OS page
+        // cache.
+        let response = self
+            .execute(
+                self.client.post(
+                    self.index_url
+                        .join("_cache/clear")
+                        .expect("Invalid cache clear URL"),
+                ),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+
+    async fn switch_alias(&self, alias: &str) -> anyhow::Result<()> {
+        let alias_url = self
+            .api_root_url
+            .join(&format!("_alias/{alias}"))
+            .expect("Invalid alias URL");
+        let response = self
+            .execute(self.client.get(alias_url))
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        // 404 means that the alias does not exist yet.
+        let current_indices: Vec<String> = match response.status() {
+            StatusCode::OK => {
+                let data: serde_json::Value = response.json().await?;
+                data.as_object()
+                    .map(|indices| indices.keys().cloned().collect())
+                    .unwrap_or_default()
+            },
+            StatusCode::NOT_FOUND => Vec::new(),
+            status => {
+                error!(resp=?response, "Elasticsearch API error");
+                bail!("http error with status code {}: {:?}", status, response);
+            },
+        };
+        let mut actions: Vec<serde_json::Value> = current_indices
+            .iter()
+            .filter(|index_id| **index_id != self.index_id)
+            .map(|index_id| json!({"remove": {"index": index_id, "alias": alias}}))
+            .collect();
+        actions.push(json!({
+            "add": {"index": self.index_id, "alias": alias, "is_write_index": true}
+        }));
+        // All actions of a single `_aliases` request are applied atomically.
+        let response = self
+            .execute(
+                self.client
+                    .post(
+                        self.api_root_url
+                            .join("_aliases")
+                            .expect("Invalid alias URL"),
+                    )
+                    .json(&json!({ "actions": actions })),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "Error on alias update, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+
+    async fn apply_retention(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        // OpenSearch replaced ILM with its own index state management plugin.
+        let poll_interval_setting = match self.flavor().await?.distribution {
+            Distribution::Elasticsearch => {
+                self.attach_ilm_delete_policy().await?;
+                // ILM only checks policies every 10 minutes by default, which
+                // would dominate the measurement.
+                ("indices.lifecycle.poll_interval", json!("1s"))
+            },
+            Distribution::Opensearch => {
+                self.attach_ism_delete_policy().await?;
+                // In minutes, 1 being the minimum.
+                ("plugins.index_state_management.job_interval", json!(1))
+            },
+        };
+        self.put_cluster_setting(poll_interval_setting.0, poll_interval_setting.1)
+            .await?;
+        // The whole index gets deleted, which releases the storage right away.
+        let wait_res = wait_until(timeout, POLL_INTERVAL, || async {
+            let response = self
+                .execute(self.client.head(self.index_url.clone()))
+                .await
+                .with_context(|| "Elasticsearch request error")?;
+            Ok(response.status() == StatusCode::NOT_FOUND)
+        })
+        .await;
+        self.put_cluster_setting(poll_interval_setting.0, json!(null))
+            .await?;
+        let elapsed = wait_res?;
+        Ok(RetentionTimings {
+            delete_applied_secs: elapsed.as_secs_f64(),
+            storage_reclaimed_secs: elapsed.as_secs_f64(),
+        })
+    }
+}
+
+impl ElasticsearchSink {
+    async fn fetch_root(&self) -> anyhow::Result<serde_json::Value> {
+        let response = self
+            .execute(
+                self.client
+                    .get(self.api_root_url.clone())
+                    .header(header::CONTENT_TYPE, "application/json"),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn flavor(&self) -> anyhow::Result<Flavor> {
+        let flavor = self
+            .flavor
+            .get_or_try_init(|| async {
+                let data = self.fetch_root().await?;
+                Flavor::parse(&data["version"])
+            })
+            .await?;
+        Ok(*flavor)
+    }
+
+    async fn attach_ilm_delete_policy(&self) -> anyhow::Result<()> {
+        if !self.flavor().await?.is_at_least(6, 6) {
+            bail!("ILM is not available before Elasticsearch 6.6");
+        }
+        let policy_url = self
+            .api_root_url
+            .join(&format!("_ilm/policy/{RETENTION_POLICY_NAME}"))
+            .expect("Invalid ILM URL");
+        let policy = json!({
+            "policy": {
+                "phases": {"delete": {"min_age": "0ms", "actions": {"delete": {}}}}
+            }
+        });
+        let response = self
+            .execute(self.client.put(policy_url).json(&policy))
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "Error on ILM policy creation, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        info!("Attaching ILM delete policy to the index...");
+        let response = self
+            .execute(
+                self.client
+                    .put(
+                        self.index_url
+                            .join("_settings")
+                            .expect("Invalid settings URL"),
+                    )
+                    .json(&json!({ "index.lifecycle.name": RETENTION_POLICY_NAME })),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "Error on index settings update, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+
+    async fn attach_ism_delete_policy(&self) -> anyhow::Result<()> {
+        let policy_url = self
+            .api_root_url
+            .join(&format!("_plugins/_ism/policies/{RETENTION_POLICY_NAME}"))
+            .expect("Invalid ISM URL");
+        let policy = json!({
+            "policy": {
+                "description": "Deletes the index right away.",
+                "default_state": "delete",
+                "states": [{"name": "delete", "actions": [{"delete": {}}], "transitions": []}]
+            }
+        });
+        let response = self
+            .execute(self.client.put(policy_url).json(&policy))
+            .await
+            .with_context(|| "OpenSearch request error")?;
+        // 409 means the policy was already created by a previous run.
+        if !matches!(
+            response.status(),
+            StatusCode::OK | StatusCode::CREATED | StatusCode::CONFLICT
+        ) {
+            error!(resp=?response, "OpenSearch API error");
+            bail!(
+                "Error on ISM policy creation, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        info!("Attaching ISM delete policy to the index...");
+        let response = self
+            .execute(
+                self.client
+                    .post(
+                        self.api_root_url
+                            .join(&format!("_plugins/_ism/add/{}", self.index_id))
+                            .expect("Invalid ISM URL"),
+                    )
+                    .json(&json!({ "policy_id": RETENTION_POLICY_NAME })),
+            )
+            .await
+            .with_context(|| "OpenSearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "OpenSearch API error");
+            bail!(
+                "Error on ISM policy attachment, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        if data["failures"].as_bool() == Some(true) {
+            error!(data=?data, "Failures contained in ISM add response");
+            bail!("Error on ISM policy attachment");
+        }
+        Ok(())
+    }
+
+    /// Sets (or resets to its default with `null`) a persistent cluster setting.
+    async fn put_cluster_setting(
+        &self,
+        name: &str,
+        value: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let response = self
+            .execute(
+                self.client
+                    .put(
+                        self.api_root_url
+                            .join("_cluster/settings")
+                            .expect("Invalid cluster settings URL"),
+                    )
+                    .json(&json!({ "persistent": { name: value } })),
+            )
+            .await
+            .with_context(|| "Elasticsearch request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Elasticsearch API error");
+            bail!(
+                "Error on cluster settings update, got status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The `_nodes/stats` counters making up the engine stats.
+const NODE_STATS_PATHS: &[&str] = &[
+    "indices.indexing.index_total",
+    "indices.indexing.index_time_in_millis",
+    "indices.indexing.throttle_time_in_millis",
+    "indices.merges.total",
+    "indices.merges.total_time_in_millis",
+    "indices.merges.total_size_in_bytes",
+    "indices.merges.total_throttled_time_in_millis",
+    "indices.refresh.total",
+    "indices.refresh.total_time_in_millis",
+    "indices.flush.total",
+    "indices.flush.total_time_in_millis",
+    "jvm.gc.collectors.young.collection_count",
+    "jvm.gc.collectors.young.collection_time_in_millis",
+    "jvm.gc.collectors.old.collection_count",
+    "jvm.gc.collectors.old.collection_time_in_millis",
+    "process.cpu.total_in_millis",
+    "transport.rx_size_in_bytes",
+    "transport.tx_size_in_bytes",
+    "http.total_opened",
+];
+
+/// Sums the counters of `NODE_STATS_PATHS` over the nodes of a `_nodes/stats`
+/// response.
+fn sum_node_stats(nodes_stats: &Value) -> EngineStats {
+    let mut engine_stats = EngineStats::new();
+    let Some(nodes) = nodes_stats["nodes"].as_object() else {
+        return engine_stats;
+    };
+    for node_stats in nodes.values() {
+        for path in NODE_STATS_PATHS {
+            let pointer = format!("/{}", path.replace('.', "/"));
+            if let Some(value) = node_stats.pointer(&pointer).and_then(Value::as_f64) {
+                *engine_stats.entry(path.to_string()).or_insert(0.0) += value;
+            }
+        }
+    }
+    engine_stats
+}
+
+/// Sets an `index.*` setting of an index config, replacing it wherever the
+/// config had it: settings can be written as `{"index": {"setting": ...}}`,
+/// `{"index.setting": ...}` or `{"setting": ...}`.
+fn set_index_setting(index_config: &mut Value, setting: &str, value: Value) {
+    if !index_config["settings"].is_object() {
+        index_config["settings"] = json!({});
+    }
+    let settings = &mut index_config["settings"];
+    if let Some(index_settings) =
+        settings.get_mut("index").and_then(Value::as_object_mut)
+    {
+        index_settings.remove(setting);
+    }
+    if let Some(settings) = settings.as_object_mut() {
+        settings.remove(&format!("index.{setting}"));
+        settings.insert(setting.to_string(), value);
+    }
+}
+
+/// The paths of the index stats recorded in `IndexInfo::engine_specific`,
+/// under the totals of the `_stats` response.
+const INDEX_STATS_PATHS: &[&str] = &[
+    "segments.count",
+    "translog.operations",
+    "translog.size_in_bytes",
+    "translog.uncommitted_operations",
+    "translog.uncommitted_size_in_bytes",
+    "refresh.total",
+    "refresh.total_time_in_millis",
+    "merges.total",
+    "merges.total_time_in_millis",
+    "merges.total_size_in_bytes",
+    "flush.total",
+];
+
+/// The `INDEX_STATS_PATHS` of a `_stats?level=shards` response, the segment
+/// count of each primary shard as `shards.<id>.segments.count`, summed over
+/// the indexes matched by the index ID, and the number of primary shards and
+/// of replicas of each.
+fn index_stats(stats: &Value) -> EngineStats {
+    let mut index_stats = EngineStats::new();
+    for path in INDEX_STATS_PATHS {
+        let pointer = format!("/_all/total/{}", path.replace('.', "/"));
+        if let Some(value) = stats.pointer(&pointer).and_then(Value::as_f64) {
+            index_stats.insert(path.to_string(), value);
+        }
+    }
+    let Some(indices) = stats["indices"].as_object() else {
+        return index_stats;
+    };
+    for index in indices.values() {
+        let Some(shards) = index["shards"].as_object() else {
+            continue;
+        };
+        for (shard_id, shard_copies) in shards {
+            let num_segments = shard_copies
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|shard_copy| shard_copy["routing"]["primary"] == true)
+                .filter_map(|shard_copy| shard_copy["segments"]["count"].as_f64())
+                .sum::<f64>();
+            *index_stats
+                .entry(format!("shards.{shard_id}.segments.count"))
+                .or_insert(0.0) += num_segments;
+            *index_stats
+                .entry("number_of_shards".to_string())
+                .or_insert(0.0) += 1.0;
+        }
+    }
+    // `_shards.total` counts the shard copies, assigned or not.
+    if let (Some(num_shards), Some(num_shard_copies)) = (
+        index_stats.get("number_of_shards").copied(),
+        stats["_shards"]["total"].as_f64(),
+    ) {
+        index_stats.insert(
+            "number_of_replicas".to_string(),
+            num_shard_copies / num_shards - 1.0,
+        );
+    }
+    index_stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_payload_chunks() {
+        let docs: Vec<Bytes> = (0..5)
+            .map(|idx| Bytes::from(format!(r#"{{"id": "{idx}", "host": "h{idx}"}}"#)))
+            .collect();
+        let doc_id = DocId::Field("id".to_string());
+        let payload = bulk_payload(&docs, Some(&doc_id), Some("host")).unwrap();
+        let chunks: Vec<Bytes> = bulk_payload_chunks(
+            docs.clone(),
+            Some(doc_id),
+            Some("host".to_string()),
+            100,
+        )
+        .collect::<anyhow::Result<_>>()
+        .unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk| chunk.len() >= 100));
+        assert_eq!(chunks.concat(), payload);
+        assert_eq!(bulk_payload_chunks(Vec::new(), None, None, 100).count(), 0);
+    }
+
+    #[test]
+    fn test_set_index_setting() {
+        let mut index_config = json!({"mappings": {}});
+        set_index_setting(&mut index_config, "number_of_shards", json!(3));
+        assert_eq!(index_config["settings"], json!({"number_of_shards": 3}));
+
+        let mut index_config = json!({"settings": {
+            "index": {"number_of_shards": 1, "refresh_interval": "5s"},
+            "index.number_of_replicas": 1,
+        }});
+        set_index_setting(&mut index_config, "number_of_shards", json!(3));
+        set_index_setting(&mut index_config, "number_of_replicas", json!(0));
+        assert_eq!(
+            index_config["settings"],
+            json!({
+                "index": {"refresh_interval": "5s"},
+                "number_of_shards": 3,
+                "number_of_replicas": 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_index_stats() {
+        let shard_copy = |primary: bool, num_segments: u64| json!({"routing": {"primary": primary}, "segments": {"count": num_segments}});
+        let stats = json!({
+            "_shards": {"total": 4, "successful": 3},
+            "_all": {"total": {
+                "segments": {"count": 7},
+                "translog": {"size_in_bytes": 2048, "operations": 10},
+                "refresh": {"total": 4},
+            }},
+            "indices": {"logs": {"shards": {
+                "0": [shard_copy(true, 3), shard_copy(false, 2)],
+                "1": [shard_copy(true, 2)],
+            }}},
+        });
+        assert_eq!(
+            index_stats(&stats),
+            EngineStats::from([
+                ("number_of_replicas".to_string(), 1.0),
+                ("number_of_shards".to_string(), 2.0),
+                ("refresh.total".to_string(), 4.0),
+                ("segments.count".to_string(), 7.0),
+                ("shards.0.segments.count".to_string(), 3.0),
+                ("shards.1.segments.count".to_string(), 2.0),
+                ("translog.operations".to_string(), 10.0),
+                ("translog.size_in_bytes".to_string(), 2048.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_sum_node_stats() {
+        let node_stats = |gc_millis: u64, num_merges: u64| {
+            json!({
+                "jvm": {"gc": {"collectors": {"young": {"collection_time_in_millis": gc_millis}}}},
+                "indices": {"merges": {"total": num_merges, "current": 1}},
+            })
+        };
+        let nodes_stats = json!({
+            "nodes": {"node-a": node_stats(100, 3), "node-b": node_stats(20, 1)}
+        });
+        assert_eq!(
+            sum_node_stats(&nodes_stats),
+            EngineStats::from([
+                ("indices.merges.total".to_string(), 4.0),
+                (
+                    "jvm.gc.collectors.young.collection_time_in_millis".to_string(),
+                    120.0
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rejected_items() {
+        assert_eq!(
+            rejected_items(&json!({"errors": false, "items": []})).unwrap(),
+            []
+        );
+        let response = json!({
+            "errors": true,
+            "items": [
+                {"create": {"status": 201}},
+                {"create": {"status": 400, "error": {"type": "mapper_parsing_exception"}}},
+                {"create": {"status": 429, "error": {"type": "es_rejected_execution_exception"}}},
+                {"create": {"status": 409, "error": {"type": "version_conflict_engine_exception"}}},
+            ]
+        });
+        let items = rejected_items(&response).unwrap();
+        assert_eq!(
+            items,
+            [
+                RejectedItem {
+                    position: 1,
+                    status: 400,
+                    error: json!({"type": "mapper_parsing_exception"}),
+                },
+                RejectedItem {
+                    position: 2,
+                    status: 429,
+                    error: json!({"type": "es_rejected_execution_exception"}),
+                },
+            ]
+        );
+        assert!(!items[0].is_retryable());
+        assert!(items[1].is_retryable());
+        assert!(rejected_items(&json!({"errors": true})).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use http::StatusCode;
+use reqwest::{Client, Url};
+
+use super::elasticsearch::{Distribution, ElasticsearchSink};
+use super::{base_url, BuildInfo, IndexInfo, RejectedDoc, Sink};
+use crate::source::DocumentBatch;
+
+/// Where to find the index stats and version of an ES-compatible engine.
+///
+/// Endpoints are paths relative to the host in which `{index}` is replaced by
+/// the index ID, and fields are located in their JSON responses with JSON
+/// pointers (RFC 6901).
+#[derive(Debug, Clone)]
+pub struct EsCompatibleEndpoints {
+    pub stats_endpoint: String,
+    pub num_docs_pointer: String,
+    pub num_bytes_pointer: String,
+    pub num_splits_pointer: String,
+    pub version_endpoint: String,
+    pub version_pointer: String,
+}
+
+/// Sink for engines exposing an Elasticsearch-compatible `_bulk` API, but
+/// different stats and build endpoints.
+pub struct EsCompatibleSink {
+    bulk_sink: ElasticsearchSink,
+    stats_url: Url,
+    version_url: Url,
+    endpoints: EsCompatibleEndpoints,
+    client: Client,
+}
+
+impl EsCompatibleSink {
+    pub fn new(
+        host: &str,
+        index_id: &str,
+        endpoints: EsCompatibleEndpoints,
+        client: Client,
+    ) -> Self {
+        let bulk_sink = ElasticsearchSink::new(
+            host,
+            index_id,
+            None,
+            Distribution::Elasticsearch,
+            client.clone(),
+        );
+        let endpoint_url = |endpoint: &str| {
+            let path = endpoint.replace("{index}", index_id);
+            Url::parse(&format!(
+                "{}/{}",
+                base_url(host),
+                path.trim_start_matches('/')
+            ))
+            .expect("Invalid endpoint URL")
+        };
+        Self {
+            bulk_sink,
+            stats_url: endpoint_url(&endpoints.stats_endpoint),
+            version_url: endpoint_url(&endpoints.version_endpoint),
+            endpoints,
+            client,
+        }
+    }
+
+    async fn get_json(&self, url: &Url) -> anyhow::Result<serde_json::Value> {
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .with_context(|| "ES-compatible request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "ES-compatible API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// Reads a number located by `pointer`, also accepting numbers serialized as
+/// strings.
+pub(super) fn u64_at(data: &serde_json::Value, pointer: &str) -> u64 {
+    let value = data.pointer(pointer);
+    let number = value.and_then(|value| {
+        value
+            .as_u64()
+            .or_else(|| value.as_f64().map(|number| number as u64))
+            .or_else(|| value.as_str().and_then(|number| number.parse().ok()))
+    });
+    number.unwrap_or_else(|| {
+        warn!(pointer, value=?value, "No number found in stats response");
+        0
+    })
+}
+
+#[async_trait]
+impl Sink for EsCompatibleSink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        self.bulk_sink.send(document_batch).await
+    }
+
+    fn num_rejected_docs(&self) -> u64 {
+        self.bulk_sink.num_rejected_docs()
+    }
+
+    fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+        self.bulk_sink.take_rejected_docs()
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        // Not all ES-compatible engines implement `_refresh`.
+        if let Err(err) = self.bulk_sink.commit().await {
+            warn!(err=?err, "Failed to refresh the index, ignoring");
+        }
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let data = self.get_json(&self.stats_url).await?;
+        Ok(IndexInfo {
+            num_docs: u64_at(&data, &self.endpoints.num_docs_pointer),
+            num_splits: u64_at(&data, &self.endpoints.num_splits_pointer),
+            num_bytes: u64_at(&data, &self.endpoints.num_bytes_pointer),
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let data = self.get_json(&self.version_url).await?;
+        let version = match data.pointer(&self.endpoints.version_pointer) {
+            Some(serde_json::Value::String(version)) => version.clone(),
+            Some(version) => version.to_string(),
+            None => "".to_string(),
+        };
+        Ok(BuildInfo {
+            version,
+            commit_date: "".to_string(),
+            commit_hash: "".to_string(),
+            build_target: "".to_string(),
+        })
+    }
+}
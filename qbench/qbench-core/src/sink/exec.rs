@@ -0,0 +1,225 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use super::{BuildInfo, IndexInfo, Sink};
+use crate::source::DocumentBatch;
+
+/// Sends the documents to an external command, to benchmark an engine
+/// without a built-in sink. The command speaks a line protocol over its
+/// stdin and stdout, one request at a time:
+///
+/// - qbench writes a JSON request line, `{"op": "send", "num_bytes": N}`
+///   followed by the N bytes of the NDJSON batch, or `{"op": "commit"}`,
+///   `{"op": "index_info"}` or `{"op": "build_info"}`.
+/// - the command answers each request with a JSON line: `{"error": "..."}` if
+///   it failed, else `{}` for `commit`, `{"num_rejected_docs": 0}` (optional)
+///   for `send`, `{"num_docs": 0, "num_splits": 0, "num_bytes": 0}` for
+///   `index_info` and `{"version": "..."}` for `build_info`.
+///
+/// The command runs in `sh`, with the index ID in `QBENCH_INDEX` and the
+/// `--host`, if any, in `QBENCH_HOST`. Its stderr goes to qbench's.
+pub struct ExecSink {
+    command: String,
+    process: Mutex<ExecProcess>,
+    num_rejected_docs: AtomicU64,
+}
+
+struct ExecProcess {
+    // Killed when the sink is dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+#[derive(Deserialize)]
+struct SendResponse {
+    #[serde(default)]
+    num_rejected_docs: u64,
+}
+
+#[derive(Deserialize)]
+struct IndexInfoResponse {
+    num_docs: u64,
+    #[serde(default)]
+    num_splits: u64,
+    #[serde(default)]
+    num_bytes: u64,
+}
+
+#[derive(Deserialize)]
+struct BuildInfoResponse {
+    version: String,
+    #[serde(default)]
+    commit_date: String,
+    #[serde(default)]
+    commit_hash: String,
+    #[serde(default)]
+    build_target: String,
+}
+
+impl ExecSink {
+    pub fn spawn(
+        command: &str,
+        index: &str,
+        host: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let mut child_command = Command::new("sh");
+        child_command
+            .arg("-c")
+            .arg(command)
+            .env("QBENCH_INDEX", index)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(host) = host {
+            child_command.env("QBENCH_HOST", host);
+        }
+        let mut child = child_command
+            .spawn()
+            .with_context(|| format!("Failed to run sink command `{command}`"))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        info!(command, "Started sink command");
+        Ok(Self {
+            command: command.to_string(),
+            process: Mutex::new(ExecProcess {
+                _child: child,
+                stdin,
+                stdout,
+            }),
+            num_rejected_docs: AtomicU64::new(0),
+        })
+    }
+
+    /// Writes the request and the payload following it, and reads the
+    /// response.
+    async fn request<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: Value,
+        payload: &[u8],
+    ) -> anyhow::Result<T> {
+        let op = request["op"].clone();
+        let mut process = self.process.lock().await;
+        let mut request_line = serde_json::to_vec(&request)?;
+        request_line.push(b'\n');
+        process.stdin.write_all(&request_line).await?;
+        process.stdin.write_all(payload).await?;
+        process.stdin.flush().await.with_context(|| {
+            format!("Failed to write to sink command `{}`", self.command)
+        })?;
+        let mut response_line = String::new();
+        if process.stdout.read_line(&mut response_line).await? == 0 {
+            bail!("Sink command `{}` exited", self.command);
+        }
+        let response: Value =
+            serde_json::from_str(&response_line).with_context(|| {
+                format!("Invalid response to {op} from sink command: {response_line:?}")
+            })?;
+        if let Some(error) = response.get("error") {
+            bail!("Sink command failed to {op}: {error}");
+        }
+        serde_json::from_value(response)
+            .with_context(|| format!("Invalid response to {op} from sink command"))
+    }
+}
+
+#[async_trait]
+impl Sink for ExecSink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let response: SendResponse = self
+            .request(
+                json!({"op": "send", "num_bytes": document_batch.bytes.len()}),
+                &document_batch.bytes,
+            )
+            .await?;
+        self.num_rejected_docs
+            .fetch_add(response.num_rejected_docs, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn num_rejected_docs(&self) -> u64 {
+        self.num_rejected_docs.load(Ordering::Relaxed)
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        let _: Value = self.request(json!({"op": "commit"}), &[]).await?;
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let response: IndexInfoResponse =
+            self.request(json!({"op": "index_info"}), &[]).await?;
+        Ok(IndexInfo {
+            num_docs: response.num_docs,
+            num_splits: response.num_splits,
+            num_bytes: response.num_bytes,
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let response: BuildInfoResponse =
+            self.request(json!({"op": "build_info"}), &[]).await?;
+        Ok(BuildInfo {
+            version: response.version,
+            commit_date: response.commit_date,
+            commit_hash: response.commit_hash,
+            build_target: response.build_target,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    /// Counts the bytes it is sent, and fails the commit if there were none.
+    const COUNTING_COMMAND: &str = r#"
+        num_bytes=0
+        while IFS= read -r request; do
+            case "$request" in
+            *'"send"'*)
+                batch_num_bytes=$(echo "$request" | sed 's/.*"num_bytes":\([0-9]*\).*/\1/')
+                head -c "$batch_num_bytes" > /dev/null
+                num_bytes=$((num_bytes + batch_num_bytes))
+                echo '{"num_rejected_docs": 1}';;
+            *'"commit"'*)
+                if [ "$num_bytes" -eq 0 ]; then echo '{"error": "nothing to commit"}'; else echo '{}'; fi;;
+            *'"index_info"'*)
+                echo "{\"num_docs\": 2, \"num_bytes\": $num_bytes}";;
+            *'"build_info"'*)
+                echo "{\"version\": \"$QBENCH_INDEX-1.0\"}";;
+            esac
+        done
+    "#;
+
+    #[tokio::test]
+    async fn test_exec_sink() {
+        let sink = ExecSink::spawn(COUNTING_COMMAND, "logs", None).unwrap();
+        assert_eq!(sink.build_info().await.unwrap().version, "logs-1.0");
+        let error = sink.commit().await.unwrap_err();
+        assert!(error.to_string().contains("nothing to commit"));
+        let batch = DocumentBatch {
+            bytes: Bytes::from_static(b"{\"a\": 1}\n{\"a\": 2}\n"),
+            last: false,
+        };
+        sink.send(&batch).await.unwrap();
+        sink.send(&batch).await.unwrap();
+        sink.commit().await.unwrap();
+        let index_info = sink.index_info().await.unwrap();
+        assert_eq!(index_info.num_docs, 2);
+        assert_eq!(index_info.num_bytes, 2 * batch.bytes.len() as u64);
+        assert_eq!(sink.num_rejected_docs(), 2);
+    }
+}
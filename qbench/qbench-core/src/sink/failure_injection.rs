@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    BuildInfo,
+    IndexInfo,
+    RejectedDoc,
+    RetentionTimings,
+    SearchResponse,
+    Sink,
+    Throttling,
+};
+use crate::engine_metrics::EngineStats;
+use crate::query::EngineQuery;
+use crate::source::DocumentBatch;
+
+/// What happens to the requests picked by a `FailureInjectingSink`, written
+/// as in the spec: a status code or `delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum FailureKind {
+    /// The request fails with this HTTP status code, without reaching the
+    /// engine.
+    Status(u16),
+    /// The request is sent after `delay_ms`.
+    Delay,
+}
+
+impl FromStr for FailureKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delay" => Ok(FailureKind::Delay),
+            _ => match s.parse::<u16>() {
+                Ok(status_code) if (400..600).contains(&status_code) => {
+                    Ok(FailureKind::Status(status_code))
+                },
+                _ => Err(format!("Unknown failure kind {s:?}")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FailureKind::Status(status_code) => write!(f, "{status_code}"),
+            FailureKind::Delay => write!(f, "delay"),
+        }
+    }
+}
+
+impl From<FailureKind> for String {
+    fn from(failure_kind: FailureKind) -> Self {
+        failure_kind.to_string()
+    }
+}
+
+impl TryFrom<String> for FailureKind {
+    type Error = String;
+
+    fn try_from(failure_kind: String) -> Result<Self, Self::Error> {
+        failure_kind.parse()
+    }
+}
+
+/// The `--inject-failures` spec, e.g. `rate=0.01,kind=500`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureInjection {
+    /// The fraction of the requests failed or delayed.
+    pub rate: f64,
+    pub kind: FailureKind,
+    /// The delay of the `delay` failures.
+    pub delay_ms: u64,
+    /// Defaults to the seed of the run.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl FromStr for FailureInjection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut failure_injection = FailureInjection {
+            rate: 0.01,
+            kind: FailureKind::Status(500),
+            delay_ms: 1000,
+            seed: None,
+        };
+        for key_value in s.split(',').filter(|key_value| !key_value.is_empty()) {
+            let Some((key, value)) = key_value.split_once('=') else {
+                return Err(format!("Expected `key=value`, got {key_value:?}"));
+            };
+            let invalid = || format!("Invalid {key} {value:?}");
+            match key {
+                "rate" => {
+                    failure_injection.rate = value.parse().map_err(|_| invalid())?
+                },
+                "kind" => failure_injection.kind = value.parse()?,
+                "delay_ms" => {
+                    failure_injection.delay_ms = value.parse().map_err(|_| invalid())?
+                },
+                "seed" => {
+                    failure_injection.seed = Some(value.parse().map_err(|_| invalid())?)
+                },
+                _ => return Err(format!("Unknown failure injection key {key:?}")),
+            }
+        }
+        if !(0.0..=1.0).contains(&failure_injection.rate) {
+            return Err(format!(
+                "The failure rate must be between 0 and 1, got {}",
+                failure_injection.rate
+            ));
+        }
+        Ok(failure_injection)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FailureInjectionStats {
+    pub num_requests: AtomicU64,
+    pub num_injected_failures: AtomicU64,
+    pub num_injected_delays: AtomicU64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FailureInjectionReport {
+    #[serde(flatten)]
+    pub failure_injection: FailureInjection,
+    pub num_requests: u64,
+    pub num_injected_failures: u64,
+    pub num_injected_delays: u64,
+}
+
+/// Randomly fails or delays the batches sent to the wrapped sink, to exercise
+/// the retry path and compare how the engines' client-visible behavior
+/// degrades. Everything but `send` goes to the wrapped sink untouched.
+///
+/// The picks are seeded and derived from the batch content and the number of
+/// times it was sent before, rather than drawn in the order the concurrent
+/// requests happen to be sent, so that runs are reproducible.
+pub struct FailureInjectingSink {
+    sink: Box<dyn Sink>,
+    failure_injection: FailureInjection,
+    /// The number of times each batch content, by hash, was sent.
+    num_sends: Mutex<HashMap<u64, u64>>,
+    stats: Arc<FailureInjectionStats>,
+}
+
+impl FailureInjectingSink {
+    pub fn new(sink: Box<dyn Sink>, failure_injection: FailureInjection) -> Self {
+        Self {
+            sink,
+            failure_injection,
+            num_sends: Mutex::default(),
+            stats: Arc::default(),
+        }
+    }
+
+    /// Whether the batch is failed or delayed this time.
+    fn is_picked(&self, document_batch: &DocumentBatch) -> bool {
+        let content_hash = hash_u64(&[&document_batch.bytes]);
+        let send_idx = {
+            let mut num_sends = self.num_sends.lock().unwrap();
+            let num_content_sends = num_sends.entry(content_hash).or_default();
+            *num_content_sends += 1;
+            *num_content_sends - 1
+        };
+        let seed = self.failure_injection.seed.unwrap_or(0);
+        let pick_seed = hash_u64(&[
+            &seed.to_le_bytes(),
+            &content_hash.to_le_bytes(),
+            &send_idx.to_le_bytes(),
+        ]);
+        StdRng::seed_from_u64(pick_seed).gen_bool(self.failure_injection.rate)
+    }
+
+    /// The counters of the sink, readable once it is boxed.
+    pub fn stats(&self) -> Arc<FailureInjectionStats> {
+        self.stats.clone()
+    }
+}
+
+/// The first 8 bytes of the blake3 hash of the concatenated parts.
+fn hash_u64(parts: &[&[u8]]) -> u64 {
+    let mut hasher = blake3::Hasher::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let hash = hasher.finalize();
+    u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap())
+}
+
+impl FailureInjection {
+    pub fn report(&self, stats: &FailureInjectionStats) -> FailureInjectionReport {
+        FailureInjectionReport {
+            failure_injection: self.clone(),
+            num_requests: stats.num_requests.load(Ordering::Relaxed),
+            num_injected_failures: stats.num_injected_failures.load(Ordering::Relaxed),
+            num_injected_delays: stats.num_injected_delays.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for FailureInjectingSink {
+    fn batch_size(&self) -> usize {
+        self.sink.batch_size()
+    }
+
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        self.stats.num_requests.fetch_add(1, Ordering::Relaxed);
+        if self.is_picked(document_batch) {
+            match self.failure_injection.kind {
+                FailureKind::Status(status_code) => {
+                    self.stats
+                        .num_injected_failures
+                        .fetch_add(1, Ordering::Relaxed);
+                    bail!("http error with status code {status_code}: injected failure");
+                },
+                FailureKind::Delay => {
+                    self.stats
+                        .num_injected_delays
+                        .fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(Duration::from_millis(
+                        self.failure_injection.delay_ms,
+                    ))
+                    .await;
+                },
+            }
+        }
+        self.sink.send(document_batch).await
+    }
+
+    fn num_rejected_docs(&self) -> u64 {
+        self.sink.num_rejected_docs()
+    }
+
+    fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+        self.sink.take_rejected_docs()
+    }
+
+    fn throttling(&self) -> Throttling {
+        self.sink.throttling()
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        self.sink.commit().await
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        self.sink.index_info().await
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        self.sink.build_info().await
+    }
+
+    async fn check_health(&self) -> anyhow::Result<()> {
+        self.sink.check_health().await
+    }
+
+    async fn create_index(&self, index_config: &str) -> anyhow::Result<()> {
+        self.sink.create_index(index_config).await
+    }
+
+    async fn delete_index(&self) -> anyhow::Result<bool> {
+        self.sink.delete_index().await
+    }
+
+    async fn switch_alias(&self, alias: &str) -> anyhow::Result<()> {
+        self.sink.switch_alias(alias).await
+    }
+
+    async fn force_merge(&self) -> anyhow::Result<()> {
+        self.sink.force_merge().await
+    }
+
+    async fn apply_retention(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        self.sink.apply_retention(timeout).await
+    }
+
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        self.sink.search(query).await
+    }
+
+    async fn clear_caches(&self) -> anyhow::Result<()> {
+        self.sink.clear_caches().await
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        self.sink.engine_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::sink::NullSink;
+
+    #[tokio::test]
+    async fn test_failure_injecting_sink() {
+        let failure_injection: FailureInjection =
+            "rate=0.25,kind=503,seed=7".parse().unwrap();
+        assert_eq!(failure_injection.kind, FailureKind::Status(503));
+        assert_eq!(failure_injection.delay_ms, 1000);
+        let batch = DocumentBatch {
+            bytes: Bytes::from_static(b"{}\n"),
+            last: false,
+        };
+        // The same seed fails the same requests.
+        let mut failed_requests = Vec::new();
+        for _ in 0..2 {
+            let sink =
+                FailureInjectingSink::new(Box::new(NullSink), failure_injection.clone());
+            let mut failed = Vec::new();
+            for _ in 0..100 {
+                failed.push(sink.send(&batch).await.is_err());
+            }
+            let report = failure_injection.report(&sink.stats());
+            assert_eq!(report.num_requests, 100);
+            assert_eq!(
+                report.num_injected_failures,
+                failed.iter().filter(|failed| **failed).count() as u64
+            );
+            assert!((10..40).contains(&report.num_injected_failures));
+            failed_requests.push(failed);
+        }
+        assert_eq!(failed_requests[0], failed_requests[1]);
+
+        // The picks don't depend on the order of the requests.
+        let batches: Vec<DocumentBatch> = (0..20)
+            .map(|batch_idx| DocumentBatch {
+                bytes: Bytes::from(format!("{{\"batch\": {batch_idx}}}\n")),
+                last: false,
+            })
+            .collect();
+        let mut failed_batches_by_order = Vec::new();
+        for batch_idxs in [(0..20).collect::<Vec<usize>>(), (0..20).rev().collect()] {
+            let sink =
+                FailureInjectingSink::new(Box::new(NullSink), failure_injection.clone());
+            let mut failed_batches = Vec::new();
+            for batch_idx in batch_idxs {
+                if sink.send(&batches[batch_idx]).await.is_err() {
+                    failed_batches.push(batch_idx);
+                }
+            }
+            failed_batches.sort();
+            failed_batches_by_order.push(failed_batches);
+        }
+        assert!(!failed_batches_by_order[0].is_empty());
+        assert_eq!(failed_batches_by_order[0], failed_batches_by_order[1]);
+
+        assert!("rate=2".parse::<FailureInjection>().is_err());
+        assert!("kind=200".parse::<FailureInjection>().is_err());
+        assert!("rate=0.1,speed=3".parse::<FailureInjection>().is_err());
+        assert_eq!(
+            "kind=delay".parse::<FailureInjection>().unwrap().kind,
+            FailureKind::Delay
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use reqwest::{header, Client, Url};
+
+use super::{base_url, BuildInfo, IndexInfo, RetentionTimings, SearchResponse, Sink};
+use crate::engine_metrics::EngineStats;
+use crate::query::EngineQuery;
+use crate::source::{DocumentBatch, DEFAULT_MAX_BODY_SIZE};
+use crate::utils::wait_until;
+
+pub const VECTOR_DEFAULT_HOST: &str = "127.0.0.1:8080";
+pub const FLUENT_BIT_DEFAULT_HOST: &str = "127.0.0.1:9880";
+
+/// Fluent Bit's HTTP input rejects bodies larger than its `buffer_max_size`,
+/// 4MB by default.
+const FLUENT_BIT_MAX_BODY_SIZE: usize = 3_000_000;
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A log agent receiving documents over HTTP and shipping them to an engine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Agent {
+    /// Vector's `http_server` source, which must be configured with the
+    /// `json` decoding codec and `newline_delimited` framing.
+    Vector,
+    /// Fluent Bit's `http` input. The documents are tagged `qbench`.
+    FluentBit,
+}
+
+impl Agent {
+    pub fn default_host(&self) -> &'static str {
+        match self {
+            Agent::Vector => VECTOR_DEFAULT_HOST,
+            Agent::FluentBit => FLUENT_BIT_DEFAULT_HOST,
+        }
+    }
+}
+
+impl Display for Agent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl FromStr for Agent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vector" => Ok(Agent::Vector),
+            "fluent-bit" => Ok(Agent::FluentBit),
+            _ => Err(format!("Unknown agent {s:?}")),
+        }
+    }
+}
+
+impl AsRef<str> for Agent {
+    fn as_ref(&self) -> &str {
+        match self {
+            Agent::Vector => "vector",
+            Agent::FluentBit => "fluent-bit",
+        }
+    }
+}
+
+/// Sends the documents to a log agent instead of the engine directly, to
+/// measure end-to-end pipelines where the agent sits between the producer and
+/// the engine.
+///
+/// Everything but ingestion goes to the engine's sink: committing waits for
+/// the agent to have delivered all the forwarded documents to the engine.
+pub struct ForwardingSink {
+    agent: Agent,
+    ingest_url: Url,
+    client: Client,
+    engine_sink: Box<dyn Sink>,
+    drain_timeout: Duration,
+    num_forwarded_docs: AtomicU64,
+}
+
+impl ForwardingSink {
+    pub fn new(
+        agent: Agent,
+        host: &str,
+        engine_sink: Box<dyn Sink>,
+        drain_timeout: Duration,
+        client: Client,
+    ) -> Self {
+        let path = match agent {
+            Agent::Vector => "",
+            // The path is used as the tag of the records.
+            Agent::FluentBit => "qbench",
+        };
+        let ingest_url = Url::parse(&format!("{}/{path}", base_url(host)))
+            .expect("Invalid agent URL");
+        Self {
+            agent,
+            ingest_url,
+            client,
+            engine_sink,
+            drain_timeout,
+            num_forwarded_docs: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for ForwardingSink {
+    fn batch_size(&self) -> usize {
+        match self.agent {
+            Agent::Vector => DEFAULT_MAX_BODY_SIZE,
+            Agent::FluentBit => FLUENT_BIT_MAX_BODY_SIZE,
+        }
+    }
+
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let docs = document_batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty());
+        let mut num_docs = 0;
+        let body = match self.agent {
+            Agent::Vector => {
+                num_docs = docs.count() as u64;
+                document_batch.bytes.clone()
+            },
+            // Fluent Bit does not accept NDJSON, but a JSON array of records.
+            Agent::FluentBit => {
+                let mut body = Vec::with_capacity(document_batch.bytes.len() + 2);
+                body.push(b'[');
+                for doc in docs {
+                    if num_docs > 0 {
+                        body.push(b',');
+                    }
+                    body.extend_from_slice(doc);
+                    num_docs += 1;
+                }
+                body.push(b']');
+                Bytes::from(body)
+            },
+        };
+        let response = self
+            .client
+            .post(self.ingest_url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("{} request error", self.agent))?;
+        if !response.status().is_success() {
+            error!(resp=?response, "{} API error", self.agent);
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        self.num_forwarded_docs
+            .fetch_add(num_docs, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        let num_forwarded_docs = self.num_forwarded_docs.load(Ordering::Relaxed);
+        info!(
+            num_forwarded_docs,
+            "Waiting for {} to deliver the documents...", self.agent
+        );
+        // The engine is committed at every poll, otherwise engines that only
+        // expose committed documents would never see the count go up.
+        let drain_res = wait_until(self.drain_timeout, DRAIN_POLL_INTERVAL, || async {
+            self.engine_sink.commit().await?;
+            let index_info = self.engine_sink.index_info().await?;
+            Ok(index_info.num_docs >= num_forwarded_docs)
+        })
+        .await;
+        match drain_res {
+            Ok(drain_duration) => {
+                info!(drain_duration=?drain_duration, "{} delivered all the documents", self.agent);
+            },
+            Err(error) => {
+                // Documents may have been dropped or rejected along the way,
+                // which the results will show.
+                warn!(error=?error, "{} did not deliver all the documents", self.agent);
+            },
+        }
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        self.engine_sink.index_info().await
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        self.engine_sink.build_info().await
+    }
+
+    async fn switch_alias(&self, alias: &str) -> anyhow::Result<()> {
+        self.engine_sink.switch_alias(alias).await
+    }
+
+    async fn force_merge(&self) -> anyhow::Result<()> {
+        self.engine_sink.force_merge().await
+    }
+
+    async fn apply_retention(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        self.engine_sink.apply_retention(timeout).await
+    }
+
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        self.engine_sink.search(query).await
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        self.engine_sink.engine_stats().await
+    }
+}
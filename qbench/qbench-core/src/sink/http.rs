@@ -0,0 +1,342 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, Method, Url};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::es_compatible::u64_at;
+use super::{BuildInfo, IndexInfo, Sink};
+use crate::source::DocumentBatch;
+
+/// How the documents of a batch are wrapped in a request body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyFormat {
+    /// The documents as is, one JSON document per line.
+    Ndjson,
+    /// A JSON array of the documents.
+    Array,
+    /// The `line_field` of the documents as plain text, one per line, for raw
+    /// log endpoints.
+    Lines,
+}
+
+/// An endpoint returning a JSON document, and the JSON pointers (RFC 6901) of
+/// the values read in it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonEndpoint {
+    pub url: String,
+    #[serde(default)]
+    pub num_docs_pointer: Option<String>,
+    #[serde(default)]
+    pub num_bytes_pointer: Option<String>,
+    #[serde(default)]
+    pub version_pointer: Option<String>,
+}
+
+/// The `--http-sink-spec` of a REST ingestion API. In the URLs, `{host}` and
+/// `{index}` are replaced by `--host` and `--index`.
+///
+/// ```yaml
+/// url: http://{host}/api/{index}/_json
+/// method: POST
+/// headers:
+///   Authorization: Basic cm9vdDpwYXNz
+/// body: array
+/// array_field: records
+/// stats:
+///   url: http://{host}/api/{index}/_stats
+///   num_docs_pointer: /doc_count
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpSinkSpec {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default = "default_body_format")]
+    pub body: BodyFormat,
+    /// Wraps the array of documents in an object under this key, e.g.
+    /// `{"records": [...]}`. `array` bodies only.
+    #[serde(default)]
+    pub array_field: Option<String>,
+    /// The field sent as a line of a `lines` body.
+    #[serde(default = "default_line_field")]
+    pub line_field: String,
+    /// Requested with the same method and headers on commit, if set.
+    #[serde(default)]
+    pub commit_url: Option<String>,
+    /// Where to read the number of documents and bytes of the index. Without
+    /// it, the documents accepted by the endpoint are counted instead.
+    #[serde(default)]
+    pub stats: Option<JsonEndpoint>,
+    /// Where to read the version of the engine.
+    #[serde(default)]
+    pub version: Option<JsonEndpoint>,
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+fn default_body_format() -> BodyFormat {
+    BodyFormat::Ndjson
+}
+
+fn default_line_field() -> String {
+    "message".to_string()
+}
+
+impl HttpSinkSpec {
+    /// Loads the spec from a YAML (or JSON) file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let spec = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read HTTP sink spec {path:?}"))?;
+        serde_yaml::from_str(&spec)
+            .with_context(|| format!("Invalid HTTP sink spec {path:?}"))
+    }
+
+    /// The request body of the documents of the batch, and its content type.
+    fn encode_body(&self, batch: &[u8]) -> anyhow::Result<(Vec<u8>, &'static str)> {
+        let lines = batch
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty());
+        match self.body {
+            BodyFormat::Ndjson => Ok((batch.to_vec(), "application/x-ndjson")),
+            BodyFormat::Array => {
+                let docs = lines
+                    .map(serde_json::from_slice)
+                    .collect::<Result<Vec<Value>, _>>()
+                    .context("Failed to parse document line as JSON")?;
+                let body = match &self.array_field {
+                    Some(array_field) => serde_json::to_vec(
+                        &serde_json::json!({ array_field.as_str(): docs }),
+                    )?,
+                    None => serde_json::to_vec(&docs)?,
+                };
+                Ok((body, "application/json"))
+            },
+            BodyFormat::Lines => {
+                let mut body = Vec::with_capacity(batch.len());
+                for line in lines {
+                    let doc: Value = serde_json::from_slice(line)
+                        .context("Failed to parse document line as JSON")?;
+                    match doc.get(&self.line_field) {
+                        Some(Value::String(text)) => {
+                            body.extend_from_slice(text.as_bytes())
+                        },
+                        Some(value) => {
+                            body.extend_from_slice(value.to_string().as_bytes())
+                        },
+                        None => bail!("Document without `{}` field", self.line_field),
+                    }
+                    body.push(b'\n');
+                }
+                Ok((body, "text/plain"))
+            },
+        }
+    }
+}
+
+/// Sends the documents to a REST ingestion API described by an
+/// `HttpSinkSpec`, for the engines whose API only differs by its URL and
+/// payload envelope.
+pub struct HttpSink {
+    spec: HttpSinkSpec,
+    url: Url,
+    method: Method,
+    headers: HeaderMap,
+    commit_url: Option<Url>,
+    client: Client,
+    num_sent_docs: AtomicU64,
+}
+
+impl HttpSink {
+    pub fn new(
+        spec: HttpSinkSpec,
+        host: &str,
+        index_id: &str,
+        client: Client,
+    ) -> anyhow::Result<Self> {
+        let expand_url = |url: &str| {
+            let url = url.replace("{host}", host).replace("{index}", index_id);
+            Url::parse(&url).with_context(|| format!("Invalid HTTP sink URL {url:?}"))
+        };
+        let method = Method::from_bytes(spec.method.to_uppercase().as_bytes())
+            .with_context(|| format!("Invalid HTTP method {:?}", spec.method))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &spec.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("Invalid header name {name:?}"))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("Invalid value of header {name:?}"))?,
+            );
+        }
+        let mut spec = spec;
+        for endpoint in spec.stats.iter_mut().chain(spec.version.iter_mut()) {
+            endpoint.url = expand_url(&endpoint.url)?.to_string();
+        }
+        Ok(Self {
+            url: expand_url(&spec.url)?,
+            commit_url: spec.commit_url.as_deref().map(expand_url).transpose()?,
+            method,
+            headers,
+            spec,
+            client,
+            num_sent_docs: AtomicU64::new(0),
+        })
+    }
+
+    async fn request(
+        &self,
+        url: &Url,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> anyhow::Result<()> {
+        let mut headers = self.headers.clone();
+        if !headers.contains_key(CONTENT_TYPE) {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+        }
+        let response = self
+            .client
+            .request(self.method.clone(), url.clone())
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| "HTTP sink request error")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let response_body = response.text().await.unwrap_or_default();
+            error!(status=?status, body=response_body, "HTTP sink API error");
+            bail!("http error with status code {status}: {response_body}");
+        }
+        Ok(())
+    }
+
+    async fn get_json(&self, url: &str) -> anyhow::Result<Value> {
+        let response = self
+            .client
+            .get(url)
+            .headers(self.headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+#[async_trait]
+impl Sink for HttpSink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let num_docs = document_batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .count() as u64;
+        let (body, content_type) = self.spec.encode_body(&document_batch.bytes)?;
+        self.request(&self.url, body, content_type).await?;
+        self.num_sent_docs.fetch_add(num_docs, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        if let Some(commit_url) = &self.commit_url {
+            self.request(commit_url, Vec::new(), "application/json")
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let Some(stats) = &self.spec.stats else {
+            return Ok(IndexInfo {
+                num_docs: self.num_sent_docs.load(Ordering::Relaxed),
+                num_splits: 0,
+                num_bytes: 0,
+                split_breakdown: None,
+                engine_specific: Default::default(),
+            });
+        };
+        let data = self.get_json(&stats.url).await?;
+        let u64_at_pointer = |pointer: &Option<String>| {
+            pointer
+                .as_deref()
+                .map(|pointer| u64_at(&data, pointer))
+                .unwrap_or_default()
+        };
+        Ok(IndexInfo {
+            num_docs: u64_at_pointer(&stats.num_docs_pointer),
+            num_splits: 0,
+            num_bytes: u64_at_pointer(&stats.num_bytes_pointer),
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let version = match &self.spec.version {
+            Some(version_endpoint) => {
+                let data = self.get_json(&version_endpoint.url).await?;
+                match version_endpoint
+                    .version_pointer
+                    .as_deref()
+                    .and_then(|pointer| data.pointer(pointer))
+                {
+                    Some(Value::String(version)) => version.clone(),
+                    Some(version) => version.to_string(),
+                    None => "".to_string(),
+                }
+            },
+            None => "unknown".to_string(),
+        };
+        Ok(BuildInfo {
+            version,
+            commit_date: "".to_string(),
+            commit_hash: "".to_string(),
+            build_target: "".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_sink_spec() {
+        let spec: HttpSinkSpec = serde_yaml::from_str(
+            "url: http://{host}/api/{index}/_json\nbody: array\narray_field: records\n",
+        )
+        .unwrap();
+        assert_eq!(spec.method, "POST");
+        let batch = b"{\"message\": \"a\", \"level\": 1}\n\n{\"message\": \"b\"}\n";
+        let (body, content_type) = spec.encode_body(batch).unwrap();
+        assert_eq!(content_type, "application/json");
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            serde_json::json!({"records": [{"message": "a", "level": 1}, {"message": "b"}]})
+        );
+
+        let spec: HttpSinkSpec = serde_yaml::from_str(
+            "url: http://{host}/logs\nbody: lines\nheaders:\n  X-Token: t\n",
+        )
+        .unwrap();
+        assert_eq!(spec.encode_body(batch).unwrap().0, b"a\nb\n");
+        let sink = HttpSink::new(spec, "127.0.0.1:8080", "logs", Client::new()).unwrap();
+        assert_eq!(sink.url.as_str(), "http://127.0.0.1:8080/logs");
+        assert_eq!(sink.headers["x-token"], "t");
+
+        assert!(serde_yaml::from_str::<HttpSinkSpec>("url: x\nbody: xml\n").is_err());
+        assert!(serde_yaml::from_str::<HttpSinkSpec>("url: x\nmethd: PUT\n").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use http::{header, StatusCode};
+use reqwest::{Client, Url};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use super::{BuildInfo, IndexInfo, Sink};
+use crate::source::DocumentBatch;
+use crate::utils::CachedToken;
+
+/// Streaming ingestion requests are limited to 4MB of data.
+const MAX_STREAMING_INGEST_SIZE: usize = 4_000_000;
+
+/// How the sink authenticates against the cluster.
+pub enum AadAuth {
+    /// No authentication, e.g. for the Kusto emulator.
+    None,
+    /// A pre-obtained bearer token.
+    Token(String),
+    /// An AAD application using the client credentials flow.
+    ClientCredentials {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+}
+
+/// Sink for Azure Data Explorer (Kusto), using the streaming ingestion REST
+/// API. Streaming ingestion must be enabled on the cluster and on the target
+/// table (or database).
+pub struct KustoSink {
+    cluster_url: Url,
+    ingest_url: Url,
+    mgmt_url: Url,
+    database: String,
+    table: String,
+    auth: AadAuth,
+    token: Mutex<Option<CachedToken>>,
+    client: Client,
+}
+
+impl KustoSink {
+    pub fn new(
+        host: &str,
+        database: &str,
+        table: &str,
+        mapping: Option<&str>,
+        auth: AadAuth,
+        client: Client,
+    ) -> Self {
+        debug!(host=?host, database=?database, table=?table, "kusto client");
+        let cluster_url = if host.contains("://") {
+            Url::parse(host)
+        } else {
+            Url::parse(&format!("https://{host}"))
+        }
+        .expect("Invalid kusto URL");
+        let mut ingest_url = cluster_url
+            .join(&format!("v1/rest/ingest/{database}/{table}"))
+            .expect("Invalid kusto URL");
+        ingest_url
+            .query_pairs_mut()
+            .append_pair("streamFormat", "multijson");
+        if let Some(mapping) = mapping {
+            ingest_url
+                .query_pairs_mut()
+                .append_pair("mappingName", mapping);
+        }
+        let mgmt_url = cluster_url.join("v1/rest/mgmt").expect("Invalid kusto URL");
+        Self {
+            cluster_url,
+            ingest_url,
+            mgmt_url,
+            database: database.to_string(),
+            table: table.to_string(),
+            auth,
+            token: Mutex::new(None),
+            client,
+        }
+    }
+
+    /// Returns the `Authorization` header value, fetching a new AAD token if
+    /// the cached one is about to expire.
+    async fn authorization(&self) -> anyhow::Result<Option<String>> {
+        let (tenant_id, client_id, client_secret) = match &self.auth {
+            AadAuth::None => return Ok(None),
+            AadAuth::Token(token) => return Ok(Some(format!("Bearer {token}"))),
+            AadAuth::ClientCredentials {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => (tenant_id, client_id, client_secret),
+        };
+        let mut cached_token = self.token.lock().await;
+        if let Some(token) = cached_token.as_ref().and_then(CachedToken::get) {
+            return Ok(Some(format!("Bearer {token}")));
+        }
+        info!("Fetching AAD token...");
+        let token_url =
+            format!("https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/token");
+        let scope = format!(
+            "{}/.default",
+            self.cluster_url.as_str().trim_end_matches('/')
+        );
+        let response = self
+            .client
+            .post(token_url)
+            .form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+                ("scope", &scope),
+            ])
+            .send()
+            .await
+            .with_context(|| "AAD token request error")?;
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Failed to fetch AAD token, got status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let token = data["access_token"]
+            .as_str()
+            .context("access_token field must be a string")?
+            .to_string();
+        let expires_in = data["expires_in"].as_u64().unwrap_or(3600);
+        let authorization = format!("Bearer {token}");
+        *cached_token = Some(CachedToken::new(token, Duration::from_secs(expires_in)));
+        Ok(Some(authorization))
+    }
+
+    /// Runs a management command and returns the first table of the (v1)
+    /// response as a list of rows keyed by column name.
+    async fn management_command(
+        &self,
+        command: &str,
+    ) -> anyhow::Result<Vec<serde_json::Map<String, serde_json::Value>>> {
+        let mut request = self
+            .client
+            .post(self.mgmt_url.clone())
+            .json(&json!({ "db": self.database, "csl": command }));
+        if let Some(authorization) = self.authorization().await? {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| "Kusto request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Kusto API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let table = &data["Tables"][0];
+        let columns: Vec<&str> = table["Columns"]
+            .as_array()
+            .context("Columns field must be an array")?
+            .iter()
+            .map(|column| column["ColumnName"].as_str().unwrap_or_default())
+            .collect();
+        let rows = table["Rows"]
+            .as_array()
+            .context("Rows field must be an array")?
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .zip(row.as_array().into_iter().flatten())
+                    .map(|(column, value)| (column.to_string(), value.clone()))
+                    .collect()
+            })
+            .collect();
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl Sink for KustoSink {
+    fn batch_size(&self) -> usize {
+        MAX_STREAMING_INGEST_SIZE
+    }
+
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let mut request = self
+            .client
+            .post(self.ingest_url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(document_batch.bytes.clone());
+        if let Some(authorization) = self.authorization().await? {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| "Kusto request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Kusto streaming ingestion error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        // Streamed data is queryable as soon as the request is acknowledged.
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let rows = self
+            .management_command(&format!(".show table ['{}'] details", self.table))
+            .await?;
+        let Some(details) = rows.first() else {
+            bail!("No details returned for table {}", self.table);
+        };
+        let num_docs = details["TotalRowCount"]
+            .as_u64()
+            .context("TotalRowCount field must be a u64")?;
+        let num_splits = details["TotalExtents"]
+            .as_u64()
+            .context("TotalExtents field must be a u64")?;
+        let num_bytes = details["TotalExtentSize"]
+            .as_f64()
+            .context("TotalExtentSize field must be a number")?
+            as u64;
+        Ok(IndexInfo {
+            num_docs,
+            num_splits,
+            num_bytes,
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let rows = self.management_command(".show version").await?;
+        let Some(version) = rows.first() else {
+            bail!("No version returned by the cluster");
+        };
+        let field = |name: &str| version[name].as_str().unwrap_or_default().to_string();
+        Ok(BuildInfo {
+            version: field("BuildVersion"),
+            commit_date: field("BuildTime"),
+            commit_hash: "".to_string(),
+            build_target: field("ServiceType"),
+        })
+    }
+}
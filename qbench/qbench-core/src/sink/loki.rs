@@ -0,0 +1,727 @@
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead as _, BufReader};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use fnv::{FnvHashMap, FnvHasher};
+use reqwest::{header, Client, StatusCode, Url};
+
+use super::{base_url, encode_blocking, BuildInfo, IndexInfo, SearchResponse, Sink};
+use crate::engine_metrics::{fetch_counters, EngineStats};
+use crate::query::EngineQuery;
+use crate::source::DocumentBatch;
+
+pub struct LokiSink {
+    push_url: Url,
+    metrics_url: Url,
+    version_url: Url,
+    ready_url: Url,
+    flush_url: Url,
+    query_range_url: Url,
+    client: Client,
+    labeler: StreamLabeler,
+    /// The tenants the batches are pushed to in turn, in `X-Scope-OrgID`.
+    /// Empty for a single-tenant Loki.
+    tenants: Vec<String>,
+    next_tenant: AtomicUsize,
+}
+
+/// The header naming the tenant of a multi-tenant Loki.
+const TENANT_HEADER: &str = "X-Scope-OrgID";
+
+/// The counters of the distributor recorded per tenant, as
+/// `tenants.<tenant>.<counter>` in the index stats.
+const TENANT_COUNTERS: &[(&str, &str)] = &[
+    ("loki_distributor_lines_received_total", "lines_received"),
+    ("loki_distributor_bytes_received_total", "bytes_received"),
+];
+
+/// Picks the stream of the documents. Cloned into the blocking tasks encoding
+/// the push requests.
+#[derive(Clone)]
+struct StreamLabeler {
+    /// The document field whose value labels the stream of the document, and
+    /// its label name.
+    routing: Option<(String, String)>,
+    num_streams: usize,
+    /// The round-robin counter of the documents without a routing value.
+    next_stream: Arc<AtomicUsize>,
+}
+
+/// The label telling apart the streams the documents are spread over.
+const STREAM_SHARD_LABEL: &str = "stream_shard";
+
+impl LokiSink {
+    pub fn new(host: &str, client: Client) -> Self {
+        debug!(host=?host, "loko client");
+        let base_url = base_url(host);
+        let push_url =
+            Url::parse(&format!("{base_url}/loki/api/v1/push")).expect("Invalid URL");
+        let metrics_url =
+            Url::parse(&format!("{base_url}/metrics")).expect("Invalid URL");
+        let flush_url = Url::parse(&format!("{base_url}/flush")).expect("Invalid URL");
+        let ready_url = Url::parse(&format!("{base_url}/ready")).expect("Invalid URL");
+        let version_url =
+            Url::parse(&format!("{base_url}/loki/api/v1/status/buildinfo"))
+                .expect("Invalid URL");
+        let query_range_url = Url::parse(&format!("{base_url}/loki/api/v1/query_range"))
+            .expect("Invalid URL");
+
+        Self {
+            push_url,
+            metrics_url,
+            version_url,
+            ready_url,
+            flush_url,
+            query_range_url,
+            client,
+            labeler: StreamLabeler {
+                routing: None,
+                num_streams: 1,
+                next_stream: Arc::new(AtomicUsize::new(0)),
+            },
+            tenants: Vec::new(),
+            next_tenant: AtomicUsize::new(0),
+        }
+    }
+
+    /// Pushes the batches to `num_tenants` tenants in turn, `tenant-0`,
+    /// `tenant-1`..., to simulate multi-tenant ingestion. Loki must run with
+    /// `auth_enabled: true`.
+    pub fn with_num_tenants(mut self, num_tenants: usize) -> anyhow::Result<Self> {
+        if num_tenants == 0 {
+            bail!("The number of Loki tenants must be at least 1");
+        }
+        self.tenants = (0..num_tenants)
+            .map(|tenant_idx| format!("tenant-{tenant_idx}"))
+            .collect();
+        Ok(self)
+    }
+
+    /// The tenant of the next batch, round-robin.
+    fn next_tenant(&self) -> Option<&str> {
+        if self.tenants.is_empty() {
+            return None;
+        }
+        let tenant_idx = self.next_tenant.fetch_add(1, Ordering::Relaxed);
+        Some(&self.tenants[tenant_idx % self.tenants.len()])
+    }
+
+    /// Spreads the documents over streams labeled with the value of this
+    /// field. The documents lacking it go to the default stream.
+    pub fn with_routing_field(mut self, routing_field: Option<String>) -> Self {
+        self.labeler.routing = routing_field.map(|routing_field| {
+            // Label names are restricted to `[a-zA-Z_][a-zA-Z0-9_]*`.
+            let mut label_name: String = routing_field
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if label_name.starts_with(|c: char| c.is_ascii_digit()) {
+                label_name.insert(0, '_');
+            }
+            (routing_field, label_name)
+        });
+        self
+    }
+
+    /// Spreads the documents over `num_streams` streams labeled with
+    /// `stream_shard`, on the hash of their routing value or round-robin.
+    /// Loki rate-limits each stream, a single one would throttle the ingestion.
+    pub fn with_num_streams(mut self, num_streams: usize) -> anyhow::Result<Self> {
+        if num_streams == 0 {
+            bail!("The number of Loki streams must be at least 1");
+        }
+        self.labeler.num_streams = num_streams;
+        Ok(self)
+    }
+
+    async fn push(&self, body: String) -> anyhow::Result<()> {
+        let mut request = self
+            .client
+            .post(self.push_url.clone())
+            .header("Content-Type", "application/json");
+        if let Some(tenant) = self.next_tenant() {
+            request = request.header(TENANT_HEADER, tenant);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .with_context(|| "Failed to send data to Loki")?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
+            _ => {
+                let error_msg = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Failed to read response text".to_string());
+                bail!("Failed to push logs to Loki: {}", error_msg)
+            },
+        }
+    }
+}
+
+impl StreamLabeler {
+    /// The label value of the stream of the document, None for the default
+    /// stream.
+    fn stream_label_value(&self, json: &Value) -> Option<String> {
+        let routing_value = self.routing.as_ref().and_then(|(routing_field, _)| {
+            match json.get(routing_field) {
+                Some(Value::String(value)) => Some(value.clone()),
+                Some(Value::Number(value)) => Some(value.to_string()),
+                _ => None,
+            }
+        });
+        if self.num_streams == 1 {
+            return routing_value;
+        }
+        let stream_idx = match routing_value {
+            Some(routing_value) => {
+                let mut hasher = FnvHasher::default();
+                routing_value.hash(&mut hasher);
+                hasher.finish() as usize
+            },
+            None => self.next_stream.fetch_add(1, Ordering::Relaxed),
+        } % self.num_streams;
+        Some(stream_idx.to_string())
+    }
+
+    /// The name of the label set by `stream_label_value`.
+    fn stream_label_name(&self) -> Option<&str> {
+        if self.num_streams > 1 {
+            return Some(STREAM_SHARD_LABEL);
+        }
+        self.routing
+            .as_ref()
+            .map(|(_, label_name)| label_name.as_str())
+    }
+
+    /// Loki Format
+    /// {
+    ///   "streams": [
+    ///     {
+    ///       "stream": {
+    ///         "app": "myApp",
+    ///         "environment": "production"
+    ///       },
+    ///       "values
+    ///         ["1605117339000000000", "This is a log line", {"key": "value"}],
+    ///         ["1605117340000000000", "This is another log line", {"key": "value"}],
+    ///       ]
+    ///     }
+    ///   ]
+    /// }
+    fn encode_push_body(&self, batch: &[u8]) -> anyhow::Result<String> {
+        let reader = BufReader::new(batch);
+        let mut values: Vec<(String, serde_json::Value)> = Vec::new();
+
+        for line_result in reader.lines() {
+            let line = line_result?;
+            let doc: serde_json::Value =
+                serde_json::from_str(&line).with_context(|| {
+                    format!("Failed to parse document line as JSON: {}", line)
+                })?;
+
+            // Extract the timestamp from the JSON document
+            let timestamp_str = doc
+                .get("timestamp")
+                .and_then(|ts| ts.as_str())
+                .expect("no `timestamp` field found");
+            // Convert timestamp to Loki's expected format
+            let timestamp =
+                parse_timestamp_to_nanoseconds(timestamp_str).with_context(|| {
+                    format!("Failed to parse timestamp: {}", timestamp_str)
+                })?;
+            values.push((timestamp, doc));
+        }
+
+        // Construct the Loki payload
+
+        let mut buffer = String::new();
+        // The values of each stream, keyed by the value of its label.
+        let mut stream_values: BTreeMap<Option<String>, Vec<_>> = BTreeMap::new();
+        for (ts, json) in values.drain(..) {
+            let stream_label_value = self.stream_label_value(&json);
+            let log_line = json.to_string();
+
+            buffer.clear();
+            let mut structured_metadata = FnvHashMap::default();
+            flatten_json(json, &mut buffer, &mut structured_metadata);
+
+            stream_values.entry(stream_label_value).or_default().push((
+                ts,
+                log_line,
+                structured_metadata,
+            ));
+        }
+        let body = LokiBody {
+            streams: stream_values
+                .into_iter()
+                .map(|(stream_label_value, values)| LokiStream {
+                    // Stream seems to be similar to an index id or a partition key
+                    stream: LokiStreamInfo {
+                        label: "benchmark",
+                        routing_labels: stream_label_value
+                            .zip(self.stream_label_name())
+                            .map(|(label_value, label_name)| {
+                                (label_name.to_string(), label_value)
+                            })
+                            .into_iter()
+                            .collect(),
+                    },
+                    values,
+                })
+                .collect(),
+        };
+
+        // Serialize the LokiBody to JSON
+        serde_json::to_string(&body).with_context(|| "Failed to serialize body to JSON")
+    }
+}
+
+const MAX_CHUNK_SIZE: usize = 2 * 1024 * 1024; // 2MB limit
+
+#[async_trait]
+impl Sink for LokiSink {
+    fn batch_size(&self) -> usize {
+        MAX_CHUNK_SIZE
+    }
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let labeler = self.labeler.clone();
+        let batch = document_batch.bytes.clone();
+        let body = encode_blocking(move || labeler.encode_push_body(&batch)).await?;
+        self.push(body).await
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .post(self.flush_url.clone())
+            .header("Content-Type", "application/json")
+            .send()
+            .await
+            .with_context(|| "Failed to send flush request to Loki")?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            let status = response.status();
+            let error_msg = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Failed to read response text".to_string());
+            bail!("Failed to flush Loki chunks: HTTP {} {}", status, error_msg)
+        }
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let response = self
+            .client
+            .get(self.metrics_url.clone())
+            .send()
+            .await
+            .with_context(|| "Error fetching metrics for index info")?;
+
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Failed to fetch metrics, got status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+
+        let text = response.text().await?;
+        let num_docs =
+            parse_number_from_metrics(&text, "loki_ingester_chunk_entries_sum");
+
+        // # HELP loki_ingester_chunk_size_bytes Distribution of stored chunk sizes (when stored).
+        let num_bytes =
+            parse_number_from_metrics(&text, "loki_ingester_chunk_size_bytes_sum");
+
+        let num_splits =
+            parse_number_from_metrics(&text, "loki_ingester_chunks_stored_total");
+
+        Ok(IndexInfo {
+            num_docs,
+            num_bytes,
+            num_splits,
+            split_breakdown: None,
+            engine_specific: tenant_stats(&text, &self.tenants),
+        })
+    }
+
+    async fn check_health(&self) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .get(self.ready_url.clone())
+            .send()
+            .await
+            .with_context(|| "Loki request error for readiness")?;
+        if response.status() != StatusCode::OK {
+            // Loki answers e.g. "Ingester not ready: waiting for 15s after
+            // being ready".
+            bail!(
+                "Loki is not ready, got status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        Ok(())
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let response = self
+            .client
+            .get(self.version_url.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .send()
+            .await
+            .with_context(|| "Loki request error for build info")?;
+
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Error fetching build info, got status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        Ok(BuildInfo {
+            version: data["version"].as_str().unwrap_or_default().to_string(),
+            commit_date: data["buildDate"].as_str().unwrap_or_default().to_string(),
+            commit_hash: data["revision"].as_str().unwrap_or_default().to_string(),
+            build_target: "".to_string(),
+        })
+    }
+
+    /// Counts the log lines of the streams, or the samples of the series of
+    /// metric queries.
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        let EngineQuery::Loki(params) = query else {
+            bail!("Loki only runs LogQL queries");
+        };
+        let Some(params) = params.as_object() else {
+            bail!("Loki query parameters must be an object");
+        };
+        let params: Vec<(&str, String)> = params
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_json::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (name.as_str(), value)
+            })
+            .collect();
+        let mut request = self.client.get(self.query_range_url.clone()).query(&params);
+        if !self.tenants.is_empty() {
+            // Loki rejects several tenants unless it runs with
+            // `multi_tenant_queries_enabled`, see `--loki-tenants`.
+            request = request.header(TENANT_HEADER, self.tenants.join("|"));
+        }
+        let response = request.send().await.with_context(|| "Loki request error")?;
+        if response.status() != StatusCode::OK {
+            bail!(
+                "Loki query failed with status code {}: {:?}",
+                response.status(),
+                response.text().await?
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let num_hits = data["data"]["result"].as_array().map_or(0, |results| {
+            results
+                .iter()
+                .filter_map(|result| result["values"].as_array())
+                .map(|values| values.len() as u64)
+                .sum()
+        });
+        Ok(SearchResponse {
+            num_hits,
+            ..Default::default()
+        })
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        fetch_counters(&self.client, self.metrics_url.clone()).await
+    }
+}
+
+/// The `TENANT_COUNTERS` of the tenants, summed over the series of each.
+fn tenant_stats(metrics: &str, tenants: &[String]) -> EngineStats {
+    let mut tenant_stats = EngineStats::new();
+    for line in metrics.lines() {
+        let Some((series, value)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let Some((metric_name, labels)) = series.split_once('{') else {
+            continue;
+        };
+        let Some((_, stat_name)) = TENANT_COUNTERS
+            .iter()
+            .find(|(counter_name, _)| *counter_name == metric_name)
+        else {
+            continue;
+        };
+        let Some(tenant) = labels
+            .split(',')
+            .find_map(|label| label.trim().strip_prefix("tenant=\""))
+            .and_then(|tenant| tenant.split('"').next())
+        else {
+            continue;
+        };
+        if !tenants.iter().any(|known_tenant| known_tenant == tenant) {
+            continue;
+        }
+        let Ok(value) = value.parse::<f64>() else {
+            continue;
+        };
+        *tenant_stats
+            .entry(format!("tenants.{tenant}.{stat_name}"))
+            .or_default() += value;
+    }
+    tenant_stats
+}
+
+fn parse_number_from_metrics(metrics: &str, metric_name: &str) -> u64 {
+    metrics
+        .lines()
+        .find(|line| line.starts_with(metric_name))
+        // may be scientific notation
+        .map(|line| {
+            let number = line.split_whitespace().nth(1).unwrap_or("0");
+            number.parse::<f64>().unwrap_or_else(|_| panic!("[metric {metric_name}]: Could not parse number({number:?}) from line: {line:?}")) as u64
+        })
+        .unwrap_or(0)
+}
+
+/// Helper function to convert rfc3339 timestamp string to a nanosecond precision string
+fn parse_timestamp_to_nanoseconds(timestamp_str: &str) -> anyhow::Result<String> {
+    let dt = chrono::DateTime::parse_from_rfc3339(timestamp_str)
+        .with_context(|| format!("Failed to parse timestamp: {}", timestamp_str))?;
+    Ok(dt
+        .timestamp_nanos_opt()
+        .expect("timestamp out of range")
+        .to_string())
+}
+
+#[derive(serde::Serialize)]
+struct LokiBody {
+    streams: Vec<LokiStream>,
+}
+
+#[derive(serde::Serialize)]
+struct LokiStream {
+    stream: LokiStreamInfo,
+    // timestamp, log line, json (structured metadata)
+    values: Vec<(String, String, FnvHashMap<String, LokoValue>)>,
+}
+
+#[derive(serde::Serialize)]
+struct LokiStreamInfo {
+    label: &'static str,
+    #[serde(flatten)]
+    routing_labels: BTreeMap<String, String>,
+}
+
+use serde_json::Value;
+
+#[derive(Debug, PartialEq)]
+enum LokoValue {
+    String(String),
+    // Unused: Loki cannot handle numbers in structured metadata???
+    #[allow(dead_code)]
+    Number(f64),
+}
+impl serde::Serialize for LokoValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            LokoValue::String(s) => serializer.serialize_str(s),
+            LokoValue::Number(n) => serializer.serialize_f64(*n),
+        }
+    }
+}
+
+/// Loki cannot handle nested JSON, so we need to flatten it
+fn flatten_json(
+    value: Value,
+    prefix: &mut String,
+    flattened: &mut FnvHashMap<String, LokoValue>,
+) {
+    match value {
+        Value::Object(obj) => {
+            let previous_len = prefix.len(); // Remember the current length of prefix
+            for (k, v) in obj {
+                if !prefix.is_empty() {
+                    prefix.push('.'); // Add a dot only if prefix is not empty
+                }
+                prefix.push_str(&k);
+                flatten_json(v, prefix, flattened);
+                prefix.truncate(previous_len); // Reset prefix to its previous state
+            }
+        },
+        Value::Array(arr) => {
+            let previous_len = prefix.len();
+            for (i, v) in arr.into_iter().enumerate() {
+                // The first element in the array will not have an index
+                if i != 0 {
+                    let index_str = format!("[{}]", i);
+                    prefix.push_str(&index_str);
+                }
+                flatten_json(v, prefix, flattened);
+                prefix.truncate(previous_len);
+            }
+        },
+        _ => {
+            // Convert values to strings
+            // loki cannot handle numbers -.-
+            let value_str = match value {
+                Value::Number(_) => LokoValue::String(value.to_string()),
+                Value::String(_) => {
+                    LokoValue::String(value.as_str().unwrap().to_string())
+                },
+                _ => LokoValue::String(value.to_string()),
+            };
+            flattened.insert(prefix.clone(), value_str);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_flatten_json() {
+        let json_value = json!({
+            "a": 1,
+            "b": {
+                "c": "2",
+                "d": ["3", 4]
+            }
+        });
+
+        let mut flattened = FnvHashMap::default();
+        let mut prefix = String::new();
+        flatten_json(json_value, &mut prefix, &mut flattened);
+
+        // Expected flattened JSON
+        let expected = vec![
+            ("a".to_string(), LokoValue::String("1".into())),
+            ("b.c".to_string(), LokoValue::String("2".to_string())),
+            ("b.d".to_string(), LokoValue::String("3".to_string())),
+            ("b.d[1]".to_string(), LokoValue::String("4".into())),
+        ]
+        .into_iter()
+        .collect::<FnvHashMap<String, LokoValue>>();
+
+        assert_eq!(flattened, expected);
+    }
+
+    #[test]
+    fn test_https_host() {
+        let sink = LokiSink::new("https://loki:3100", Client::new());
+        assert_eq!(sink.push_url.as_str(), "https://loki:3100/loki/api/v1/push");
+        let sink = LokiSink::new("localhost:3100", Client::new());
+        assert_eq!(sink.ready_url.as_str(), "http://localhost:3100/ready");
+    }
+
+    #[test]
+    fn test_tenants() {
+        assert!(LokiSink::new("localhost:3100", Client::new())
+            .with_num_tenants(0)
+            .is_err());
+        let sink = LokiSink::new("localhost:3100", Client::new())
+            .with_num_tenants(2)
+            .unwrap();
+        let tenants: Vec<&str> = (0..3).filter_map(|_| sink.next_tenant()).collect();
+        assert_eq!(tenants, ["tenant-0", "tenant-1", "tenant-0"]);
+        assert_eq!(
+            LokiSink::new("localhost:3100", Client::new()).next_tenant(),
+            None
+        );
+
+        let metrics = r#"# TYPE loki_distributor_lines_received_total counter
+loki_distributor_lines_received_total{tenant="tenant-0"} 1200
+loki_distributor_lines_received_total{tenant="tenant-1"} 800
+loki_distributor_lines_received_total{tenant="other"} 5
+loki_distributor_bytes_received_total{retention_hours="744",tenant="tenant-0"} 1.5e+06
+loki_distributor_bytes_received_total{retention_hours="24",tenant="tenant-0"} 5e+05
+loki_ingester_chunk_entries_sum 2000
+"#;
+        let tenant_stats = tenant_stats(metrics, &sink.tenants);
+        assert_eq!(tenant_stats.len(), 3);
+        assert_eq!(tenant_stats["tenants.tenant-0.lines_received"], 1200.0);
+        assert_eq!(tenant_stats["tenants.tenant-1.lines_received"], 800.0);
+        assert_eq!(tenant_stats["tenants.tenant-0.bytes_received"], 2_000_000.0);
+    }
+
+    #[test]
+    fn test_routing_label_name() {
+        let routing_label = |routing_field: &str| {
+            LokiSink::new("localhost:3100", Client::new())
+                .with_routing_field(Some(routing_field.to_string()))
+                .labeler
+                .routing
+                .map(|(_, label_name)| label_name)
+        };
+        assert_eq!(routing_label("tenant_id").as_deref(), Some("tenant_id"));
+        assert_eq!(
+            routing_label("resource.service-name").as_deref(),
+            Some("resource_service_name")
+        );
+        assert_eq!(routing_label("1st").as_deref(), Some("_1st"));
+    }
+
+    #[test]
+    fn test_stream_label_value() {
+        let sink = LokiSink::new("localhost:3100", Client::new())
+            .with_routing_field(Some("host".to_string()))
+            .with_num_streams(4)
+            .unwrap();
+        let sink = &sink.labeler;
+        assert_eq!(sink.stream_label_name(), Some("stream_shard"));
+        let stream_idx = sink.stream_label_value(&json!({"host": "h1"})).unwrap();
+        assert_eq!(
+            sink.stream_label_value(&json!({"host": "h1", "message": "hello"})),
+            Some(stream_idx)
+        );
+        // The documents without routing value are spread round-robin.
+        let stream_idxs: Vec<String> = (0..4)
+            .filter_map(|_| sink.stream_label_value(&json!({"message": "hello"})))
+            .collect();
+        assert_eq!(stream_idxs, ["0", "1", "2", "3"]);
+
+        assert!(LokiSink::new("localhost:3100", Client::new())
+            .with_num_streams(0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_timestamp_to_nanoseconds() {
+        // Define a sample RFC3339 timestamp
+        let timestamp_str = "2020-01-01T12:00:00Z"; // January 1, 2020, at 12:00:00 UTC
+
+        // Expected nanoseconds since the UNIX epoch for the given timestamp
+        // This value is calculated based on the specific timestamp above
+        let expected = "1577880000000000000"; // This value should be the equivalent in nanoseconds
+
+        // Call the function with the sample timestamp
+        let result = parse_timestamp_to_nanoseconds(timestamp_str).unwrap();
+
+        // Assert that the result is as expected
+        assert_eq!(
+            result, expected,
+            "The parsed timestamp did not match the expected value"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::engine_metrics::EngineStats;
+use crate::query::EngineQuery;
+use crate::source::{DocumentBatch, DEFAULT_MAX_BODY_SIZE};
+pub mod bigquery;
+pub mod doc_id;
+pub mod elasticsearch;
+pub mod es_compatible;
+pub mod exec;
+pub mod failure_injection;
+pub mod forwarding;
+pub mod http;
+pub mod kusto;
+pub mod loki;
+pub mod parseable;
+pub mod quickwit;
+pub mod simulated_network;
+#[cfg(feature = "tantivy")]
+pub mod tantivy;
+pub mod zincobserve;
+
+/// A document rejected by the engine in a request that succeeded otherwise.
+#[derive(Debug, Clone)]
+pub struct RejectedDoc {
+    pub doc: Bytes,
+    pub error: String,
+}
+
+pub struct IndexInfo {
+    pub num_docs: u64,
+    pub num_splits: u64,
+    pub num_bytes: u64,
+    /// Only for the engines listing their splits, i.e. Quickwit.
+    pub split_breakdown: Option<SplitBreakdown>,
+    /// The index stats specific to the engine, e.g. the segments per shard
+    /// and the translog size of Elasticsearch. Empty for most engines.
+    pub engine_specific: EngineStats,
+}
+
+/// The published splits of an index by maturity and merge level, telling
+/// whether the merges kept up with the ingestion, which `num_splits` alone
+/// hides.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SplitBreakdown {
+    /// The splits still to be merged.
+    pub num_immature_splits: u64,
+    pub immature_num_bytes: u64,
+    pub num_mature_splits: u64,
+    pub mature_num_bytes: u64,
+    /// The splits by number of merges they went through, in increasing order.
+    pub merge_levels: Vec<MergeLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeLevel {
+    pub num_merge_ops: u64,
+    pub num_splits: u64,
+    pub num_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    pub commit_date: String,
+    pub commit_hash: String,
+    pub build_target: String,
+}
+
+/// What the engine returned for a query.
+#[derive(Debug, Default)]
+pub struct SearchResponse {
+    pub num_hits: u64,
+    /// The ids of the returned documents, for the engines assigning ids.
+    pub doc_ids: Vec<String>,
+    /// The returned documents, in order.
+    pub docs: Vec<Value>,
+    pub aggregations: Option<Value>,
+}
+
+/// The ingest requests rejected by the backpressure of the engine (e.g. 429)
+/// and retried.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Throttling {
+    pub num_throttled_requests: u64,
+    /// The time waited before resending them, summed over the concurrent
+    /// requests.
+    pub throttled_duration: Duration,
+}
+
+/// How long it took for the engine to enforce a retention that drops all the
+/// ingested documents.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionTimings {
+    /// Time until the documents were not searchable anymore.
+    pub delete_applied_secs: f64,
+    /// Time until the underlying storage was actually released.
+    pub storage_reclaimed_secs: f64,
+}
+
+#[async_trait]
+pub trait Sink: Sync + Send + 'static {
+    /// The maximum size of the batch to be sent to `send`
+    fn batch_size(&self) -> usize {
+        DEFAULT_MAX_BODY_SIZE
+    }
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()>;
+    /// The documents rejected individually by the engine, e.g. on mapping
+    /// errors, since the sink was created.
+    fn num_rejected_docs(&self) -> u64 {
+        0
+    }
+    /// The documents rejected individually by the engine since the last call,
+    /// along with their errors. Sinks keep a bounded number of them.
+    fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+        Vec::new()
+    }
+    /// The requests throttled by the engine since the sink was created.
+    fn throttling(&self) -> Throttling {
+        Throttling::default()
+    }
+    async fn commit(&self) -> anyhow::Result<()>;
+    async fn index_info(&self) -> anyhow::Result<IndexInfo>;
+    async fn build_info(&self) -> anyhow::Result<BuildInfo>;
+    /// Checks that the engine is up and ready to index. Engines without a
+    /// health endpoint are ready once they report their build info.
+    async fn check_health(&self) -> anyhow::Result<()> {
+        self.build_info().await?;
+        Ok(())
+    }
+    /// Creates the sink's index from an engine index config, in YAML or JSON.
+    async fn create_index(&self, _index_config: &str) -> anyhow::Result<()> {
+        bail!("creating indexes is not supported by this engine")
+    }
+    /// Deletes the sink's index. Returns false if it did not exist.
+    async fn delete_index(&self) -> anyhow::Result<bool> {
+        bail!("deleting indexes is not supported by this engine")
+    }
+    /// Atomically points `alias` to the sink's index, detaching it from any
+    /// other index it was previously pointing to.
+    async fn switch_alias(&self, _alias: &str) -> anyhow::Result<()> {
+        bail!("index aliases are not supported by this engine")
+    }
+    /// Merges the index into a single segment.
+    async fn force_merge(&self) -> anyhow::Result<()> {
+        bail!("force merge is not supported by this engine")
+    }
+    /// Applies a retention dropping all the documents of the index, and waits
+    /// (at most `timeout`) for it to be enforced.
+    async fn apply_retention(
+        &self,
+        _timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        bail!("retention measurement is not supported by this engine")
+    }
+    /// Executes the query, translated in the engine's dialect, and returns its
+    /// response.
+    async fn search(&self, _query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        bail!("queries are not supported by this engine")
+    }
+    /// Clears the engine's query caches for the sink's index.
+    async fn clear_caches(&self) -> anyhow::Result<()> {
+        bail!("clearing the caches is not supported by this engine")
+    }
+    /// A snapshot of the cumulative counters of the engine (GC time, merges,
+    /// flushes, network bytes...), for the whole engine rather than the index.
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        bail!("engine stats are not supported by this engine")
+    }
+}
+
+/// Runs the CPU-bound encoding of a request payload on the blocking thread
+/// pool, so that the runtime workers stay dedicated to I/O at high ingestion
+/// rates.
+pub(crate) async fn encode_blocking<T, F>(encode: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(encode)
+        .await
+        .context("Payload encoding panicked")?
+}
+
+/// The size of the chunks of the request bodies streamed with chunked
+/// transfer encoding.
+pub(crate) const TRANSFER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Splits the body into chunks of `chunk_size` bytes, without copying it.
+fn body_chunks(body: Bytes, chunk_size: usize) -> impl Iterator<Item = Bytes> {
+    (0..body.len())
+        .step_by(chunk_size)
+        .map(move |start| body.slice(start..(start + chunk_size).min(body.len())))
+}
+
+/// The body of an ingest request, sent at once with a `Content-Length`, or
+/// streamed with chunked transfer encoding if `chunked_transfer` is set. The
+/// batch is in memory already either way: the chunks only slice it, so that
+/// the modes differ by the framing of the request alone.
+pub(crate) fn ingest_body(body: Bytes, chunked_transfer: bool) -> reqwest::Body {
+    if !chunked_transfer {
+        return body.into();
+    }
+    let chunks = body_chunks(body, TRANSFER_CHUNK_SIZE).map(Ok::<_, std::io::Error>);
+    reqwest::Body::wrap_stream(futures::stream::iter(chunks))
+}
+
+/// The root URL of the engine at `host`, without a trailing slash: `host` as
+/// is if it has a scheme, e.g. `https://opensearch:9200` for a cluster with
+/// TLS, plain HTTP otherwise.
+pub fn base_url(host: &str) -> String {
+    if host.contains("://") {
+        host.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{host}")
+    }
+}
+
+/// How often the engine health is polled by `wait_until_healthy`.
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Polls the health of the engine until it is ready, returning the last error
+/// if it still isn't after `timeout`.
+pub async fn wait_until_healthy(
+    sink: &dyn Sink,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut last_error = None;
+    loop {
+        match tokio::time::timeout_at(deadline.into(), sink.check_health()).await {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(error)) => {
+                debug!(error=?error, "Engine not ready yet");
+                last_error = Some(error);
+            },
+            // The deadline passed during the health check.
+            Err(_) => {},
+        }
+        if Instant::now() >= deadline {
+            return Err(
+                last_error.unwrap_or_else(|| anyhow::anyhow!("health check timed out"))
+            );
+        }
+        let next_poll = (Instant::now() + HEALTH_POLL_INTERVAL).min(deadline);
+        tokio::time::sleep_until(next_poll.into()).await;
+    }
+}
+
+/// How often the split count is polled by `wait_until_merges_settle`.
+const MERGES_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// When the background merges of an index settled.
+pub struct MergesSettled {
+    /// From the start of the wait to the last change of the split count.
+    pub time_to_merged: Duration,
+    pub num_splits: u64,
+}
+
+/// Tracks the split count of an index, until it stops changing.
+struct SplitCountWatch {
+    stable_for: Duration,
+    start: Instant,
+    num_splits: Option<u64>,
+    last_change: Instant,
+}
+
+impl SplitCountWatch {
+    fn new(stable_for: Duration, start: Instant) -> Self {
+        Self {
+            stable_for,
+            start,
+            num_splits: None,
+            last_change: start,
+        }
+    }
+
+    /// Records the split count polled at `now`, returning the settled merges
+    /// once the count has been stable for `stable_for`.
+    fn observe(&mut self, num_splits: u64, now: Instant) -> Option<MergesSettled> {
+        if self.num_splits != Some(num_splits) {
+            if self.num_splits.is_some() {
+                self.last_change = now;
+            }
+            self.num_splits = Some(num_splits);
+        }
+        (now - self.last_change >= self.stable_for).then(|| MergesSettled {
+            time_to_merged: self.last_change - self.start,
+            num_splits,
+        })
+    }
+}
+
+/// Polls the split count of the index until it has not changed for
+/// `stable_for`, i.e. the engine's background merges are done. Fails if the
+/// merges are still running after `timeout`.
+pub async fn wait_until_merges_settle(
+    sink: &dyn Sink,
+    stable_for: Duration,
+    timeout: Duration,
+) -> anyhow::Result<MergesSettled> {
+    let start = Instant::now();
+    let mut split_count_watch = SplitCountWatch::new(stable_for, start);
+    loop {
+        let num_splits = sink.index_info().await?.num_splits;
+        if let Some(merges_settled) =
+            split_count_watch.observe(num_splits, Instant::now())
+        {
+            return Ok(merges_settled);
+        }
+        if start.elapsed() > timeout {
+            bail!(
+                "Merges still running after {timeout:?}, {num_splits} splits published"
+            );
+        }
+        tokio::time::sleep(MERGES_POLL_INTERVAL).await;
+    }
+}
+
+/// A sink accepting and discarding every batch, wrapped by the tests of the
+/// sinks wrapping another one.
+#[cfg(test)]
+pub(crate) struct NullSink;
+
+#[cfg(test)]
+#[async_trait]
+impl Sink for NullSink {
+    async fn send(&self, _document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        Ok(IndexInfo {
+            num_docs: 0,
+            num_splits: 0,
+            num_bytes: 0,
+            split_breakdown: None,
+            engine_specific: EngineStats::new(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        Ok(BuildInfo {
+            version: "null".to_string(),
+            commit_date: String::new(),
+            commit_hash: String::new(),
+            build_target: String::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_count_watch() {
+        let start = Instant::now();
+        let secs = |secs| start + Duration::from_secs(secs);
+        let mut split_count_watch = SplitCountWatch::new(Duration::from_secs(10), start);
+        assert!(split_count_watch.observe(12, secs(0)).is_none());
+        assert!(split_count_watch.observe(12, secs(5)).is_none());
+        assert!(split_count_watch.observe(4, secs(6)).is_none());
+        assert!(split_count_watch.observe(4, secs(15)).is_none());
+        let merges_settled = split_count_watch.observe(4, secs(16)).unwrap();
+        assert_eq!(merges_settled.time_to_merged, Duration::from_secs(6));
+        assert_eq!(merges_settled.num_splits, 4);
+
+        // Nothing to merge.
+        let mut split_count_watch = SplitCountWatch::new(Duration::from_secs(10), start);
+        assert!(split_count_watch.observe(1, secs(0)).is_none());
+        let merges_settled = split_count_watch.observe(1, secs(10)).unwrap();
+        assert_eq!(merges_settled.time_to_merged, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("127.0.0.1:9200"), "http://127.0.0.1:9200");
+        assert_eq!(
+            base_url("https://opensearch:9200/"),
+            "https://opensearch:9200"
+        );
+    }
+
+    #[test]
+    fn test_body_chunks() {
+        let body = Bytes::from_static(b"0123456789");
+        let chunks: Vec<Bytes> = body_chunks(body, 4).collect();
+        assert_eq!(chunks, ["0123", "4567", "89"]);
+        assert_eq!(body_chunks(Bytes::new(), 4).count(), 0);
+        assert!(ingest_body(Bytes::from_static(b"{}"), false)
+            .as_bytes()
+            .is_some());
+        // A streamed body has no length known in advance.
+        assert!(ingest_body(Bytes::from_static(b"{}"), true)
+            .as_bytes()
+            .is_none());
+    }
+}
@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use super::{BuildInfo, IndexInfo, Sink};
 use crate::source::DocumentBatch;
 
+#[allow(dead_code)]
 #[derive(Clone)]
 pub struct ParseableSink {
     // uri: Uri,
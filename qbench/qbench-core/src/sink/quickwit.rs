@@ -0,0 +1,716 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use http::{header, HeaderMap, StatusCode};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::doc_id::{inject_hash_doc_ids, DocId, HASH_DOC_ID_FIELD};
+use super::{
+    base_url,
+    ingest_body,
+    BuildInfo,
+    IndexInfo,
+    MergeLevel,
+    RetentionTimings,
+    SearchResponse,
+    Sink,
+    SplitBreakdown,
+    Throttling,
+};
+use crate::engine_metrics::{fetch_counters, EngineStats};
+use crate::query::EngineQuery;
+use crate::source::DocumentBatch;
+use crate::utils::wait_until;
+
+/// The interval at which the index state is polled while waiting for the
+/// janitor.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The wait before resending a throttled request without `Retry-After`,
+/// doubled at each retry up to `MAX_BACKOFF`.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// The `commit` parameter of the ingest requests: `auto` acknowledges the
+/// documents once received, `wait_for` once searchable after the next
+/// scheduled commit, `force` commits them right away.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickwitCommit {
+    #[default]
+    Auto,
+    WaitFor,
+    Force,
+}
+
+impl Display for QuickwitCommit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl FromStr for QuickwitCommit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(QuickwitCommit::Auto),
+            "wait_for" => Ok(QuickwitCommit::WaitFor),
+            "force" => Ok(QuickwitCommit::Force),
+            _ => Err(format!("Unknown commit mode {s:?}")),
+        }
+    }
+}
+
+impl AsRef<str> for QuickwitCommit {
+    fn as_ref(&self) -> &str {
+        match self {
+            QuickwitCommit::Auto => "auto",
+            QuickwitCommit::WaitFor => "wait_for",
+            QuickwitCommit::Force => "force",
+        }
+    }
+}
+
+#[derive(Clone)]
+
+pub struct QuickwitSink {
+    api_root_url: Url,
+    index_url: Url,
+    ingest_url: Url,
+    index_id: String,
+    client: Client,
+    inject_hash_doc_ids: bool,
+    partition_key: Option<String>,
+    /// The ID and type of the source the documents of the ingest API go
+    /// through.
+    ingest_source: (&'static str, &'static str),
+    vrl_transform: Option<String>,
+    /// The commit of the batches but the last one, always forced.
+    commit: QuickwitCommit,
+    chunked_transfer: bool,
+    throttle_stats: Arc<ThrottleStats>,
+}
+
+#[derive(Default)]
+struct ThrottleStats {
+    num_throttled_requests: AtomicU64,
+    throttled_micros: AtomicU64,
+}
+
+impl QuickwitSink {
+    pub fn new(host: &str, index_id: &str, ingest_v2: bool, client: Client) -> Self {
+        let base_url = base_url(host);
+        let api_root_url =
+            Url::parse(&format!("{base_url}/api/v1/")).expect("Invalid quickwit URL");
+        let index_url = Url::parse(&format!("{base_url}/api/v1/indexes/{index_id}/"))
+            .expect("Invalid quickwit URL");
+        let ingest_url_component = if ingest_v2 { "ingest-v2" } else { "ingest" };
+        let ingest_url = Url::parse(&format!(
+            "{base_url}/api/v1/{index_id}/{ingest_url_component}"
+        ))
+        .expect("Invalid quickwit URL");
+        Self {
+            api_root_url,
+            ingest_url,
+            index_url,
+            index_id: index_id.to_string(),
+            client,
+            inject_hash_doc_ids: false,
+            partition_key: None,
+            ingest_source: if ingest_v2 {
+                ("_ingest-source", "ingest")
+            } else {
+                ("_ingest-api-source", "ingest-api")
+            },
+            vrl_transform: None,
+            commit: QuickwitCommit::Auto,
+            chunked_transfer: false,
+            throttle_stats: Arc::default(),
+        }
+    }
+
+    /// Partitions the indexes created by the sink on the value of this field.
+    /// Quickwit partitions at indexing time, according to the index config.
+    pub fn with_partition_key(mut self, partition_key: Option<String>) -> Self {
+        self.partition_key = partition_key;
+        self
+    }
+
+    /// Transforms the documents with this VRL script in the indexes created by
+    /// the sink, set on the source of the ingest API (Quickwit 0.8+).
+    pub fn with_vrl_transform(mut self, vrl_transform: Option<String>) -> Self {
+        self.vrl_transform = vrl_transform;
+        self
+    }
+
+    /// Sends the batches with this commit, to measure the latency of the
+    /// ingestion acknowledged once the documents are searchable. The last
+    /// batch is always committed with `force`.
+    pub fn with_commit(mut self, commit: QuickwitCommit) -> Self {
+        self.commit = commit;
+        self
+    }
+
+    /// Streams the batches with chunked transfer encoding rather than sending
+    /// them with a `Content-Length`.
+    pub fn with_chunked_transfer(mut self, chunked_transfer: bool) -> Self {
+        self.chunked_transfer = chunked_transfer;
+        self
+    }
+
+    /// Quickwit has no document ID: hash-based IDs are added to the documents
+    /// in their `doc_id` field, field-based IDs are already in them.
+    pub fn with_doc_id(mut self, doc_id: Option<&DocId>) -> Self {
+        self.inject_hash_doc_ids = doc_id == Some(&DocId::Hash);
+        if self.inject_hash_doc_ids {
+            info!("Adding hash-based IDs to the documents in `{HASH_DOC_ID_FIELD}`");
+        }
+        self
+    }
+}
+
+impl QuickwitSink {
+    /// Updates the config of the ingest API source with a VRL transform.
+    async fn set_ingest_source_transform(
+        &self,
+        vrl_transform: &str,
+    ) -> anyhow::Result<()> {
+        let (source_id, source_type) = self.ingest_source;
+        let source_config = json!({
+            "version": "0.8",
+            "source_id": source_id,
+            "source_type": source_type,
+            "transform": {"script": vrl_transform},
+        });
+        let response = self
+            .client
+            .put(
+                self.index_url
+                    .join(&format!("sources/{source_id}"))
+                    .expect("Invalid quickwit URL"),
+            )
+            .json(&source_config)
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            let status = response.status();
+            let response_body = response.text().await.unwrap_or_default();
+            error!(status=?status, body=response_body, "Quickwit API error");
+            bail!("Failed to set the VRL transform, got status code {status}: {response_body}");
+        }
+        info!(source_id, "VRL transform set");
+        Ok(())
+    }
+
+    async fn published_splits(&self) -> anyhow::Result<Vec<serde_json::Value>> {
+        let mut splits_url =
+            self.index_url.join("splits").expect("Invalid quickwit URL");
+        splits_url.set_query(Some("split_states=Published"));
+        let response = self
+            .client
+            .get(splits_url)
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            bail!("http error with status code {}", response.status());
+        }
+        let mut data: serde_json::Value = response.json().await?;
+        match data["splits"].take() {
+            serde_json::Value::Array(splits) => Ok(splits),
+            _ => bail!("Invalid splits list response"),
+        }
+    }
+}
+
+/// Breaks down the splits of the `splits` endpoint. Splits of Quickwit versions
+/// without maturity (before 0.6) are counted as mature.
+fn split_breakdown(splits: &[serde_json::Value]) -> SplitBreakdown {
+    let mut split_breakdown = SplitBreakdown::default();
+    for split in splits {
+        // The split file ends with its footer.
+        let num_bytes = split["footer_offsets"]["end"].as_u64().unwrap_or_default();
+        if split["maturity"]["type"] == "immature" {
+            split_breakdown.num_immature_splits += 1;
+            split_breakdown.immature_num_bytes += num_bytes;
+        } else {
+            split_breakdown.num_mature_splits += 1;
+            split_breakdown.mature_num_bytes += num_bytes;
+        }
+        let num_merge_ops = split["num_merge_ops"].as_u64().unwrap_or_default();
+        let merge_levels = &mut split_breakdown.merge_levels;
+        let level_idx = match merge_levels
+            .binary_search_by_key(&num_merge_ops, |merge_level| {
+                merge_level.num_merge_ops
+            }) {
+            Ok(level_idx) => level_idx,
+            Err(level_idx) => {
+                merge_levels.insert(
+                    level_idx,
+                    MergeLevel {
+                        num_merge_ops,
+                        num_splits: 0,
+                        num_bytes: 0,
+                    },
+                );
+                level_idx
+            },
+        };
+        merge_levels[level_idx].num_splits += 1;
+        merge_levels[level_idx].num_bytes += num_bytes;
+    }
+    split_breakdown
+}
+
+#[async_trait]
+impl Sink for QuickwitSink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let commit = if document_batch.last {
+            info!("Forcing commit to quickwit...");
+            QuickwitCommit::Force
+        } else {
+            self.commit
+        };
+        let mut ingest_url = self.ingest_url.clone();
+        if commit != QuickwitCommit::Auto {
+            ingest_url.set_query(Some(&format!("commit={commit}")));
+        }
+        let body = if self.inject_hash_doc_ids {
+            Bytes::from(inject_hash_doc_ids(&document_batch.bytes)?)
+        } else {
+            document_batch.bytes.clone()
+        };
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let response = self
+                .client
+                .post(ingest_url.clone())
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(ingest_body(body.clone(), self.chunked_transfer))
+                .send()
+                .await?;
+            let status = response.status();
+            if status == StatusCode::OK {
+                return Ok(());
+            }
+            let retry_after = retry_after(response.headers(), Utc::now());
+            let response_body = response.text().await.unwrap_or_default();
+            if !is_backpressure(status, &response_body) {
+                error!(status=?status, body=response_body, "Quickwit API error");
+                bail!("http error with status code {status}: {response_body}");
+            }
+            let delay = retry_after.unwrap_or(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            warn!(status=?status, body=response_body, "Quickwit applied backpressure, retrying in {delay:?}");
+            self.throttle_stats
+                .num_throttled_requests
+                .fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(delay).await;
+            self.throttle_stats
+                .throttled_micros
+                .fetch_add(delay.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn throttling(&self) -> Throttling {
+        Throttling {
+            num_throttled_requests: self
+                .throttle_stats
+                .num_throttled_requests
+                .load(Ordering::Relaxed),
+            throttled_duration: Duration::from_micros(
+                self.throttle_stats.throttled_micros.load(Ordering::Relaxed),
+            ),
+        }
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn create_index(&self, index_config: &str) -> anyhow::Result<()> {
+        let mut index_config: serde_json::Value =
+            serde_yaml::from_str(index_config).context("Invalid index config")?;
+        // The config may have been written for another index ID.
+        index_config["index_id"] = json!(self.index_id);
+        if let Some(partition_key) = &self.partition_key {
+            index_config["doc_mapping"]["partition_key"] = json!(partition_key);
+        }
+        let response = self
+            .client
+            .post(
+                self.api_root_url
+                    .join("indexes")
+                    .expect("Invalid quickwit URL"),
+            )
+            .json(&index_config)
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Quickwit API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        if let Some(vrl_transform) = &self.vrl_transform {
+            self.set_ingest_source_transform(vrl_transform).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_index(&self) -> anyhow::Result<bool> {
+        let response = self
+            .client
+            .delete(
+                self.api_root_url
+                    .join(&format!("indexes/{}", self.index_id))
+                    .expect("Invalid quickwit URL"),
+            )
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        match response.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => {
+                error!(resp=?response, "Quickwit API error");
+                bail!("http error with status code {}: {:?}", status, response);
+            },
+        }
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let describe_url = self
+            .index_url
+            .join("describe")
+            .expect("Invalid quickwit URL");
+        let response = self
+            .client
+            .get(describe_url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Quickwit API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let num_docs = data["num_published_docs"]
+            .as_u64()
+            .expect("num_published_docs field must be a u64");
+        let num_splits = data["num_published_splits"]
+            .as_u64()
+            .expect("num_published_splits field must be a u64");
+        let num_bytes = data["size_published_splits"]
+            .as_u64()
+            .expect("size_published_docs_uncompressed field must be a u64");
+
+        // The breakdown is a bonus, not worth failing the polls of the index.
+        let split_breakdown = match self.published_splits().await {
+            Ok(splits) => Some(split_breakdown(&splits)),
+            Err(error) => {
+                warn!(error=?error, "Failed to list the splits");
+                None
+            },
+        };
+
+        Ok(IndexInfo {
+            num_docs,
+            num_bytes,
+            num_splits,
+            split_breakdown,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn apply_retention(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        info!("Creating a delete task matching all documents...");
+        let delete_tasks_url = self
+            .api_root_url
+            .join(&format!("{}/delete-tasks", self.index_id))
+            .expect("Invalid quickwit URL");
+        let start = Instant::now();
+        let response = self
+            .client
+            .post(delete_tasks_url)
+            .json(&json!({ "query": "*" }))
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Quickwit API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        wait_until(timeout, POLL_INTERVAL, || async {
+            Ok(self.index_info().await?.num_docs == 0)
+        })
+        .await?;
+        let delete_applied_secs = start.elapsed().as_secs_f64();
+        info!("Waiting for the garbage collection of the deleted splits...");
+        let mut splits_url =
+            self.index_url.join("splits").expect("Invalid quickwit URL");
+        splits_url.set_query(Some("split_states=MarkedForDeletion"));
+        wait_until(timeout, POLL_INTERVAL, || async {
+            let response = self
+                .client
+                .get(splits_url.clone())
+                .send()
+                .await
+                .with_context(|| "Quickwit request error")?;
+            if response.status() != StatusCode::OK {
+                bail!(
+                    "http error with status code {}: {:?}",
+                    response.status(),
+                    response
+                );
+            }
+            let data: serde_json::Value = response.json().await?;
+            let num_splits_marked_for_deletion = data["splits"]
+                .as_array()
+                .expect("splits field must be an array")
+                .len();
+            Ok(num_splits_marked_for_deletion == 0)
+        })
+        .await?;
+        Ok(RetentionTimings {
+            delete_applied_secs,
+            storage_reclaimed_secs: start.elapsed().as_secs_f64(),
+        })
+    }
+
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        let EngineQuery::Quickwit(search_request) = query else {
+            bail!("Quickwit only runs queries in its own dialect");
+        };
+        let search_url = self
+            .api_root_url
+            .join(&format!("{}/search", self.index_id))
+            .expect("Invalid quickwit URL");
+        let response = self
+            .client
+            .post(search_url)
+            .json(search_request)
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Quickwit API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let num_hits = data["num_hits"]
+            .as_u64()
+            .expect("num_hits field must be a u64");
+        let docs = data["hits"].as_array().cloned().unwrap_or_default();
+        Ok(SearchResponse {
+            num_hits,
+            doc_ids: Vec::new(),
+            docs,
+            aggregations: data.get("aggregations").cloned(),
+        })
+    }
+
+    async fn check_health(&self) -> anyhow::Result<()> {
+        let response = self
+            .client
+            .get(
+                self.api_root_url
+                    .join("/health/readyz")
+                    .expect("Invalid quickwit URL"),
+            )
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            bail!("http error with status code {}", response.status());
+        }
+        Ok(())
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        let build_url = self
+            .api_root_url
+            .join("version")
+            .expect("Invalid quickwit URL");
+        let response = self
+            .client
+            .get(build_url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .send()
+            .await
+            .with_context(|| "Quickwit request error")?;
+        if response.status() != StatusCode::OK {
+            error!(resp=?response, "Quickwit API error");
+            bail!(
+                "http error with status code {}: {:?}",
+                response.status(),
+                response
+            );
+        }
+        let data: serde_json::Value = response.json().await?;
+        let build_info_json = data["build"]
+            .as_object()
+            .expect("build field must be an object");
+        let version = build_info_json["version"]
+            .as_str()
+            .expect("version field must be a string")
+            .to_string();
+        let commit_date = build_info_json["commit_date"]
+            .as_str()
+            .expect("commit_date field must be a string")
+            .to_string();
+        let commit_hash = build_info_json["commit_hash"]
+            .as_str()
+            .expect("commit_hash field must be a string")
+            .to_string();
+        let build_target = build_info_json["build_target"]
+            .as_str()
+            .expect("build_target field must be a string")
+            .to_string();
+
+        Ok(super::BuildInfo {
+            version,
+            commit_date,
+            commit_hash,
+            build_target,
+        })
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        let metrics_url = self
+            .api_root_url
+            .join("/metrics")
+            .expect("Invalid quickwit URL");
+        fetch_counters(&self.client, metrics_url).await
+    }
+}
+
+/// Whether the ingest request was rejected by the backpressure of the engine,
+/// to be retried: rate limited (429), or no ingest v2 shard being available
+/// (503) e.g. while shards are opened or scaled up.
+fn is_backpressure(status: StatusCode, response_body: &str) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::SERVICE_UNAVAILABLE
+            && response_body.to_lowercase().contains("shard"))
+}
+
+/// The delay of the `Retry-After` header, in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let retry_after = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = retry_after.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let retry_at = DateTime::parse_from_rfc2822(retry_after).ok()?;
+    Some(
+        (retry_at.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_quickwit_commit() {
+        for commit in [
+            QuickwitCommit::Auto,
+            QuickwitCommit::WaitFor,
+            QuickwitCommit::Force,
+        ] {
+            assert_eq!(commit.to_string().parse::<QuickwitCommit>(), Ok(commit));
+            assert_eq!(
+                serde_json::to_value(commit).unwrap(),
+                json!(commit.as_ref())
+            );
+        }
+        assert!("wait-for".parse::<QuickwitCommit>().is_err());
+    }
+
+    #[test]
+    fn test_split_breakdown() {
+        let splits = json!([
+            {"maturity": {"type": "mature"}, "num_merge_ops": 2, "footer_offsets": {"start": 90, "end": 100}},
+            {"maturity": {"type": "immature", "maturation_period_millis": 172800000}, "num_merge_ops": 0, "footer_offsets": {"start": 5, "end": 10}},
+            {"maturity": {"type": "immature", "maturation_period_millis": 172800000}, "num_merge_ops": 0, "footer_offsets": {"start": 5, "end": 20}},
+            {"num_merge_ops": 1},
+        ]);
+        let split_breakdown = split_breakdown(splits.as_array().unwrap());
+        assert_eq!(split_breakdown.num_immature_splits, 2);
+        assert_eq!(split_breakdown.immature_num_bytes, 30);
+        assert_eq!(split_breakdown.num_mature_splits, 2);
+        assert_eq!(split_breakdown.mature_num_bytes, 100);
+        let merge_levels: Vec<(u64, u64, u64)> = split_breakdown
+            .merge_levels
+            .iter()
+            .map(|level| (level.num_merge_ops, level.num_splits, level.num_bytes))
+            .collect();
+        assert_eq!(merge_levels, [(0, 2, 30), (1, 1, 0), (2, 1, 100)]);
+    }
+
+    #[test]
+    fn test_backpressure() {
+        assert!(is_backpressure(StatusCode::TOO_MANY_REQUESTS, ""));
+        assert!(is_backpressure(
+            StatusCode::SERVICE_UNAVAILABLE,
+            r#"{"message": "no shards available for index `logs`"}"#
+        ));
+        assert!(!is_backpressure(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "overloaded"
+        ));
+        assert!(!is_backpressure(StatusCode::BAD_REQUEST, "shard"));
+
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+        headers.insert(header::RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(3)));
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static("Mon, 01 Jan 2024 00:00:05 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(5)));
+        headers.insert(
+            header::RETRY_AFTER,
+            HeaderValue::from_static("Sun, 31 Dec 2023 23:59:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+    }
+}
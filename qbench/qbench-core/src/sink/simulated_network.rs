@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use super::{
+    BuildInfo,
+    IndexInfo,
+    RejectedDoc,
+    RetentionTimings,
+    SearchResponse,
+    Sink,
+    Throttling,
+};
+use crate::engine_metrics::EngineStats;
+use crate::query::EngineQuery;
+use crate::source::DocumentBatch;
+
+/// Delays the batches sent to the wrapped sink as if they went through a
+/// slower network, to model an ingestion from a remote region without
+/// setting up `tc`/`netem` on the host.
+///
+/// Each batch waits for `latency`, and with a `bandwidth`, for its
+/// transmission over a link shared by the concurrent requests: a batch only
+/// starts being transmitted once the previous ones are. Everything but `send`
+/// goes to the wrapped sink undelayed.
+pub struct SimulatedNetworkSink {
+    sink: Box<dyn Sink>,
+    latency: Duration,
+    /// In bytes per second.
+    bandwidth: Option<f64>,
+    /// When the link is done transmitting the batches sent so far.
+    link_free_at: Mutex<Instant>,
+}
+
+impl SimulatedNetworkSink {
+    /// `bandwidth_mbps` is in megabytes per second, like `--ingest-rate-mbps`.
+    pub fn new(
+        sink: Box<dyn Sink>,
+        latency: Duration,
+        bandwidth_mbps: Option<f64>,
+    ) -> Self {
+        Self {
+            sink,
+            latency,
+            bandwidth: bandwidth_mbps.map(|bandwidth_mbps| bandwidth_mbps * 1_000_000.0),
+            link_free_at: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// When a batch of `num_bytes` sent now has been transmitted, reserving
+    /// the link until then.
+    async fn transmitted_at(&self, num_bytes: usize) -> Instant {
+        let now = Instant::now();
+        let Some(bandwidth) = self.bandwidth else {
+            return now;
+        };
+        let mut link_free_at = self.link_free_at.lock().await;
+        let transmitted_at = (*link_free_at).max(now)
+            + Duration::from_secs_f64(num_bytes as f64 / bandwidth);
+        *link_free_at = transmitted_at;
+        transmitted_at
+    }
+}
+
+#[async_trait]
+impl Sink for SimulatedNetworkSink {
+    fn batch_size(&self) -> usize {
+        self.sink.batch_size()
+    }
+
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        let transmitted_at = self.transmitted_at(document_batch.bytes.len()).await;
+        tokio::time::sleep_until(transmitted_at + self.latency).await;
+        self.sink.send(document_batch).await
+    }
+
+    fn num_rejected_docs(&self) -> u64 {
+        self.sink.num_rejected_docs()
+    }
+
+    fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+        self.sink.take_rejected_docs()
+    }
+
+    fn throttling(&self) -> Throttling {
+        self.sink.throttling()
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        self.sink.commit().await
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        self.sink.index_info().await
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        self.sink.build_info().await
+    }
+
+    async fn check_health(&self) -> anyhow::Result<()> {
+        self.sink.check_health().await
+    }
+
+    async fn create_index(&self, index_config: &str) -> anyhow::Result<()> {
+        self.sink.create_index(index_config).await
+    }
+
+    async fn delete_index(&self) -> anyhow::Result<bool> {
+        self.sink.delete_index().await
+    }
+
+    async fn switch_alias(&self, alias: &str) -> anyhow::Result<()> {
+        self.sink.switch_alias(alias).await
+    }
+
+    async fn force_merge(&self) -> anyhow::Result<()> {
+        self.sink.force_merge().await
+    }
+
+    async fn apply_retention(
+        &self,
+        timeout: Duration,
+    ) -> anyhow::Result<RetentionTimings> {
+        self.sink.apply_retention(timeout).await
+    }
+
+    async fn search(&self, query: &EngineQuery) -> anyhow::Result<SearchResponse> {
+        self.sink.search(query).await
+    }
+
+    async fn clear_caches(&self) -> anyhow::Result<()> {
+        self.sink.clear_caches().await
+    }
+
+    async fn engine_stats(&self) -> anyhow::Result<EngineStats> {
+        self.sink.engine_stats().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::sink::NullSink;
+
+    #[tokio::test]
+    async fn test_simulated_network_sink() {
+        // 1MB/s: a 100KB batch takes 100ms to transmit.
+        let sink = SimulatedNetworkSink::new(
+            Box::new(NullSink),
+            Duration::from_millis(50),
+            Some(1.0),
+        );
+        let batch = DocumentBatch {
+            bytes: Bytes::from(vec![b' '; 100_000]),
+            last: false,
+        };
+        let start = Instant::now();
+        // The concurrent batches share the link.
+        let (first, second) = tokio::join!(sink.send(&batch), sink.send(&batch));
+        first.unwrap();
+        second.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(250), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1000), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,433 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tantivy::schema::{
+    DateOptions,
+    DateTimePrecision,
+    Field,
+    FieldType,
+    IndexRecordOption,
+    JsonObjectOptions,
+    NumericOptions,
+    Schema,
+    TextFieldIndexing,
+    TextOptions,
+};
+use tantivy::{DateTime, Index, IndexWriter, TantivyDocument};
+
+use super::{BuildInfo, IndexInfo, Sink};
+use crate::source::DocumentBatch;
+
+/// Memory budget shared by all the indexing threads of the writer.
+const WRITER_MEMORY_BUDGET: usize = 1 << 30;
+/// Catch-all JSON field receiving the fields absent from the mapping when
+/// the doc mapping mode is `dynamic`, as Quickwit does.
+const DYNAMIC_FIELD_NAME: &str = "_dynamic";
+
+/// Indexes documents in-process into a local tantivy index. There is no
+/// network nor server in the way, which gives a floor to compare the
+/// engines' overheads against.
+pub struct TantivySink {
+    index: Index,
+    index_dir: PathBuf,
+    mapping: Mapping,
+    writer: RwLock<IndexWriter>,
+}
+
+impl TantivySink {
+    /// Creates a new index in `index_dir`, with a schema derived from the doc
+    /// mapping of the Quickwit index config at `mapping_path`.
+    pub fn create(mapping_path: &Path, index_dir: &Path) -> anyhow::Result<Self> {
+        let index_config = std::fs::read_to_string(mapping_path)
+            .with_context(|| format!("Failed to read mapping {mapping_path:?}"))?;
+        let index_config: IndexConfig = serde_yaml::from_str(&index_config)
+            .with_context(|| format!("Invalid index config {mapping_path:?}"))?;
+        let mapping = Mapping::from_doc_mapping(&index_config.doc_mapping)?;
+        std::fs::create_dir_all(index_dir)?;
+        let index = Index::create_in_dir(index_dir, mapping.schema.clone())
+            .with_context(|| {
+                format!("Failed to create tantivy index in {index_dir:?}")
+            })?;
+        let writer = index.writer(WRITER_MEMORY_BUDGET)?;
+        info!(index_dir=?index_dir, "created tantivy index");
+        Ok(Self {
+            index,
+            index_dir: index_dir.to_path_buf(),
+            mapping,
+            writer: RwLock::new(writer),
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for TantivySink {
+    async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+        // Parsing is CPU bound and the writer blocks when its indexing threads
+        // lag behind, so keep it off the other tasks' way.
+        tokio::task::block_in_place(|| {
+            let writer = self.writer.read().unwrap();
+            for line in document_batch.bytes.split(|byte| *byte == b'\n') {
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                let doc: Map<String, Value> = serde_json::from_slice(line)
+                    .context("Failed to parse document line as JSON")?;
+                writer.add_document(self.mapping.to_document(doc)?)?;
+            }
+            Ok(())
+        })
+    }
+
+    async fn commit(&self) -> anyhow::Result<()> {
+        tokio::task::block_in_place(|| {
+            let mut writer = self.writer.write().unwrap();
+            writer.commit()?;
+            Ok(())
+        })
+    }
+
+    async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+        let reader = self.index.reader()?;
+        let searcher = reader.searcher();
+        Ok(IndexInfo {
+            num_docs: searcher.num_docs(),
+            num_splits: searcher.segment_readers().len() as u64,
+            num_bytes: dir_size(&self.index_dir)?,
+            split_breakdown: None,
+            engine_specific: Default::default(),
+        })
+    }
+
+    async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+        Ok(BuildInfo {
+            version: tantivy::version_string().to_string(),
+            commit_date: "".to_string(),
+            commit_hash: "".to_string(),
+            build_target: "".to_string(),
+        })
+    }
+}
+
+fn dir_size(dir: &Path) -> anyhow::Result<u64> {
+    let mut num_bytes = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            num_bytes += metadata.len();
+        }
+    }
+    Ok(num_bytes)
+}
+
+/// The subset of a Quickwit index config we need to derive the schema.
+#[derive(Deserialize)]
+struct IndexConfig {
+    doc_mapping: DocMapping,
+}
+
+#[derive(Deserialize)]
+struct DocMapping {
+    #[serde(default = "default_mode")]
+    mode: String,
+    #[serde(default)]
+    field_mappings: Vec<FieldMapping>,
+}
+
+fn default_mode() -> String {
+    "dynamic".to_string()
+}
+
+#[derive(Deserialize)]
+struct FieldMapping {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: String,
+    tokenizer: Option<String>,
+    record: Option<String>,
+    #[serde(default)]
+    fieldnorms: bool,
+    /// Either a boolean or, for text fields, the fast field normalizer.
+    #[serde(default)]
+    fast: serde_yaml::Value,
+    precision: Option<String>,
+    #[serde(default = "default_true")]
+    stored: bool,
+    #[serde(default = "default_true")]
+    indexed: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl FieldMapping {
+    fn is_fast(&self) -> bool {
+        !matches!(
+            self.fast,
+            serde_yaml::Value::Null | serde_yaml::Value::Bool(false)
+        )
+    }
+
+    fn text_indexing(&self) -> anyhow::Result<TextFieldIndexing> {
+        let record = match self.record.as_deref() {
+            None | Some("basic") => IndexRecordOption::Basic,
+            Some("freq") => IndexRecordOption::WithFreqs,
+            Some("position") => IndexRecordOption::WithFreqsAndPositions,
+            Some(record) => {
+                bail!("Unknown record option {record:?} for `{}`", self.name)
+            },
+        };
+        Ok(TextFieldIndexing::default()
+            .set_tokenizer(self.tokenizer.as_deref().unwrap_or("default"))
+            .set_index_option(record)
+            .set_fieldnorms(self.fieldnorms))
+    }
+
+    fn numeric_options(&self) -> NumericOptions {
+        let mut options = NumericOptions::default();
+        if self.stored {
+            options = options.set_stored();
+        }
+        if self.indexed {
+            options = options.set_indexed();
+        }
+        if self.is_fast() {
+            options = options.set_fast();
+        }
+        options
+    }
+}
+
+/// The tantivy schema derived from a doc mapping, along with what is needed
+/// to turn JSON documents into tantivy documents.
+struct Mapping {
+    schema: Schema,
+    dynamic_field: Option<Field>,
+}
+
+impl Mapping {
+    fn from_doc_mapping(doc_mapping: &DocMapping) -> anyhow::Result<Self> {
+        let mut schema_builder = Schema::builder();
+        for field_mapping in &doc_mapping.field_mappings {
+            let name = &field_mapping.name;
+            let field_type = field_mapping.field_type.as_str();
+            // Tantivy fields are multivalued, arrays map to their element type.
+            let field_type = field_type
+                .strip_prefix("array<")
+                .and_then(|field_type| field_type.strip_suffix('>'))
+                .unwrap_or(field_type);
+            match field_type {
+                "text" => {
+                    let mut options = TextOptions::default();
+                    if field_mapping.stored {
+                        options = options.set_stored();
+                    }
+                    if field_mapping.indexed {
+                        options =
+                            options.set_indexing_options(field_mapping.text_indexing()?);
+                    }
+                    if field_mapping.is_fast() {
+                        options = options.set_fast(None);
+                    }
+                    schema_builder.add_text_field(name, options);
+                },
+                "i64" => {
+                    schema_builder.add_i64_field(name, field_mapping.numeric_options());
+                },
+                "u64" => {
+                    schema_builder.add_u64_field(name, field_mapping.numeric_options());
+                },
+                "f64" => {
+                    schema_builder.add_f64_field(name, field_mapping.numeric_options());
+                },
+                "bool" => {
+                    schema_builder.add_bool_field(name, field_mapping.numeric_options());
+                },
+                "datetime" => {
+                    let precision = match field_mapping.precision.as_deref() {
+                        None | Some("seconds") | Some("second") => {
+                            DateTimePrecision::Seconds
+                        },
+                        Some("milliseconds") | Some("millisecond") => {
+                            DateTimePrecision::Milliseconds
+                        },
+                        Some("microseconds") | Some("microsecond") => {
+                            DateTimePrecision::Microseconds
+                        },
+                        Some("nanoseconds") | Some("nanosecond") => {
+                            DateTimePrecision::Nanoseconds
+                        },
+                        Some(precision) => {
+                            bail!(
+                                "Unknown datetime precision {precision:?} for `{name}`"
+                            )
+                        },
+                    };
+                    let mut options = DateOptions::default().set_precision(precision);
+                    if field_mapping.stored {
+                        options = options.set_stored();
+                    }
+                    if field_mapping.indexed {
+                        options = options.set_indexed();
+                    }
+                    if field_mapping.is_fast() {
+                        options = options.set_fast();
+                    }
+                    schema_builder.add_date_field(name, options);
+                },
+                // Sub-fields of objects are not mapped one by one, the whole
+                // object is indexed as JSON instead.
+                "json" | "object" => {
+                    let mut options = JsonObjectOptions::default();
+                    if field_mapping.stored {
+                        options = options.set_stored();
+                    }
+                    if field_mapping.indexed {
+                        options =
+                            options.set_indexing_options(field_mapping.text_indexing()?);
+                    }
+                    if field_mapping.is_fast() {
+                        options = options.set_fast(None);
+                    }
+                    schema_builder.add_json_field(name, options);
+                },
+                _ => bail!("Unsupported field type {field_type:?} for `{name}`"),
+            }
+        }
+        let dynamic_field = match doc_mapping.mode.as_str() {
+            "dynamic" => {
+                let options = JsonObjectOptions::default()
+                    .set_stored()
+                    .set_indexing_options(TextFieldIndexing::default());
+                Some(schema_builder.add_json_field(DYNAMIC_FIELD_NAME, options))
+            },
+            "lenient" | "strict" => None,
+            mode => bail!("Unknown doc mapping mode {mode:?}"),
+        };
+        Ok(Self {
+            schema: schema_builder.build(),
+            dynamic_field,
+        })
+    }
+
+    fn to_document(
+        &self,
+        json_doc: Map<String, Value>,
+    ) -> anyhow::Result<TantivyDocument> {
+        let mut doc = TantivyDocument::default();
+        let mut dynamic_fields = Map::new();
+        for (field_name, json_value) in json_doc {
+            let Ok(field) = self.schema.get_field(&field_name) else {
+                dynamic_fields.insert(field_name, json_value);
+                continue;
+            };
+            let field_type = self.schema.get_field_entry(field).field_type();
+            let json_values = match json_value {
+                Value::Array(json_values) => json_values,
+                json_value => vec![json_value],
+            };
+            for json_value in json_values {
+                if let (FieldType::Date(_), Value::Number(timestamp)) =
+                    (field_type, &json_value)
+                {
+                    doc.add_date(field, datetime_from_unix_timestamp(timestamp)?);
+                    continue;
+                }
+                let value =
+                    field_type.value_from_json(json_value).with_context(|| {
+                        format!("Invalid value for field `{field_name}`")
+                    })?;
+                doc.add_field_value(field, value);
+            }
+        }
+        if let Some(dynamic_field) = self.dynamic_field {
+            if !dynamic_fields.is_empty() {
+                let field_type = self.schema.get_field_entry(dynamic_field).field_type();
+                let value = field_type.value_from_json(Value::Object(dynamic_fields))?;
+                doc.add_field_value(dynamic_field, value);
+            }
+        }
+        Ok(doc)
+    }
+}
+
+/// Like Quickwit's `unix_timestamp` input format, the precision is inferred
+/// from the magnitude of the timestamp.
+fn datetime_from_unix_timestamp(
+    timestamp: &serde_json::Number,
+) -> anyhow::Result<DateTime> {
+    if let Some(timestamp) = timestamp.as_i64() {
+        let datetime = match timestamp.unsigned_abs() {
+            0..=99_999_999_999 => DateTime::from_timestamp_secs(timestamp),
+            100_000_000_000..=99_999_999_999_999 => {
+                DateTime::from_timestamp_millis(timestamp)
+            },
+            100_000_000_000_000..=99_999_999_999_999_999 => {
+                DateTime::from_timestamp_micros(timestamp)
+            },
+            _ => DateTime::from_timestamp_nanos(timestamp),
+        };
+        return Ok(datetime);
+    }
+    let Some(timestamp_secs) = timestamp.as_f64() else {
+        bail!("Invalid unix timestamp {timestamp}");
+    };
+    Ok(DateTime::from_timestamp_nanos(
+        (timestamp_secs * 1_000_000_000.0) as i64,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tantivy::schema::document::Value as _;
+
+    use super::*;
+
+    #[test]
+    fn test_mapping_from_quickwit_index_config() {
+        let index_config: IndexConfig = serde_yaml::from_str(
+            r#"
+version: 0.8
+index_id: generated-logs
+doc_mapping:
+  field_mappings:
+    - name: timestamp
+      type: datetime
+      precision: milliseconds
+      fast: true
+      input_formats:
+        - unix_timestamp
+    - name: message
+      type: text
+      record: position
+    - name: tags
+      type: array<text>
+      tokenizer: raw
+"#,
+        )
+        .unwrap();
+        let mapping = Mapping::from_doc_mapping(&index_config.doc_mapping).unwrap();
+        let timestamp = mapping.schema.get_field("timestamp").unwrap();
+        let tags = mapping.schema.get_field("tags").unwrap();
+        assert!(mapping.dynamic_field.is_some());
+
+        let Value::Object(json_doc) = json!({
+            "timestamp": 1_700_000_000_123i64,
+            "message": "hello",
+            "tags": ["a", "b"],
+            "unmapped": 3,
+        }) else {
+            unreachable!()
+        };
+        let doc = mapping.to_document(json_doc).unwrap();
+        let datetime = doc.get_first(timestamp).unwrap().as_datetime().unwrap();
+        assert_eq!(datetime.into_timestamp_millis(), 1_700_000_000_123);
+        assert_eq!(doc.get_all(tags).count(), 2);
+        assert!(doc.get_first(mapping.dynamic_field.unwrap()).is_some());
+    }
+}
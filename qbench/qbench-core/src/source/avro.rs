@@ -0,0 +1,156 @@
+use std::io::{self, Read};
+use std::mem;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::Context;
+use bytes::Bytes;
+use serde_json::Value as JsonValue;
+use tokio_util::io::SyncIoBridge;
+
+use super::UriReader;
+
+/// Converts the records of an Avro object container file to NDJSON batches.
+///
+/// The Avro decoder is synchronous: it runs on a blocking thread, reading
+/// from the async reader through a bridge.
+pub(crate) struct AvroBatchReader {
+    batch_rx: flume::Receiver<anyhow::Result<Bytes>>,
+    b3_hash: Arc<OnceLock<String>>,
+}
+
+impl AvroBatchReader {
+    pub fn new(reader: UriReader, max_batch_num_bytes: usize) -> Self {
+        let (batch_tx, batch_rx) = flume::bounded(1);
+        let b3_hash = Arc::new(OnceLock::new());
+        tokio::task::spawn_blocking({
+            let b3_hash = b3_hash.clone();
+            move || {
+                let mut reader = HashingReader {
+                    reader: SyncIoBridge::new(reader),
+                    hasher: blake3::Hasher::new(),
+                };
+                match read_records(&mut reader, max_batch_num_bytes, &batch_tx) {
+                    Ok(true) => {
+                        let _ =
+                            b3_hash.set(reader.hasher.finalize().to_hex().to_string());
+                    },
+                    Ok(false) => {},
+                    Err(error) => {
+                        let _ = batch_tx.send(Err(error));
+                    },
+                }
+            }
+        });
+        Self { batch_rx, b3_hash }
+    }
+
+    /// The blake3 hash of the Avro file, once all its records have been
+    /// read.
+    pub fn b3_hash(&self) -> Option<String> {
+        self.b3_hash.get().cloned()
+    }
+
+    pub async fn next_batch(&mut self) -> anyhow::Result<Option<Bytes>> {
+        match self.batch_rx.recv_async().await {
+            Ok(batch_res) => batch_res.map(Some),
+            // The file has been read entirely.
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Hashes the bytes as they are read.
+struct HashingReader<R> {
+    reader: R,
+    hasher: blake3::Hasher,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let num_bytes = self.reader.read(buf)?;
+        self.hasher.update(&buf[..num_bytes]);
+        Ok(num_bytes)
+    }
+}
+
+/// Returns whether the file has been read to the end.
+fn read_records(
+    mut reader: impl Read,
+    max_batch_num_bytes: usize,
+    batch_tx: &flume::Sender<anyhow::Result<Bytes>>,
+) -> anyhow::Result<bool> {
+    let avro_reader =
+        apache_avro::Reader::new(&mut reader).context("Failed to read Avro header")?;
+    let mut batch = Vec::with_capacity(max_batch_num_bytes);
+    for value_res in avro_reader {
+        let value = value_res.context("Failed to read Avro record")?;
+        let doc = JsonValue::try_from(value)
+            .context("Failed to convert Avro record to JSON")?;
+        let doc_start = batch.len();
+        serde_json::to_writer(&mut batch, &doc)?;
+        batch.push(b'\n');
+        if batch.len() > max_batch_num_bytes && doc_start > 0 {
+            let next_batch = batch.split_off(doc_start);
+            let full_batch = mem::replace(&mut batch, next_batch);
+            if batch_tx.send(Ok(Bytes::from(full_batch))).is_err() {
+                // The consumer stopped reading.
+                return Ok(false);
+            }
+        }
+    }
+    // Trailing bytes after the last block, if any, are part of the file.
+    io::copy(&mut reader, &mut io::sink())?;
+    if !batch.is_empty() && batch_tx.send(Ok(Bytes::from(batch))).is_err() {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::types::Record;
+    use apache_avro::{Schema, Writer};
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_avro_batch_reader() {
+        let schema = Schema::parse_str(
+            r#"{"type": "record", "name": "event", "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "message", "type": ["null", "string"]}
+            ]}"#,
+        )
+        .unwrap();
+        let mut writer = Writer::new(&schema, Vec::new());
+        for id in 0..3 {
+            let mut record = Record::new(&schema).unwrap();
+            record.put("id", id as i64);
+            let message = (id != 1).then(|| format!("message {id}"));
+            record.put("message", apache_avro::types::Value::from(message));
+            writer.append(record).unwrap();
+        }
+        let avro_file = writer.into_inner().unwrap();
+
+        // Small enough for each document to end up in its own batch.
+        let mut batch_reader =
+            AvroBatchReader::new(Box::new(std::io::Cursor::new(avro_file.clone())), 10);
+        let mut docs: Vec<JsonValue> = Vec::new();
+        while let Some(batch) = batch_reader.next_batch().await.unwrap() {
+            docs.push(serde_json::from_slice(&batch).unwrap());
+        }
+        assert_eq!(
+            docs,
+            vec![
+                json!({"id": 0, "message": "message 0"}),
+                json!({"id": 1, "message": null}),
+                json!({"id": 2, "message": "message 2"}),
+            ]
+        );
+        assert_eq!(
+            batch_reader.b3_hash().unwrap(),
+            blake3::hash(&avro_file).to_hex().as_str()
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{bail, Context};
+use serde_json::{Map, Number, Value};
+
+/// How delimited datasets are turned into JSON documents.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    /// Convert integer, float and boolean values to JSON numbers and
+    /// booleans, and drop empty values. Otherwise, all values are strings.
+    pub infer_types: bool,
+}
+
+/// Converts the records of a delimited file to NDJSON documents keyed by the
+/// file's header row, batch after batch.
+///
+/// Records are expected on a single line: quoted fields containing line
+/// breaks are not supported.
+pub(crate) struct CsvDecoder {
+    options: CsvOptions,
+    header: Option<Vec<String>>,
+    num_records: u64,
+}
+
+impl CsvDecoder {
+    pub fn new(options: CsvOptions) -> Self {
+        Self {
+            options,
+            header: None,
+            num_records: 0,
+        }
+    }
+
+    pub fn decode(&mut self, batch: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.options.delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(batch);
+        let mut docs = Vec::with_capacity(batch.len() * 2);
+        for record_res in reader.records() {
+            let record = record_res.context("Failed to parse CSV record")?;
+            let Some(header) = &self.header else {
+                let header = record
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, name)| {
+                        // Spreadsheet exports often start with a BOM.
+                        let name = if idx == 0 {
+                            name.trim_start_matches('\u{feff}')
+                        } else {
+                            name
+                        };
+                        name.to_string()
+                    })
+                    .collect();
+                self.header = Some(header);
+                continue;
+            };
+            self.num_records += 1;
+            if record.len() != header.len() {
+                bail!(
+                    "CSV record {} has {} fields, but the header has {}",
+                    self.num_records,
+                    record.len(),
+                    header.len()
+                );
+            }
+            let mut doc = Map::with_capacity(header.len());
+            for (name, value) in header.iter().zip(record.iter()) {
+                let value = if self.options.infer_types {
+                    match infer_value(value) {
+                        Some(value) => value,
+                        None => continue,
+                    }
+                } else {
+                    Value::String(value.to_string())
+                };
+                doc.insert(name.clone(), value);
+            }
+            serde_json::to_writer(&mut docs, &doc)?;
+            docs.push(b'\n');
+        }
+        Ok(docs)
+    }
+}
+
+/// Returns `None` for empty values.
+fn infer_value(value: &str) -> Option<Value> {
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(int_value) = value.parse::<i64>() {
+        return Some(Value::from(int_value));
+    }
+    if let Some(float_value) = value.parse::<f64>().ok().and_then(Number::from_f64) {
+        return Some(Value::Number(float_value));
+    }
+    match value {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ => Some(Value::String(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse_docs(docs: &[u8]) -> Vec<Value> {
+        docs.split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_csv_decoder() {
+        let mut decoder = CsvDecoder::new(CsvOptions {
+            delimiter: b',',
+            infer_types: true,
+        });
+        let docs = decoder
+            .decode(b"\xEF\xBB\xBFid,fare,paid,note\r\n1,12.5,true,\"a, b\"\n")
+            .unwrap();
+        assert_eq!(
+            parse_docs(&docs),
+            vec![json!({"id": 1, "fare": 12.5, "paid": true, "note": "a, b"})]
+        );
+        // The header is kept from one batch to the next.
+        let docs = decoder.decode(b"2,,false,NaN\n").unwrap();
+        assert_eq!(
+            parse_docs(&docs),
+            vec![json!({"id": 2, "paid": false, "note": "NaN"})]
+        );
+        assert!(decoder.decode(b"3,1.0\n").is_err());
+
+        let mut tsv_decoder = CsvDecoder::new(CsvOptions {
+            delimiter: b'\t',
+            infer_types: false,
+        });
+        let docs = tsv_decoder.decode(b"id\tfare\n1\t\n").unwrap();
+        assert_eq!(parse_docs(&docs), vec![json!({"id": "1", "fare": ""})]);
+    }
+}
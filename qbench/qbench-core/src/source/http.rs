@@ -0,0 +1,660 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use tracing::Instrument;
+
+use super::csv::CsvDecoder;
+use super::mutate::mutate_ids;
+use super::progress::{SourceProgress, UriProgress, UriState};
+use super::sampler::DocSampler;
+use super::{
+    expand_uris,
+    gcs_auth,
+    DatasetFormat,
+    DocumentBatch,
+    ShardInfo,
+    UriBatchReader,
+    UriErrorPolicy,
+    GCS_URI_PREFIX,
+};
+use crate::gcp_auth::GcpAuth;
+use crate::source::Source;
+
+/// A dataset source that produces data by streaming from a 3rd party HTTP
+/// server, from GCS (`gs://bucket/object`) or from local files.
+///
+/// This source can expand range short hand to produce multiple uris e.g.
+///
+/// `https://data.gharchive.org/2015-01-{01..31}-{0..23}.json.gz` to download the
+/// entire month of the 2015 Jan dataset.
+///
+/// Local directories and glob patterns (e.g. `/data/gharchive/*.json.gz`)
+/// are expanded to the sorted list of files they contain or match.
+///
+/// The source will also automatically decompress data if a uri ends with `.gz`.
+///
+/// CSV and TSV files are converted to JSON documents using their header row,
+/// Avro files record by record.
+///
+/// GCS objects are read with application default credentials, unless an
+/// access token is given.
+///
+/// With a sampler, only a deterministic subset of the documents is produced.
+/// The dataset can also be repeated to reach a larger corpus size.
+///
+/// The URIs are read one after the other, or one batch from each in turn when
+/// interleaved.
+///
+/// The per-URI progress of the stream is exposed through `progress()`.
+pub struct UriSource {
+    uris: VecDeque<String>,
+    progress: Arc<SourceProgress>,
+    gcp_access_token: Option<String>,
+    dataset_format: DatasetFormat,
+    csv_infer_types: bool,
+    sampler: Option<DocSampler>,
+    num_repetitions: usize,
+    mutate_ids: bool,
+    uri_error_policy: UriErrorPolicy,
+    /// The maximum number of URIs open at once, when interleaved.
+    interleave_uris: Option<usize>,
+}
+
+impl UriSource {
+    pub fn new(uri: &str) -> anyhow::Result<Self> {
+        let uris = expand_uris(uri.to_string())?;
+        let progress = Arc::new(SourceProgress::new(uris.iter().cloned()));
+        Ok(Self {
+            uris,
+            progress,
+            gcp_access_token: None,
+            dataset_format: DatasetFormat::Ndjson,
+            csv_infer_types: false,
+            sampler: None,
+            num_repetitions: 1,
+            mutate_ids: false,
+            uri_error_policy: UriErrorPolicy::Abort,
+            interleave_uris: None,
+        })
+    }
+
+    pub fn with_gcp_access_token(mut self, access_token: &str) -> Self {
+        self.gcp_access_token = Some(access_token.to_string());
+        self
+    }
+
+    /// Reads the dataset's files in another format than NDJSON.
+    /// `csv_infer_types` only applies to CSV and TSV files.
+    pub fn with_dataset_format(
+        mut self,
+        dataset_format: DatasetFormat,
+        csv_infer_types: bool,
+    ) -> Self {
+        self.dataset_format = dataset_format;
+        self.csv_infer_types = csv_infer_types;
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: DocSampler) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Streams the dataset `num_repetitions` times. If `mutate_ids` is set,
+    /// the IDs and timestamps of the repeated documents are perturbed so that
+    /// engines don't dedupe them.
+    pub fn with_repetitions(mut self, num_repetitions: usize, mutate_ids: bool) -> Self {
+        self.num_repetitions = num_repetitions;
+        self.mutate_ids = mutate_ids;
+        self
+    }
+
+    /// Reads the URIs in the random order given by the seed, rather than in
+    /// their expansion order.
+    pub fn with_shuffled_uris(mut self, seed: u64) -> Self {
+        self.uris
+            .make_contiguous()
+            .shuffle(&mut StdRng::seed_from_u64(seed));
+        self.progress = Arc::new(SourceProgress::new(self.uris.iter().cloned()));
+        self
+    }
+
+    /// Reads one batch from each URI in turn rather than the URIs one after
+    /// the other, mixing the documents of time-partitioned files. Up to
+    /// `max_open_uris` are read in turn, the next URI taking the place of one
+    /// read to the end.
+    pub fn with_interleaved_uris(mut self, max_open_uris: usize) -> Self {
+        self.interleave_uris = Some(max_open_uris.max(1));
+        self
+    }
+
+    pub fn with_uri_error_policy(mut self, uri_error_policy: UriErrorPolicy) -> Self {
+        self.uri_error_policy = uri_error_policy;
+        self
+    }
+
+    pub fn progress(&self) -> Arc<SourceProgress> {
+        self.progress.clone()
+    }
+}
+
+/// How the URIs of a stream are read.
+struct ReadOptions {
+    batch_size: usize,
+    gcs_auth: Option<Arc<GcpAuth>>,
+    dataset_format: DatasetFormat,
+    csv_infer_types: bool,
+    /// Read the URIs in turn rather than one after the other, with up to
+    /// this many of them open at once.
+    interleave_uris: Option<usize>,
+}
+
+/// What has been read from a URI so far, kept across the attempts at reading
+/// it.
+struct UriReadState {
+    /// The number of bytes of the reader's output already turned into
+    /// documents, skipped when resuming.
+    num_consumed_bytes: u64,
+    /// The CSV header is only read on the first attempt.
+    csv_decoder: Option<CsvDecoder>,
+    /// Documents not sent yet. Its allocation is reused once the batches
+    /// split off it are dropped.
+    bytes: BytesMut,
+}
+
+impl UriReadState {
+    fn new(read_options: &ReadOptions) -> Self {
+        Self {
+            num_consumed_bytes: 0,
+            csv_decoder: read_options
+                .dataset_format
+                .csv_options(read_options.csv_infer_types)
+                .map(CsvDecoder::new),
+            bytes: BytesMut::new(),
+        }
+    }
+}
+
+/// Turns a batch read from a URI into documents: skips what was consumed
+/// before a retry, converts CSV, samples and mutates the IDs. Returns `None`
+/// if the whole batch was consumed before.
+fn decode_batch(
+    batch: Bytes,
+    uri_progress: &UriProgress,
+    uri_state: &mut UriReadState,
+    num_bytes_to_skip: &mut u64,
+    sampler: Option<&mut DocSampler>,
+    mutated_repetition: Option<u64>,
+) -> anyhow::Result<Option<Bytes>> {
+    // Batches are made of whole lines, so resuming after the consumed
+    // bytes resumes after a line.
+    let num_skipped_bytes = (*num_bytes_to_skip).min(batch.len() as u64);
+    *num_bytes_to_skip -= num_skipped_bytes;
+    let batch = batch.slice(num_skipped_bytes as usize..);
+    if batch.is_empty() {
+        return Ok(None);
+    }
+    let num_batch_bytes = batch.len() as u64;
+    uri_progress.add_read_bytes(num_batch_bytes);
+    let batch = match &mut uri_state.csv_decoder {
+        Some(csv_decoder) => Bytes::from(csv_decoder.decode(&batch)?),
+        None => batch,
+    };
+    let batch = match sampler {
+        Some(sampler) => Bytes::from(sampler.sample(&batch)),
+        None => batch,
+    };
+    let batch = match mutated_repetition {
+        Some(repetition) => Bytes::from(mutate_ids(&batch, repetition)?),
+        None => batch,
+    };
+    uri_state.num_consumed_bytes += num_batch_bytes;
+    uri_progress.add_docs(
+        batch
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .count() as u64,
+    );
+    Ok(Some(batch))
+}
+
+/// Adds documents to the batch being assembled in `bytes`, sending it once
+/// full.
+fn push_documents(
+    bytes: &mut BytesMut,
+    batch: Bytes,
+    batch_size: usize,
+    batch_tx: &flume::Sender<anyhow::Result<DocumentBatch>>,
+) -> anyhow::Result<()> {
+    if bytes.len() + batch.len() > batch_size {
+        batch_tx.send(Ok(DocumentBatch {
+            bytes: bytes.split().freeze(),
+            last: false,
+        }))?;
+    }
+    if bytes.is_empty() && batch.len() <= batch_size && batch.len() * 2 > batch_size {
+        // Too large to be merged with most batches, sent as read rather
+        // than copied.
+        batch_tx.send(Ok(DocumentBatch {
+            bytes: batch,
+            last: false,
+        }))?;
+    } else if batch.len() > batch_size {
+        // Converted CSV batches can outgrow the batch size, split them
+        // line by line.
+        for line in batch.split_inclusive(|byte| *byte == b'\n') {
+            if !bytes.is_empty() && bytes.len() + line.len() > batch_size {
+                batch_tx.send(Ok(DocumentBatch {
+                    bytes: bytes.split().freeze(),
+                    last: false,
+                }))?;
+            }
+            bytes.extend_from_slice(line);
+        }
+    } else {
+        bytes.extend_from_slice(&batch);
+    }
+    Ok(())
+}
+
+#[instrument(level = "debug", skip_all, fields(uri = %uri_progress.uri))]
+async fn send_documents_from_uri(
+    uri_progress: &UriProgress,
+    batch_tx: &flume::Sender<anyhow::Result<DocumentBatch>>,
+    last_uri: bool,
+    read_options: &ReadOptions,
+    uri_state: &mut UriReadState,
+    mut sampler: Option<&mut DocSampler>,
+    mutated_repetition: Option<u64>,
+) -> anyhow::Result<()> {
+    let uri = &uri_progress.uri;
+    info!("Send data from uri: {uri:?}", uri = uri);
+    let batch_size = read_options.batch_size;
+    let mut batch_reader = UriBatchReader::open(
+        uri,
+        batch_size,
+        read_options.gcs_auth.as_deref(),
+        read_options.dataset_format,
+    )
+    .await?;
+    let mut num_bytes_to_skip = uri_state.num_consumed_bytes;
+    let mut sampler_exhausted = false;
+    while let Some(batch) = batch_reader
+        .next_batch()
+        .instrument(debug_span!("source_read"))
+        .await?
+    {
+        let _batch_assembly_span = debug_span!("batch_assembly").entered();
+        let Some(batch) = decode_batch(
+            batch,
+            uri_progress,
+            uri_state,
+            &mut num_bytes_to_skip,
+            sampler.as_deref_mut(),
+            mutated_repetition,
+        )?
+        else {
+            continue;
+        };
+        push_documents(&mut uri_state.bytes, batch, batch_size, batch_tx)?;
+        if sampler.as_deref().is_some_and(DocSampler::is_exhausted) {
+            sampler_exhausted = true;
+            break;
+        }
+    }
+    if !sampler_exhausted {
+        if let Some(b3_hash) = batch_reader.b3_hash() {
+            uri_progress.set_b3_hash(b3_hash);
+        }
+    }
+    // Don't forget to send the last batch.
+    batch_tx.send(Ok(DocumentBatch {
+        bytes: uri_state.bytes.split().freeze(),
+        last: last_uri || sampler_exhausted,
+    }))?;
+
+    Ok::<_, anyhow::Error>(())
+}
+
+/// A URI read in turn with the others of an interleaved stream.
+struct InterleavedUri<'a> {
+    uri_idx: usize,
+    uri_progress: &'a UriProgress,
+    uri_state: UriReadState,
+    /// Opened on the URI's first turn, and reopened after an error.
+    batch_reader: Option<UriBatchReader>,
+    num_bytes_to_skip: u64,
+    num_retries: usize,
+}
+
+impl InterleavedUri<'_> {
+    /// Reads the next batch of documents of the URI, `None` once it is read
+    /// to the end.
+    async fn next_batch(
+        &mut self,
+        read_options: &ReadOptions,
+        mut sampler: Option<&mut DocSampler>,
+        mutated_repetition: Option<u64>,
+    ) -> anyhow::Result<Option<Bytes>> {
+        let batch_reader = match &mut self.batch_reader {
+            Some(batch_reader) => batch_reader,
+            None => {
+                info!("Send data from uri: {uri:?}", uri = self.uri_progress.uri);
+                self.uri_progress.set_state(UriState::Reading);
+                self.num_bytes_to_skip = self.uri_state.num_consumed_bytes;
+                self.batch_reader.insert(
+                    UriBatchReader::open(
+                        &self.uri_progress.uri,
+                        read_options.batch_size,
+                        read_options.gcs_auth.as_deref(),
+                        read_options.dataset_format,
+                    )
+                    .await?,
+                )
+            },
+        };
+        while let Some(batch) = batch_reader
+            .next_batch()
+            .instrument(debug_span!("source_read"))
+            .await?
+        {
+            let batch = decode_batch(
+                batch,
+                self.uri_progress,
+                &mut self.uri_state,
+                &mut self.num_bytes_to_skip,
+                sampler.as_deref_mut(),
+                mutated_repetition,
+            )?;
+            if batch.is_some() {
+                return Ok(batch);
+            }
+        }
+        if let Some(b3_hash) = batch_reader.b3_hash() {
+            self.uri_progress.set_b3_hash(b3_hash);
+        }
+        Ok(None)
+    }
+}
+
+/// Sends the documents of the URIs one read batch from each in turn, rather
+/// than one URI after the other, so that the documents of time-partitioned
+/// files are mixed. Only `read_options.interleave_uris` URIs are read in turn
+/// at once, the next one joining them when one is read to the end. Returns
+/// `false` if the stream stopped early.
+async fn send_interleaved_documents_from_uris(
+    uris: &[UriProgress],
+    batch_tx: &flume::Sender<anyhow::Result<DocumentBatch>>,
+    last_repetition: bool,
+    read_options: &ReadOptions,
+    uri_error_policy: UriErrorPolicy,
+    mut sampler: Option<&mut DocSampler>,
+    mutated_repetition: Option<u64>,
+) -> anyhow::Result<bool> {
+    let max_open_uris = read_options.interleave_uris.unwrap_or(uris.len());
+    let mut pending_uris =
+        uris.iter()
+            .enumerate()
+            .map(|(uri_idx, uri_progress)| InterleavedUri {
+                uri_idx,
+                uri_progress,
+                uri_state: UriReadState::new(read_options),
+                batch_reader: None,
+                num_bytes_to_skip: 0,
+                num_retries: 0,
+            });
+    let mut interleaved_uris: VecDeque<InterleavedUri> =
+        pending_uris.by_ref().take(max_open_uris).collect();
+    let mut bytes = BytesMut::new();
+    while let Some(mut interleaved_uri) = interleaved_uris.pop_front() {
+        let uri_idx = interleaved_uri.uri_idx;
+        let uri_progress = interleaved_uri.uri_progress;
+        match interleaved_uri
+            .next_batch(read_options, sampler.as_deref_mut(), mutated_repetition)
+            .await
+        {
+            Ok(Some(batch)) => {
+                push_documents(&mut bytes, batch, read_options.batch_size, batch_tx)?;
+                if sampler.as_deref().is_some_and(DocSampler::is_exhausted) {
+                    info!("Reached the maximum number of docs or bytes to send");
+                    batch_tx.send(Ok(DocumentBatch {
+                        bytes: bytes.split().freeze(),
+                        last: true,
+                    }))?;
+                    return Ok(false);
+                }
+                interleaved_uris.push_back(interleaved_uri);
+                continue;
+            },
+            Ok(None) => uri_progress.set_state(UriState::Done),
+            Err(error) => {
+                if batch_tx.is_disconnected() {
+                    // The consumer stopped reading early, e.g. once the budget is
+                    // exhausted or enough documents are sampled.
+                    return Ok(false);
+                }
+                match handle_uri_error(
+                    uri_idx,
+                    uri_progress,
+                    &error,
+                    uri_error_policy,
+                    &mut interleaved_uri.num_retries,
+                )
+                .await
+                {
+                    UriErrorAction::Retry => {
+                        interleaved_uri.batch_reader = None;
+                        interleaved_uris.push_back(interleaved_uri);
+                        continue;
+                    },
+                    UriErrorAction::Skip => {},
+                    UriErrorAction::Abort => {
+                        // The documents read before the error are valid.
+                        if !bytes.is_empty() {
+                            batch_tx.send(Ok(DocumentBatch {
+                                bytes: bytes.split().freeze(),
+                                last: false,
+                            }))?;
+                        }
+                        batch_tx.send(Err(error))?;
+                        return Ok(false);
+                    },
+                }
+            },
+        }
+        // The URI is done with, the next one takes its turn.
+        interleaved_uris.extend(pending_uris.next());
+    }
+    // Don't forget to send the last batch.
+    batch_tx.send(Ok(DocumentBatch {
+        bytes: bytes.split().freeze(),
+        last: last_repetition,
+    }))?;
+    Ok(true)
+}
+
+/// The delay before retrying a URI under the `retry:N` policy.
+const URI_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// What to do with a URI that failed to be read.
+enum UriErrorAction {
+    /// Read it again, from where the error happened.
+    Retry,
+    Skip,
+    /// End the stream with the error.
+    Abort,
+}
+
+/// Records the error of a URI and applies the URI error policy to it, waiting
+/// before a retry. `num_retries` counts the retries of the URI so far.
+async fn handle_uri_error(
+    uri_idx: usize,
+    uri_progress: &UriProgress,
+    error: &anyhow::Error,
+    uri_error_policy: UriErrorPolicy,
+    num_retries: &mut usize,
+) -> UriErrorAction {
+    uri_progress.record_error();
+    match uri_error_policy {
+        UriErrorPolicy::Retry(max_retries) if *num_retries < max_retries => {
+            *num_retries += 1;
+            warn!(uri_idx, uri = uri_progress.uri.as_str(), error = ?error, num_retries, "Failed to send documents from uri, retrying");
+            tokio::time::sleep(URI_RETRY_DELAY).await;
+            UriErrorAction::Retry
+        },
+        UriErrorPolicy::Skip => {
+            uri_progress.set_state(UriState::Skipped);
+            warn!(uri_idx, uri = uri_progress.uri.as_str(), error = ?error, "Failed to send documents from uri, skipping it");
+            UriErrorAction::Skip
+        },
+        UriErrorPolicy::Abort | UriErrorPolicy::Retry(_) => {
+            uri_progress.set_state(UriState::Failed);
+            error!(uri_idx, uri = uri_progress.uri.as_str(), error = ?error, "Failed to send documents from uri");
+            UriErrorAction::Abort
+        },
+    }
+}
+
+async fn send_documents_from_uris(
+    progress: Arc<SourceProgress>,
+    batch_tx: flume::Sender<anyhow::Result<DocumentBatch>>,
+    read_options: ReadOptions,
+    uri_error_policy: UriErrorPolicy,
+    mut sampler: Option<DocSampler>,
+    num_repetitions: usize,
+    mutate_ids: bool,
+) -> anyhow::Result<()> {
+    'repetitions: for repetition in 0..num_repetitions {
+        if num_repetitions > 1 {
+            info!(
+                "Sending dataset repetition {}/{}",
+                repetition + 1,
+                num_repetitions
+            );
+        }
+        // The first repetition is the original dataset.
+        let mutated_repetition =
+            (mutate_ids && repetition > 0).then_some(repetition as u64);
+        let uris = &progress.uris;
+        if read_options.interleave_uris.is_some() {
+            let last_repetition = repetition == num_repetitions - 1;
+            if !send_interleaved_documents_from_uris(
+                uris,
+                &batch_tx,
+                last_repetition,
+                &read_options,
+                uri_error_policy,
+                sampler.as_mut(),
+                mutated_repetition,
+            )
+            .await?
+            {
+                break;
+            }
+            continue;
+        }
+        for (uri_idx, uri_progress) in uris.iter().enumerate() {
+            if sampler.as_ref().is_some_and(DocSampler::is_exhausted) {
+                info!("Reached the maximum number of docs or bytes to send");
+                break 'repetitions;
+            }
+            let last = repetition == num_repetitions - 1 && uri_idx == uris.len() - 1;
+            uri_progress.set_state(UriState::Reading);
+            let mut uri_state = UriReadState::new(&read_options);
+            let mut num_retries = 0;
+            loop {
+                let Err(error) = send_documents_from_uri(
+                    uri_progress,
+                    &batch_tx,
+                    last,
+                    &read_options,
+                    &mut uri_state,
+                    sampler.as_mut(),
+                    mutated_repetition,
+                )
+                .await
+                else {
+                    uri_progress.set_state(UriState::Done);
+                    break;
+                };
+                if batch_tx.is_disconnected() {
+                    // The consumer stopped reading early, e.g. once the budget is
+                    // exhausted or enough documents are sampled.
+                    break 'repetitions;
+                }
+                // The documents read before the error are valid.
+                if !uri_state.bytes.is_empty() {
+                    batch_tx.send(Ok(DocumentBatch {
+                        bytes: uri_state.bytes.split().freeze(),
+                        last: false,
+                    }))?;
+                }
+                match handle_uri_error(
+                    uri_idx,
+                    uri_progress,
+                    &error,
+                    uri_error_policy,
+                    &mut num_retries,
+                )
+                .await
+                {
+                    UriErrorAction::Retry => {},
+                    UriErrorAction::Skip => break,
+                    UriErrorAction::Abort => {
+                        batch_tx.send(Err(error))?;
+                        break 'repetitions;
+                    },
+                }
+            }
+        }
+    }
+    Ok::<_, anyhow::Error>(())
+}
+
+#[async_trait]
+impl Source for UriSource {
+    async fn batch_stream(
+        &self,
+        batch_size: usize,
+    ) -> anyhow::Result<flume::Receiver<anyhow::Result<DocumentBatch>>> {
+        let (batch_tx, batch_rx) = flume::bounded(1);
+        let gcs_auth = if self.uris.iter().any(|uri| uri.starts_with(GCS_URI_PREFIX)) {
+            Some(Arc::new(gcs_auth(self.gcp_access_token.as_deref())?))
+        } else {
+            None
+        };
+        tokio::task::spawn(send_documents_from_uris(
+            self.progress.clone(),
+            batch_tx,
+            ReadOptions {
+                batch_size,
+                gcs_auth,
+                dataset_format: self.dataset_format,
+                csv_infer_types: self.csv_infer_types,
+                interleave_uris: self.interleave_uris,
+            },
+            self.uri_error_policy,
+            self.sampler.clone(),
+            self.num_repetitions,
+            self.mutate_ids,
+        ));
+        Ok(batch_rx)
+    }
+
+    fn shard_infos(&self) -> Vec<ShardInfo> {
+        self.progress
+            .uris
+            .iter()
+            .map(|uri_progress| ShardInfo {
+                uri: uri_progress.uri.clone(),
+                b3_hash: uri_progress.b3_hash().unwrap_or_default().to_string(),
+            })
+            .collect()
+    }
+}
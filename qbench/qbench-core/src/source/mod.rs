@@ -0,0 +1,620 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::{io, mem};
+
+use anyhow::{bail, Context};
+use async_compression::tokio::bufread::GzipDecoder;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::{header, Url};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+use self::avro::AvroBatchReader;
+use crate::gcp_auth::GcpAuth;
+
+mod avro;
+mod csv;
+mod http;
+mod mutate;
+mod progress;
+mod sampler;
+
+pub use self::csv::CsvOptions;
+pub use self::http::UriSource;
+pub use self::progress::{SourceProgress, UriState, UriSummary};
+pub use self::sampler::DocSampler;
+
+/// The maximum size of the body to be sent as a single request. (5MB)
+pub const DEFAULT_MAX_BODY_SIZE: usize = 5_000_000;
+
+pub(crate) const GCS_URI_PREFIX: &str = "gs://";
+const GCS_READ_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+static URI_EXPAND_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\{\d+..\d+})").unwrap());
+
+/// The format of the dataset's files.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DatasetFormat {
+    /// One JSON document per line.
+    Ndjson,
+    /// Comma-separated values, with a header row.
+    Csv,
+    /// Tab-separated values, with a header row.
+    Tsv,
+    /// Avro object container files.
+    Avro,
+}
+
+impl DatasetFormat {
+    /// Guesses the format from the extension of the URI, NDJSON by default.
+    pub fn detect(uri: &str) -> Self {
+        let uri = uri.strip_suffix(".gz").unwrap_or(uri);
+        if uri.ends_with(".csv") {
+            DatasetFormat::Csv
+        } else if uri.ends_with(".tsv") {
+            DatasetFormat::Tsv
+        } else if uri.ends_with(".avro") {
+            DatasetFormat::Avro
+        } else {
+            DatasetFormat::Ndjson
+        }
+    }
+
+    /// The options to convert the files to JSON documents, if they are
+    /// delimited.
+    pub fn csv_options(&self, infer_types: bool) -> Option<CsvOptions> {
+        let delimiter = match self {
+            DatasetFormat::Ndjson | DatasetFormat::Avro => return None,
+            DatasetFormat::Csv => b',',
+            DatasetFormat::Tsv => b'\t',
+        };
+        Some(CsvOptions {
+            delimiter,
+            infer_types,
+        })
+    }
+}
+
+impl Display for DatasetFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_ref())
+    }
+}
+
+impl FromStr for DatasetFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ndjson" => Ok(DatasetFormat::Ndjson),
+            "csv" => Ok(DatasetFormat::Csv),
+            "tsv" => Ok(DatasetFormat::Tsv),
+            "avro" => Ok(DatasetFormat::Avro),
+            _ => Err(format!("Unknown dataset format {s:?}")),
+        }
+    }
+}
+
+impl AsRef<str> for DatasetFormat {
+    fn as_ref(&self) -> &str {
+        match self {
+            DatasetFormat::Ndjson => "ndjson",
+            DatasetFormat::Csv => "csv",
+            DatasetFormat::Tsv => "tsv",
+            DatasetFormat::Avro => "avro",
+        }
+    }
+}
+
+/// What the source does when reading a URI fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UriErrorPolicy {
+    /// Stop the run.
+    Abort,
+    /// Move on to the next URI, keeping the documents already sent.
+    Skip,
+    /// Retry the URI up to this many times, resuming after the documents
+    /// already sent, then stop the run.
+    Retry(usize),
+}
+
+impl Display for UriErrorPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UriErrorPolicy::Abort => write!(f, "abort"),
+            UriErrorPolicy::Skip => write!(f, "skip"),
+            UriErrorPolicy::Retry(max_retries) => write!(f, "retry:{max_retries}"),
+        }
+    }
+}
+
+impl FromStr for UriErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(UriErrorPolicy::Abort),
+            "skip" => Ok(UriErrorPolicy::Skip),
+            _ => s
+                .strip_prefix("retry:")
+                .and_then(|max_retries| max_retries.parse().ok())
+                .map(UriErrorPolicy::Retry)
+                .ok_or_else(|| format!("Unknown URI error policy {s:?}")),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DocumentBatch {
+    /// The NDJSON documents. Cloning it is cheap, so that sinks resending a
+    /// batch do not copy it.
+    pub bytes: Bytes,
+    pub last: bool,
+}
+
+#[async_trait]
+pub trait Source: Sync + Send + 'static {
+    /// Creates a new data source which produces request bodies.
+    async fn batch_stream(
+        &self,
+        batch_size: usize,
+    ) -> anyhow::Result<flume::Receiver<anyhow::Result<DocumentBatch>>>;
+
+    /// The dataset's URIs, with the hash of the ones that have been read
+    /// entirely.
+    fn shard_infos(&self) -> Vec<ShardInfo>;
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShardInfo {
+    pub uri: String,
+    /// The blake3 hash of the URI's (decompressed) content, computed while
+    /// streaming it. Empty if the URI was not read to the end, e.g. when the
+    /// run stopped after `--max-docs`.
+    pub b3_hash: String,
+}
+
+/// A reader over the (decompressed) content of a dataset URI.
+pub(crate) type UriReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// Opens a local file, an http(s) URL or a `gs://bucket/object` URI,
+/// decompressing it if it ends with `.gz`. `gcs_auth` is required to read
+/// `gs://` URIs.
+pub(crate) async fn open_uri(
+    uri: &str,
+    gcs_auth: Option<&GcpAuth>,
+) -> anyhow::Result<UriReader> {
+    if uri.starts_with(GCS_URI_PREFIX) {
+        let Some(gcs_auth) = gcs_auth else {
+            bail!("Missing GCP credentials to read {uri:?}");
+        };
+        open_gcs_uri(uri, gcs_auth).await
+    } else if uri.starts_with("http") {
+        open_http_uri(uri).await
+    } else {
+        open_file(uri).await
+    }
+}
+
+async fn open_http_uri(uri: &str) -> anyhow::Result<UriReader> {
+    let decompress_gzip = uri.ends_with(".gz");
+    let client = reqwest::Client::new();
+    let response = client.get(uri).send().await?;
+    reader_from_response(response, decompress_gzip)
+}
+
+/// Downloads a `gs://bucket/object` URI through the GCS JSON API.
+async fn open_gcs_uri(uri: &str, gcs_auth: &GcpAuth) -> anyhow::Result<UriReader> {
+    let decompress_gzip = uri.ends_with(".gz");
+    let (bucket, object) = uri
+        .strip_prefix(GCS_URI_PREFIX)
+        .and_then(|path| path.split_once('/'))
+        .with_context(|| format!("Invalid GCS URI {uri:?}"))?;
+    let mut url = Url::parse("https://storage.googleapis.com/storage/v1/b")
+        .expect("Invalid GCS URL");
+    // Pushing the object name as a single segment percent-encodes its `/`s.
+    url.path_segments_mut()
+        .expect("Invalid GCS URL")
+        .extend([bucket, "o", object]);
+    url.query_pairs_mut().append_pair("alt", "media");
+    let client = reqwest::Client::new();
+    let response = client
+        .get(url)
+        .header(header::AUTHORIZATION, gcs_auth.authorization().await?)
+        // Objects uploaded with `Content-Encoding: gzip` would otherwise be
+        // decompressed by GCS before we get a chance to.
+        .header(header::ACCEPT_ENCODING, "gzip")
+        .send()
+        .await?;
+    reader_from_response(response, decompress_gzip)
+}
+
+fn reader_from_response(
+    response: reqwest::Response,
+    decompress_gzip: bool,
+) -> anyhow::Result<UriReader> {
+    if response.status() != reqwest::StatusCode::OK {
+        bail!(
+            "http error with status code {}: {:?}",
+            response.status(),
+            response
+        );
+    }
+    let stream = response
+        .bytes_stream()
+        .map_err(io::Error::other)
+        .into_async_read()
+        .compat();
+    let reader = if decompress_gzip {
+        Box::new(GzipDecoder::new(BufReader::new(stream))) as UriReader
+    } else {
+        Box::new(stream) as UriReader
+    };
+    Ok(reader)
+}
+
+async fn open_file(uri: &str) -> anyhow::Result<UriReader> {
+    let decompress_gzip = uri.ends_with(".gz");
+    let file = tokio::fs::File::open(&Path::new(uri)).await?;
+    let reader = if decompress_gzip {
+        Box::new(GzipDecoder::new(BufReader::new(file))) as UriReader
+    } else {
+        Box::new(file) as UriReader
+    };
+    Ok(reader)
+}
+
+/// Reads the content of a URI as batches of NDJSON lines, converting binary
+/// formats on the fly.
+pub(crate) enum UriBatchReader {
+    Lines(BatchLineReader),
+    Avro(AvroBatchReader),
+}
+
+impl UriBatchReader {
+    pub async fn open(
+        uri: &str,
+        max_batch_num_bytes: usize,
+        gcs_auth: Option<&GcpAuth>,
+        dataset_format: DatasetFormat,
+    ) -> anyhow::Result<Self> {
+        let reader = open_uri(uri, gcs_auth).await?;
+        let batch_reader = match dataset_format {
+            DatasetFormat::Avro => {
+                Self::Avro(AvroBatchReader::new(reader, max_batch_num_bytes))
+            },
+            DatasetFormat::Ndjson | DatasetFormat::Csv | DatasetFormat::Tsv => {
+                Self::Lines(BatchLineReader::new(reader, max_batch_num_bytes))
+            },
+        };
+        Ok(batch_reader)
+    }
+
+    pub async fn next_batch(&mut self) -> anyhow::Result<Option<Bytes>> {
+        match self {
+            Self::Lines(batch_line_reader) => Ok(batch_line_reader.next_batch().await?),
+            Self::Avro(avro_batch_reader) => avro_batch_reader.next_batch().await,
+        }
+    }
+
+    /// The blake3 hash of the URI's content, once it has been read entirely.
+    pub fn b3_hash(&self) -> Option<String> {
+        match self {
+            Self::Lines(batch_line_reader) => batch_line_reader.b3_hash(),
+            Self::Avro(avro_batch_reader) => avro_batch_reader.b3_hash(),
+        }
+    }
+}
+
+pub(crate) struct BatchLineReader {
+    buf_reader: BufReader<UriReader>,
+    buffer: Vec<u8>,
+    alloc_num_bytes: usize,
+    max_batch_num_bytes: usize,
+    num_lines: usize,
+    // Boxed, the hasher's state is large.
+    hasher: Box<blake3::Hasher>,
+    b3_hash: Option<String>,
+}
+
+impl BatchLineReader {
+    pub fn new(reader: UriReader, max_batch_num_bytes: usize) -> Self {
+        let alloc_num_bytes = max_batch_num_bytes + 100 * 1024; // Add 100 KiB headroom to avoid reallocation.
+        Self {
+            buf_reader: BufReader::new(reader),
+            buffer: Vec::with_capacity(alloc_num_bytes),
+            alloc_num_bytes,
+            max_batch_num_bytes,
+            num_lines: 0,
+            hasher: Box::default(),
+            b3_hash: None,
+        }
+    }
+
+    /// The blake3 hash of all the bytes read, skipped lines included, once
+    /// the end of the reader has been reached.
+    pub fn b3_hash(&self) -> Option<String> {
+        self.b3_hash.clone()
+    }
+
+    pub async fn next_batch(&mut self) -> io::Result<Option<Bytes>> {
+        loop {
+            let line_num_bytes =
+                self.buf_reader.read_until(b'\n', &mut self.buffer).await?;
+            self.hasher
+                .update(&self.buffer[self.buffer.len() - line_num_bytes..]);
+
+            if line_num_bytes > self.max_batch_num_bytes {
+                warn!(
+                    "Skipping line {}, which exceeds the maximum allowed content length ({} vs. \
+                     {} bytes).",
+                    self.num_lines + 1,
+                    line_num_bytes,
+                    self.max_batch_num_bytes
+                );
+                let new_len = self.buffer.len() - line_num_bytes;
+                self.buffer.truncate(new_len);
+                continue;
+            }
+            if self.buffer.len() > self.max_batch_num_bytes {
+                let mut new_buffer = Vec::with_capacity(self.alloc_num_bytes);
+                let new_len = self.buffer.len() - line_num_bytes;
+                new_buffer.extend_from_slice(&self.buffer[new_len..]);
+                self.buffer.truncate(new_len);
+                let batch = mem::replace(&mut self.buffer, new_buffer);
+                return Ok(Some(Bytes::from(batch)));
+            }
+            if line_num_bytes == 0 {
+                if self.b3_hash.is_none() {
+                    self.b3_hash = Some(self.hasher.finalize().to_hex().to_string());
+                }
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                let batch = mem::take(&mut self.buffer);
+                return Ok(Some(Bytes::from(batch)));
+            }
+            self.num_lines += 1;
+        }
+    }
+}
+
+/// Whether the URI points to a remote object rather than a local file.
+pub(crate) fn is_remote_uri(uri: &str) -> bool {
+    uri.starts_with("http") || uri.starts_with(GCS_URI_PREFIX)
+}
+
+/// Resolves the credentials used to read `gs://` URIs.
+pub(crate) fn gcs_auth(access_token: Option<&str>) -> anyhow::Result<GcpAuth> {
+    match access_token {
+        Some(access_token) => Ok(GcpAuth::from_access_token(access_token)),
+        None => GcpAuth::application_default(GCS_READ_SCOPE),
+    }
+}
+
+pub struct RangeExpand<'a> {
+    replace_str: &'a str,
+    range: Range<usize>,
+    zero_pad_by: usize,
+}
+
+/// Expands a uri with the range syntax into the exported/expected uris, then
+/// local directories and glob patterns into the sorted list of files they
+/// match.
+fn expand_uris(uri: String) -> anyhow::Result<VecDeque<String>> {
+    let mut uris = VecDeque::new();
+    for uri in expand_uri_ranges(uri) {
+        if is_remote_uri(&uri) {
+            uris.push_back(uri);
+        } else if Path::new(&uri).is_dir() {
+            let mut file_paths = Vec::new();
+            for entry in std::fs::read_dir(&uri)
+                .with_context(|| format!("Failed to list directory {uri:?}"))?
+            {
+                let entry = entry?;
+                let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if entry.file_type()?.is_file() && !is_hidden {
+                    file_paths.push(entry.path().to_string_lossy().into_owned());
+                }
+            }
+            if file_paths.is_empty() {
+                bail!("No files found in directory {uri:?}");
+            }
+            file_paths.sort();
+            uris.extend(file_paths);
+        } else if uri.contains(['*', '?', '[']) {
+            let mut file_paths = Vec::new();
+            for path in glob::glob(&uri)
+                .with_context(|| format!("Invalid glob pattern {uri:?}"))?
+            {
+                let path = path?;
+                if path.is_file() {
+                    file_paths.push(path.to_string_lossy().into_owned());
+                }
+            }
+            if file_paths.is_empty() {
+                bail!("No files match {uri:?}");
+            }
+            file_paths.sort();
+            uris.extend(file_paths);
+        } else {
+            uris.push_back(uri);
+        }
+    }
+    let local_uris: Vec<&String> =
+        uris.iter().filter(|uri| !is_remote_uri(uri)).collect();
+    if !local_uris.is_empty() {
+        let mut num_bytes = 0;
+        for uri in &local_uris {
+            num_bytes += std::fs::metadata(uri)
+                .with_context(|| format!("Failed to read dataset file {uri:?}"))?
+                .len();
+        }
+        info!(
+            "Dataset has {} local files, {} in total",
+            local_uris.len(),
+            humansize::format_size(num_bytes, humansize::DECIMAL)
+        );
+    }
+    Ok(uris)
+}
+
+/// Expands a uri with the `{start..end}` range syntax.
+fn expand_uri_ranges(uri: String) -> VecDeque<String> {
+    let mut total_variants = 0;
+    let mut ranges = Vec::new();
+    for capture in URI_EXPAND_PATTERN.captures_iter(&uri) {
+        let cap = capture.get(0).unwrap();
+        let replace_str = cap.as_str();
+
+        let range_str = replace_str.trim_matches('{').trim_matches('}');
+        let (start, end) = range_str.split_once("..").unwrap();
+        let pad_start = start.starts_with('0');
+        let zero_pad_by = if pad_start { start.len() } else { 0 };
+        let start = start.parse::<usize>().unwrap();
+        let end = end.parse::<usize>().unwrap();
+        let range = start..end;
+
+        total_variants += range.len();
+        ranges.push(RangeExpand {
+            replace_str,
+            range,
+            zero_pad_by,
+        })
+    }
+
+    let mut uris = VecDeque::with_capacity(total_variants);
+    uris.push_back(uri.clone());
+
+    // This is likely horrifically un-optimised, but it does work for convenience.
+    for range in ranges {
+        for _ in 0..uris.len() {
+            let uri = uris.pop_front().unwrap();
+
+            for i in range.range.clone() {
+                let value = format!("{i:0>pad_by$}", pad_by = range.zero_pad_by);
+                let populated_uri = uri.replacen(range.replace_str, &value, 1);
+                uris.push_back(populated_uri);
+            }
+        }
+    }
+
+    uris
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_expand() {
+        let uri = "http://localhost:3000/{0..5}.json";
+        let uris = expand_uris(uri.to_string()).unwrap();
+
+        assert_eq!(
+            uris,
+            vec![
+                "http://localhost:3000/0.json",
+                "http://localhost:3000/1.json",
+                "http://localhost:3000/2.json",
+                "http://localhost:3000/3.json",
+                "http://localhost:3000/4.json",
+            ]
+        )
+    }
+
+    #[test]
+    fn test_uri_source_shuffled_uris() {
+        let uri = "http://localhost:3000/{0..20}.json";
+        let uris = |source: &UriSource| -> Vec<String> {
+            source
+                .progress()
+                .uris
+                .iter()
+                .map(|uri| uri.uri.clone())
+                .collect()
+        };
+        let source = UriSource::new(uri).unwrap();
+        let shuffled_source = UriSource::new(uri).unwrap().with_shuffled_uris(3);
+        // The same seed reads the URIs in the same order.
+        assert_eq!(
+            uris(&shuffled_source),
+            uris(&UriSource::new(uri).unwrap().with_shuffled_uris(3))
+        );
+        assert_ne!(uris(&shuffled_source), uris(&source));
+        let mut sorted_uris = uris(&shuffled_source);
+        sorted_uris.sort_by(|left, right| (left.len(), left).cmp(&(right.len(), right)));
+        assert_eq!(sorted_uris, uris(&source));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_uri_source_interleaved_uris() {
+        let dataset_dir = std::env::temp_dir()
+            .join(format!("qbench-interleave-{}", std::process::id()));
+        std::fs::create_dir_all(&dataset_dir).unwrap();
+        for (file_name, num_docs) in [("a.json", 3), ("b.json", 1), ("c.json", 2)] {
+            let docs: String = (0..num_docs)
+                .map(|idx| format!("{{\"id\": \"{file_name}{idx}\"}}\n"))
+                .collect();
+            std::fs::write(dataset_dir.join(file_name), docs).unwrap();
+        }
+        let source = UriSource::new(dataset_dir.to_str().unwrap())
+            .unwrap()
+            .with_interleaved_uris(2);
+        let batch_rx = source.batch_stream(20).await.unwrap();
+        let mut docs = Vec::new();
+        let mut num_last_batches = 0;
+        while let Ok(batch) = batch_rx.recv_async().await {
+            let batch = batch.unwrap();
+            num_last_batches += batch.last as usize;
+            docs.extend(
+                String::from_utf8(batch.bytes.to_vec())
+                    .unwrap()
+                    .lines()
+                    .map(str::to_string),
+            );
+        }
+        std::fs::remove_dir_all(&dataset_dir).unwrap();
+
+        assert_eq!(
+            docs,
+            [
+                r#"{"id": "a.json0"}"#,
+                r#"{"id": "b.json0"}"#,
+                r#"{"id": "a.json1"}"#,
+                // c.json is only opened once b.json is read to the end.
+                r#"{"id": "a.json2"}"#,
+                r#"{"id": "c.json0"}"#,
+                r#"{"id": "c.json1"}"#,
+            ]
+        );
+        assert_eq!(num_last_batches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_line_reader_b3_hash() {
+        let content =
+            b"{\"id\": 1}\n{\"id\": 2, \"message\": \"too long\"}\n{\"id\": 3}";
+        let mut batch_reader =
+            BatchLineReader::new(Box::new(std::io::Cursor::new(content.to_vec())), 20);
+        let mut batches = Vec::new();
+        while let Some(batch) = batch_reader.next_batch().await.unwrap() {
+            batches.push(batch);
+        }
+        // The long line is skipped, but still hashed.
+        assert_eq!(batches, vec![&b"{\"id\": 1}\n{\"id\": 3}"[..]]);
+        assert_eq!(
+            batch_reader.b3_hash().unwrap(),
+            blake3::hash(content).to_hex().as_str()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, SecondsFormat};
+use serde_json::{Map, Value};
+
+/// Top-level fields holding the timestamp of the documents.
+const TIMESTAMP_FIELDS: [&str; 4] = ["timestamp", "@timestamp", "time", "ts"];
+/// Added to numeric IDs at each repetition, large enough not to collide with
+/// the IDs of the original dataset.
+const NUMERIC_ID_OFFSET: i64 = 1_000_000_000_000;
+
+/// Perturbs the documents of a repeated dataset so that engines don't dedupe
+/// them: the top-level ID fields (`id`, `_id` and `*_id`) get the repetition
+/// appended, or offset if numeric, and the timestamps are shifted by
+/// `repetition` milliseconds.
+pub fn mutate_ids(batch: &[u8], repetition: u64) -> anyhow::Result<Vec<u8>> {
+    let mut mutated = Vec::with_capacity(batch.len() + batch.len() / 10);
+    for line in batch.split_inclusive(|byte| *byte == b'\n') {
+        if line.trim_ascii().is_empty() {
+            mutated.extend_from_slice(line);
+            continue;
+        }
+        let mut doc: Map<String, Value> = serde_json::from_slice(line)
+            .context("Failed to parse document line as JSON")?;
+        mutate_doc(&mut doc, repetition);
+        serde_json::to_writer(&mut mutated, &doc)?;
+        mutated.push(b'\n');
+    }
+    Ok(mutated)
+}
+
+fn mutate_doc(doc: &mut Map<String, Value>, repetition: u64) {
+    for (field_name, value) in doc.iter_mut() {
+        if field_name == "id" || field_name.ends_with("_id") {
+            match value {
+                Value::String(id) => {
+                    id.push_str(&format!("-{repetition}"));
+                },
+                Value::Number(id) => {
+                    if let Some(mutated_id) = id.as_i64().and_then(|id| {
+                        id.checked_add(NUMERIC_ID_OFFSET * repetition as i64)
+                    }) {
+                        *value = Value::from(mutated_id);
+                    }
+                },
+                _ => {},
+            }
+        } else if TIMESTAMP_FIELDS.contains(&field_name.as_str()) {
+            let shift = Duration::milliseconds(repetition as i64);
+            match value {
+                Value::String(timestamp) => {
+                    if let Ok(datetime) = DateTime::parse_from_rfc3339(timestamp) {
+                        *timestamp = (datetime + shift).to_rfc3339_opts(
+                            SecondsFormat::AutoSi,
+                            timestamp.ends_with('Z'),
+                        );
+                    }
+                },
+                // The unit of numeric timestamps is unknown, they are shifted
+                // by `repetition` units.
+                Value::Number(timestamp) => {
+                    if let Some(timestamp) = timestamp.as_i64() {
+                        *value = Value::from(timestamp + repetition as i64);
+                    } else if let Some(timestamp) = timestamp.as_f64() {
+                        *value = Value::from(timestamp + repetition as f64);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutate_ids() {
+        let batch =
+            br#"{"id":"a","trace_id":12,"timestamp":"2024-01-01T00:00:00Z","msg":"x"}"#;
+        let mutated = mutate_ids(&[&batch[..], b"\n\n"].concat(), 2).unwrap();
+        let (doc, empty_line) = mutated.split_at(mutated.len() - 1);
+        assert_eq!(empty_line, b"\n");
+        let doc: Value = serde_json::from_slice(doc).unwrap();
+        assert_eq!(
+            doc,
+            serde_json::json!({
+                "id": "a-2",
+                "trace_id": 2_000_000_000_012i64,
+                "timestamp": "2024-01-01T00:00:00.002Z",
+                "msg": "x",
+            })
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum UriState {
+    Pending = 0,
+    Reading = 1,
+    Done = 2,
+    Failed = 3,
+    Skipped = 4,
+}
+
+impl AsRef<str> for UriState {
+    fn as_ref(&self) -> &str {
+        match self {
+            UriState::Pending => "pending",
+            UriState::Reading => "reading",
+            UriState::Done => "done",
+            UriState::Failed => "failed",
+            UriState::Skipped => "skipped",
+        }
+    }
+}
+
+/// Progress of the source on one of the dataset's URIs, updated while the
+/// URI is being streamed.
+#[derive(Debug)]
+pub struct UriProgress {
+    pub uri: String,
+    num_read_bytes: AtomicU64,
+    num_docs: AtomicU64,
+    num_errors: AtomicU64,
+    state: AtomicU8,
+    b3_hash: OnceLock<String>,
+}
+
+impl UriProgress {
+    fn new(uri: String) -> Self {
+        Self {
+            uri,
+            num_read_bytes: AtomicU64::new(0),
+            num_docs: AtomicU64::new(0),
+            num_errors: AtomicU64::new(0),
+            state: AtomicU8::new(UriState::Pending as u8),
+            b3_hash: OnceLock::new(),
+        }
+    }
+
+    /// The number of bytes read from the URI, before sampling. Accumulates
+    /// over the repetitions of the dataset.
+    pub fn num_read_bytes(&self) -> u64 {
+        self.num_read_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn state(&self) -> UriState {
+        match self.state.load(Ordering::Relaxed) {
+            0 => UriState::Pending,
+            1 => UriState::Reading,
+            2 => UriState::Done,
+            3 => UriState::Failed,
+            _ => UriState::Skipped,
+        }
+    }
+
+    /// The number of documents sent from the URI.
+    pub fn num_docs(&self) -> u64 {
+        self.num_docs.load(Ordering::Relaxed)
+    }
+
+    /// The number of failed attempts at reading the URI.
+    pub fn num_errors(&self) -> u64 {
+        self.num_errors.load(Ordering::Relaxed)
+    }
+
+    /// The blake3 hash of the URI's content, known once it has been read to
+    /// the end.
+    pub fn b3_hash(&self) -> Option<&str> {
+        self.b3_hash.get().map(String::as_str)
+    }
+
+    pub(crate) fn set_b3_hash(&self, b3_hash: String) {
+        // Repetitions read the same content again.
+        let _ = self.b3_hash.set(b3_hash);
+    }
+
+    pub(crate) fn add_read_bytes(&self, num_bytes: u64) {
+        self.num_read_bytes.fetch_add(num_bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_docs(&self, num_docs: u64) {
+        self.num_docs.fetch_add(num_docs, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_error(&self) {
+        self.num_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_state(&self, state: UriState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+}
+
+/// Per-URI progress of a source, shared with the reporters running alongside
+/// the ingestion loop.
+#[derive(Debug)]
+pub struct SourceProgress {
+    pub uris: Vec<UriProgress>,
+}
+
+impl SourceProgress {
+    pub fn new(uris: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            uris: uris.into_iter().map(UriProgress::new).collect(),
+        }
+    }
+
+    pub fn summary(&self) -> Vec<UriSummary> {
+        self.uris
+            .iter()
+            .map(|uri| {
+                let state = uri.state();
+                UriSummary {
+                    uri: uri.uri.clone(),
+                    state: state.as_ref().to_string(),
+                    num_read_bytes: uri.num_read_bytes(),
+                    num_docs: uri.num_docs(),
+                    num_errors: uri.num_errors(),
+                    skipped: state == UriState::Skipped,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UriSummary {
+    pub uri: String,
+    pub state: String,
+    pub num_read_bytes: u64,
+    pub num_docs: u64,
+    pub num_errors: u64,
+    /// Whether the rest of the URI was skipped after an error.
+    pub skipped: bool,
+}
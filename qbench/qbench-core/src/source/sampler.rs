@@ -0,0 +1,111 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Restricts the dataset to a deterministic subset of its documents, to run
+/// quick smoke benchmarks on huge datasets.
+///
+/// Documents are first sampled with a seeded random generator, then the
+/// sampled ones are taken until `max_docs` or `max_bytes` is reached.
+#[derive(Clone)]
+pub struct DocSampler {
+    max_docs: Option<u64>,
+    max_bytes: Option<u64>,
+    sample_ratio: f64,
+    rng: StdRng,
+    num_docs: u64,
+    num_bytes: u64,
+    exhausted: bool,
+}
+
+impl DocSampler {
+    pub fn new(
+        max_docs: Option<u64>,
+        max_bytes: Option<u64>,
+        sample_ratio: f64,
+        seed: u64,
+    ) -> Self {
+        Self {
+            max_docs,
+            max_bytes,
+            sample_ratio,
+            rng: StdRng::seed_from_u64(seed),
+            num_docs: 0,
+            num_bytes: 0,
+            exhausted: false,
+        }
+    }
+
+    /// Whether `max_docs` or `max_bytes` has been reached.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Returns the lines of the batch making it into the subset.
+    pub fn sample(&mut self, batch: &[u8]) -> Vec<u8> {
+        let mut sampled = Vec::with_capacity(batch.len());
+        for line in batch.split_inclusive(|byte| *byte == b'\n') {
+            if self.exhausted {
+                break;
+            }
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            if self.sample_ratio < 1.0 && !self.rng.gen_bool(self.sample_ratio) {
+                continue;
+            }
+            let num_bytes = self.num_bytes + line.len() as u64;
+            if self
+                .max_bytes
+                .is_some_and(|max_bytes| num_bytes > max_bytes)
+            {
+                self.exhausted = true;
+                break;
+            }
+            sampled.extend_from_slice(line);
+            if !line.ends_with(b"\n") {
+                sampled.push(b'\n');
+            }
+            self.num_docs += 1;
+            self.num_bytes = num_bytes;
+            if self
+                .max_docs
+                .is_some_and(|max_docs| self.num_docs >= max_docs)
+            {
+                self.exhausted = true;
+            }
+        }
+        sampled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_sampler() {
+        let batch = b"{\"a\":1}\n\n{\"a\":2}\n{\"a\":3}".to_vec();
+        let mut max_docs_sampler = DocSampler::new(Some(2), None, 1.0, 0);
+        assert_eq!(max_docs_sampler.sample(&batch), b"{\"a\":1}\n{\"a\":2}\n");
+        assert!(max_docs_sampler.is_exhausted());
+
+        let mut max_bytes_sampler = DocSampler::new(None, Some(20), 1.0, 0);
+        assert_eq!(max_bytes_sampler.sample(&batch), b"{\"a\":1}\n{\"a\":2}\n");
+        assert!(max_bytes_sampler.is_exhausted());
+
+        let mut unlimited_sampler = DocSampler::new(None, None, 1.0, 0);
+        assert_eq!(
+            unlimited_sampler.sample(&batch),
+            b"{\"a\":1}\n{\"a\":2}\n{\"a\":3}\n"
+        );
+        assert!(!unlimited_sampler.is_exhausted());
+
+        let large_batch = b"{\"a\":1}\n".repeat(1000);
+        let mut ratio_sampler = DocSampler::new(None, None, 0.1, 42);
+        let sampled = ratio_sampler.sample(&large_batch);
+        let num_sampled_docs = sampled.split(|byte| *byte == b'\n').count() - 1;
+        assert!((50..150).contains(&num_sampled_docs));
+        let mut same_seed_sampler = DocSampler::new(None, None, 0.1, 42);
+        assert_eq!(same_seed_sampler.sample(&large_batch), sampled);
+    }
+}
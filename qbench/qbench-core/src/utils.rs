@@ -0,0 +1,80 @@
+// use http::HeaderValue;
+
+// pub fn basic_auth<U, P>(username: U, password: Option<P>) -> HeaderValue
+// where
+//     U: std::fmt::Display,
+//     P: std::fmt::Display,
+// {
+//     use std::io::Write;
+
+//     use base64::prelude::BASE64_STANDARD;
+//     use base64::write::EncoderWriter;
+
+//     let mut buf = b"Basic ".to_vec();
+//     {
+//         let mut encoder = EncoderWriter::new(&mut buf, &BASE64_STANDARD);
+//         let _ = write!(encoder, "{username}:");
+//         if let Some(password) = password {
+//             let _ = write!(encoder, "{password}");
+//         }
+//     }
+//     let mut header =
+//         HeaderValue::from_bytes(&buf).expect("base64 is always valid HeaderValue");
+//     header.set_sensitive(true);
+//     header
+// }
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+
+/// Tokens are refreshed a bit before they actually expire.
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(300);
+
+/// An OAuth access token along with its expiry.
+pub struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+impl CachedToken {
+    pub fn new(token: String, expires_in: Duration) -> Self {
+        Self {
+            token,
+            expires_at: Instant::now() + expires_in,
+        }
+    }
+
+    /// Returns the token, unless it is about to expire.
+    pub fn get(&self) -> Option<&str> {
+        if self.expires_at > Instant::now() + TOKEN_EXPIRY_MARGIN {
+            Some(&self.token)
+        } else {
+            None
+        }
+    }
+}
+
+/// Polls `condition` every `poll_interval` until it returns `true`, and returns
+/// the time it took. Fails if the condition does not hold after `timeout`.
+pub async fn wait_until<F, Fut>(
+    timeout: Duration,
+    poll_interval: Duration,
+    mut condition: F,
+) -> anyhow::Result<Duration>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<bool>>,
+{
+    let start = Instant::now();
+    loop {
+        if condition().await? {
+            return Ok(start.elapsed());
+        }
+        if start.elapsed() > timeout {
+            bail!("Condition not met after {:?}", timeout);
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
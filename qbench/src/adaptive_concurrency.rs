@@ -0,0 +1,203 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use qbench_core::run_results::reports::{AdaptiveConcurrencyReport, ConcurrencyStep};
+
+/// A request slower per byte than this many times the fastest one seen is a
+/// sign of congestion.
+const LATENCY_TOLERANCE: u32 = 2;
+/// How much the concurrency is cut on congestion.
+const DECREASE_FACTOR: f64 = 0.5;
+
+/// An AIMD controller of the number of requests in flight, to find the
+/// concurrency an engine sustains without re-running with different
+/// `--concurrency` values.
+///
+/// The concurrency grows by one after every window of `concurrency` fast
+/// requests, and is halved when a request fails, is throttled, or is slower
+/// than `LATENCY_TOLERANCE` times the fastest one since the last decrease. The
+/// latencies are compared per byte sent, so that a large batch is not taken
+/// for congestion. The requests sent before the last change do not count, they
+/// were sent at the previous concurrency.
+pub struct AdaptiveConcurrency {
+    start: Instant,
+    max_concurrency: usize,
+    state: Mutex<ControllerState>,
+}
+
+struct ControllerState {
+    concurrency: usize,
+    last_change: Instant,
+    num_fast_requests: usize,
+    /// The latency per MB of the fastest request. Reset on decreases, so that
+    /// a single lucky request cannot keep the concurrency down for the rest of
+    /// the run.
+    min_latency_per_mb: Option<Duration>,
+    /// The latency of the fastest request since the last decrease.
+    min_latency: Option<Duration>,
+    num_throttled_requests: u64,
+    num_increases: u64,
+    num_decreases: u64,
+    max_reached: usize,
+    trajectory: Vec<ConcurrencyStep>,
+}
+
+impl AdaptiveConcurrency {
+    pub fn new(initial_concurrency: usize, max_concurrency: usize) -> Self {
+        let start = Instant::now();
+        Self {
+            start,
+            max_concurrency,
+            state: Mutex::new(ControllerState {
+                concurrency: initial_concurrency,
+                last_change: start,
+                num_fast_requests: 0,
+                min_latency_per_mb: None,
+                min_latency: None,
+                num_throttled_requests: 0,
+                num_increases: 0,
+                num_decreases: 0,
+                max_reached: initial_concurrency,
+                trajectory: vec![ConcurrencyStep {
+                    elapsed_secs: 0.0,
+                    concurrency: initial_concurrency,
+                }],
+            }),
+        }
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.state.lock().unwrap().concurrency
+    }
+
+    /// Records a request of `num_bytes` sent at `request_start`, which took
+    /// `latency` and failed if `failed`.
+    pub fn record_request(
+        &self,
+        request_start: Instant,
+        latency: Duration,
+        num_bytes: u64,
+        failed: bool,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if request_start < state.last_change {
+            return;
+        }
+        let latency_per_mb = latency.mul_f64(1_000_000.0 / num_bytes.max(1) as f64);
+        let min_latency_per_mb = *state.min_latency_per_mb.get_or_insert(latency_per_mb);
+        if failed || latency_per_mb > min_latency_per_mb * LATENCY_TOLERANCE {
+            self.decrease(&mut state);
+            return;
+        }
+        state.min_latency_per_mb = Some(min_latency_per_mb.min(latency_per_mb));
+        state.min_latency =
+            Some(state.min_latency.map_or(latency, |min| min.min(latency)));
+        state.num_fast_requests += 1;
+        if state.num_fast_requests >= state.concurrency
+            && state.concurrency < self.max_concurrency
+        {
+            state.concurrency += 1;
+            state.num_increases += 1;
+            state.max_reached = state.max_reached.max(state.concurrency);
+            self.record_change(&mut state);
+        }
+    }
+
+    /// Records the sink's count of throttled requests, backing off if it grew.
+    /// Throttled requests are retried by the sink, without failing.
+    pub fn record_throttling(&self, num_throttled_requests: u64) {
+        let mut state = self.state.lock().unwrap();
+        if num_throttled_requests <= state.num_throttled_requests {
+            return;
+        }
+        state.num_throttled_requests = num_throttled_requests;
+        // Throttled once per window at most, like the slow requests.
+        let window = state.min_latency.unwrap_or_default();
+        if state.last_change.elapsed() >= window {
+            self.decrease(&mut state);
+        }
+    }
+
+    fn decrease(&self, state: &mut ControllerState) {
+        let concurrency = ((state.concurrency as f64 * DECREASE_FACTOR) as usize).max(1);
+        if concurrency < state.concurrency {
+            state.concurrency = concurrency;
+            state.num_decreases += 1;
+        }
+        state.min_latency_per_mb = None;
+        state.min_latency = None;
+        self.record_change(state);
+    }
+
+    fn record_change(&self, state: &mut ControllerState) {
+        state.last_change = Instant::now();
+        state.num_fast_requests = 0;
+        let step = ConcurrencyStep {
+            elapsed_secs: self.start.elapsed().as_secs_f64(),
+            concurrency: state.concurrency,
+        };
+        if state.trajectory.last().map(|step| step.concurrency) != Some(step.concurrency)
+        {
+            state.trajectory.push(step);
+        }
+    }
+
+    pub fn report(&self) -> AdaptiveConcurrencyReport {
+        let state = self.state.lock().unwrap();
+        AdaptiveConcurrencyReport {
+            initial_concurrency: state.trajectory[0].concurrency,
+            max_concurrency: self.max_concurrency,
+            final_concurrency: state.concurrency,
+            peak_concurrency: state.max_reached,
+            num_increases: state.num_increases,
+            num_decreases: state.num_decreases,
+            trajectory: state.trajectory.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_concurrency() {
+        let controller = AdaptiveConcurrency::new(2, 4);
+        let fast = Duration::from_millis(100);
+        let num_bytes = 1_000_000;
+        // A window of 2 fast requests, then 3, then 4 at the maximum.
+        for _ in 0..2 + 3 + 4 + 4 {
+            controller.record_request(Instant::now(), fast, num_bytes, false);
+        }
+        assert_eq!(controller.concurrency(), 4);
+        // As fast per byte as the others.
+        controller.record_request(Instant::now(), fast * 3, num_bytes * 3, false);
+        assert_eq!(controller.concurrency(), 4);
+
+        let request_start = Instant::now();
+        controller.record_request(request_start, fast * 3, num_bytes, false);
+        assert_eq!(controller.concurrency(), 2);
+        // Sent before the decrease.
+        controller.record_request(request_start, fast, num_bytes, true);
+        assert_eq!(controller.concurrency(), 2);
+        controller.record_request(Instant::now(), fast, num_bytes, true);
+        assert_eq!(controller.concurrency(), 1);
+        controller.record_request(Instant::now(), fast, num_bytes, true);
+        assert_eq!(controller.concurrency(), 1);
+        // The fast requests before the decreases are not the baseline anymore.
+        controller.record_request(Instant::now(), fast * 3, num_bytes, false);
+        assert_eq!(controller.concurrency(), 2);
+
+        let report = controller.report();
+        assert_eq!(report.initial_concurrency, 2);
+        assert_eq!(report.peak_concurrency, 4);
+        assert_eq!(report.final_concurrency, 2);
+        assert_eq!((report.num_increases, report.num_decreases), (3, 2));
+        let trajectory: Vec<usize> = report
+            .trajectory
+            .iter()
+            .map(|step| step.concurrency)
+            .collect();
+        assert_eq!(trajectory, [2, 3, 4, 2, 1, 2]);
+    }
+}
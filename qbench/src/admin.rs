@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Args;
+use qbench_core::engine::Engine;
+
+use crate::EngineArgs;
+
+#[derive(Args, Debug)]
+pub struct SetupIndexArgs {
+    #[command(flatten)]
+    target: EngineArgs,
+
+    #[arg(long, env)]
+    /// The engine index config, in YAML or JSON, e.g. a track's
+    /// `index-config.quickwit.yaml`. The `index_id` of Quickwit configs is
+    /// replaced by `--index`.
+    index_config: PathBuf,
+
+    #[arg(long, env)]
+    /// Delete the index first if it already exists.
+    overwrite: bool,
+}
+
+/// Creates the index, which `qbench index` expects to exist for most engines.
+pub async fn setup_index(args: SetupIndexArgs) -> anyhow::Result<()> {
+    if args.target.engine == Engine::Tantivy {
+        bail!("Tantivy indexes are created by `qbench index` from --tantivy-mapping");
+    }
+    let index_config =
+        std::fs::read_to_string(&args.index_config).with_context(|| {
+            format!("Failed to read index config {:?}", args.index_config)
+        })?;
+    let sink = args.target.build_sink(None)?;
+    args.target.wait_for_engine(sink.as_ref()).await?;
+    args.target.install_es_pipeline().await?;
+    if args.overwrite && sink.delete_index().await? {
+        info!(index = args.target.index, "Deleted the existing index");
+    }
+    sink.create_index(&index_config).await?;
+    info!(index = args.target.index, "Index created");
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+pub struct CleanArgs {
+    #[command(flatten)]
+    target: EngineArgs,
+}
+
+/// Deletes the index, if it exists.
+pub async fn clean(args: CleanArgs) -> anyhow::Result<()> {
+    if delete_index(&args.target).await? {
+        info!(index = args.target.index, "Index deleted");
+    } else {
+        info!(index = args.target.index, "Index does not exist");
+    }
+    Ok(())
+}
+
+/// Deletes the index. Returns false if it did not exist.
+pub async fn delete_index(target: &EngineArgs) -> anyhow::Result<bool> {
+    if target.engine != Engine::Tantivy {
+        return target.build_sink(None)?.delete_index().await;
+    }
+    // Creating the sink would create the index.
+    let index_dir = target.tantivy_index_dir();
+    if !index_dir.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_dir_all(&index_dir)
+        .with_context(|| format!("Failed to delete {index_dir:?}"))?;
+    Ok(true)
+}
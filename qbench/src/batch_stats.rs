@@ -0,0 +1,142 @@
+use qbench_core::run_results::reports::{
+    BatchSizeBucket,
+    BatchSizeDistribution,
+    BatchSizeReport,
+};
+
+/// Batches under this fraction of the sink's batch size are counted as small.
+const SMALL_BATCH_RATIO: f64 = 0.1;
+/// The size of the batches actually sent to the sink, once cut by the source
+/// on document boundaries and rewritten by the transforms.
+///
+/// A source flushes a batch before the sink's `batch_size()` when the next
+/// document wouldn't fit or at the end of a dataset URI, so many small
+/// batches hint at large documents or many small dataset files.
+#[derive(Debug, Default)]
+pub struct BatchSizeHistogram {
+    /// The number of bytes and documents of each batch. There are few enough
+    /// batches to keep them all and compute exact percentiles.
+    batch_sizes: Vec<(u64, u64)>,
+}
+
+impl BatchSizeHistogram {
+    /// Records an NDJSON batch about to be sent.
+    pub fn record_batch(&mut self, bytes: &[u8]) {
+        let num_docs = bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty())
+            .count();
+        self.batch_sizes.push((bytes.len() as u64, num_docs as u64));
+    }
+
+    fn distribution(mut values: Vec<u64>) -> BatchSizeDistribution {
+        if values.is_empty() {
+            return BatchSizeDistribution {
+                min: 0,
+                mean: 0.0,
+                p50: 0,
+                p90: 0,
+                p99: 0,
+                max: 0,
+            };
+        }
+        values.sort_unstable();
+        let percentile = |percentile: f64| {
+            let rank = (values.len() as f64 * percentile).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+        BatchSizeDistribution {
+            min: values[0],
+            mean: values.iter().sum::<u64>() as f64 / values.len() as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: values[values.len() - 1],
+        }
+    }
+
+    pub fn report(&self, sink_batch_num_bytes: usize) -> BatchSizeReport {
+        let small_batch_num_bytes = sink_batch_num_bytes as f64 * SMALL_BATCH_RATIO;
+        let mut buckets: Vec<BatchSizeBucket> = Vec::new();
+        let mut bucket_idxs: Vec<u32> = self
+            .batch_sizes
+            .iter()
+            .map(|(num_bytes, _)| u64::BITS - num_bytes.leading_zeros())
+            .collect();
+        bucket_idxs.sort_unstable();
+        for bucket_idx in bucket_idxs {
+            let max_num_bytes = (1u64 << bucket_idx) - 1;
+            match buckets.last_mut() {
+                Some(bucket) if bucket.max_num_bytes == max_num_bytes => {
+                    bucket.num_batches += 1;
+                },
+                _ => buckets.push(BatchSizeBucket {
+                    max_num_bytes,
+                    num_batches: 1,
+                }),
+            }
+        }
+        BatchSizeReport {
+            num_batches: self.batch_sizes.len() as u64,
+            sink_batch_num_bytes: sink_batch_num_bytes as u64,
+            num_small_batches: self
+                .batch_sizes
+                .iter()
+                .filter(|(num_bytes, _)| (*num_bytes as f64) < small_batch_num_bytes)
+                .count() as u64,
+            num_bytes: Self::distribution(
+                self.batch_sizes
+                    .iter()
+                    .map(|(num_bytes, _)| *num_bytes)
+                    .collect(),
+            ),
+            num_docs: Self::distribution(
+                self.batch_sizes
+                    .iter()
+                    .map(|(_, num_docs)| *num_docs)
+                    .collect(),
+            ),
+            buckets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_size_histogram() {
+        let mut histogram = BatchSizeHistogram::default();
+        histogram.record_batch(b"{\"a\":1}\n{\"a\":2}\n\n");
+        histogram.record_batch(b"{\"message\":\"hello world\"}\n");
+        histogram.record_batch(&[b' '; 100]);
+        let report = histogram.report(100);
+        assert_eq!(report.num_batches, 3);
+        assert_eq!(report.num_small_batches, 0);
+        assert_eq!(report.num_bytes.min, 17);
+        assert_eq!(report.num_bytes.p50, 26);
+        assert_eq!(report.num_bytes.max, 100);
+        assert_eq!(report.num_docs.p90, 2);
+        assert_eq!(report.num_docs.min, 0);
+        assert_eq!(
+            report.buckets,
+            vec![
+                BatchSizeBucket {
+                    max_num_bytes: 31,
+                    num_batches: 2,
+                },
+                BatchSizeBucket {
+                    max_num_bytes: 127,
+                    num_batches: 1,
+                },
+            ]
+        );
+        assert_eq!(histogram.report(1000).num_small_batches, 2);
+        assert!(!histogram.report(1000).is_mostly_small());
+        assert_eq!(
+            BatchSizeHistogram::default().report(100).num_bytes.mean,
+            0.0
+        );
+    }
+}
@@ -0,0 +1,61 @@
+/// Spend guard stopping ingestion before a cloud target bills more than allowed.
+///
+/// The cost model is deliberately simple: SaaS offerings bill per ingested
+/// byte, so the estimated spend is the number of bytes sent so far multiplied
+/// by the price of a GB (10^9 bytes).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Budget {
+    pub cost_per_gb_usd: Option<f64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_billed_bytes: Option<u64>,
+}
+
+impl Budget {
+    /// Returns the estimated spend for the given number of billed bytes, if a
+    /// price was configured.
+    pub fn estimated_cost_usd(&self, billed_bytes: u64) -> Option<f64> {
+        self.cost_per_gb_usd.map(|cost_per_gb_usd| {
+            billed_bytes as f64 / 1_000_000_000.0 * cost_per_gb_usd
+        })
+    }
+
+    /// Returns whether sending `billed_bytes` in total would exceed the budget.
+    pub fn is_exceeded(&self, billed_bytes: u64) -> bool {
+        if let Some(max_billed_bytes) = self.max_billed_bytes {
+            if billed_bytes > max_billed_bytes {
+                return true;
+            }
+        }
+        match (self.max_cost_usd, self.estimated_cost_usd(billed_bytes)) {
+            (Some(max_cost_usd), Some(cost_usd)) => cost_usd > max_cost_usd,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_is_exceeded() {
+        let unlimited = Budget::default();
+        assert!(!unlimited.is_exceeded(u64::MAX));
+
+        let bytes_budget = Budget {
+            max_billed_bytes: Some(1_000),
+            ..Default::default()
+        };
+        assert!(!bytes_budget.is_exceeded(1_000));
+        assert!(bytes_budget.is_exceeded(1_001));
+
+        let cost_budget = Budget {
+            cost_per_gb_usd: Some(0.5),
+            max_cost_usd: Some(10.0),
+            max_billed_bytes: None,
+        };
+        assert_eq!(cost_budget.estimated_cost_usd(2_000_000_000), Some(1.0));
+        assert!(!cost_budget.is_exceeded(20_000_000_000));
+        assert!(cost_budget.is_exceeded(20_000_000_001));
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use clap::{Args, Parser};
+use qbench_core::engine::Engine;
+use qbench_core::results::OutputFormat;
+
+use crate::report::{print_comparison, read_runs};
+use crate::{run_indexing, shutdown, CliArgs, Command, IndexArgs};
+
+/// A setting of one of the compared engines, written `engine=value`.
+#[derive(Debug, Clone)]
+pub struct EngineSetting {
+    engine: Engine,
+    value: String,
+}
+
+impl FromStr for EngineSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((engine, value)) = s.split_once('=') else {
+            return Err(format!("Expected `engine=value`, got {s:?}"));
+        };
+        Ok(EngineSetting {
+            engine: engine.parse()?,
+            value: value.to_string(),
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    #[arg(long, env, value_delimiter = ',', required = true)]
+    /// The comma-separated engines the dataset is sent to, one after the
+    /// other.
+    engines: Vec<Engine>,
+
+    #[arg(long, env, alias = "dataset")]
+    /// The dataset sent to every engine, see `qbench index --dataset-uri`.
+    dataset_uri: String,
+
+    #[arg(long, env)]
+    /// The index of every engine, unless set with `--engine-index`.
+    index: String,
+
+    #[arg(long, env, value_delimiter = ',')]
+    /// The comma-separated hosts of the engines, e.g.
+    /// `quickwit=10.0.0.1:7280,elasticsearch=10.0.0.2:9200`. The engines
+    /// missing are reached on their default host.
+    engine_host: Vec<EngineSetting>,
+
+    #[arg(long, env, value_delimiter = ',')]
+    /// The comma-separated indexes of the engines, e.g.
+    /// `elasticsearch=logs-es`, overriding `--index`.
+    engine_index: Vec<EngineSetting>,
+
+    #[arg(long, env, default_value = "comparison_results.json")]
+    /// The file collecting the results of all the runs.
+    output_path: PathBuf,
+
+    #[arg(long, env, default_value = "json")]
+    /// The format of the results file: "json", "yaml" or "msgpack".
+    output_format: OutputFormat,
+
+    #[arg(last = true)]
+    /// Flags passed to every `qbench index` run, after `--`, e.g.
+    /// `-- --max-docs 1000000 --concurrency 4`.
+    index_args: Vec<String>,
+}
+
+impl CompareArgs {
+    fn engine_setting(settings: &[EngineSetting], engine: Engine) -> Option<&str> {
+        settings
+            .iter()
+            .find(|setting| setting.engine == engine)
+            .map(|setting| setting.value.as_str())
+    }
+
+    /// The arguments of the indexing run against `engine`.
+    fn index_args(&self, engine: Engine) -> anyhow::Result<IndexArgs> {
+        let index =
+            Self::engine_setting(&self.engine_index, engine).unwrap_or(&self.index);
+        let mut argv = vec![
+            "qbench",
+            "index",
+            "--engine",
+            engine.as_ref(),
+            "--index",
+            index,
+            "--dataset-uri",
+            &self.dataset_uri,
+            "--output-format",
+            self.output_format.extension(),
+            "--append-output",
+        ];
+        if let Some(host) = Self::engine_setting(&self.engine_host, engine) {
+            argv.extend(["--host", host]);
+        }
+        let output_path = self.output_path.to_string_lossy();
+        argv.extend(["--output-path", &output_path]);
+        argv.extend(self.index_args.iter().map(String::as_str));
+        let cli = CliArgs::try_parse_from(argv)
+            .with_context(|| format!("Invalid indexing arguments for {engine}"))?;
+        let Command::Index(index_args) = cli.command else {
+            unreachable!("the indexing subcommand is set above");
+        };
+        Ok(*index_args)
+    }
+}
+
+/// Sends the same dataset to several engines in turn, and prints their
+/// results side by side.
+pub async fn compare(args: CompareArgs) -> anyhow::Result<()> {
+    // Check all the arguments before starting the first run.
+    let runs_args = args
+        .engines
+        .iter()
+        .map(|&engine| Ok((engine, args.index_args(engine)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // The runs append their results.
+    std::fs::File::create(&args.output_path).with_context(|| {
+        format!("Failed to create results file {:?}", args.output_path)
+    })?;
+    // Installed once for all the engines rather than by every run.
+    shutdown::install_signal_handler()?;
+    let mut failed_engines = Vec::new();
+    for (engine, index_args) in runs_args {
+        if shutdown::is_requested() {
+            warn!("Skipping the remaining engines");
+            break;
+        }
+        info!(engine = %engine, "Starting the comparison run");
+        if let Err(error) = run_indexing(index_args).await {
+            error!(engine = %engine, "The run failed: {error:#}");
+            failed_engines.push(engine.to_string());
+        }
+    }
+    let runs = read_runs(&args.output_path, args.output_format)?;
+    if !runs.is_empty() {
+        print_comparison(&runs);
+    }
+    info!("Comparison results written in `{:?}`", args.output_path);
+    if !failed_engines.is_empty() {
+        bail!("The runs against {} failed", failed_engines.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_index_args() {
+        let cli = CliArgs::try_parse_from([
+            "qbench",
+            "compare",
+            "--engines",
+            "quickwit,elasticsearch",
+            "--dataset",
+            "logs.json",
+            "--index",
+            "logs",
+            "--engine-index",
+            "elasticsearch=logs-es",
+            "--engine-host",
+            "quickwit=10.0.0.1:7280",
+            "--",
+            "--concurrency",
+            "4",
+        ])
+        .unwrap();
+        let Command::Compare(compare_args) = cli.command else {
+            panic!("expected the compare subcommand");
+        };
+        let quickwit_args = compare_args.index_args(Engine::Quickwit).unwrap();
+        assert_eq!(quickwit_args.target.index, "logs");
+        assert_eq!(quickwit_args.target.host.as_deref(), Some("10.0.0.1:7280"));
+        assert_eq!(quickwit_args.concurrency, 4);
+        assert!(quickwit_args.append_output);
+        let elasticsearch_args = compare_args.index_args(Engine::Elasticsearch).unwrap();
+        assert_eq!(elasticsearch_args.target.index, "logs-es");
+        assert_eq!(elasticsearch_args.target.host, None);
+        assert_eq!(elasticsearch_args.dataset_uri, "logs.json");
+    }
+}
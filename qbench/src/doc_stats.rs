@@ -0,0 +1,146 @@
+use qbench_core::run_results::reports::{DocSizeBucket, DocSizeReport};
+
+/// Bucket `i` holds the documents of `2^(i-1)` to `2^i - 1` bytes, the last
+/// one catching anything larger.
+const NUM_BUCKETS: usize = 40;
+
+/// Distribution of the size of the documents (NDJSON lines, without their
+/// line feed) streamed from the dataset.
+///
+/// Sizes are bucketed in powers of two: the same MB/s yields very different
+/// docs/s depending on whether the corpus is made of a few large documents or
+/// many small ones.
+#[derive(Debug, Clone)]
+pub struct DocSizeHistogram {
+    buckets: [u64; NUM_BUCKETS],
+    num_docs: u64,
+    num_bytes: u64,
+    num_empty_lines: u64,
+    min_num_bytes: u64,
+    max_num_bytes: u64,
+}
+
+impl Default for DocSizeHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; NUM_BUCKETS],
+            num_docs: 0,
+            num_bytes: 0,
+            num_empty_lines: 0,
+            min_num_bytes: u64::MAX,
+            max_num_bytes: 0,
+        }
+    }
+}
+
+impl DocSizeHistogram {
+    /// Records the lines of an NDJSON batch. Whitespace-only lines are counted
+    /// as empty lines rather than documents.
+    pub fn record_batch(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+        for line in bytes.split(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                self.num_empty_lines += 1;
+                continue;
+            }
+            self.record_doc(line.len() as u64);
+        }
+    }
+
+    fn record_doc(&mut self, num_bytes: u64) {
+        let bucket_idx = (u64::BITS - num_bytes.leading_zeros()) as usize;
+        self.buckets[bucket_idx.min(NUM_BUCKETS - 1)] += 1;
+        self.num_docs += 1;
+        self.num_bytes += num_bytes;
+        self.min_num_bytes = self.min_num_bytes.min(num_bytes);
+        self.max_num_bytes = self.max_num_bytes.max(num_bytes);
+    }
+
+    fn bucket_max_num_bytes(bucket_idx: usize) -> u64 {
+        (1u64 << bucket_idx) - 1
+    }
+
+    fn percentile(&self, percentile: f64) -> u64 {
+        let rank = (self.num_docs as f64 * percentile).ceil() as u64;
+        let mut num_docs = 0;
+        for (bucket_idx, bucket_num_docs) in self.buckets.iter().enumerate() {
+            num_docs += bucket_num_docs;
+            if num_docs >= rank.max(1) {
+                return Self::bucket_max_num_bytes(bucket_idx).min(self.max_num_bytes);
+            }
+        }
+        self.max_num_bytes
+    }
+
+    pub fn report(&self) -> DocSizeReport {
+        let buckets = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, num_docs)| **num_docs > 0)
+            .map(|(bucket_idx, num_docs)| DocSizeBucket {
+                max_num_bytes: Self::bucket_max_num_bytes(bucket_idx),
+                num_docs: *num_docs,
+            })
+            .collect();
+        DocSizeReport {
+            num_docs: self.num_docs,
+            num_empty_lines: self.num_empty_lines,
+            min_num_bytes: if self.num_docs == 0 {
+                0
+            } else {
+                self.min_num_bytes
+            },
+            max_num_bytes: self.max_num_bytes,
+            mean_num_bytes: if self.num_docs == 0 {
+                0.0
+            } else {
+                self.num_bytes as f64 / self.num_docs as f64
+            },
+            p50_num_bytes: self.percentile(0.5),
+            p90_num_bytes: self.percentile(0.9),
+            p99_num_bytes: self.percentile(0.99),
+            buckets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_size_histogram() {
+        let mut histogram = DocSizeHistogram::default();
+        histogram.record_batch(b"{}\n{\"a\":1}\n\n  \n");
+        histogram.record_batch(b"{\"message\":\"hello world\"}");
+        histogram.record_batch(b"");
+        let report = histogram.report();
+        assert_eq!(report.num_docs, 3);
+        assert_eq!(report.num_empty_lines, 2);
+        assert_eq!(report.min_num_bytes, 2);
+        assert_eq!(report.max_num_bytes, 25);
+        assert_eq!(
+            report.buckets,
+            vec![
+                DocSizeBucket {
+                    max_num_bytes: 3,
+                    num_docs: 1,
+                },
+                DocSizeBucket {
+                    max_num_bytes: 7,
+                    num_docs: 1,
+                },
+                DocSizeBucket {
+                    max_num_bytes: 31,
+                    num_docs: 1,
+                },
+            ]
+        );
+        assert_eq!(report.p50_num_bytes, 7);
+        assert_eq!(report.p99_num_bytes, 25);
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use qbench_core::run_results::reports::DriverUsageReport;
+
+/// A `getrusage` snapshot of the qbench process.
+pub struct DriverUsage {
+    cpu_user: Duration,
+    cpu_system: Duration,
+    peak_rss_bytes: u64,
+}
+
+impl DriverUsage {
+    pub fn read() -> Self {
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        // Cannot fail with a valid pointer and `RUSAGE_SELF`.
+        unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut rusage) };
+        let to_duration = |timeval: libc::timeval| {
+            Duration::from_secs(timeval.tv_sec as u64)
+                + Duration::from_micros(timeval.tv_usec as u64)
+        };
+        Self {
+            cpu_user: to_duration(rusage.ru_utime),
+            cpu_system: to_duration(rusage.ru_stime),
+            // In kilobytes on Linux.
+            peak_rss_bytes: rusage.ru_maxrss as u64 * 1024,
+        }
+    }
+
+    /// The resources used since `start`, over a run of `elapsed`.
+    pub fn report_since(
+        &self,
+        start: &DriverUsage,
+        elapsed: Duration,
+    ) -> DriverUsageReport {
+        let cpu_user_secs = self.cpu_user.saturating_sub(start.cpu_user).as_secs_f64();
+        let cpu_system_secs = self
+            .cpu_system
+            .saturating_sub(start.cpu_system)
+            .as_secs_f64();
+        DriverUsageReport {
+            cpu_user_secs,
+            cpu_system_secs,
+            cpu_utilization: (cpu_user_secs + cpu_system_secs)
+                / elapsed.as_secs_f64().max(f64::EPSILON),
+            peak_rss_bytes: self.peak_rss_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_driver_usage() {
+        let start = DriverUsage::read();
+        // Burn some CPU.
+        let mut hash = blake3::Hasher::new();
+        for _ in 0..10_000 {
+            hash.update(&[0u8; 1024]);
+        }
+        let usage = DriverUsage::read().report_since(&start, Duration::from_secs(1));
+        assert!(usage.cpu_user_secs + usage.cpu_system_secs > 0.0);
+        assert!(usage.peak_rss_bytes > 0);
+        let cpu_bound = DriverUsageReport {
+            cpu_user_secs: 9.0,
+            cpu_system_secs: 1.0,
+            cpu_utilization: 1.0,
+            peak_rss_bytes: 0,
+        };
+        assert!(cpu_bound.is_cpu_bound(1));
+        assert!(!DriverUsageReport {
+            cpu_utilization: 0.5,
+            ..cpu_bound
+        }
+        .is_cpu_bound(1));
+    }
+}
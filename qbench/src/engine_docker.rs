@@ -0,0 +1,337 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use clap::{Args, Subcommand};
+use qbench_core::engine::Engine;
+use qbench_core::run_results::reports::EngineContainer;
+use qbench_core::sink::wait_until_healthy;
+use reqwest::Client;
+use tokio::process::Command;
+
+use crate::query::search_sink;
+
+#[derive(Args, Debug)]
+pub struct EngineCommandArgs {
+    #[command(subcommand)]
+    command: EngineCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum EngineCommand {
+    /// Start the engine in a Docker container, and wait until it is ready.
+    Up(EngineUpArgs),
+    /// Stop and remove the engine's container.
+    Down(EngineDownArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct EngineUpArgs {
+    #[arg(short, long, env)]
+    /// The engine to start: "quickwit", "elasticsearch", "opensearch" or
+    /// "loki".
+    pub engine: Engine,
+
+    #[arg(long, env)]
+    /// The tag of the engine's image, e.g. "8.13.4" for Elasticsearch.
+    pub version: String,
+
+    #[arg(long, env, default_value = "4g")]
+    /// The JVM heap size of Elasticsearch and OpenSearch.
+    pub heap_size: String,
+
+    #[arg(long, env)]
+    /// Mount this host directory as the engine's data directory, created if
+    /// needed. The data stays in the container otherwise.
+    pub data_dir: Option<PathBuf>,
+
+    #[arg(long, env)]
+    /// The memory limit of the container, e.g. "8g".
+    pub memory: Option<String>,
+
+    #[arg(long, env)]
+    /// The number of CPUs of the container, e.g. "4".
+    pub cpus: Option<String>,
+
+    #[arg(long, env, default_value_t = 120)]
+    /// How long to wait for the engine to be ready.
+    pub wait_for_engine_secs: u64,
+
+    #[arg(long, env, default_value = "engine-container.json")]
+    /// Where to write the container config, to be recorded in the results
+    /// with `qbench index --engine-container`.
+    pub container_config_path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct EngineDownArgs {
+    #[arg(short, long, env)]
+    /// The engine to stop.
+    pub engine: Engine,
+}
+
+/// The host PID of the init process of the container, the engine running
+/// under it.
+pub async fn container_pid(container: &EngineContainer) -> anyhow::Result<u32> {
+    let pid = docker(&[
+        "inspect",
+        "--format={{.State.Pid}}",
+        &container.container_id,
+    ])
+    .await?;
+    match pid.parse() {
+        Ok(0) | Err(_) => bail!("Container {} is not running", container.container_name),
+        Ok(pid) => Ok(pid),
+    }
+}
+
+/// How an engine is run in Docker, after `engines/*/Makefile`.
+struct EngineImage {
+    image: &'static str,
+    /// The port the engine listens on, published on the same host port.
+    port: u16,
+    data_dir: &'static str,
+    env: Vec<String>,
+    command: &'static [&'static str],
+}
+
+impl EngineImage {
+    fn of(engine: Engine, heap_size: &str) -> anyhow::Result<Self> {
+        let engine_image = match engine {
+            Engine::Elasticsearch => EngineImage {
+                image: "docker.elastic.co/elasticsearch/elasticsearch",
+                port: 9200,
+                data_dir: "/usr/share/elasticsearch/data",
+                env: vec![
+                    "discovery.type=single-node".to_string(),
+                    "xpack.security.enabled=false".to_string(),
+                    "bootstrap.memory_lock=false".to_string(),
+                    "indices.queries.cache.size=0".to_string(),
+                    "indices.fielddata.cache.size=0".to_string(),
+                    format!("ES_JAVA_OPTS=-Xms{heap_size} -Xmx{heap_size}"),
+                ],
+                command: &[],
+            },
+            Engine::Opensearch => EngineImage {
+                image: "opensearchproject/opensearch",
+                // See `OPENSEARCH_DEFAULT_HOST`.
+                port: 9301,
+                data_dir: "/usr/share/opensearch/data",
+                env: vec![
+                    "discovery.type=single-node".to_string(),
+                    "http.port=9301".to_string(),
+                    "DISABLE_INSTALL_DEMO_CONFIG=true".to_string(),
+                    "DISABLE_SECURITY_PLUGIN=true".to_string(),
+                    "indices.queries.cache.size=0".to_string(),
+                    "indices.fielddata.cache.size=0".to_string(),
+                    format!("OPENSEARCH_JAVA_OPTS=-Xms{heap_size} -Xmx{heap_size}"),
+                ],
+                command: &[],
+            },
+            Engine::Quickwit => EngineImage {
+                image: "quickwit/quickwit",
+                port: 7280,
+                data_dir: "/quickwit/qwdata",
+                env: vec!["QW_DISABLE_TELEMETRY=1".to_string()],
+                command: &["run"],
+            },
+            Engine::Loki => EngineImage {
+                image: "grafana/loki",
+                port: 3100,
+                data_dir: "/loki",
+                env: Vec::new(),
+                command: &["-config.file=/etc/loki/local-config.yaml"],
+            },
+            _ => bail!("Running {engine} in Docker is not supported"),
+        };
+        Ok(engine_image)
+    }
+}
+
+fn container_name(engine: Engine) -> String {
+    format!("qbench-{engine}")
+}
+
+pub async fn engine_command(args: EngineCommandArgs) -> anyhow::Result<()> {
+    match args.command {
+        EngineCommand::Up(up_args) => engine_up(up_args).await,
+        EngineCommand::Down(down_args) => engine_down(down_args).await,
+    }
+}
+
+pub async fn engine_up(args: EngineUpArgs) -> anyhow::Result<()> {
+    let engine_image = EngineImage::of(args.engine, &args.heap_size)?;
+    let data_dir = args
+        .data_dir
+        .as_ref()
+        .map(|data_dir| {
+            std::fs::create_dir_all(data_dir)
+                .and_then(|_| data_dir.canonicalize())
+                .with_context(|| format!("Failed to create data directory {data_dir:?}"))
+        })
+        .transpose()?;
+    let container_name = container_name(args.engine);
+    let image = format!("{}:{}", engine_image.image, args.version);
+    let docker_run_args = docker_run_args(
+        &container_name,
+        &image,
+        &engine_image,
+        data_dir.as_deref(),
+        args.memory.as_deref(),
+        args.cpus.as_deref(),
+    );
+    info!(container_name, image, "Starting the engine container");
+    let container_id = docker(&docker_run_args).await?;
+
+    let host = format!("127.0.0.1:{}", engine_image.port);
+    let sink = search_sink(args.engine, &host, "", Client::new())?;
+    wait_until_healthy(
+        sink.as_ref(),
+        Duration::from_secs(args.wait_for_engine_secs),
+    )
+    .await
+    .with_context(|| {
+        format!(
+            "{} is not ready after {}s, see `docker logs {container_name}`",
+            args.engine, args.wait_for_engine_secs
+        )
+    })?;
+    info!(host, "The engine is ready");
+
+    let is_jvm = matches!(args.engine, Engine::Elasticsearch | Engine::Opensearch);
+    let engine_container = EngineContainer {
+        container_name,
+        container_id,
+        image,
+        heap_size: is_jvm.then_some(args.heap_size),
+        data_dir: data_dir.map(|data_dir| data_dir.display().to_string()),
+        memory: args.memory,
+        cpus: args.cpus,
+        env: engine_image.env,
+    };
+    std::fs::write(
+        &args.container_config_path,
+        serde_json::to_vec_pretty(&engine_container)?,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to write the container config {:?}",
+            args.container_config_path
+        )
+    })?;
+    Ok(())
+}
+
+pub async fn engine_down(args: EngineDownArgs) -> anyhow::Result<()> {
+    let container_name = container_name(args.engine);
+    // `-v` removes the anonymous volumes of the image's data directory.
+    docker(&["rm", "--force", "--volumes", &container_name]).await?;
+    info!(container_name, "Engine container removed");
+    Ok(())
+}
+
+fn docker_run_args(
+    container_name: &str,
+    image: &str,
+    engine_image: &EngineImage,
+    data_dir: Option<&Path>,
+    memory: Option<&str>,
+    cpus: Option<&str>,
+) -> Vec<String> {
+    let mut docker_run_args: Vec<String> = vec![
+        "run".to_string(),
+        "--detach".to_string(),
+        "--init".to_string(),
+        format!("--name={container_name}"),
+        format!("--publish={port}:{port}", port = engine_image.port),
+    ];
+    if let Some(data_dir) = data_dir {
+        // `:z` is needed with SELinux.
+        docker_run_args.push(format!(
+            "--volume={}:{}:z",
+            data_dir.display(),
+            engine_image.data_dir
+        ));
+    }
+    if let Some(memory) = memory {
+        docker_run_args.push(format!("--memory={memory}"));
+    }
+    if let Some(cpus) = cpus {
+        docker_run_args.push(format!("--cpus={cpus}"));
+    }
+    for env_var in &engine_image.env {
+        docker_run_args.push(format!("--env={env_var}"));
+    }
+    docker_run_args.push(image.to_string());
+    docker_run_args.extend(engine_image.command.iter().map(|arg| arg.to_string()));
+    docker_run_args
+}
+
+/// Runs a docker command, returning its trimmed output.
+async fn docker(args: &[impl AsRef<std::ffi::OsStr>]) -> anyhow::Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run docker")?;
+    if !output.status.success() {
+        bail!(
+            "docker {} failed: {}",
+            args.first()
+                .map(|arg| arg.as_ref().to_string_lossy())
+                .unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docker_run_args() {
+        let engine_image = EngineImage::of(Engine::Elasticsearch, "2g").unwrap();
+        let run_args = docker_run_args(
+            "qbench-elasticsearch",
+            "docker.elastic.co/elasticsearch/elasticsearch:8.13.4",
+            &engine_image,
+            Some(Path::new("/mnt/data")),
+            None,
+            Some("4"),
+        );
+        assert_eq!(
+            run_args[..7],
+            [
+                "run",
+                "--detach",
+                "--init",
+                "--name=qbench-elasticsearch",
+                "--publish=9200:9200",
+                "--volume=/mnt/data:/usr/share/elasticsearch/data:z",
+                "--cpus=4",
+            ]
+        );
+        assert!(run_args.contains(&"--env=ES_JAVA_OPTS=-Xms2g -Xmx2g".to_string()));
+        assert_eq!(
+            run_args.last().unwrap(),
+            "docker.elastic.co/elasticsearch/elasticsearch:8.13.4"
+        );
+
+        let engine_image = EngineImage::of(Engine::Quickwit, "2g").unwrap();
+        let run_args = docker_run_args(
+            "qbench-quickwit",
+            "quickwit/quickwit:0.8.2",
+            &engine_image,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            run_args[run_args.len() - 2..],
+            ["quickwit/quickwit:0.8.2", "run"]
+        );
+        assert!(EngineImage::of(Engine::Kusto, "2g").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{bail, Context};
+use qbench_core::sink::RejectedDoc;
+
+/// Keeps the payload and the error (with the engine's response body, when the
+/// sink reports it) of the last failed requests on disk, to reproduce
+/// engine-side errors after the run. The documents rejected individually by a
+/// request that succeeded otherwise are dumped the same way.
+pub struct FailedBatchDumper {
+    dir: PathBuf,
+    max_batches: usize,
+    num_dumped: AtomicUsize,
+}
+
+impl FailedBatchDumper {
+    pub fn new(dir: &Path, max_batches: usize) -> anyhow::Result<Self> {
+        if max_batches == 0 {
+            bail!("--dump-failed-batches-max must be at least 1");
+        }
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create failed batches dir {dir:?}"))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_batches,
+            num_dumped: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn num_dumped(&self) -> usize {
+        self.num_dumped.load(Ordering::Relaxed)
+    }
+
+    /// Writes `failed-batch-<n>.ndjson` and `failed-batch-<n>.error.txt`, and
+    /// removes the files of the batch dumped `max_batches` before. Failing to
+    /// do so only warns: the dump shouldn't end the run.
+    pub fn dump(&self, payload: &[u8], error: &anyhow::Error) {
+        let batch_idx = self.num_dumped.fetch_add(1, Ordering::Relaxed);
+        if let Err(dump_error) = self.write_files(batch_idx, payload, error) {
+            warn!(error=?dump_error, "Failed to dump the failed batch");
+        }
+        if let Some(expired_idx) = batch_idx.checked_sub(self.max_batches) {
+            for path in self.paths(expired_idx) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Dumps the documents rejected individually by the engine as a batch,
+    /// along with the error of each.
+    pub fn dump_rejected_docs(&self, rejected_docs: &[RejectedDoc]) {
+        let mut payload = Vec::new();
+        let mut errors = String::new();
+        for (line_idx, rejected_doc) in rejected_docs.iter().enumerate() {
+            payload.extend_from_slice(&rejected_doc.doc);
+            payload.push(b'\n');
+            errors.push_str(&format!("line {}: {}\n", line_idx + 1, rejected_doc.error));
+        }
+        let error = anyhow::anyhow!(
+            "{} documents rejected by the engine:\n{errors}",
+            rejected_docs.len()
+        );
+        self.dump(&payload, &error);
+    }
+
+    fn paths(&self, batch_idx: usize) -> [PathBuf; 2] {
+        [
+            self.dir.join(format!("failed-batch-{batch_idx:06}.ndjson")),
+            self.dir
+                .join(format!("failed-batch-{batch_idx:06}.error.txt")),
+        ]
+    }
+
+    fn write_files(
+        &self,
+        batch_idx: usize,
+        payload: &[u8],
+        error: &anyhow::Error,
+    ) -> anyhow::Result<()> {
+        let [payload_path, error_path] = self.paths(batch_idx);
+        std::fs::write(&payload_path, payload)?;
+        let error_report = format!(
+            "{}\n\n{error:?}\n",
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        );
+        std::fs::write(&error_path, error_report)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_batch_dumper() {
+        let dir = std::env::temp_dir()
+            .join(format!("qbench-failed-batches-{}", std::process::id()));
+        let dumper = FailedBatchDumper::new(&dir, 2).unwrap();
+        for batch_idx in 0..3 {
+            let error = anyhow::anyhow!(
+                "http error with status code 400: mapper_parsing_exception"
+            )
+            .context(format!("batch {batch_idx}"));
+            dumper.dump(format!("{{\"batch\": {batch_idx}}}\n").as_bytes(), &error);
+        }
+        assert_eq!(dumper.num_dumped(), 3);
+        let mut file_names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        file_names.sort();
+        assert_eq!(
+            file_names,
+            [
+                "failed-batch-000001.error.txt",
+                "failed-batch-000001.ndjson",
+                "failed-batch-000002.error.txt",
+                "failed-batch-000002.ndjson",
+            ]
+        );
+        let error_report =
+            std::fs::read_to_string(dir.join("failed-batch-000002.error.txt")).unwrap();
+        assert!(error_report.contains("mapper_parsing_exception"));
+
+        dumper.dump_rejected_docs(&[RejectedDoc {
+            doc: "{\"status\": \"ok\"}".into(),
+            error: r#"{"type":"mapper_parsing_exception"}"#.to_string(),
+        }]);
+        let payload =
+            std::fs::read_to_string(dir.join("failed-batch-000003.ndjson")).unwrap();
+        assert_eq!(payload, "{\"status\": \"ok\"}\n");
+        let error_report =
+            std::fs::read_to_string(dir.join("failed-batch-000003.error.txt")).unwrap();
+        assert!(error_report.contains("line 1: {\"type\":\"mapper_parsing_exception\"}"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(FailedBatchDumper::new(&dir, 0).is_err());
+    }
+}
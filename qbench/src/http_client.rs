@@ -0,0 +1,468 @@
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Context;
+use clap::Args;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Identity};
+
+/// The HTTP version spoken to the engine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// HTTP/1.1 over plain HTTP, HTTP/2 if negotiated with ALPN over TLS.
+    Auto,
+    Http1,
+    /// HTTP/2 without TLS ("prior knowledge").
+    Http2,
+}
+
+impl FromStr for HttpVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(HttpVersion::Auto),
+            "http1" => Ok(HttpVersion::Http1),
+            "http2" => Ok(HttpVersion::Http2),
+            _ => Err(format!("Unknown HTTP version {s:?}")),
+        }
+    }
+}
+
+impl Display for HttpVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let http_version = match self {
+            HttpVersion::Auto => "auto",
+            HttpVersion::Http1 => "http1",
+            HttpVersion::Http2 => "http2",
+        };
+        f.write_str(http_version)
+    }
+}
+
+/// A host name resolved to a fixed IP address, written `host:ip`. The port
+/// is always the one of the URL: the client ignores the port of a resolved
+/// address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveOverride {
+    host: String,
+    ip: IpAddr,
+}
+
+impl FromStr for ResolveOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((host, ip)) = s.split_once(':') else {
+            return Err(format!("Expected `host:ip`, got {s:?}"));
+        };
+        if host.is_empty() {
+            return Err(format!("Missing host in {s:?}"));
+        }
+        let ip = ip.trim_start_matches('[').trim_end_matches(']');
+        let ip = ip.parse::<IpAddr>().map_err(|_| {
+            if ip.parse::<SocketAddr>().is_ok() {
+                format!("Unexpected port in {s:?}, the port of the host's URL is used")
+            } else {
+                format!("Invalid IP address {ip:?} in {s:?}")
+            }
+        })?;
+        Ok(ResolveOverride {
+            host: host.to_string(),
+            ip,
+        })
+    }
+}
+
+/// A header added to every request, written `Name: value`.
+#[derive(Debug, Clone)]
+pub struct RequestHeader {
+    name: HeaderName,
+    value: HeaderValue,
+}
+
+impl FromStr for RequestHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, value)) = s.split_once(':') else {
+            return Err(format!("Expected `Name: value`, got {s:?}"));
+        };
+        Ok(RequestHeader {
+            name: name
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid header name in {s:?}"))?,
+            value: value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid header value in {s:?}"))?,
+        })
+    }
+}
+
+/// The settings of the HTTP client shared by the sinks, so that they all
+/// behave the same at high concurrency.
+#[derive(Args, Debug, Clone)]
+pub struct HttpClientArgs {
+    #[arg(
+        long,
+        env,
+        alias = "connect-timeout",
+        default_value_t = 5,
+        help_heading = "HTTP client options"
+    )]
+    /// The maximum time to establish a connection to the engine.
+    connect_timeout_secs: u64,
+
+    #[arg(
+        long,
+        env,
+        alias = "request-timeout",
+        default_value_t = 60,
+        help_heading = "HTTP client options"
+    )]
+    /// The maximum time of a request to the engine, from sending it to
+    /// reading the whole response. Requests known to be long, such as force
+    /// merges, get a longer timeout.
+    request_timeout_secs: u64,
+
+    #[arg(long, env, help_heading = "HTTP client options")]
+    /// The maximum number of idle connections kept per host. Unlimited by
+    /// default, 0 opens a new connection for every request.
+    pool_max_idle_per_host: Option<usize>,
+
+    #[arg(long, env, default_value_t = 90, help_heading = "HTTP client options")]
+    /// How long idle connections are kept alive in the pool.
+    pool_idle_timeout_secs: u64,
+
+    #[arg(long, env, help_heading = "HTTP client options")]
+    /// Send TCP keep-alive probes on idle connections at this interval.
+    tcp_keepalive_secs: Option<u64>,
+
+    #[arg(long, env, help_heading = "HTTP client options")]
+    /// The HTTP version spoken to the engine: "http1", "http2" (without TLS,
+    /// "prior knowledge"), the requests being then multiplexed on fewer
+    /// connections, or "auto" to let the client negotiate it. Defaults to
+    /// "http1", and to "auto" for the BigQuery and Kusto APIs, which
+    /// negotiate HTTP/2 over TLS.
+    http_version: Option<HttpVersion>,
+
+    #[arg(long, env, hide = true, conflicts_with = "http_version")]
+    /// Deprecated, same as `--http-version http2`.
+    http2: bool,
+
+    #[arg(long, env, help_heading = "HTTP client options")]
+    /// Let the kernel delay small writes (Nagle's algorithm), which is
+    /// disabled by default.
+    no_tcp_nodelay: bool,
+
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        value_name = "HOST:IP",
+        help_heading = "HTTP client options"
+    )]
+    /// Resolve a host name to this IP address instead of asking the DNS,
+    /// e.g. `quickwit:172.18.0.2` to reach a container by the name of its
+    /// internal network. The port is the one of `--host`. Can be repeated.
+    resolve: Vec<ResolveOverride>,
+
+    #[arg(
+        long,
+        env,
+        requires = "client_key",
+        help_heading = "HTTP client options"
+    )]
+    /// The PEM certificate presented to the engine for mutual TLS, e.g. to
+    /// an OpenSearch cluster whose security plugin enforces it. The engine is
+    /// reached over TLS with an `https://` scheme in `--host`.
+    client_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        env,
+        requires = "client_cert",
+        help_heading = "HTTP client options"
+    )]
+    /// The PKCS#8 PEM private key of `--client-cert`.
+    client_key: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "HTTP client options")]
+    /// A PEM CA certificate trusted in addition to the system ones, e.g. the
+    /// CA of a cluster with self-signed certificates.
+    ca_cert: Option<PathBuf>,
+
+    #[arg(
+        long = "header",
+        value_name = "NAME: VALUE",
+        help_heading = "HTTP client options"
+    )]
+    /// A header added to every request, e.g. `X-Scope-OrgID: tenant-1` for
+    /// the tenant of Loki or a routing header of a gateway. Can be repeated.
+    headers: Vec<RequestHeader>,
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))
+}
+
+impl HttpClientArgs {
+    /// The `--http-version`, `default_http_version` if not set.
+    pub fn http_version(&self, default_http_version: HttpVersion) -> HttpVersion {
+        if self.http2 {
+            HttpVersion::Http2
+        } else {
+            self.http_version.unwrap_or(default_http_version)
+        }
+    }
+
+    pub fn build_client(&self) -> anyhow::Result<Client> {
+        self.build_client_with_default_version(HttpVersion::Http1)
+    }
+
+    /// The client speaking `default_http_version` unless `--http-version` is
+    /// set.
+    pub fn build_client_with_default_version(
+        &self,
+        default_http_version: HttpVersion,
+    ) -> anyhow::Result<Client> {
+        let mut client_builder = Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .timeout(Duration::from_secs(self.request_timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_timeout_secs))
+            .tcp_keepalive(self.tcp_keepalive_secs.map(Duration::from_secs))
+            .tcp_nodelay(!self.no_tcp_nodelay);
+        if let (Some(client_cert), Some(client_key)) =
+            (&self.client_cert, &self.client_key)
+        {
+            let identity = Identity::from_pkcs8_pem(
+                &read_pem(client_cert)?,
+                &read_pem(client_key)?,
+            )
+            .with_context(|| {
+                format!(
+                    "Invalid client certificate {client_cert:?} or key {client_key:?}"
+                )
+            })?;
+            client_builder = client_builder.identity(identity);
+        }
+        if let Some(ca_cert) = &self.ca_cert {
+            let certificate = Certificate::from_pem(&read_pem(ca_cert)?)
+                .with_context(|| format!("Invalid CA certificate {ca_cert:?}"))?;
+            client_builder = client_builder.add_root_certificate(certificate);
+        }
+        if !self.headers.is_empty() {
+            let headers: HeaderMap = self
+                .headers
+                .iter()
+                .map(|header| (header.name.clone(), header.value.clone()))
+                .collect();
+            client_builder = client_builder.default_headers(headers);
+        }
+        for resolve_override in &self.resolve {
+            // The port of the address is ignored by the client.
+            client_builder = client_builder.resolve(
+                &resolve_override.host,
+                SocketAddr::new(resolve_override.ip, 0),
+            );
+        }
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            client_builder =
+                client_builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        client_builder = match self.http_version(default_http_version) {
+            HttpVersion::Auto => client_builder,
+            HttpVersion::Http1 => client_builder.http1_only(),
+            HttpVersion::Http2 => client_builder.http2_prior_knowledge(),
+        };
+        Ok(client_builder.build()?)
+    }
+}
+
+/// The HTTP version the engine at `url` answers the client with, e.g.
+/// "HTTP/2.0", whatever the status of the response.
+pub async fn negotiated_http_version(
+    client: &Client,
+    url: &str,
+) -> anyhow::Result<String> {
+    let response = client.get(url).send().await?;
+    Ok(format!("{:?}", response.version()))
+}
+
+/// Whether the error, or one of its causes, is a connect or request timeout.
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_timeout)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use clap::Parser;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        http_client: HttpClientArgs,
+    }
+
+    #[tokio::test]
+    async fn test_is_timeout() {
+        // Accepts connections but never answers.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let client = Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        let error = client
+            .get(&url)
+            .send()
+            .await
+            .context("request error")
+            .unwrap_err();
+        assert!(is_timeout(&error));
+        assert!(!is_timeout(&anyhow::anyhow!(
+            "http error with status code 500"
+        )));
+        drop(listener);
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_http_version() {
+        assert_eq!("http2".parse::<HttpVersion>(), Ok(HttpVersion::Http2));
+        assert_eq!(HttpVersion::Auto.to_string(), "auto");
+        assert!("h2".parse::<HttpVersion>().is_err());
+        let cli = Cli::try_parse_from(["qbench"]).unwrap();
+        assert_eq!(
+            cli.http_client.http_version(HttpVersion::Auto),
+            HttpVersion::Auto
+        );
+        let cli = Cli::try_parse_from(["qbench", "--http-version", "http1"]).unwrap();
+        assert_eq!(
+            cli.http_client.http_version(HttpVersion::Auto),
+            HttpVersion::Http1
+        );
+        let cli = Cli::try_parse_from(["qbench", "--http2"]).unwrap();
+        assert_eq!(
+            cli.http_client.http_version(HttpVersion::Http1),
+            HttpVersion::Http2
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let http_version = negotiated_http_version(&Client::new(), &url).await.unwrap();
+        assert_eq!(http_version, "HTTP/1.1");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_override() {
+        let resolve_override: ResolveOverride = "quickwit:172.18.0.2".parse().unwrap();
+        assert_eq!(resolve_override.ip, "172.18.0.2".parse::<IpAddr>().unwrap());
+        let resolve_override: ResolveOverride = "es:[::1]".parse().unwrap();
+        assert_eq!(resolve_override.host, "es");
+        assert_eq!(resolve_override.ip, "::1".parse::<IpAddr>().unwrap());
+        let error = "quickwit:172.18.0.2:7280"
+            .parse::<ResolveOverride>()
+            .unwrap_err();
+        assert!(error.contains("Unexpected port"));
+        assert!("quickwit".parse::<ResolveOverride>().is_err());
+        assert!("quickwit:not-an-ip".parse::<ResolveOverride>().is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let cli = Cli::try_parse_from([
+            "qbench",
+            "--resolve",
+            "engine.qbench.invalid:127.0.0.1",
+        ])
+        .unwrap();
+        let client = cli.http_client.build_client().unwrap();
+        let url = format!("http://engine.qbench.invalid:{port}/");
+        assert!(negotiated_http_version(&client, &url).await.is_ok());
+    }
+
+    #[test]
+    fn test_client_cert() {
+        assert!(Cli::try_parse_from(["qbench", "--client-cert", "client.pem"]).is_err());
+        let not_pem = std::env::temp_dir()
+            .join(format!("qbench-client-{}.pem", std::process::id()));
+        std::fs::write(&not_pem, "not a certificate").unwrap();
+        let cli = Cli::try_parse_from([
+            "qbench",
+            "--client-cert",
+            not_pem.to_str().unwrap(),
+            "--client-key",
+            not_pem.to_str().unwrap(),
+        ])
+        .unwrap();
+        let error = cli.http_client.build_client().unwrap_err();
+        assert!(format!("{error:#}").contains("Invalid client certificate"));
+        std::fs::remove_file(&not_pem).unwrap();
+        let cli = Cli::try_parse_from(["qbench", "--ca-cert", "missing.pem"]).unwrap();
+        let error = cli.http_client.build_client().unwrap_err();
+        assert!(format!("{error:#}").contains("Failed to read"));
+    }
+
+    #[tokio::test]
+    async fn test_request_headers() {
+        assert!("X-Scope-OrgID".parse::<RequestHeader>().is_err());
+        assert!("Bad Name: value".parse::<RequestHeader>().is_err());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let request = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let num_bytes = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..num_bytes]).to_lowercase()
+        });
+        let cli = Cli::try_parse_from([
+            "qbench",
+            "--header",
+            "X-Scope-OrgID: tenant-1",
+            "--header",
+            "x-route:  blue ",
+        ])
+        .unwrap();
+        let client = cli.http_client.build_client().unwrap();
+        negotiated_http_version(&client, &url).await.unwrap();
+        let request = request.await.unwrap();
+        assert!(request.contains("x-scope-orgid: tenant-1\r\n"));
+        assert!(request.contains("x-route: blue\r\n"));
+    }
+}
@@ -0,0 +1,638 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use clap::Args;
+use qbench_core::source::DatasetFormat;
+use serde_json::{json, Map, Value};
+
+use crate::query::sample_docs;
+use crate::report::format_table;
+
+/// The distinct values tracked per field: fields with more are not keywords.
+const MAX_TRACKED_VALUES: usize = 1000;
+
+/// Strings longer than this are free text rather than keywords.
+const MAX_KEYWORD_LEN: usize = 64;
+
+#[derive(Args, Debug)]
+pub struct InferMappingArgs {
+    #[arg(long, env)]
+    /// The dataset to sample, see `qbench index --dataset-uri`.
+    dataset_uri: String,
+
+    #[arg(short, long, env)]
+    /// The engine to generate the index config for: "quickwit",
+    /// "elasticsearch", "opensearch" or "clickhouse".
+    engine: MappingEngine,
+
+    #[arg(short, long, env, default_value = "benchmark")]
+    /// The index ID of the generated Quickwit index config, or the table name
+    /// of the generated ClickHouse DDL.
+    index: String,
+
+    #[arg(long, env, default_value_t = 1000)]
+    /// The number of documents sampled from the dataset.
+    num_sample_docs: usize,
+
+    #[arg(long, env, default_value_t = 100)]
+    /// String fields with at most this many distinct values over the sample
+    /// are mapped as keywords (raw tokenizer in Quickwit), the others as text.
+    max_keyword_cardinality: usize,
+
+    #[arg(long, env)]
+    /// The timestamp field of the index. By default, the first datetime field
+    /// named like a timestamp, or else the first datetime field.
+    timestamp_field: Option<String>,
+
+    #[arg(long, env)]
+    /// The format of the dataset's files, see `qbench index --dataset-format`.
+    dataset_format: Option<DatasetFormat>,
+
+    #[arg(long, env)]
+    /// Convert the integer, float and boolean CSV/TSV values to JSON numbers
+    /// and booleans.
+    csv_infer_types: bool,
+
+    #[arg(long, env)]
+    /// A pre-obtained GCP access token to sample `gs://` datasets.
+    gcp_access_token: Option<String>,
+
+    #[arg(long, env)]
+    /// Write the index config to this file instead of stdout.
+    output_path: Option<PathBuf>,
+}
+
+impl InferMappingArgs {
+    pub fn prints_to_stdout(&self) -> bool {
+        self.output_path.is_none()
+    }
+}
+
+/// The engines an index config can be generated for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MappingEngine {
+    Quickwit,
+    Elasticsearch,
+    Opensearch,
+    Clickhouse,
+}
+
+impl FromStr for MappingEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quickwit" => Ok(MappingEngine::Quickwit),
+            "elasticsearch" => Ok(MappingEngine::Elasticsearch),
+            "opensearch" => Ok(MappingEngine::Opensearch),
+            "clickhouse" => Ok(MappingEngine::Clickhouse),
+            _ => Err(format!(
+                "Generating index configs for {s:?} is not supported"
+            )),
+        }
+    }
+}
+
+/// The type of a field, inferred from its values over the sample.
+#[derive(Debug, Clone, PartialEq)]
+enum FieldType {
+    Bool,
+    I64,
+    U64,
+    F64,
+    Datetime,
+    Keyword,
+    Text,
+    Object(BTreeMap<String, InferredField>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct InferredField {
+    field_type: FieldType,
+    is_array: bool,
+    /// The fraction of the sampled documents (or parent objects) having the
+    /// field.
+    presence: f64,
+    /// The distinct values seen, None if there were more than
+    /// `MAX_TRACKED_VALUES`.
+    cardinality: Option<usize>,
+}
+
+/// What was seen of a field over the sample.
+#[derive(Default)]
+struct FieldStats {
+    num_docs: usize,
+    is_array: bool,
+    num_bools: usize,
+    num_i64s: usize,
+    num_u64s: usize,
+    num_f64s: usize,
+    num_datetimes: usize,
+    num_strings: usize,
+    num_others: usize,
+    max_string_len: usize,
+    distinct_values: BTreeSet<String>,
+    too_many_values: bool,
+    object: Option<ObjectStats>,
+}
+
+#[derive(Default)]
+struct ObjectStats {
+    num_docs: usize,
+    fields: BTreeMap<String, FieldStats>,
+}
+
+impl ObjectStats {
+    fn record(&mut self, object: &Map<String, Value>) {
+        self.num_docs += 1;
+        for (name, value) in object {
+            let field_stats = self.fields.entry(name.clone()).or_default();
+            field_stats.num_docs += 1;
+            match value {
+                Value::Array(values) => {
+                    field_stats.is_array = true;
+                    for value in values {
+                        field_stats.record_value(value);
+                    }
+                },
+                value => field_stats.record_value(value),
+            }
+        }
+    }
+
+    fn infer(&self, max_keyword_cardinality: usize) -> BTreeMap<String, InferredField> {
+        self.fields
+            .iter()
+            .filter_map(|(name, field_stats)| {
+                let field_type = field_stats.infer_type(max_keyword_cardinality)?;
+                let inferred_field = InferredField {
+                    field_type,
+                    is_array: field_stats.is_array,
+                    presence: field_stats.num_docs as f64 / self.num_docs as f64,
+                    cardinality: (!field_stats.too_many_values)
+                        .then_some(field_stats.distinct_values.len()),
+                };
+                Some((name.clone(), inferred_field))
+            })
+            .collect()
+    }
+}
+
+impl FieldStats {
+    fn record_value(&mut self, value: &Value) {
+        match value {
+            Value::Null => return,
+            Value::Bool(_) => self.num_bools += 1,
+            Value::Number(number) if number.is_i64() => self.num_i64s += 1,
+            Value::Number(number) if number.is_u64() => self.num_u64s += 1,
+            Value::Number(_) => self.num_f64s += 1,
+            Value::String(string) => {
+                self.num_strings += 1;
+                self.max_string_len = self.max_string_len.max(string.len());
+                if chrono::DateTime::parse_from_rfc3339(string).is_ok() {
+                    self.num_datetimes += 1;
+                }
+            },
+            Value::Object(object) => {
+                self.object
+                    .get_or_insert_with(ObjectStats::default)
+                    .record(object);
+                return;
+            },
+            Value::Array(_) => self.num_others += 1,
+        }
+        if !self.too_many_values {
+            self.distinct_values.insert(value.to_string());
+            if self.distinct_values.len() > MAX_TRACKED_VALUES {
+                self.too_many_values = true;
+                self.distinct_values.clear();
+            }
+        }
+    }
+
+    /// None if the field only had nulls.
+    fn infer_type(&self, max_keyword_cardinality: usize) -> Option<FieldType> {
+        let num_numbers = self.num_i64s + self.num_u64s + self.num_f64s;
+        let num_scalars = self.num_bools + num_numbers + self.num_strings;
+        if let Some(object) = &self.object {
+            // Objects mixed with scalars can't be mapped as objects.
+            if num_scalars + self.num_others == 0 {
+                return Some(FieldType::Object(object.infer(max_keyword_cardinality)));
+            }
+            return Some(FieldType::Text);
+        }
+        if num_scalars + self.num_others == 0 {
+            return None;
+        }
+        let field_type = if self.num_bools == num_scalars {
+            FieldType::Bool
+        } else if num_numbers == num_scalars && self.num_f64s > 0 {
+            FieldType::F64
+        } else if num_numbers == num_scalars && self.num_u64s > 0 {
+            FieldType::U64
+        } else if num_numbers == num_scalars {
+            FieldType::I64
+        } else if self.num_datetimes == num_scalars {
+            FieldType::Datetime
+        } else if !self.too_many_values
+            && self.distinct_values.len() <= max_keyword_cardinality
+            && self.max_string_len <= MAX_KEYWORD_LEN
+        {
+            FieldType::Keyword
+        } else {
+            // Mixed types are indexed as text as well.
+            FieldType::Text
+        };
+        Some(field_type)
+    }
+}
+
+fn infer_fields(
+    docs: &[Value],
+    max_keyword_cardinality: usize,
+) -> BTreeMap<String, InferredField> {
+    let mut doc_stats = ObjectStats::default();
+    for doc in docs {
+        if let Value::Object(doc) = doc {
+            doc_stats.record(doc);
+        }
+    }
+    doc_stats.infer(max_keyword_cardinality)
+}
+
+/// The timestamp field: the first top-level datetime field named like a
+/// timestamp, or else the first one.
+fn default_timestamp_field(fields: &BTreeMap<String, InferredField>) -> Option<&str> {
+    let datetime_fields: Vec<&str> = fields
+        .iter()
+        .filter(|(_, field)| field.field_type == FieldType::Datetime && !field.is_array)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    datetime_fields
+        .iter()
+        .find(|name| {
+            let name = name.to_lowercase();
+            name.contains("timestamp") || name == "ts" || name == "time"
+        })
+        .or(datetime_fields.first())
+        .copied()
+}
+
+fn quickwit_field_mappings(fields: &BTreeMap<String, InferredField>) -> Vec<Value> {
+    fields
+        .iter()
+        .map(|(name, field)| {
+            let mut field_mapping = match &field.field_type {
+                FieldType::Object(object_fields) => json!({
+                    "type": "object",
+                    "field_mappings": quickwit_field_mappings(object_fields),
+                }),
+                FieldType::Bool => json!({"type": "bool", "fast": true}),
+                FieldType::I64 => json!({"type": "i64", "fast": true}),
+                FieldType::U64 => json!({"type": "u64", "fast": true}),
+                FieldType::F64 => json!({"type": "f64", "fast": true}),
+                FieldType::Datetime => json!({
+                    "type": "datetime",
+                    "input_formats": ["rfc3339"],
+                    "fast": true,
+                    "fast_precision": "milliseconds",
+                }),
+                FieldType::Keyword => {
+                    json!({"type": "text", "tokenizer": "raw", "fast": true})
+                },
+                FieldType::Text => json!({"type": "text", "tokenizer": "default"}),
+            };
+            if field.is_array && !matches!(field.field_type, FieldType::Object(_)) {
+                let field_type = field_mapping["type"].as_str().unwrap_or_default();
+                field_mapping["type"] = json!(format!("array<{field_type}>"));
+            }
+            field_mapping["name"] = json!(name);
+            field_mapping
+        })
+        .collect()
+}
+
+fn quickwit_index_config(
+    index_id: &str,
+    fields: &BTreeMap<String, InferredField>,
+    timestamp_field: Option<&str>,
+) -> Value {
+    let default_search_fields: Vec<&str> = fields
+        .iter()
+        .filter(|(_, field)| field.field_type == FieldType::Text)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let mut doc_mapping = json!({
+        "mode": "dynamic",
+        "field_mappings": quickwit_field_mappings(fields),
+    });
+    if let Some(timestamp_field) = timestamp_field {
+        doc_mapping["timestamp_field"] = json!(timestamp_field);
+    }
+    json!({
+        "version": "0.8",
+        "index_id": index_id,
+        "doc_mapping": doc_mapping,
+        "search_settings": {"default_search_fields": default_search_fields},
+        "indexing_settings": {"commit_timeout_secs": 60},
+    })
+}
+
+fn elasticsearch_properties(
+    fields: &BTreeMap<String, InferredField>,
+) -> Map<String, Value> {
+    fields
+        .iter()
+        .map(|(name, field)| {
+            // Arrays are transparent in Elasticsearch mappings.
+            let property = match &field.field_type {
+                FieldType::Object(object_fields) => {
+                    json!({"properties": elasticsearch_properties(object_fields)})
+                },
+                FieldType::Bool => json!({"type": "boolean"}),
+                FieldType::I64 => json!({"type": "long"}),
+                FieldType::U64 => json!({"type": "unsigned_long"}),
+                FieldType::F64 => json!({"type": "double"}),
+                FieldType::Datetime => json!({"type": "date"}),
+                FieldType::Keyword => json!({"type": "keyword"}),
+                FieldType::Text => json!({"type": "text"}),
+            };
+            (name.clone(), property)
+        })
+        .collect()
+}
+
+fn elasticsearch_index_config(fields: &BTreeMap<String, InferredField>) -> Value {
+    json!({
+        "settings": {
+            "index.number_of_shards": 1,
+            "index.number_of_replicas": 0,
+        },
+        "mappings": {
+            "date_detection": false,
+            "properties": elasticsearch_properties(fields),
+        },
+    })
+}
+
+fn clickhouse_column_type(field: &InferredField) -> String {
+    let column_type = match &field.field_type {
+        FieldType::Object(object_fields) => {
+            let elements: Vec<String> = object_fields
+                .iter()
+                .map(|(name, field)| {
+                    format!("`{name}` {}", clickhouse_column_type(field))
+                })
+                .collect();
+            format!("Tuple({})", elements.join(", "))
+        },
+        FieldType::Bool => "Bool".to_string(),
+        FieldType::I64 => "Int64".to_string(),
+        FieldType::U64 => "UInt64".to_string(),
+        FieldType::F64 => "Float64".to_string(),
+        FieldType::Datetime => "DateTime64(3)".to_string(),
+        FieldType::Keyword => "LowCardinality(String)".to_string(),
+        FieldType::Text => "String".to_string(),
+    };
+    if field.is_array {
+        format!("Array({column_type})")
+    } else {
+        column_type
+    }
+}
+
+/// A `CREATE TABLE` statement for the documents inserted as `JSONEachRow`.
+/// Missing and null values take the column's default, so the columns are not
+/// `Nullable`.
+fn clickhouse_create_table(
+    table: &str,
+    fields: &BTreeMap<String, InferredField>,
+    timestamp_field: Option<&str>,
+) -> String {
+    let columns: Vec<String> = fields
+        .iter()
+        .map(|(name, field)| format!("    `{name}` {}", clickhouse_column_type(field)))
+        .collect();
+    let order_by = match timestamp_field {
+        Some(timestamp_field) => format!("`{timestamp_field}`"),
+        None => "tuple()".to_string(),
+    };
+    format!(
+        "-- Insert with `date_time_input_format = 'best_effort'` to parse the RFC 3339 \
+         datetimes.\nCREATE TABLE `{table}`\n(\n{}\n)\nENGINE = MergeTree\nORDER BY \
+         {order_by};\n",
+        columns.join(",\n")
+    )
+}
+
+/// Prints the inferred fields, nested ones with their dotted path.
+fn print_field_summary(fields: &BTreeMap<String, InferredField>) {
+    fn push_rows(
+        prefix: &str,
+        fields: &BTreeMap<String, InferredField>,
+        rows: &mut Vec<Vec<String>>,
+    ) {
+        for (name, field) in fields {
+            let path = format!("{prefix}{name}");
+            let field_type = match &field.field_type {
+                FieldType::Object(object_fields) => {
+                    push_rows(&format!("{path}."), object_fields, rows);
+                    continue;
+                },
+                field_type => format!("{field_type:?}").to_lowercase(),
+            };
+            rows.push(vec![
+                path,
+                if field.is_array {
+                    format!("array<{field_type}>")
+                } else {
+                    field_type
+                },
+                format!("{:.0}%", field.presence * 100.0),
+                field
+                    .cardinality
+                    .map(|cardinality| cardinality.to_string())
+                    .unwrap_or_else(|| format!(">{MAX_TRACKED_VALUES}")),
+            ]);
+        }
+    }
+    let mut summary_rows = Vec::new();
+    push_rows("", fields, &mut summary_rows);
+    let header: Vec<String> = ["field", "type", "presence", "cardinality"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    eprint!("{}", format_table(&header, &summary_rows, 2));
+}
+
+/// Samples the dataset and prints an index config for the engine, inferred
+/// from the sampled documents.
+pub async fn infer_mapping(args: InferMappingArgs) -> anyhow::Result<()> {
+    let dataset_format = args
+        .dataset_format
+        .unwrap_or_else(|| DatasetFormat::detect(&args.dataset_uri));
+    let docs = sample_docs(
+        &args.dataset_uri,
+        args.num_sample_docs,
+        args.gcp_access_token.as_deref(),
+        dataset_format,
+        args.csv_infer_types,
+    )
+    .await?;
+    if docs.is_empty() {
+        bail!("No document found in `{}`", args.dataset_uri);
+    }
+    info!(
+        num_docs = docs.len(),
+        "Inferring the mapping of the sampled documents"
+    );
+    let fields = infer_fields(&docs, args.max_keyword_cardinality);
+    print_field_summary(&fields);
+
+    let timestamp_field = match &args.timestamp_field {
+        Some(timestamp_field) => {
+            if fields.get(timestamp_field).map(|field| &field.field_type)
+                != Some(&FieldType::Datetime)
+            {
+                warn!(
+                    timestamp_field,
+                    "The timestamp field is not an RFC 3339 datetime"
+                );
+            }
+            Some(timestamp_field.as_str())
+        },
+        None => default_timestamp_field(&fields),
+    };
+    let index_config = match args.engine {
+        MappingEngine::Quickwit => {
+            let index_config =
+                quickwit_index_config(&args.index, &fields, timestamp_field);
+            serde_yaml::to_string(&index_config)?
+        },
+        MappingEngine::Clickhouse => {
+            clickhouse_create_table(&args.index, &fields, timestamp_field)
+        },
+        MappingEngine::Elasticsearch | MappingEngine::Opensearch => {
+            let mut index_config =
+                serde_json::to_string_pretty(&elasticsearch_index_config(&fields))?;
+            index_config.push('\n');
+            index_config
+        },
+    };
+    match &args.output_path {
+        Some(output_path) => {
+            std::fs::write(output_path, index_config).with_context(|| {
+                format!("Failed to write the index config to {output_path:?}")
+            })?;
+            info!("Index config written to {output_path:?}");
+        },
+        None => print!("{index_config}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_fields() {
+        let docs: Vec<Value> = (0..20)
+            .map(|idx| {
+                json!({
+                    "timestamp": format!("2024-01-01T00:00:{idx:02}Z"),
+                    "level": if idx % 2 == 0 { "info" } else { "error" },
+                    "message": format!("request {idx} served"),
+                    "latency": idx as f64 / 10.0,
+                    "status": 200 + idx,
+                    "tags": ["a", "b"],
+                    "attributes": {"host": "h1", "cached": idx % 3 == 0},
+                    "trace_id": if idx == 0 { json!(null) } else { json!(idx) },
+                })
+            })
+            .collect();
+        let fields = infer_fields(&docs, 15);
+        let field_type = |name: &str| fields[name].field_type.clone();
+        assert_eq!(field_type("timestamp"), FieldType::Datetime);
+        assert_eq!(field_type("level"), FieldType::Keyword);
+        assert_eq!(field_type("message"), FieldType::Text);
+        assert_eq!(field_type("latency"), FieldType::F64);
+        assert_eq!(field_type("status"), FieldType::I64);
+        assert_eq!(fields["status"].cardinality, Some(20));
+        assert!(fields["tags"].is_array);
+        assert_eq!(fields["level"].cardinality, Some(2));
+        let FieldType::Object(attributes) = field_type("attributes") else {
+            panic!("attributes must be an object");
+        };
+        assert_eq!(attributes["cached"].field_type, FieldType::Bool);
+        assert_eq!(default_timestamp_field(&fields), Some("timestamp"));
+
+        let quickwit_config = quickwit_index_config("logs", &fields, Some("timestamp"));
+        let field_mappings = quickwit_config["doc_mapping"]["field_mappings"]
+            .as_array()
+            .unwrap();
+        let field_mapping = |name: &str| {
+            field_mappings
+                .iter()
+                .find(|field_mapping| field_mapping["name"] == name)
+                .unwrap()
+        };
+        assert_eq!(field_mapping("level")["tokenizer"], "raw");
+        assert_eq!(field_mapping("tags")["type"], "array<text>");
+        assert_eq!(
+            field_mapping("attributes")["field_mappings"][0]["type"],
+            "bool"
+        );
+        assert_eq!(
+            quickwit_config["search_settings"]["default_search_fields"],
+            json!(["message"])
+        );
+
+        let elasticsearch_config = elasticsearch_index_config(&fields);
+        let properties = &elasticsearch_config["mappings"]["properties"];
+        assert_eq!(properties["level"]["type"], "keyword");
+        assert_eq!(properties["timestamp"]["type"], "date");
+        assert_eq!(
+            properties["attributes"]["properties"]["host"]["type"],
+            "keyword"
+        );
+    }
+
+    #[test]
+    fn test_clickhouse_create_table() {
+        let docs: Vec<Value> = (0..10)
+            .map(|idx| {
+                json!({
+                    "timestamp": format!("2024-01-01T00:00:{idx:02}Z"),
+                    "level": "info",
+                    "status": idx,
+                    "tags": ["a", "b"],
+                    "attributes": {"host": "h1", "cached": idx % 3 == 0},
+                })
+            })
+            .collect();
+        let fields = infer_fields(&docs, 15);
+        assert_eq!(
+            clickhouse_create_table("logs", &fields, Some("timestamp")),
+            "-- Insert with `date_time_input_format = 'best_effort'` to parse the RFC \
+             3339 datetimes.
+CREATE TABLE `logs`
+(
+    `attributes` Tuple(`cached` Bool, `host` LowCardinality(String)),
+    `level` LowCardinality(String),
+    `status` Int64,
+    `tags` Array(LowCardinality(String)),
+    `timestamp` DateTime64(3)
+)
+ENGINE = MergeTree
+ORDER BY `timestamp`;
+"
+        );
+        assert!(
+            clickhouse_create_table("logs", &fields, None).contains("ORDER BY tuple();")
+        );
+        assert!("mysql".parse::<MappingEngine>().is_err());
+    }
+}
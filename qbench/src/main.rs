@@ -1,39 +1,209 @@
 #[macro_use]
 extern crate tracing;
 
-use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::time::Instant;
-
-use anyhow::bail;
-use clap::Parser;
-use futures_util::stream::FuturesUnordered;
-use rayon::prelude::*;
-use serde::Serialize;
-use serde_json::json;
-use source::{DocumentBatch, Source};
-use tokio_stream::StreamExt;
-mod sink;
-mod source;
-mod utils;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use adaptive_concurrency::AdaptiveConcurrency;
+use admin::{CleanArgs, SetupIndexArgs};
+use anyhow::{bail, Context};
+use batch_stats::BatchSizeHistogram;
+use budget::Budget;
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{Args, Parser, Subcommand};
+use compare::CompareArgs;
+use doc_stats::DocSizeHistogram;
+use driver_usage::DriverUsage;
+use engine_docker::EngineCommandArgs;
+use failed_batches::FailedBatchDumper;
+use http_client::{is_timeout, negotiated_http_version, HttpClientArgs, HttpVersion};
+use infer_mapping::InferMappingArgs;
+use matrix::MatrixArgs;
+use memstats::EngineMemorySampler;
+use metrics::{IngestCounters, LiveMetricsFormat};
+use netstats::TcpStatsSampler;
+use opentelemetry_sdk::trace::TracerProvider;
+use profiles::Pacer;
+use qbench_core::aws_sigv4::AwsSigV4;
+use qbench_core::driver::InFlightRequests;
+use qbench_core::engine::Engine;
+use qbench_core::engine_metrics::{
+    process_cpu_seconds,
+    stats_delta,
+    EngineMetricsScraper,
+    EngineStats,
+};
+use qbench_core::gcp_auth::GcpAuth;
+use qbench_core::results::{OutputFormat, RunResultsFile};
+use qbench_core::run_results::reports::{
+    EngineContainer,
+    PacingProfile,
+    PhaseStats,
+    SchemaDriftKind,
+    TimeSlicesReport,
+};
+use qbench_core::run_results::{
+    RunResults,
+    TimeRange,
+    DATASET_FINGERPRINT_VERSION,
+    DEFAULT_CONCURRENCY,
+    SCHEMA_VERSION,
+};
+use qbench_core::sink::doc_id::DocId;
+use qbench_core::sink::elasticsearch::{Distribution, ElasticsearchSink};
+use qbench_core::sink::es_compatible::EsCompatibleEndpoints;
+use qbench_core::sink::failure_injection::{FailureInjectingSink, FailureInjection};
+use qbench_core::sink::forwarding::{Agent, ForwardingSink};
+use qbench_core::sink::http::HttpSinkSpec;
+use qbench_core::sink::kusto::AadAuth;
+use qbench_core::sink::quickwit::QuickwitCommit;
+use qbench_core::sink::simulated_network::SimulatedNetworkSink;
+use qbench_core::source::{
+    DatasetFormat,
+    DocSampler,
+    DocumentBatch,
+    ShardInfo,
+    Source,
+    UriErrorPolicy,
+    UriState,
+};
+use qbench_core::{sink, source};
+use query::{MixedWorkload, QueryArgs, VisibilityProbe};
+use reorder::ReorderBuffer;
+use report::{print_statistics, run_metrics, ReportArgs, SummaryFormat};
+use run_results::{RunLabelsArgs, ValidateResultsArgs};
+use schema_drift::SchemaDrift;
+use serde_json::{json, Map, Value};
+use source_errors::SourceErrorInjector;
+use time_slices::TimeSliceRecorder;
+use tracing::level_filters::LevelFilter;
+use tracing::Instrument;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+use transform::{BuiltinTransform, TransformPipeline};
+use trend::TrendArgs;
+mod adaptive_concurrency;
+mod admin;
+mod batch_stats;
+mod budget;
+mod compare;
+mod doc_stats;
+mod driver_usage;
+mod engine_docker;
+mod failed_batches;
+mod http_client;
+mod infer_mapping;
+mod matrix;
+mod memstats;
+mod metrics;
+mod netstats;
+mod otel;
+mod profiles;
+mod query;
+mod reorder;
+mod report;
+mod run_config;
+mod run_results;
+mod schema_drift;
+mod shutdown;
+mod source_errors;
+mod stats;
+mod time_slices;
+mod transform;
+mod trend;
+mod tui;
 
 #[derive(Parser, Debug)]
 pub struct CliArgs {
-    #[arg(long, env)]
-    /// Print rtsc and exit.
-    print_only_rtsc: bool,
+    #[command(subcommand)]
+    command: Command,
+
+    #[arg(long, global = true)]
+    /// A YAML file holding the flags of the run, e.g. to reproduce a published
+    /// run from a checked-in file. The keys are the long flags, such as
+    /// `dataset_uri` or `index_config`. The flags given on the command line
+    /// override the file, which overrides the environment variables.
+    config: Option<PathBuf>,
+
+    #[arg(long, env, default_value = "info", global = true)]
+    /// The default log level, e.g. "warn", "info" or "debug".
+    log_level: tracing::Level,
+
+    #[arg(long, env = "RUST_LOG", global = true)]
+    /// Additional comma-separated per-module log directives overriding
+    /// `--log-level`, using the `RUST_LOG` syntax.
+    ///
+    /// The per-batch throughput lines are logged under the `qbench::throughput`
+    /// target, e.g. `--log-filter qbench::throughput=warn` silences them.
+    log_filter: Option<String>,
 
+    #[arg(long, env, default_value = "pretty", global = true)]
+    /// The log output format: "pretty" (human readable) or "json".
+    log_format: LogFormat,
+
+    #[arg(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT", global = true)]
+    /// Export OpenTelemetry spans of the source reads, batch assembly and sink
+    /// sends to this OTLP/gRPC collector, e.g. `http://127.0.0.1:4317`, to
+    /// tell which of them bottlenecks the throughput.
+    otlp_endpoint: Option<String>,
+
+    #[arg(long, env, default_value_t = 4, global = true)]
+    /// The number of worker threads of the async runtime. Raise it on large
+    /// drivers, so that high-throughput runs are not capped by the driver.
+    worker_threads: usize,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run an indexing benchmark.
+    Index(Box<IndexArgs>),
+    /// Run query benchmarks against the index of a previous indexing run.
+    #[command(alias = "query")]
+    Search(QueryArgs),
+    /// Create an index from an engine index config.
+    SetupIndex(SetupIndexArgs),
+    /// Delete an index.
+    Clean(CleanArgs),
+    /// Send the same dataset to several engines, and compare their results.
+    Compare(CompareArgs),
+    /// Run the same benchmark against several versions of an engine started
+    /// in Docker, and combine their results.
+    Matrix(MatrixArgs),
+    /// Summarize the runs of an indexing results file.
+    Report(ReportArgs),
+    /// Print the trend of a metric over the engine versions of stored runs.
+    Trend(TrendArgs),
+    /// Check that a results file matches the current results schema.
+    ValidateResults(ValidateResultsArgs),
+    /// Start or stop an engine in Docker.
+    Engine(EngineCommandArgs),
+    /// Generate an engine index config from the types of the fields of
+    /// documents sampled from a dataset.
+    InferMapping(InferMappingArgs),
+}
+
+/// The engine and index a command targets.
+#[derive(Args, Debug, Clone)]
+pub struct EngineArgs {
     #[arg(short, long, env)]
     /// The search engine to benchmark against.
     ///
     /// Options are currently
-    /// "quickwit", "elasticsearch", "opensearch", "loki".
+    /// "quickwit", "elasticsearch", "opensearch", "es-compatible", "loki",
+    /// "kusto", "bigquery", "tantivy", "exec" for an external sink command
+    /// and "http" for a generic REST ingestion API.
     engine: Engine,
 
     #[arg(long, env)]
-    /// The target engine's host address.
+    /// The target engine's host address, with an `https://` scheme for an
+    /// engine with TLS.
     ///
     /// If not provided the default engine port and localhost are used.
     host: Option<String>,
@@ -42,28 +212,1188 @@ pub struct CliArgs {
     /// The target index ID to benchmark.
     index: String,
 
+    #[arg(long, env, alias = "wait-for-engine")]
+    /// Wait up to this many seconds for the engine to be healthy before
+    /// starting (cluster health for Elasticsearch and OpenSearch,
+    /// `/health/readyz` for Quickwit, `/ready` for Loki), e.g. when it is
+    /// started alongside qbench.
+    wait_for_engine_secs: Option<u64>,
+
+    #[arg(long, env)]
+    /// Stream the ingest requests with chunked transfer encoding instead of
+    /// sending each batch with a `Content-Length`, see
+    /// `--compare-transfer-modes`. Only available for Quickwit, Elasticsearch
+    /// and OpenSearch.
+    chunked_transfer: bool,
+
+    #[arg(long, env, help_heading = "Quickwit options")]
+    /// Whether the v2 ingestion for Quickwit should be used.
+    /// Only makes sense when engine is Engine::Quickwit.
+    qw_ingest_v2: bool,
+
+    #[arg(long, env, help_heading = "Quickwit options")]
+    /// Transform the documents with the VRL script of this file, set on the
+    /// ingest API source of the indexes created by qbench (`setup-index`, and
+    /// between the runs of `--runs`). Quickwit 0.8+.
+    qw_vrl_transform: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "Quickwit options")]
+    /// The `commit` of the ingest requests: `auto`, `wait_for` (acknowledged
+    /// once the documents are searchable) or `force`. The last batch is
+    /// always sent with `force`.
+    qw_commit: Option<QuickwitCommit>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Resend the documents of a bulk request rejected with a retryable
+    /// status (429 or 5xx), up to 3 times, instead of counting them as
+    /// rejected right away.
+    es_retry_rejected_docs: bool,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// The number of primary shards of the indexes created by qbench
+    /// (`setup-index`, and between the runs of `--runs`), overriding the
+    /// index config.
+    es_shards: Option<u32>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// The number of replicas of the indexes created by qbench, overriding
+    /// the index config.
+    es_replicas: Option<u32>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Run the documents through this ingest pipeline, e.g. to measure the
+    /// overhead of grok or date parsing.
+    es_pipeline: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        requires = "es_pipeline",
+        help_heading = "Elasticsearch and OpenSearch options"
+    )]
+    /// Create or replace the `--es-pipeline` with this definition, in YAML or
+    /// JSON, before the run.
+    es_pipeline_file: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Set the `refresh_interval` of the index for the run, e.g. `-1` to
+    /// disable the refreshes, and restore it at the end.
+    es_refresh_interval: Option<String>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Set the `translog.durability` of the index for the run, e.g. `async`
+    /// to fsync the translog in the background, and restore it at the end.
+    es_translog_durability: Option<String>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Set the `number_of_replicas` of the index for the run, e.g. 0, and
+    /// restore it at the end.
+    es_ingest_replicas: Option<u32>,
+
+    #[arg(
+        long,
+        env,
+        requires = "aws_region",
+        help_heading = "Elasticsearch and OpenSearch options"
+    )]
+    /// Sign the requests of the sink with AWS SigV4, using the credentials of
+    /// the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` environment variables, for Amazon OpenSearch
+    /// Service and OpenSearch Serverless endpoints. This includes its
+    /// searches and node stats, but not the requests of the mixed workload,
+    /// the visibility probe and `--engine-metrics-url`, sent with their own
+    /// clients.
+    aws_sigv4: bool,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// The AWS region of the endpoint signed with `--aws-sigv4`.
+    aws_region: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        default_value = "es",
+        help_heading = "Elasticsearch and OpenSearch options"
+    )]
+    /// The AWS service the requests are signed for: `es` for Amazon
+    /// OpenSearch Service, `aoss` for OpenSearch Serverless.
+    aws_service: String,
+
+    #[arg(long, env, default_value_t = 1, help_heading = "Loki options")]
+    /// Spread the documents over this many streams, on the hash of their
+    /// `--routing-field` value or round-robin, as Loki rate-limits each
+    /// stream.
+    loki_streams: usize,
+
+    #[arg(long, env, help_heading = "Loki options")]
+    /// Push the batches to this many tenants in turn (`X-Scope-OrgID`
+    /// `tenant-0`, `tenant-1`...), to simulate multi-tenant ingestion. The
+    /// documents received by each tenant are recorded in the index stats.
+    /// Loki must run with `auth_enabled: true`, and with
+    /// `multi_tenant_queries_enabled: true` for the queries, which search all
+    /// the tenants at once.
+    loki_tenants: Option<usize>,
+
+    #[arg(long, env, help_heading = "Document ID options")]
+    /// Give the documents the ID held in this top-level field, so that a
+    /// document sent twice is only indexed once. Elasticsearch and OpenSearch
+    /// only, the Quickwit documents already carry it.
+    doc_id_field: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        conflicts_with = "doc_id_field",
+        help_heading = "Document ID options"
+    )]
+    /// Give the documents the hash of their content as ID. Quickwit, which
+    /// has no document ID, gets it in the `doc_id` field of the documents.
+    doc_id_hash: bool,
+
+    #[arg(long, env, help_heading = "Document ID options")]
+    /// Route the documents on the value of this top-level field: it is the
+    /// `_routing` of Elasticsearch and OpenSearch documents, a stream label
+    /// of Loki documents, and the partition key of the Quickwit indexes
+    /// created by `setup-index`.
+    routing_field: Option<String>,
+
+    #[arg(
+        long,
+        env,
+        default_value = "{index}/_stats",
+        help_heading = "ES-compatible options"
+    )]
+    /// The index stats endpoint of an ES-compatible engine, `{index}` being
+    /// replaced by the index ID.
+    stats_endpoint: String,
+
+    #[arg(
+        long,
+        env,
+        default_value = "/_all/total/docs/count",
+        help_heading = "ES-compatible options"
+    )]
+    /// The JSON pointer to the number of documents in the stats response.
+    stats_num_docs_pointer: String,
+
+    #[arg(
+        long,
+        env,
+        default_value = "/_all/total/store/size_in_bytes",
+        help_heading = "ES-compatible options"
+    )]
+    /// The JSON pointer to the index size in the stats response.
+    stats_num_bytes_pointer: String,
+
+    #[arg(
+        long,
+        env,
+        default_value = "/_all/total/segments/count",
+        help_heading = "ES-compatible options"
+    )]
+    /// The JSON pointer to the number of segments/splits in the stats response.
+    stats_num_splits_pointer: String,
+
+    #[arg(long, env, default_value = "/", help_heading = "ES-compatible options")]
+    /// The version endpoint of an ES-compatible engine.
+    version_endpoint: String,
+
+    #[arg(
+        long,
+        env,
+        default_value = "/version/number",
+        help_heading = "ES-compatible options"
+    )]
+    /// The JSON pointer to the version in the version endpoint response.
+    version_pointer: String,
+
+    #[arg(long, env, help_heading = "Kusto options")]
+    /// The Kusto database containing the `--index` table.
+    /// Required when engine is Engine::Kusto.
+    kusto_database: Option<String>,
+
+    #[arg(long, env, help_heading = "Kusto options")]
+    /// The name of a pre-created JSON ingestion mapping of the Kusto table.
+    kusto_mapping: Option<String>,
+
+    #[arg(long, env, help_heading = "Kusto options")]
+    /// A pre-obtained AAD bearer token used to authenticate against Kusto.
+    aad_token: Option<String>,
+
+    #[arg(long, env, requires_all = ["aad_client_id", "aad_client_secret"], help_heading = "Kusto options")]
+    /// The AAD tenant of the application used to authenticate against Kusto
+    /// with the client credentials flow.
+    aad_tenant_id: Option<String>,
+
+    #[arg(long, env, help_heading = "Kusto options")]
+    /// The AAD application (client) ID.
+    aad_client_id: Option<String>,
+
+    #[arg(long, env, help_heading = "Kusto options")]
+    /// The AAD application secret.
+    aad_client_secret: Option<String>,
+
+    #[arg(long, env, help_heading = "BigQuery options")]
+    /// The GCP project of the BigQuery dataset.
+    /// Required when engine is Engine::Bigquery.
+    bq_project: Option<String>,
+
+    #[arg(long, env, help_heading = "BigQuery options")]
+    /// The BigQuery dataset containing the `--index` table.
+    /// Required when engine is Engine::Bigquery.
+    bq_dataset: Option<String>,
+
+    #[arg(long, env, help_heading = "Google Cloud options")]
+    /// A pre-obtained GCP access token, used for BigQuery and `gs://` datasets.
+    /// If not provided, application default credentials are used.
+    gcp_access_token: Option<String>,
+
+    #[arg(long, env, help_heading = "Tantivy options")]
+    /// A Quickwit index config (YAML) whose doc mapping defines the tantivy
+    /// schema. Required when engine is Engine::Tantivy.
+    tantivy_mapping: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "Tantivy options")]
+    /// The directory the tantivy index is created in. Defaults to
+    /// `tantivy-indexes/{index}`, it must not contain an index already.
+    tantivy_index_dir: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "Exec options")]
+    /// The command receiving the documents, run with `sh -c`. It speaks the
+    /// line protocol of `qbench_core::sink::exec::ExecSink` over its stdin and
+    /// stdout. Required when engine is Engine::Exec.
+    exec_command: Option<String>,
+
+    #[arg(long, env, help_heading = "HTTP options")]
+    /// The spec (YAML) of the REST ingestion API: URL template, method,
+    /// headers and body format (ndjson, array or lines), see
+    /// `qbench_core::sink::http::HttpSinkSpec`. Required when engine is
+    /// Engine::Http.
+    http_sink_spec: Option<PathBuf>,
+
+    #[command(flatten)]
+    http_client: HttpClientArgs,
+}
+
+impl EngineArgs {
+    fn doc_id(&self) -> Option<DocId> {
+        if self.doc_id_hash {
+            Some(DocId::Hash)
+        } else {
+            self.doc_id_field.clone().map(DocId::Field)
+        }
+    }
+
+    /// The engine's host, or its default host.
+    fn host(&self) -> String {
+        self.host
+            .clone()
+            .unwrap_or_else(|| self.engine.default_host().to_string())
+    }
+
+    /// The directory of the tantivy index.
+    fn tantivy_index_dir(&self) -> PathBuf {
+        self.tantivy_index_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("tantivy-indexes").join(&self.index))
+    }
+
+    /// The HTTP version of the engine's client when `--http-version` is not
+    /// set: HTTP/1.1, negotiated for the cloud APIs reached over TLS.
+    fn default_http_version(&self) -> HttpVersion {
+        match self.engine {
+            Engine::Bigquery | Engine::Kusto => HttpVersion::Auto,
+            _ => HttpVersion::Http1,
+        }
+    }
+
+    /// The HTTP version the engine answers with, for the engines reached over
+    /// HTTP at `--host`.
+    async fn negotiated_http_version(&self) -> Option<String> {
+        if !matches!(
+            self.engine,
+            Engine::Quickwit
+                | Engine::Elasticsearch
+                | Engine::Opensearch
+                | Engine::EsCompatible
+                | Engine::Loki
+        ) {
+            return None;
+        }
+        let url = format!("{}/", sink::base_url(&self.host()));
+        let client = self.http_client.build_client().ok()?;
+        match negotiated_http_version(&client, &url).await {
+            Ok(http_version) => {
+                info!(http_version, "Negotiated HTTP version");
+                Some(http_version)
+            },
+            Err(error) => {
+                warn!("Failed to get the negotiated HTTP version: {error:#}");
+                None
+            },
+        }
+    }
+
+    /// Polls the health of the engine until it is ready, for at most
+    /// `--wait-for-engine-secs`.
+    async fn wait_for_engine(&self, sink: &dyn sink::Sink) -> anyhow::Result<()> {
+        let Some(wait_for_engine_secs) = self.wait_for_engine_secs else {
+            return Ok(());
+        };
+        info!("Waiting for {} to be ready", self.engine);
+        sink::wait_until_healthy(sink, Duration::from_secs(wait_for_engine_secs))
+            .await
+            .with_context(|| {
+                format!(
+                    "{} at {} is not ready after {wait_for_engine_secs}s",
+                    self.engine,
+                    self.host()
+                )
+            })
+    }
+
+    fn build_elasticsearch_sink(
+        &self,
+        alias: Option<&str>,
+    ) -> anyhow::Result<ElasticsearchSink> {
+        let sink = ElasticsearchSink::new(
+            &self.host(),
+            &self.index,
+            alias,
+            if self.engine == Engine::Opensearch {
+                Distribution::Opensearch
+            } else {
+                Distribution::Elasticsearch
+            },
+            self.http_client.build_client()?,
+        )
+        .with_retry_rejected_docs(self.es_retry_rejected_docs)
+        .with_doc_id(self.doc_id())
+        .with_routing_field(self.routing_field.clone())
+        .with_shards(self.es_shards, self.es_replicas)
+        .with_pipeline(self.es_pipeline.clone())
+        .with_chunked_transfer(self.chunked_transfer)
+        .with_aws_sigv4(self.aws_sigv4()?.map(Arc::new));
+        Ok(sink)
+    }
+
+    /// The signer of `--aws-sigv4`, if any.
+    fn aws_sigv4(&self) -> anyhow::Result<Option<AwsSigV4>> {
+        let Some(aws_region) = self.aws_region.as_deref().filter(|_| self.aws_sigv4)
+        else {
+            return Ok(None);
+        };
+        AwsSigV4::from_env(aws_region, &self.aws_service)
+            .map(Some)
+            .context("Missing AWS credentials for --aws-sigv4")
+    }
+
+    /// The VRL script of `--qw-vrl-transform`, if any.
+    fn qw_vrl_transform_script(&self) -> anyhow::Result<Option<String>> {
+        self.qw_vrl_transform
+            .as_ref()
+            .map(|script_path| {
+                std::fs::read_to_string(script_path).with_context(|| {
+                    format!("Failed to read VRL transform {script_path:?}")
+                })
+            })
+            .transpose()
+    }
+
+    /// Puts the `--es-pipeline-file` definition of the `--es-pipeline`, if
+    /// any.
+    async fn install_es_pipeline(&self) -> anyhow::Result<()> {
+        let Some(pipeline_path) = &self.es_pipeline_file else {
+            return Ok(());
+        };
+        let pipeline_definition =
+            std::fs::read_to_string(pipeline_path).with_context(|| {
+                format!("Failed to read ingest pipeline {pipeline_path:?}")
+            })?;
+        self.build_elasticsearch_sink(None)?
+            .put_pipeline(&pipeline_definition)
+            .await?;
+        info!(pipeline = self.es_pipeline, "Ingest pipeline installed");
+        Ok(())
+    }
+
+    /// The index settings set for the run by `--es-refresh-interval`,
+    /// `--es-translog-durability` and `--es-ingest-replicas`.
+    fn es_ingest_settings(&self) -> Map<String, Value> {
+        let mut settings = Map::new();
+        if let Some(refresh_interval) = &self.es_refresh_interval {
+            settings.insert(
+                "index.refresh_interval".to_string(),
+                json!(refresh_interval),
+            );
+        }
+        if let Some(translog_durability) = &self.es_translog_durability {
+            settings.insert(
+                "index.translog.durability".to_string(),
+                json!(translog_durability),
+            );
+        }
+        if let Some(num_replicas) = self.es_ingest_replicas {
+            // Elasticsearch returns the settings as strings.
+            settings.insert(
+                "index.number_of_replicas".to_string(),
+                json!(num_replicas.to_string()),
+            );
+        }
+        settings
+    }
+
+    /// Applies the `es_ingest_settings` to the index, if any, keeping their
+    /// previous values to restore them after the run.
+    async fn tune_es_index(&self) -> anyhow::Result<Option<EsIndexTuning>> {
+        let settings = self.es_ingest_settings();
+        if settings.is_empty() {
+            return Ok(None);
+        }
+        let sink = self.build_elasticsearch_sink(None)?;
+        let setting_names: Vec<&str> = settings.keys().map(String::as_str).collect();
+        let previous_settings = sink.index_settings(&setting_names).await?;
+        sink.put_index_settings(&settings).await?;
+        info!(settings=?settings, previous_settings=?previous_settings, "Index settings tuned for the run");
+        Ok(Some(EsIndexTuning {
+            settings,
+            previous_settings,
+        }))
+    }
+
+    /// Restores the index settings changed by `tune_es_index`.
+    async fn restore_es_index(&self, tuning: &EsIndexTuning) -> anyhow::Result<()> {
+        self.build_elasticsearch_sink(None)?
+            .put_index_settings(&tuning.previous_settings)
+            .await?;
+        info!(settings=?tuning.previous_settings, "Index settings restored");
+        Ok(())
+    }
+
+    /// Creates the sink writing to the index, through `alias` if set.
+    fn build_sink(&self, alias: Option<&str>) -> anyhow::Result<Box<dyn sink::Sink>> {
+        let host = self.host();
+        let client = self
+            .http_client
+            .build_client_with_default_version(self.default_http_version())?;
+        let doc_id = self.doc_id();
+        if doc_id.is_some()
+            && !matches!(
+                self.engine,
+                Engine::Quickwit | Engine::Elasticsearch | Engine::Opensearch
+            )
+        {
+            bail!("Document IDs are only supported by Quickwit, Elasticsearch and OpenSearch");
+        }
+        if self.routing_field.is_some()
+            && !matches!(
+                self.engine,
+                Engine::Quickwit
+                    | Engine::Elasticsearch
+                    | Engine::Opensearch
+                    | Engine::Loki
+            )
+        {
+            bail!("Routing is only supported by Quickwit, Elasticsearch, OpenSearch and Loki");
+        }
+        if (self.es_shards.is_some() || self.es_replicas.is_some())
+            && !matches!(self.engine, Engine::Elasticsearch | Engine::Opensearch)
+        {
+            bail!("--es-shards and --es-replicas only apply to Elasticsearch and OpenSearch");
+        }
+        if self.es_pipeline.is_some()
+            && !matches!(self.engine, Engine::Elasticsearch | Engine::Opensearch)
+        {
+            bail!("--es-pipeline only applies to Elasticsearch and OpenSearch");
+        }
+        if !self.es_ingest_settings().is_empty()
+            && !matches!(self.engine, Engine::Elasticsearch | Engine::Opensearch)
+        {
+            bail!(
+                "--es-refresh-interval, --es-translog-durability and --es-ingest-replicas \
+                 only apply to Elasticsearch and OpenSearch"
+            );
+        }
+        if self.chunked_transfer
+            && !matches!(
+                self.engine,
+                Engine::Quickwit | Engine::Elasticsearch | Engine::Opensearch
+            )
+        {
+            bail!("--chunked-transfer is only supported by Quickwit, Elasticsearch and OpenSearch");
+        }
+        if self.aws_sigv4 {
+            if !matches!(self.engine, Engine::Elasticsearch | Engine::Opensearch) {
+                bail!("--aws-sigv4 only applies to Elasticsearch and OpenSearch");
+            }
+            if self.chunked_transfer {
+                bail!(
+                    "--aws-sigv4 cannot sign the streamed bodies of --chunked-transfer"
+                );
+            }
+        }
+        if self.engine == Engine::Http
+            && self
+                .host
+                .as_deref()
+                .is_some_and(|host| host.contains("://"))
+        {
+            bail!("The scheme of engine http is set in the URL of --http-sink-spec, not in --host");
+        }
+        if self.qw_vrl_transform.is_some() && self.engine != Engine::Quickwit {
+            bail!("--qw-vrl-transform only applies to Quickwit");
+        }
+        if self.qw_commit.is_some() && self.engine != Engine::Quickwit {
+            bail!("--qw-commit only applies to Quickwit");
+        }
+        if self.loki_streams > 1 && self.engine != Engine::Loki {
+            bail!("--loki-streams is only supported by Loki");
+        }
+        if self.loki_tenants.is_some() && self.engine != Engine::Loki {
+            bail!("--loki-tenants is only supported by Loki");
+        }
+        let sink: Box<dyn sink::Sink> = match self.engine {
+            Engine::Quickwit => {
+                let sink = sink::quickwit::QuickwitSink::new(
+                    &host,
+                    &self.index,
+                    self.qw_ingest_v2,
+                    client,
+                )
+                .with_doc_id(doc_id.as_ref())
+                .with_partition_key(self.routing_field.clone())
+                .with_vrl_transform(self.qw_vrl_transform_script()?)
+                .with_commit(self.qw_commit.unwrap_or_default())
+                .with_chunked_transfer(self.chunked_transfer);
+                Box::new(sink)
+            },
+            Engine::Elasticsearch | Engine::Opensearch => {
+                Box::new(self.build_elasticsearch_sink(alias)?)
+            },
+            Engine::EsCompatible => {
+                let endpoints = EsCompatibleEndpoints {
+                    stats_endpoint: self.stats_endpoint.clone(),
+                    num_docs_pointer: self.stats_num_docs_pointer.clone(),
+                    num_bytes_pointer: self.stats_num_bytes_pointer.clone(),
+                    num_splits_pointer: self.stats_num_splits_pointer.clone(),
+                    version_endpoint: self.version_endpoint.clone(),
+                    version_pointer: self.version_pointer.clone(),
+                };
+                let sink = sink::es_compatible::EsCompatibleSink::new(
+                    &host,
+                    &self.index,
+                    endpoints,
+                    client,
+                );
+                Box::new(sink)
+            },
+            Engine::Kusto => {
+                let Some(database) = &self.kusto_database else {
+                    bail!("--kusto-database is required for engine kusto");
+                };
+                let auth = match (
+                    &self.aad_token,
+                    &self.aad_tenant_id,
+                    &self.aad_client_id,
+                    &self.aad_client_secret,
+                ) {
+                    (Some(token), ..) => AadAuth::Token(token.clone()),
+                    (None, Some(tenant_id), Some(client_id), Some(client_secret)) => {
+                        AadAuth::ClientCredentials {
+                            tenant_id: tenant_id.clone(),
+                            client_id: client_id.clone(),
+                            client_secret: client_secret.clone(),
+                        }
+                    },
+                    _ => AadAuth::None,
+                };
+                let sink = sink::kusto::KustoSink::new(
+                    &host,
+                    database,
+                    &self.index,
+                    self.kusto_mapping.as_deref(),
+                    auth,
+                    client,
+                );
+                Box::new(sink)
+            },
+            Engine::Bigquery => {
+                let (Some(project), Some(dataset)) =
+                    (&self.bq_project, &self.bq_dataset)
+                else {
+                    bail!(
+                        "--bq-project and --bq-dataset are required for engine bigquery"
+                    );
+                };
+                let auth = match &self.gcp_access_token {
+                    Some(access_token) => GcpAuth::from_access_token(access_token),
+                    None => {
+                        GcpAuth::application_default(sink::bigquery::BIGQUERY_SCOPE)?
+                    },
+                };
+                let sink = sink::bigquery::BigQuerySink::new(
+                    project,
+                    dataset,
+                    &self.index,
+                    auth,
+                    client,
+                );
+                Box::new(sink)
+            },
+            #[cfg(feature = "tantivy")]
+            Engine::Tantivy => {
+                let Some(mapping_path) = &self.tantivy_mapping else {
+                    bail!("--tantivy-mapping is required for engine tantivy");
+                };
+                let sink = sink::tantivy::TantivySink::create(
+                    mapping_path,
+                    &self.tantivy_index_dir(),
+                )?;
+                Box::new(sink)
+            },
+            #[cfg(not(feature = "tantivy"))]
+            Engine::Tantivy => {
+                bail!("qbench was built without the `tantivy` feature");
+            },
+            Engine::Loki => {
+                let sink = sink::loki::LokiSink::new(&host, client)
+                    .with_routing_field(self.routing_field.clone())
+                    .with_num_streams(self.loki_streams)?;
+                let sink = match self.loki_tenants {
+                    Some(num_tenants) => sink.with_num_tenants(num_tenants)?,
+                    None => sink,
+                };
+                Box::new(sink)
+            },
+            Engine::Exec => {
+                let Some(command) = &self.exec_command else {
+                    bail!("--exec-command is required for engine exec");
+                };
+                let sink = sink::exec::ExecSink::spawn(
+                    command,
+                    &self.index,
+                    self.host.as_deref(),
+                )?;
+                Box::new(sink)
+            },
+            Engine::Http => {
+                let Some(spec_path) = &self.http_sink_spec else {
+                    bail!("--http-sink-spec is required for engine http");
+                };
+                let spec = HttpSinkSpec::load(spec_path)?;
+                let sink = sink::http::HttpSink::new(spec, &host, &self.index, client)?;
+                Box::new(sink)
+            },
+            _ => {
+                bail!("Engine not supported");
+            },
+        };
+        Ok(sink)
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct IndexArgs {
+    #[command(flatten)]
+    target: EngineArgs,
+
     #[arg(long, env)]
+    /// Print rtsc and exit.
+    print_only_rtsc: bool,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
+    /// Write through this alias instead of the index directly.
+    ///
+    /// Before indexing, the alias is atomically switched from whatever
+    /// indices it currently points to onto `--index`, so previous runs' indices
+    /// are kept around for inspection. Only available for Elasticsearch and
+    /// OpenSearch.
+    alias: Option<String>,
+
+    #[arg(long, env)]
+    /// The container config written by `qbench engine up`, recorded in the
+    /// results.
+    engine_container: Option<PathBuf>,
+
+    #[arg(long, env, help_heading = "Elasticsearch and OpenSearch options")]
     /// Merge the index into one segment/split after indexing.
-    /// Only available for Elasticsearch.
+    /// Only available for Elasticsearch and OpenSearch.
     merge: bool,
 
+    #[arg(
+        long,
+        env,
+        alias = "wait-for-merges",
+        help_heading = "Quickwit options"
+    )]
+    /// After indexing, wait for the background merges to settle, i.e. for the
+    /// split count not to change for this long, and record the time until
+    /// the last merge. Only available for Quickwit.
+    wait_for_merges_secs: Option<u64>,
+
+    #[arg(long, env, default_value_t = 3600, help_heading = "Quickwit options")]
+    /// The maximum time to wait for the merges to settle.
+    merges_timeout_secs: u64,
+
+    #[arg(long, env, default_value_t = DEFAULT_CONCURRENCY)]
+    /// The maximum number of indexing requests in flight.
+    concurrency: usize,
+
+    #[arg(long, env)]
+    /// Adapt the number of requests in flight to the engine, starting from
+    /// `--concurrency`: it grows while the requests stay fast, and is halved
+    /// when they slow down, fail or are throttled. The results record its
+    /// trajectory.
+    adaptive_concurrency: bool,
+
+    #[arg(long, env, default_value_t = 64, requires = "adaptive_concurrency")]
+    /// The number of requests in flight `--adaptive-concurrency` stops growing
+    /// at.
+    max_concurrency: usize,
+
+    #[command(flatten)]
+    labels: RunLabelsArgs,
+
+    #[arg(long, env, default_value_t = 1)]
+    /// Run the benchmark this many times, wiping the index between runs, and
+    /// report the statistics of the key metrics over the runs. The results of
+    /// every run are collected in the output file.
+    runs: usize,
+
+    #[arg(long, env, conflicts_with_all = ["runs", "chunked_transfer"])]
+    /// Run the benchmark once with a `Content-Length` and once with
+    /// `--chunked-transfer`, wiping the index in between, and print the
+    /// results of both modes side by side.
+    compare_transfer_modes: bool,
+
+    #[arg(long, env)]
+    /// The index config the index is recreated from between runs, see `qbench
+    /// setup-index`. Without it, the index is only deleted, which is enough
+    /// for the engines creating indexes on the fly, such as Elasticsearch.
+    index_config: Option<PathBuf>,
+
     #[arg(long, env)]
     /// Whether indexing errors should be retried (in which case, they will
     /// be retried indefinitely).
     retry_indexing_errors: bool,
 
     #[arg(long, env)]
-    /// Whether the v2 ingestion for Quickwit should be used.
-    /// Only makes sense when engine is Engine::Quickwit.
-    qw_ingest_v2: bool,
+    /// Write the payload and the error (with the engine's response) of the
+    /// failed ingest requests to this directory, as
+    /// `failed-batch-<n>.ndjson` and `failed-batch-<n>.error.txt`.
+    dump_failed_batches: Option<PathBuf>,
+
+    #[arg(long, env, default_value_t = 10)]
+    /// The number of failed requests kept by `--dump-failed-batches`, the
+    /// last ones.
+    dump_failed_batches_max: usize,
+
+    #[arg(long, env)]
+    /// Send the documents through a log agent ("vector" or "fluent-bit")
+    /// HTTP source instead of the engine directly. The agent must be
+    /// configured to ship them to the engine's `--index`.
+    forward_to: Option<Agent>,
 
     #[arg(long, env)]
-    /// Specify the datasets path.
+    /// The host of the agent's HTTP source. Defaults to 127.0.0.1:8080 for
+    /// Vector and 127.0.0.1:9880 for Fluent Bit.
+    forwarder_host: Option<String>,
+
+    #[arg(long, env, default_value_t = 600)]
+    /// The maximum time to wait for the agent to deliver all the documents to
+    /// the engine after the last one was sent.
+    forwarder_drain_timeout_secs: u64,
+
+    #[arg(long, env)]
+    /// Delay every ingest request by this many milliseconds, to model an
+    /// ingestion from a remote region without setting up `tc`/`netem`.
+    simulated_latency_ms: Option<u64>,
+
+    #[arg(long, env)]
+    /// Transmit the ingest requests over a simulated link of this many
+    /// megabytes per second, shared by the concurrent requests.
+    simulated_bandwidth_mbps: Option<f64>,
+
+    #[arg(long, env)]
+    /// Randomly fail or delay a fraction of the ingest requests before they
+    /// reach the engine, e.g. `rate=0.01,kind=500`: `kind` is an HTTP status
+    /// code, or `delay` for a `delay_ms` wait (1000 by default), and `seed`
+    /// picks the requests. To exercise the retries and compare how the
+    /// engines degrade.
+    inject_failures: Option<FailureInjection>,
+
+    #[arg(long, env)]
+    /// Specify the datasets path: local files, directories or globs, http(s)
+    /// URLs or `gs://bucket/object` URIs, expanding `{0..n}` ranges.
     dataset_uri: String,
 
+    #[arg(long, env)]
+    /// The format of the dataset's files: "ndjson", "csv", "tsv" or "avro".
+    /// CSV and TSV files must have a header row, whose names become the
+    /// documents' fields. Avro object container files are converted record
+    /// by record. Guessed from the dataset URI's extension by default.
+    dataset_format: Option<DatasetFormat>,
+
+    #[arg(long, env)]
+    /// Convert the integer, float and boolean CSV/TSV values to JSON numbers
+    /// and booleans, and drop the empty ones, instead of sending all the
+    /// values as strings.
+    csv_infer_types: bool,
+
+    #[arg(long, env, default_value = "abort")]
+    /// What to do when reading one of the dataset's URIs fails: "abort" the
+    /// run, "skip" the rest of the URI, or "retry:N" reading it up to N
+    /// times, resuming after the documents already sent, before aborting.
+    on_uri_error: UriErrorPolicy,
+
+    #[arg(long, env)]
+    /// Stop after sending this many documents.
+    max_docs: Option<u64>,
+
+    #[arg(long, env)]
+    /// Stop before sending more than this many bytes of documents.
+    max_bytes: Option<u64>,
+
+    #[arg(long, env, alias = "max-duration")]
+    /// Stop pulling batches this many seconds after the start of the
+    /// ingestion, then commit as usual, e.g. to compare how much each engine
+    /// ingests in an hour.
+    max_duration_secs: Option<u64>,
+
+    #[arg(long, env)]
+    /// Stop pulling batches once the engine reports this many indexed
+    /// documents, polling its index info, to compare the engines on the same
+    /// corpus even if batches are dropped or duplicated.
+    target_num_docs: Option<u64>,
+
+    #[arg(long, env, default_value_t = 1.0)]
+    /// The fraction of the dataset's documents randomly picked to be sent.
+    sample_ratio: f64,
+
+    #[arg(long, env)]
+    /// The seed of the documents picked by `--sample-ratio`. Derived from
+    /// `--seed` by default.
+    sample_seed: Option<u64>,
+
+    #[arg(long, env)]
+    /// Seed every randomized behavior of the run (document sampling, URI
+    /// shuffling, source error and failure injection), so that two runs on
+    /// the same dataset send identical byte streams. The seeds of the
+    /// features override it, and they default to 0 without it.
+    seed: Option<u64>,
+
+    #[arg(long, env)]
+    /// Read the dataset URIs in a random order, given by `--seed`.
+    shuffle_uris: bool,
+
+    #[arg(long, env)]
+    /// Send one batch from each dataset file in turn rather than the files
+    /// one after the other, mixing the documents of time-partitioned files
+    /// (e.g. the hourly GitHub Archive files).
+    interleave_uris: bool,
+
+    #[arg(long, env, default_value_t = 16)]
+    /// The number of dataset files read in turn at once by `--interleave-uris`,
+    /// the next file taking the place of one read to the end.
+    interleave_max_open_uris: usize,
+
+    #[arg(long, env, default_value_t = 1)]
+    /// Stream the dataset this many times, to reach a larger corpus size.
+    repeat_dataset: usize,
+
+    #[arg(long, env)]
+    /// Perturb the IDs (`id`, `_id`, `*_id`) and timestamps of the repeated
+    /// documents so that engines don't dedupe them.
+    mutate_ids: bool,
+
     #[arg(long, env)]
     /// Specify output file path.
     output_path: Option<PathBuf>,
+
+    #[arg(long, env, default_value = "json")]
+    /// The format of the results file: "json", "yaml" or "msgpack".
+    output_format: OutputFormat,
+
+    #[arg(long, env)]
+    /// Also write a table of the main figures of the runs to this file, in
+    /// CSV (`.csv`) or Markdown (`.md`) to paste it in GitHub issues, or as
+    /// aligned text.
+    summary_path: Option<PathBuf>,
+
+    #[arg(long, env, requires = "summary_path")]
+    /// The format of `--summary-path`: "text", "csv" or "markdown". Guessed
+    /// from the file extension by default.
+    summary_format: Option<SummaryFormat>,
+
+    #[arg(long, env)]
+    /// Append the results to the output file instead of replacing it, e.g. to
+    /// collect the runs of a suite into a single file. JSON results are then
+    /// written one per line and YAML results as separate documents.
+    append_output: bool,
+
+    #[arg(long, env, default_value_t = 60)]
+    /// Rewrite the results file with the metrics measured so far at this
+    /// interval during the ingestion, so that a run that dies midway still
+    /// leaves them behind, marked `"partial": true`. 0 disables it.
+    results_flush_interval_secs: u64,
+
+    #[arg(long, env)]
+    /// The price in USD of ingesting one GB (10^9 bytes) into the target.
+    ///
+    /// Used to estimate the spend of the run, see `--max-cost-usd`.
+    cost_per_gb_usd: Option<f64>,
+
+    #[arg(long, env, requires = "cost_per_gb_usd")]
+    /// Stop ingesting before the estimated spend exceeds this amount in USD.
+    max_cost_usd: Option<f64>,
+
+    #[arg(long, env)]
+    /// Stop ingesting before more than this number of bytes is sent to the
+    /// target.
+    max_billed_bytes: Option<u64>,
+
+    #[arg(long, env)]
+    /// Rewrite the documents between the source and the sink with the
+    /// transforms of this JSON file (rename, drop or add fields, lowercase
+    /// keys), applied in order. See `transform::TransformSpec`.
+    transforms: Option<PathBuf>,
+
+    #[arg(long, env, value_delimiter = ',')]
+    /// Rewrite the documents with built-in transforms, applied before the ones
+    /// of `--transforms`: "gharchive" flattens the GitHub Archive events (the
+    /// `actor`, `repo` and `org` objects, and the large `payload` fields).
+    transform: Vec<BuiltinTransform>,
+
+    #[arg(long, env, value_delimiter = ',', requires = "transform")]
+    /// The comma-separated GitHub Archive event types kept by the "gharchive"
+    /// transform, e.g. `PushEvent,IssuesEvent`. All of them by default.
+    gharchive_event_types: Vec<String>,
+
+    #[arg(long, env)]
+    /// Start mutating documents once this many input bytes have been sent,
+    /// to measure the impact of a schema change partway through ingestion.
+    schema_drift_after_bytes: Option<u64>,
+
+    #[arg(long, env, default_value_t = 0.1)]
+    /// The fraction of documents mutated once the schema drift started.
+    schema_drift_ratio: f64,
+
+    #[arg(long, env, default_value = "both")]
+    /// How drifted documents are mutated: "new-fields", "type-changes" or
+    /// "both".
+    schema_drift_kind: SchemaDriftKind,
+
+    #[arg(long, env, conflicts_with = "retry_indexing_errors")]
+    /// Randomly truncate or corrupt this fraction of the documents before
+    /// sending them, to test how malformed input is handled.
+    inject_source_errors: Option<f64>,
+
+    #[arg(long, env)]
+    /// The seed of the documents broken by `--inject-source-errors`. Derived
+    /// from `--seed` by default.
+    inject_source_errors_seed: Option<u64>,
+
+    #[arg(long, env)]
+    /// Sort the documents by `--reorder-timestamp-field` within a sliding
+    /// window of this many documents before sending them, to compare ordered
+    /// and unordered ingestion.
+    reorder_window: Option<usize>,
+
+    #[arg(long, env, default_value = "timestamp")]
+    /// The timestamp field sorted by `--reorder-window`, an RFC 3339 string or
+    /// a number.
+    reorder_timestamp_field: String,
+
+    #[arg(long, env)]
+    /// After indexing, apply a retention dropping all the documents (delete
+    /// task for Quickwit, ILM/ISM delete policy for Elasticsearch/OpenSearch)
+    /// and measure how long it takes for it to be applied and for the storage
+    /// to be reclaimed.
+    measure_retention: bool,
+
+    #[arg(long, env, default_value_t = 3600)]
+    /// The maximum time to wait for the retention to be enforced.
+    retention_timeout_secs: u64,
+
+    #[arg(long, env)]
+    /// Print live metrics (timestamp, MB/s, docs/s, errors, in-flight requests)
+    /// to stdout at every interval. Only "tsv" is supported. Logs are written
+    /// to stderr in that case.
+    live_metrics: Option<LiveMetricsFormat>,
+
+    #[arg(long, env, default_value_t = 1)]
+    /// The interval between two live metrics lines.
+    live_metrics_interval_secs: u64,
+
+    #[arg(long, env, conflicts_with = "live_metrics")]
+    /// Show a terminal dashboard of the ingestion (throughput, request
+    /// latencies, errors, in-flight requests and per-URI progress) during the
+    /// run. Logs are written to `qbench.log` in that case.
+    tui: bool,
+
+    #[arg(long, env)]
+    /// Serve Prometheus metrics of the ingestion progress (bytes, documents
+    /// and batches ingested, failed batches, retries, in-flight requests and
+    /// current MB/s) on `http://0.0.0.0:<PORT>/metrics` during the run.
+    metrics_port: Option<u16>,
+
+    #[arg(long, env)]
+    /// Sample the host TCP counters (`/proc/net/snmp`) during the run and
+    /// report retransmissions and connection resets. Linux only.
+    sample_tcp_stats: bool,
+
+    #[arg(long, env)]
+    /// Sample the memory (RSS of the process tree, and cgroup memory) of the
+    /// engine process with this PID during the run, and report its maximum
+    /// and average. The engine must run on the same host. With
+    /// `--engine-container`, the container's process is sampled by default.
+    /// Linux only.
+    engine_pid: Option<u32>,
+
+    #[arg(long, env)]
+    /// Report the distribution of the dataset's document sizes, as well as
+    /// its number of empty lines.
+    doc_size_histogram: bool,
+
+    #[arg(long, env)]
+    /// Report the ingestion throughput and errors over slices of this
+    /// duration, aligned on the wall clock, along with the engine metrics at
+    /// the end of each slice if `--engine-metrics-url` is set.
+    time_slice_interval_secs: Option<u64>,
+
+    #[arg(long, env, requires = "time_slice_interval_secs")]
+    /// The engine's Prometheus metrics endpoint, e.g.
+    /// `http://127.0.0.1:7280/metrics`, scraped at every time slice.
+    engine_metrics_url: Option<String>,
+
+    #[arg(long, env, value_delimiter = ',', requires = "engine_metrics_url")]
+    /// The comma-separated names of the engine metrics to record, summed over
+    /// their labels.
+    engine_metrics: Vec<String>,
+
+    #[arg(long, env)]
+    /// Throttle the ingestion to this many megabytes per second, e.g. to
+    /// measure the search latencies at a typical ingestion rate with
+    /// `--mixed-query-suite`.
+    ingest_rate_mbps: Option<f64>,
+
+    #[arg(long, env, requires = "ingest_rate_mbps")]
+    /// Shape the send rate over time, `--ingest-rate-mbps` being its average
+    /// over a period: "steady", "burst" (5x for the first sixth of each
+    /// period, then 0.2x), "ramp" (a sawtooth from 0.2x to 1.8x) or "diurnal"
+    /// (a sine wave from 0.2x to 1.8x), to observe the merges and compactions
+    /// of the engine under realistic traffic.
+    profile: Option<PacingProfile>,
+
+    #[arg(
+        long,
+        env,
+        requires = "profile",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    /// The period of `--profile`. Defaults to 60s for "steady" and "burst",
+    /// 600s for "ramp" and 3600s (a compressed day) for "diurnal".
+    profile_period_secs: Option<u64>,
+
+    #[arg(long, env)]
+    /// Run the queries of this suite (see `qbench search --query-suite`)
+    /// against the index while it is being fed, and report their latencies
+    /// under ingestion. Combine with `--ingest-rate-mbps` to query at a given
+    /// ingestion rate.
+    mixed_query_suite: Option<PathBuf>,
+
+    #[arg(long, env, default_value_t = 1, requires = "mixed_query_suite")]
+    /// The number of concurrent clients issuing the queries while indexing.
+    mixed_num_clients: usize,
+
+    #[arg(long, env, requires = "mixed_query_suite")]
+    /// Issue the queries at this rate while indexing (open loop). By default,
+    /// each client issues its next query as soon as the previous one returns.
+    mixed_target_qps: Option<f64>,
+
+    #[arg(long, env, alias = "visibility-probe-interval")]
+    /// While indexing, send a sentinel document at this interval and measure
+    /// how long it takes to be searchable. The sentinel is a copy of the first
+    /// document with a unique token in `--visibility-probe-field`. Only
+    /// available for Quickwit, Elasticsearch, OpenSearch and Loki.
+    visibility_probe_interval_secs: Option<u64>,
+
+    #[arg(long, env, default_value = "message")]
+    /// The field of the sentinel documents holding their token.
+    visibility_probe_field: String,
+
+    #[arg(long, env, default_value_t = 120)]
+    /// Stop searching for a sentinel document after this long, counting it as
+    /// timed out.
+    visibility_probe_timeout_secs: u64,
+}
+
+/// The tracing target of the per-batch throughput log lines.
+const THROUGHPUT_LOG_TARGET: &str = "qbench::throughput";
+/// Where the logs go while the terminal dashboard is shown.
+const TUI_LOG_PATH: &str = "qbench.log";
+/// How often the index info is polled with `--target-num-docs`.
+const TARGET_NUM_DOCS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format {s:?}")),
+        }
+    }
+}
+
+/// Returns the OpenTelemetry tracer provider to shut down at the end of the
+/// run, if spans are exported.
+fn init_tracing(args: &CliArgs) -> anyhow::Result<Option<TracerProvider>> {
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::from_level(args.log_level).into())
+        .parse(args.log_filter.as_deref().unwrap_or_default())?;
+    // Keep stdout clean for the live metrics, the dashboard and the generated
+    // index configs.
+    let (live_metrics, tui) = match &args.command {
+        Command::Index(index_args) => {
+            (index_args.live_metrics.is_some(), index_args.tui)
+        },
+        Command::InferMapping(infer_mapping_args) => {
+            (infer_mapping_args.prints_to_stdout(), false)
+        },
+        _ => (false, false),
+    };
+    let writer = if tui {
+        BoxMakeWriter::new(Arc::new(File::create(TUI_LOG_PATH)?))
+    } else if live_metrics {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let tracer_provider = args
+        .otlp_endpoint
+        .as_deref()
+        .map(otel::init_tracer_provider)
+        .transpose()?;
+    let subscriber =
+        tracing_subscriber::registry().with(tracer_provider.as_ref().map(otel::layer));
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(!tui)
+        .with_writer(writer);
+    match args.log_format {
+        LogFormat::Pretty => subscriber.with(fmt_layer.with_filter(env_filter)).init(),
+        LogFormat::Json => subscriber
+            .with(fmt_layer.json().with_filter(env_filter))
+            .init(),
+    }
+    Ok(tracer_provider)
 }
 
 // Expose for python
@@ -77,182 +1407,1258 @@ fn read_rdtsc() -> u64 {
     0
 }
 
-#[derive(Serialize)]
-pub struct ShardInfo {
-    pub uri: String,
-    pub b3_hash: String,
+/// The ingest settings of an Elasticsearch or OpenSearch index changed for a
+/// run.
+struct EsIndexTuning {
+    settings: Map<String, Value>,
+    /// The values of the settings before the run, `null` for the defaults.
+    previous_settings: Map<String, Value>,
 }
 
-// This re-reads all the input files which is a bit wasteful, but computing
-// the hashes online as part of the sources is cumbersome.
-fn compute_shard_infos(uris: Vec<String>) -> Vec<ShardInfo> {
-    let shard_infos_res: Vec<anyhow::Result<ShardInfo>> = uris
-        .par_iter()
-        .map(|uri| -> anyhow::Result<ShardInfo> {
-            if uri.starts_with("http") {
-                Ok(ShardInfo {
-                    uri: uri.clone(),
-                    b3_hash: "".to_string(),
-                })
-            } else {
-                let mut hasher = blake3::Hasher::new();
-                info!("Hashing file {}", uri);
-                Ok(ShardInfo {
-                    uri: uri.clone(),
-                    b3_hash: hasher
-                        .update_reader(File::open(uri)?)?
-                        .finalize()
-                        .to_hex()
-                        .as_str()
-                        .to_string(),
-                })
-            }
+/// The streams of the seeds derived from `--seed`, one per randomized feature
+/// so that they don't draw the same numbers.
+const SAMPLE_SEED_STREAM: u64 = 1;
+const URI_SHUFFLE_SEED_STREAM: u64 = 2;
+const SOURCE_ERRORS_SEED_STREAM: u64 = 3;
+const FAILURE_INJECTION_SEED_STREAM: u64 = 4;
+
+/// The seed of a randomized feature: its own seed if given, else derived from
+/// the seed of the run, else 0.
+fn feature_seed(own_seed: Option<u64>, run_seed: Option<u64>, stream: u64) -> u64 {
+    own_seed.unwrap_or_else(|| {
+        run_seed.map_or(0, |run_seed| {
+            run_seed.wrapping_add(stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        })
+    })
+}
+
+/// Hashes the shards' hashes (or their URIs when they could not be hashed)
+/// into a single fingerprint of the whole dataset.
+fn dataset_fingerprint(shard_infos: &[ShardInfo]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for shard_info in shard_infos {
+        if shard_info.b3_hash.is_empty() {
+            hasher.update(shard_info.uri.as_bytes());
+        } else {
+            hasher.update(shard_info.b3_hash.as_bytes());
+        }
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = CliArgs::parse_from(run_config::expand_config_args(
+        std::env::args_os().collect(),
+    )?);
+    if cli.worker_threads == 0 {
+        bail!("--worker-threads must be at least 1");
+    }
+    // Built after parsing the args, to be sized by `--worker-threads`.
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(cli.worker_threads)
+        .enable_all()
+        .build()
+        .context("Failed to build the async runtime")?
+        .block_on(run_command(cli))
+}
+
+async fn run_command(cli: CliArgs) -> anyhow::Result<()> {
+    let tracer_provider = init_tracing(&cli)?;
+    if let Some(config_path) = &cli.config {
+        info!(config_path=?config_path, "Using run config");
+    }
+    let command_res = match cli.command {
+        Command::Index(index_args) => run_indexing(*index_args).await,
+        Command::Search(query_args) => query::run_queries(query_args).await,
+        Command::SetupIndex(setup_index_args) => {
+            admin::setup_index(setup_index_args).await
+        },
+        Command::Clean(clean_args) => admin::clean(clean_args).await,
+        Command::Compare(compare_args) => compare::compare(compare_args).await,
+        Command::Matrix(matrix_args) => matrix::matrix(matrix_args).await,
+        Command::Report(report_args) => report::report(report_args),
+        Command::Trend(trend_args) => trend::trend(trend_args),
+        Command::ValidateResults(validate_args) => {
+            run_results::validate_results(validate_args)
+        },
+        Command::Engine(engine_args) => engine_docker::engine_command(engine_args).await,
+        Command::InferMapping(infer_mapping_args) => {
+            infer_mapping::infer_mapping(infer_mapping_args).await
+        },
+    };
+    if let Some(tracer_provider) = tracer_provider {
+        otel::shutdown(tracer_provider).await;
+    }
+    command_res
+}
+
+impl IndexArgs {
+    /// The number of runs of the benchmark, one per transfer mode with
+    /// `--compare-transfer-modes`.
+    fn num_runs(&self) -> usize {
+        if self.compare_transfer_modes {
+            2
+        } else {
+            self.runs
+        }
+    }
+
+    fn output_path(&self) -> PathBuf {
+        self.output_path.clone().unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "indexing_results.{}",
+                self.output_format.extension()
+            ))
         })
+    }
+}
+
+async fn run_indexing(args: IndexArgs) -> anyhow::Result<()> {
+    if args.runs == 0 {
+        bail!("--runs must be at least 1");
+    }
+    shutdown::install_signal_handler()?;
+    if args.print_only_rtsc {
+        return run_indexing_once(args).await;
+    }
+    if args.num_runs() == 1 {
+        run_indexing_once(args.clone()).await?;
+        return write_summary(&args);
+    }
+    let runs_args: Vec<IndexArgs> = if args.compare_transfer_modes {
+        [false, true]
+            .into_iter()
+            .map(|chunked_transfer| {
+                let mut run_args = args.clone();
+                run_args.target.chunked_transfer = chunked_transfer;
+                run_args
+            })
+            .collect()
+    } else {
+        vec![args.clone(); args.runs]
+    };
+    // Check the arguments of the chunked run before starting the first one.
+    for run_args in &runs_args {
+        run_args.target.build_sink(None)?;
+    }
+    let index_config = args
+        .index_config
+        .as_ref()
+        .map(|index_config_path| {
+            std::fs::read_to_string(index_config_path).with_context(|| {
+                format!("Failed to read index config {index_config_path:?}")
+            })
+        })
+        .transpose()?;
+    let output_path = args.output_path();
+    if !args.append_output {
+        File::create(&output_path)
+            .with_context(|| format!("Failed to create results file {output_path:?}"))?;
+    }
+    let num_runs = runs_args.len();
+    for (run_idx, mut run_args) in runs_args.into_iter().enumerate() {
+        if shutdown::is_requested() {
+            warn!("Skipping the remaining runs");
+            break;
+        }
+        if run_idx > 0 {
+            info!(index = args.target.index, "Wiping the index");
+            admin::delete_index(&args.target).await?;
+            if let Some(index_config) = &index_config {
+                args.target
+                    .build_sink(None)?
+                    .create_index(index_config)
+                    .await?;
+            }
+        }
+        info!(run = run_idx + 1, runs = num_runs, "Starting run");
+        run_args.output_path = Some(output_path.clone());
+        run_args.append_output = true;
+        run_indexing_once(run_args).await?;
+    }
+    let mut runs = report::read_runs(&output_path, args.output_format)?;
+    let runs = runs.split_off(runs.len().saturating_sub(num_runs));
+    if args.compare_transfer_modes {
+        print_transfer_modes_comparison(runs);
+    } else {
+        print_statistics(&run_metrics(&runs.iter().collect::<Vec<_>>()));
+    }
+    write_summary(&args)
+}
+
+/// Prints the runs of `--compare-transfer-modes` side by side, labelled with
+/// their transfer mode.
+fn print_transfer_modes_comparison(mut runs: Vec<RunResults>) {
+    for run in &mut runs {
+        let transfer_mode = if run.chunked_transfer {
+            "chunked"
+        } else {
+            "content-length"
+        };
+        run.engine = format!("{} ({transfer_mode})", run.engine);
+    }
+    report::print_comparison(&runs);
+}
+
+/// Writes the `--summary-path` table of the runs just written to the results
+/// file, if any.
+fn write_summary(args: &IndexArgs) -> anyhow::Result<()> {
+    let Some(summary_path) = &args.summary_path else {
+        return Ok(());
+    };
+    let summary_format = args
+        .summary_format
+        .unwrap_or_else(|| SummaryFormat::from_path(summary_path));
+    let runs = report::read_runs(&args.output_path(), args.output_format)?;
+    let runs: Vec<&RunResults> = runs[runs.len().saturating_sub(args.num_runs())..]
+        .iter()
         .collect();
+    std::fs::write(summary_path, report::summary(&runs, summary_format))
+        .with_context(|| format!("Failed to write summary {summary_path:?}"))?;
+    info!("Summary written in `{summary_path:?}`");
+    Ok(())
+}
 
-    let mut shard_infos: Vec<ShardInfo> = Vec::new();
-    for shard_res in shard_infos_res {
-        match shard_res {
-            Err(e) => error!(err=?e),
-            Ok(shard_info) => shard_infos.push(shard_info),
+async fn run_indexing_once(args: IndexArgs) -> anyhow::Result<()> {
+    let target = args.target.clone();
+    // The index settings tuned for the run are restored whether it succeeds
+    // or not, unless the run restored them already.
+    let mut es_index_tuning = None;
+    let run_res = index_dataset(args, &mut es_index_tuning).await;
+    if let Some(es_index_tuning) = es_index_tuning {
+        if let Err(error) = target.restore_es_index(&es_index_tuning).await {
+            warn!(error=?error, "Failed to restore the index settings");
         }
     }
-    shard_infos
+    run_res
 }
 
-#[tokio::main(worker_threads = 4)]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
-    let args: CliArgs = CliArgs::parse();
+async fn index_dataset(
+    args: IndexArgs,
+    es_index_tuning: &mut Option<EsIndexTuning>,
+) -> anyhow::Result<()> {
     if args.print_only_rtsc {
         let rtsc = read_rdtsc();
         println!("{}", rtsc);
         return Ok(());
     }
-    let host = args
-        .host
-        .unwrap_or_else(|| args.engine.default_host().to_string());
-    let source: Box<dyn Source> = Box::new(source::UriSource::new(&args.dataset_uri));
-    let sink: Box<dyn sink::Sink> = match args.engine {
-        Engine::Quickwit => {
-            let sink =
-                sink::quickwit::QuickwitSink::new(&host, &args.index, args.qw_ingest_v2);
-            Box::new(sink)
-        },
-        Engine::Elasticsearch | Engine::Opensearch => {
-            let sink = sink::elasticsearch::ElasticsearchSink::new(
-                &host,
-                &args.index,
-                args.merge,
-            );
-            Box::new(sink)
-        },
-        Engine::Loki => {
-            let sink = sink::loki::LokiSink::new(
-                &host,
-                //&args.index,
-            );
-            Box::new(sink)
+    let run_start_time = Utc::now();
+    let host = args.target.host();
+    let mut source = source::UriSource::new(&args.dataset_uri)?;
+    if let Some(access_token) = &args.target.gcp_access_token {
+        source = source.with_gcp_access_token(access_token);
+    }
+    if !(args.sample_ratio > 0.0 && args.sample_ratio <= 1.0) {
+        bail!("--sample-ratio must be in ]0, 1]");
+    }
+    if args.max_docs.is_some() || args.max_bytes.is_some() || args.sample_ratio < 1.0 {
+        source = source.with_sampler(DocSampler::new(
+            args.max_docs,
+            args.max_bytes,
+            args.sample_ratio,
+            feature_seed(args.sample_seed, args.seed, SAMPLE_SEED_STREAM),
+        ));
+    }
+    if args.shuffle_uris {
+        source = source.with_shuffled_uris(feature_seed(
+            None,
+            args.seed,
+            URI_SHUFFLE_SEED_STREAM,
+        ));
+    }
+    if args.interleave_uris {
+        source = source.with_interleaved_uris(args.interleave_max_open_uris);
+    }
+    if args.repeat_dataset == 0 {
+        bail!("--repeat-dataset must be at least 1");
+    }
+    if args.mutate_ids && args.repeat_dataset < 2 {
+        bail!("--mutate-ids requires --repeat-dataset to be at least 2");
+    }
+    source = source.with_repetitions(args.repeat_dataset, args.mutate_ids);
+    let dataset_format = args
+        .dataset_format
+        .unwrap_or_else(|| DatasetFormat::detect(&args.dataset_uri));
+    if dataset_format != DatasetFormat::Ndjson {
+        info!("Converting {dataset_format} dataset to JSON documents");
+        source = source.with_dataset_format(dataset_format, args.csv_infer_types);
+    }
+    source = source.with_uri_error_policy(args.on_uri_error);
+    let source_progress = source.progress();
+    let source: Box<dyn Source> = Box::new(source);
+    if args.concurrency == 0 {
+        bail!("--concurrency must be at least 1");
+    }
+    if args.adaptive_concurrency && args.max_concurrency < args.concurrency {
+        bail!("--max-concurrency must be at least --concurrency");
+    }
+    if args.merge
+        && !matches!(
+            args.target.engine,
+            Engine::Elasticsearch | Engine::Opensearch
+        )
+    {
+        bail!("--merge is only available for Elasticsearch and OpenSearch");
+    }
+    if args.wait_for_merges_secs.is_some() && args.target.engine != Engine::Quickwit {
+        bail!("--wait-for-merges-secs is only available for Quickwit");
+    }
+    if args
+        .ingest_rate_mbps
+        .is_some_and(|ingest_rate_mbps| ingest_rate_mbps <= 0.0)
+    {
+        bail!("--ingest-rate-mbps must be positive");
+    }
+    let sink = args.target.build_sink(args.alias.as_deref())?;
+    args.target.wait_for_engine(sink.as_ref()).await?;
+    let negotiated_http_version = args.target.negotiated_http_version().await;
+    args.target.install_es_pipeline().await?;
+    *es_index_tuning = args.target.tune_es_index().await?;
+    let es_index_settings = es_index_tuning
+        .as_ref()
+        .map(|es_index_tuning| es_index_tuning.settings.clone());
+    let sink: Box<dyn sink::Sink> = match args.forward_to {
+        Some(agent) => {
+            let forwarder_host = args
+                .forwarder_host
+                .clone()
+                .unwrap_or_else(|| agent.default_host().to_string());
+            info!("Forwarding documents through {agent} at {forwarder_host}");
+            Box::new(ForwardingSink::new(
+                agent,
+                &forwarder_host,
+                sink,
+                Duration::from_secs(args.forwarder_drain_timeout_secs),
+                args.target.http_client.build_client()?,
+            ))
         },
-        _ => {
-            bail!("Engine not supported");
+        None => sink,
+    };
+    if args
+        .simulated_bandwidth_mbps
+        .is_some_and(|bandwidth_mbps| bandwidth_mbps <= 0.0)
+    {
+        bail!("--simulated-bandwidth-mbps must be positive");
+    }
+    let sink: Box<dyn sink::Sink> = if args.simulated_latency_ms.is_some()
+        || args.simulated_bandwidth_mbps.is_some()
+    {
+        info!(
+            latency_ms = args.simulated_latency_ms,
+            bandwidth_mbps = args.simulated_bandwidth_mbps,
+            "Simulating the network to the engine"
+        );
+        Box::new(SimulatedNetworkSink::new(
+            sink,
+            Duration::from_millis(args.simulated_latency_ms.unwrap_or(0)),
+            args.simulated_bandwidth_mbps,
+        ))
+    } else {
+        sink
+    };
+    let (sink, failure_injection_stats): (Box<dyn sink::Sink>, _) = match &args
+        .inject_failures
+    {
+        Some(failure_injection) => {
+            let mut failure_injection = failure_injection.clone();
+            failure_injection.seed = Some(feature_seed(
+                failure_injection.seed,
+                args.seed,
+                FAILURE_INJECTION_SEED_STREAM,
+            ));
+            info!(failure_injection=?failure_injection, "Injecting failures in the ingest requests");
+            let sink = FailureInjectingSink::new(sink, failure_injection.clone());
+            let stats = sink.stats();
+            (Box::new(sink), Some((failure_injection, stats)))
         },
+        None => (sink, None),
     };
-    let output_path = args
-        .output_path
-        .unwrap_or_else(|| PathBuf::from("indexing_results.json"));
+    let output_path = args.output_path();
     info!(
         "Start indexing, results will be written in `{:?}`",
         output_path
     );
-    // Write an empty file to avoid error at the end of indexing.
-    std::fs::write(output_path.clone(), "{}")?;
+    let run_results_file =
+        RunResultsFile::open(&output_path, args.output_format, args.append_output)?;
+    // Write empty results to avoid error at the end of indexing.
+    if !args.append_output {
+        run_results_file.write(&json!({}))?;
+    }
     let build_info = sink.build_info().await?;
+    if let Some(alias) = &args.alias {
+        info!(
+            "Switching alias `{}` to index `{}`",
+            alias, args.target.index
+        );
+        sink.switch_alias(alias).await?;
+    }
+    let engine_stats_before = engine_stats(sink.as_ref(), args.target.engine).await;
     let mut num_ingested_bytes = 0u64;
     let mut num_ingestion_error_bytes = 0u64;
+    let budget = Budget {
+        cost_per_gb_usd: args.cost_per_gb_usd,
+        max_cost_usd: args.max_cost_usd,
+        max_billed_bytes: args.max_billed_bytes,
+    };
+    let mut num_billed_bytes = 0u64;
+    let mut budget_exceeded = false;
+    let mut unsent_docs = Bytes::new();
+    let mut aborted = false;
+    let mut deadline_reached = false;
+    let mut target_num_docs_reached = false;
 
+    let engine_container = args
+        .engine_container
+        .as_deref()
+        .map(EngineContainer::read)
+        .transpose()?;
+    let engine_pid = match (args.engine_pid, &engine_container) {
+        (Some(engine_pid), _) => Some(engine_pid),
+        (None, Some(engine_container)) => {
+            Some(engine_docker::container_pid(engine_container).await?)
+        },
+        (None, None) => None,
+    };
+    let engine_memory_sampler =
+        engine_pid.map(EngineMemorySampler::start).transpose()?;
+    let tcp_stats_sampler = if args.sample_tcp_stats {
+        Some(TcpStatsSampler::start()?)
+    } else {
+        None
+    };
+    let mut transform_specs: Vec<_> = args
+        .transform
+        .iter()
+        .map(|builtin_transform| builtin_transform.spec(&args.gharchive_event_types))
+        .collect();
+    if let Some(transforms_path) = &args.transforms {
+        transform_specs.extend(TransformPipeline::load_specs(transforms_path)?);
+    }
+    let transform_pipeline =
+        (!transform_specs.is_empty()).then(|| TransformPipeline::new(transform_specs));
+    let mut schema_drift = args.schema_drift_after_bytes.map(|after_bytes| {
+        SchemaDrift::new(after_bytes, args.schema_drift_ratio, args.schema_drift_kind)
+    });
+    // Elapsed time, ingested bytes and error bytes when the drift started.
+    let mut drift_point = None;
+    let counters = Arc::new(IngestCounters::default());
+    let live_metrics_printer = args.live_metrics.map(|format| {
+        metrics::spawn_live_metrics_printer(
+            counters.clone(),
+            format,
+            Duration::from_secs(args.live_metrics_interval_secs),
+        )
+    });
+    let metrics_server = match args.metrics_port {
+        Some(port) => Some(
+            metrics::spawn_metrics_server(
+                counters.clone(),
+                port,
+                args.target.engine.to_string(),
+                args.target.index.clone(),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+    let time_slice_recorder = match args.time_slice_interval_secs {
+        Some(0) => bail!("--time-slice-interval-secs must be at least 1"),
+        Some(interval_secs) => {
+            let engine_metrics_scraper = match &args.engine_metrics_url {
+                Some(_) if args.engine_metrics.is_empty() => {
+                    bail!("--engine-metrics is required with --engine-metrics-url");
+                },
+                Some(url) => {
+                    Some(EngineMetricsScraper::new(url, args.engine_metrics.clone()))
+                },
+                None => None,
+            };
+            Some(
+                TimeSliceRecorder::start(
+                    Duration::from_secs(interval_secs),
+                    counters.clone(),
+                    engine_metrics_scraper,
+                )
+                .await,
+            )
+        },
+        None => None,
+    };
+    let mixed_workload = args
+        .mixed_query_suite
+        .as_deref()
+        .map(|query_suite_path| {
+            MixedWorkload::start(
+                query_suite_path,
+                args.mixed_num_clients,
+                args.mixed_target_qps,
+                args.target.engine,
+                &host,
+                &args.target.index,
+                args.target.http_client.build_client()?,
+            )
+        })
+        .transpose()?;
+    let visibility_probe = args
+        .visibility_probe_interval_secs
+        .map(|interval_secs| {
+            VisibilityProbe::start(
+                Duration::from_secs(interval_secs),
+                Duration::from_secs(args.visibility_probe_timeout_secs),
+                &args.visibility_probe_field,
+                args.target.engine,
+                &host,
+                &args.target.index,
+                args.target.http_client.build_client()?,
+            )
+        })
+        .transpose()?;
+    // Durations are only ever measured with the monotonic clock: the wall
+    // clock can jump (NTP adjustments) during long runs. UTC timestamps are
+    // recorded for correlation with external data only.
     let start = Instant::now();
+    let start_time = Utc::now();
+    let driver_usage_start = DriverUsage::read();
+    let deadline = args
+        .max_duration_secs
+        .map(|max_duration_secs| start + Duration::from_secs(max_duration_secs));
 
-    let mut futures = FuturesUnordered::new();
+    let mut reorder_buffer = match args.reorder_window {
+        Some(0) => bail!("--reorder-window must be positive"),
+        Some(window_num_docs) => Some(ReorderBuffer::new(
+            args.reorder_timestamp_field.clone(),
+            window_num_docs,
+        )),
+        None => None,
+    };
+    let mut source_error_injector = match args.inject_source_errors {
+        Some(rate) if !(0.0..=1.0).contains(&rate) => {
+            bail!("--inject-source-errors must be between 0 and 1");
+        },
+        Some(rate) => Some(SourceErrorInjector::new(
+            rate,
+            feature_seed(
+                args.inject_source_errors_seed,
+                args.seed,
+                SOURCE_ERRORS_SEED_STREAM,
+            ),
+        )),
+        None => None,
+    };
+    let mut doc_size_histogram = args.doc_size_histogram.then(DocSizeHistogram::default);
+    let mut batch_size_histogram = BatchSizeHistogram::default();
+    let failed_batch_dumper = args
+        .dump_failed_batches
+        .as_deref()
+        .map(|dir| FailedBatchDumper::new(dir, args.dump_failed_batches_max))
+        .transpose()?;
+    let adaptive_concurrency = args
+        .adaptive_concurrency
+        .then(|| AdaptiveConcurrency::new(args.concurrency, args.max_concurrency));
+    let mut pacer = args.ingest_rate_mbps.map(|ingest_rate_mbps| {
+        Pacer::new(
+            ingest_rate_mbps,
+            args.profile.unwrap_or(PacingProfile::Steady),
+            args.profile_period_secs.map(Duration::from_secs),
+        )
+    });
+    // Started last, so that a failed setup logs its error to a terminal
+    // left as is.
+    let dashboard = if args.tui {
+        Some(tui::Dashboard::start(
+            format!("{} {}", args.target.engine, args.target.index),
+            counters.clone(),
+            source_progress.clone(),
+        )?)
+    } else {
+        None
+    };
+    let mut in_flight_requests = InFlightRequests::default();
+    let mut first_batch_instant = None;
+    let results_flush_interval = Duration::from_secs(args.results_flush_interval_secs);
+    let mut last_results_flush = Instant::now();
+    let mut last_num_docs_poll = Instant::now();
 
     for batch_res in source.batch_stream(sink.batch_size()).await? {
-        let doc_batch = batch_res.map_err(|err| {
+        if shutdown::is_requested() {
+            aborted = true;
+            break;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let num_done_uris = source_progress
+                .uris
+                .iter()
+                .filter(|uri| uri.state() == UriState::Done)
+                .count();
+            info!(
+                num_billed_bytes,
+                "Deadline reached, stopping ingestion after reading {num_done_uris} \
+                 out of {} dataset URIs",
+                source_progress.uris.len()
+            );
+            deadline_reached = true;
+            break;
+        }
+        if let Some(target_num_docs) = args.target_num_docs {
+            if last_num_docs_poll.elapsed() >= TARGET_NUM_DOCS_POLL_INTERVAL {
+                last_num_docs_poll = Instant::now();
+                match sink.index_info().await {
+                    Ok(index_info) if index_info.num_docs >= target_num_docs => {
+                        info!(
+                            num_docs = index_info.num_docs,
+                            "Target number of documents reached, stopping ingestion"
+                        );
+                        target_num_docs_reached = true;
+                        break;
+                    },
+                    Ok(_) => {},
+                    Err(error) => {
+                        warn!(error=?error, "Failed to poll the number of indexed documents");
+                    },
+                }
+            }
+        }
+        first_batch_instant.get_or_insert_with(Instant::now);
+        let mut doc_batch = batch_res.map_err(|err| {
             error!(err=?err);
             err
         })?;
-        futures.push(send_with_retry(
-            &sink,
+        if let Some(transform_pipeline) = &transform_pipeline {
+            transform_pipeline.apply(&mut doc_batch)?;
+            if doc_batch.bytes.is_empty() && !doc_batch.last {
+                continue;
+            }
+        }
+        if let Some(reorder_buffer) = &mut reorder_buffer {
+            reorder_buffer.apply(&mut doc_batch);
+            if doc_batch.bytes.is_empty() && !doc_batch.last {
+                continue;
+            }
+        }
+        if budget.is_exceeded(num_billed_bytes + doc_batch.bytes.len() as u64) {
+            warn!(
+                num_billed_bytes,
+                estimated_cost_usd = ?budget.estimated_cost_usd(num_billed_bytes),
+                "Budget exceeded, stopping ingestion"
+            );
+            budget_exceeded = true;
+            // The documents of the batch may have been taken out of the reorder
+            // buffer, which sends them with the buffered ones as far as the
+            // budget allows.
+            unsent_docs = doc_batch.bytes;
+            break;
+        }
+        if let Some(doc_size_histogram) = &mut doc_size_histogram {
+            doc_size_histogram.record_batch(&doc_batch.bytes);
+        }
+        if let Some(schema_drift) = &mut schema_drift {
+            if schema_drift.is_started() && drift_point.is_none() {
+                info!("Starting schema drift");
+                drift_point = Some((
+                    start.elapsed(),
+                    num_ingested_bytes,
+                    num_ingestion_error_bytes,
+                ));
+            }
+            schema_drift.apply(&mut doc_batch)?;
+        }
+        if let Some(source_error_injector) = &mut source_error_injector {
+            source_error_injector.apply(&mut doc_batch);
+        }
+        if let Some(pacer) = &mut pacer {
+            let due = start + pacer.schedule(doc_batch.bytes.len());
+            let pacing_sleep = tokio::time::sleep_until(due.into());
+            tokio::pin!(pacing_sleep);
+            // The requests completing meanwhile are recorded on time.
+            loop {
+                tokio::select! {
+                    _ = &mut pacing_sleep => break,
+                    Some(result) = in_flight_requests.next() => {
+                        handle_result(
+                            result,
+                            &mut num_ingested_bytes,
+                            &mut num_ingestion_error_bytes,
+                            &counters,
+                            start,
+                        );
+                        counters
+                            .num_inflight_requests
+                            .store(in_flight_requests.len() as u64, Ordering::Relaxed);
+                    },
+                }
+            }
+        }
+        num_billed_bytes += doc_batch.bytes.len() as u64;
+        if let Some(visibility_probe) = &visibility_probe {
+            visibility_probe.observe_batch(&doc_batch.bytes);
+        }
+        batch_size_histogram.record_batch(&doc_batch.bytes);
+        in_flight_requests.push(send_with_retry(
+            sink.as_ref(),
             doc_batch,
             args.retry_indexing_errors,
+            &counters,
+            failed_batch_dumper.as_ref(),
+            adaptive_concurrency.as_ref(),
         ));
+        counters
+            .num_inflight_requests
+            .store(in_flight_requests.len() as u64, Ordering::Relaxed);
 
-        // Allow 2 futures to run in parallel
-        if futures.len() >= 2 {
-            if let Some(result) = futures.next().await {
-                handle_result(
-                    result,
-                    &mut num_ingested_bytes,
-                    &mut num_ingestion_error_bytes,
-                    start,
-                )
+        let concurrency = match &adaptive_concurrency {
+            Some(adaptive_concurrency) => {
+                adaptive_concurrency
+                    .record_throttling(sink.throttling().num_throttled_requests);
+                adaptive_concurrency.concurrency()
+            },
+            None => args.concurrency,
+        };
+        // More than one request completes when the adaptive concurrency
+        // decreased.
+        while let Some(result) = in_flight_requests.make_room(concurrency).await {
+            handle_result(
+                result,
+                &mut num_ingested_bytes,
+                &mut num_ingestion_error_bytes,
+                &counters,
+                start,
+            );
+            counters
+                .num_inflight_requests
+                .store(in_flight_requests.len() as u64, Ordering::Relaxed);
+        }
+        if !results_flush_interval.is_zero()
+            && last_results_flush.elapsed() >= results_flush_interval
+        {
+            let partial_results = partial_results(
+                &args,
+                start_time,
+                start.elapsed(),
+                &counters,
+                num_ingestion_error_bytes,
+                num_billed_bytes,
+                time_slice_recorder
+                    .as_ref()
+                    .map(TimeSliceRecorder::report_so_far),
+            );
+            // A failed flush shouldn't end a run that may still succeed.
+            if let Err(error) = run_results_file.write(&partial_results) {
+                warn!(error=?error, "Failed to flush the partial results");
             }
+            last_results_flush = Instant::now();
+        }
+    }
+
+    // The documents still buffered when the ingestion stopped early were read
+    // and are sent, as far as the budget allows.
+    let flushed_batch = reorder_buffer.as_mut().and_then(|reorder_buffer| {
+        reorder_buffer.flush(&unsent_docs, |num_bytes| {
+            !budget.is_exceeded(num_billed_bytes + num_bytes)
+        })
+    });
+    if let Some(flushed_batch) = flushed_batch {
+        num_billed_bytes += flushed_batch.bytes.len() as u64;
+        if let Some(visibility_probe) = &visibility_probe {
+            visibility_probe.observe_batch(&flushed_batch.bytes);
         }
+        batch_size_histogram.record_batch(&flushed_batch.bytes);
+        in_flight_requests.push(send_with_retry(
+            sink.as_ref(),
+            flushed_batch,
+            args.retry_indexing_errors,
+            &counters,
+            failed_batch_dumper.as_ref(),
+            adaptive_concurrency.as_ref(),
+        ));
     }
 
     // Don't forget to handle the last results.
-    while let Some(result) = futures.next().await {
+    while let Some(result) = in_flight_requests.next().await {
         handle_result(
             result,
             &mut num_ingested_bytes,
             &mut num_ingestion_error_bytes,
+            &counters,
             start,
-        )
+        );
+        counters
+            .num_inflight_requests
+            .store(in_flight_requests.len() as u64, Ordering::Relaxed);
+    }
+    let ingest_end = Instant::now();
+    let mixed_workload_report = match mixed_workload {
+        Some(mixed_workload) => Some(mixed_workload.finish().await?),
+        None => None,
+    };
+    let visibility_report = match visibility_probe {
+        Some(visibility_probe) => Some(visibility_probe.finish().await?),
+        None => None,
+    };
+    if let Some(live_metrics_printer) = live_metrics_printer {
+        live_metrics_printer.abort();
+    }
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+        // Release the port before a next run binds it.
+        let _ = metrics_server.await;
     }
+    if let Some(dashboard) = dashboard {
+        dashboard.stop().await?;
+    }
+    let time_slices = match time_slice_recorder {
+        Some(time_slice_recorder) => Some(time_slice_recorder.finish().await),
+        None => None,
+    };
 
+    if aborted {
+        warn!("Run aborted, committing and writing the results measured so far");
+    }
+    let commit_start = Instant::now();
     sink.commit().await?;
+    let commit_duration = commit_start.elapsed();
+    if let Some(es_index_tuning) = es_index_tuning.take() {
+        if let Err(error) = args.target.restore_es_index(&es_index_tuning).await {
+            warn!(error=?error, "Failed to restore the index settings");
+        }
+    }
+    let (force_merge_duration, num_segments_before_merge) = if args.merge && !aborted {
+        let num_segments_before_merge = sink.index_info().await?.num_splits;
+        info!(num_segments_before_merge, "Force merging the index...");
+        let force_merge_start = Instant::now();
+        sink.force_merge().await?;
+        (
+            Some(force_merge_start.elapsed()),
+            Some(num_segments_before_merge),
+        )
+    } else {
+        (None, None)
+    };
     let index_info = sink.index_info().await?;
+    if let Some(split_breakdown) = &index_info.split_breakdown {
+        info!(
+            num_immature_splits = split_breakdown.num_immature_splits,
+            num_mature_splits = split_breakdown.num_mature_splits,
+            max_num_merge_ops = split_breakdown
+                .merge_levels
+                .last()
+                .map(|merge_level| merge_level.num_merge_ops),
+            "Published splits"
+        );
+    }
+    if let Some(target_num_docs) = args.target_num_docs {
+        if index_info.num_docs < target_num_docs {
+            warn!(
+                num_docs = index_info.num_docs,
+                target_num_docs,
+                "The dataset ended before the target number of documents"
+            );
+        }
+    }
+    let indexing_duration = start.elapsed();
+    let end_time = Utc::now();
+    let tcp_stats = tcp_stats_sampler
+        .map(|sampler| sampler.finish())
+        .transpose()?;
+    let driver_usage =
+        DriverUsage::read().report_since(&driver_usage_start, indexing_duration);
+    let adaptive_concurrency_report = adaptive_concurrency
+        .as_ref()
+        .map(AdaptiveConcurrency::report);
+    if let Some(report) = &adaptive_concurrency_report {
+        info!(
+            peak_concurrency = report.peak_concurrency,
+            final_concurrency = report.final_concurrency,
+            num_decreases = report.num_decreases,
+            "Adaptive concurrency"
+        );
+    }
+    let peak_concurrency = adaptive_concurrency_report
+        .as_ref()
+        .map_or(args.concurrency, |report| report.peak_concurrency);
+    if driver_usage.is_cpu_bound(peak_concurrency) {
+        warn!(
+            cpu_utilization = driver_usage.cpu_utilization,
+            "qbench used most of the CPU it could use, it may have been the bottleneck \
+             (see --worker-threads)"
+        );
+    }
+    let engine_memory = engine_memory_sampler.map(EngineMemorySampler::finish);
+    if let Some(engine_memory) = &engine_memory {
+        info!(
+            max_rss_mb = engine_memory.max_rss_bytes / 1_000_000,
+            avg_rss_mb = engine_memory.avg_rss_bytes / 1_000_000,
+            "Engine memory"
+        );
+    }
 
-    let elapsed_time: f64 = start.elapsed().as_secs_f64();
+    let num_timed_out_requests = counters.num_timed_out_requests.load(Ordering::Relaxed);
+    let elapsed_time: f64 = indexing_duration.as_secs_f64();
     let doc_per_second = index_info.num_docs as f64 / elapsed_time;
     let megabytes_per_second = num_ingested_bytes as f64 / 1_000_000.0 / elapsed_time;
     info!("Indexing ended in {:.2} min. Final indexing throughput: {:.2} MB/s, {:.2} docs/s.\n\
-          {:.2} MBs successfully ingested, {:.2} MBs with ingestion errors.",
+          {:.2} MBs successfully ingested, {:.2} MBs with ingestion errors, {} timed out requests, {} rejected docs.",
         elapsed_time / 60.0, megabytes_per_second, doc_per_second,
-        num_ingested_bytes as f64 / 1_000_000., num_ingestion_error_bytes as f64 / 1_000_000.);
+        num_ingested_bytes as f64 / 1_000_000., num_ingestion_error_bytes as f64 / 1_000_000.,
+        num_timed_out_requests, sink.num_rejected_docs());
+    if let Some(failed_batch_dumper) = &failed_batch_dumper {
+        if failed_batch_dumper.num_dumped() > 0 {
+            warn!(
+                num_failed_requests = failed_batch_dumper.num_dumped(),
+                dir = ?failed_batch_dumper.dir(),
+                "Dumped the last {} failed batches",
+                failed_batch_dumper.num_dumped().min(args.dump_failed_batches_max)
+            );
+        }
+    }
+    let batch_sizes = batch_size_histogram.report(sink.batch_size());
+    if batch_sizes.is_mostly_small() {
+        warn!(
+            num_small_batches = batch_sizes.num_small_batches,
+            num_batches = batch_sizes.num_batches,
+            p50_num_bytes = batch_sizes.num_bytes.p50,
+            "Most batches were smaller than a tenth of the sink's batch size of {} bytes",
+            batch_sizes.sink_batch_num_bytes
+        );
+    }
+    let throttling = sink.throttling();
+    if throttling.num_throttled_requests > 0 {
+        warn!(
+            num_throttled_requests = throttling.num_throttled_requests,
+            throttled_secs = throttling.throttled_duration.as_secs_f64(),
+            "The engine throttled the ingestion"
+        );
+    }
 
-    let results = json!({
-        "engine": args.engine.as_ref(),
-        "index": args.index,
-        "num_ingested_bytes": num_ingested_bytes,
-        "num_indexed_docs": index_info.num_docs,
-        "num_indexed_bytes": index_info.num_bytes,
-        "num_splits": index_info.num_splits,
-        "indexing_duration_secs": elapsed_time,
-        "doc_per_second": doc_per_second,
-        "megabytes_per_second": megabytes_per_second,
-        "build_info": build_info,
-        "input_shard_info": compute_shard_infos(source.uris()),
+    // Waited for after the indexing duration is measured, the quiet period
+    // not being part of the ingestion.
+    let merges_settled = match args.wait_for_merges_secs {
+        Some(wait_for_merges_secs) if !aborted => {
+            info!(
+                num_splits = index_info.num_splits,
+                "Waiting for the merges to settle..."
+            );
+            let merges_settled = sink::wait_until_merges_settle(
+                sink.as_ref(),
+                Duration::from_secs(wait_for_merges_secs),
+                Duration::from_secs(args.merges_timeout_secs),
+            )
+            .await?;
+            info!(
+                num_splits = merges_settled.num_splits,
+                "Merges settled after {:.2}s",
+                merges_settled.time_to_merged.as_secs_f64()
+            );
+            Some(merges_settled)
+        },
+        _ => None,
+    };
+
+    // Before the retention, whose deletes and merges are not part of the
+    // ingestion.
+    let engine_stats_delta = match engine_stats_before {
+        Some(engine_stats_before) => engine_stats(sink.as_ref(), args.target.engine)
+            .await
+            .map(|engine_stats_after| {
+                stats_delta(&engine_stats_before, &engine_stats_after)
+            }),
+        None => None,
+    };
+    let engine_cpu_seconds = engine_stats_delta.as_ref().and_then(process_cpu_seconds);
+    // The CPU time of the whole engine, whatever else it was doing.
+    let cpu_seconds_per = |quantity: f64| {
+        engine_cpu_seconds
+            .filter(|_| quantity > 0.0)
+            .map(|cpu_seconds| cpu_seconds / quantity)
+    };
+    let cpu_seconds_per_gb_ingested =
+        cpu_seconds_per(num_ingested_bytes as f64 / 1_000_000_000.0);
+    let cpu_seconds_per_million_docs =
+        cpu_seconds_per(index_info.num_docs as f64 / 1_000_000.0);
+    if let Some(cpu_seconds_per_gb_ingested) = cpu_seconds_per_gb_ingested {
+        info!(
+            engine_cpu_seconds,
+            "Engine CPU efficiency: {cpu_seconds_per_gb_ingested:.2} CPU-s/GB"
+        );
+    }
+
+    let retention_timings = if args.measure_retention && !aborted {
+        info!("Measuring retention...");
+        let timeout = Duration::from_secs(args.retention_timeout_secs);
+        Some(sink.apply_retention(timeout).await?)
+    } else {
+        None
+    };
+
+    let schema_drift_report = schema_drift.map(|schema_drift| {
+        let (drift_elapsed, drift_ingested_bytes, drift_error_bytes) = drift_point
+            .unwrap_or((
+                indexing_duration,
+                num_ingested_bytes,
+                num_ingestion_error_bytes,
+            ));
+        schema_drift.report(
+            PhaseStats::new(drift_elapsed, drift_ingested_bytes, drift_error_bytes),
+            PhaseStats::new(
+                indexing_duration - drift_elapsed,
+                num_ingested_bytes - drift_ingested_bytes,
+                num_ingestion_error_bytes - drift_error_bytes,
+            ),
+        )
     });
-    std::fs::write(output_path, serde_json::to_string_pretty(&results)?)?;
+
+    let input_shard_info = source.shard_infos();
+    let results = RunResults {
+        schema_version: SCHEMA_VERSION,
+        engine: args.target.engine.to_string(),
+        host,
+        index: args.target.index.clone(),
+        es_shards: args.target.es_shards,
+        es_replicas: args.target.es_replicas,
+        es_pipeline: args.target.es_pipeline.clone(),
+        es_index_settings,
+        qw_vrl_transform: args.target.qw_vrl_transform_script()?,
+        qw_commit: args.target.qw_commit,
+        chunked_transfer: args.target.chunked_transfer,
+        http_version: Some(
+            args.target
+                .http_client
+                .http_version(args.target.default_http_version())
+                .to_string(),
+        ),
+        negotiated_http_version,
+        dataset_uri: args.dataset_uri.clone(),
+        dataset_fingerprint: dataset_fingerprint(&input_shard_info),
+        dataset_fingerprint_version: DATASET_FINGERPRINT_VERSION,
+        dataset_format: dataset_format.to_string(),
+        csv_infer_types: args.csv_infer_types,
+        on_uri_error: args.on_uri_error.to_string(),
+        uri_summary: source_progress.summary(),
+        repeat_dataset: args.repeat_dataset,
+        mutate_ids: args.mutate_ids,
+        concurrency: args.concurrency,
+        adaptive_concurrency: adaptive_concurrency_report,
+        worker_threads: Some(tokio::runtime::Handle::current().metrics().num_workers()),
+        tags: args.labels.tags(),
+        key_value_tags: args.labels.key_value_tags(),
+        comment: args.labels.comment.clone(),
+        time_range: TimeRange {
+            start: to_utc_timestamp(start_time),
+            end: to_utc_timestamp(end_time),
+        },
+        run_time_range: TimeRange {
+            start: to_utc_timestamp(run_start_time),
+            end: to_utc_timestamp(Utc::now()),
+        },
+        alias: args.alias.clone(),
+        engine_container,
+        forwarded_to: args.forward_to.map(|agent| agent.to_string()),
+        num_ingested_bytes,
+        num_timed_out_requests,
+        num_rejected_docs: sink.num_rejected_docs(),
+        num_throttled_requests: throttling.num_throttled_requests,
+        throttled_secs: throttling.throttled_duration.as_secs_f64(),
+        num_indexed_docs: index_info.num_docs,
+        num_indexed_bytes: index_info.num_bytes,
+        num_splits: merges_settled
+            .as_ref()
+            .map_or(index_info.num_splits, |merges_settled| {
+                merges_settled.num_splits
+            }),
+        indexing_duration_secs: elapsed_time,
+        time_to_first_batch_secs: (first_batch_instant.unwrap_or(ingest_end) - start)
+            .as_secs_f64(),
+        ingest_duration_secs: (ingest_end - first_batch_instant.unwrap_or(ingest_end))
+            .as_secs_f64(),
+        commit_duration_secs: commit_duration.as_secs_f64(),
+        merge_duration_secs: force_merge_duration.map(|duration| duration.as_secs_f64()),
+        num_segments_before_merge,
+        num_segments_after_merge: num_segments_before_merge
+            .map(|_| index_info.num_splits),
+        wait_for_merges_secs: args.wait_for_merges_secs,
+        merges_settled_secs: merges_settled
+            .as_ref()
+            .map(|merges_settled| merges_settled.time_to_merged.as_secs_f64()),
+        num_splits_before_merges: merges_settled.as_ref().map(|_| index_info.num_splits),
+        split_breakdown: index_info.split_breakdown,
+        index_stats: (!index_info.engine_specific.is_empty())
+            .then_some(index_info.engine_specific),
+        doc_per_second,
+        megabytes_per_second,
+        build_info,
+        num_billed_bytes,
+        estimated_cost_usd: budget.estimated_cost_usd(num_billed_bytes),
+        budget_exceeded,
+        aborted,
+        max_duration_secs: args.max_duration_secs,
+        deadline_reached,
+        target_num_docs: args.target_num_docs,
+        target_num_docs_reached,
+        transforms: transform_pipeline
+            .as_ref()
+            .map(|transform_pipeline| transform_pipeline.specs().to_vec()),
+        tcp_stats,
+        engine_memory,
+        driver_usage: Some(driver_usage),
+        schema_drift: schema_drift_report,
+        retention: retention_timings,
+        source_errors: source_error_injector.map(|injector| injector.report()),
+        reorder: reorder_buffer.as_ref().map(ReorderBuffer::report),
+        doc_size_histogram: doc_size_histogram.map(|histogram| histogram.report()),
+        batch_sizes: Some(batch_sizes),
+        time_slices,
+        ingest_rate_mbps: args.ingest_rate_mbps,
+        pacing: pacer
+            .as_ref()
+            .filter(|_| args.profile.is_some())
+            .map(Pacer::report),
+        simulated_latency_ms: args.simulated_latency_ms,
+        simulated_bandwidth_mbps: args.simulated_bandwidth_mbps,
+        seed: args.seed,
+        shuffle_uris: args.shuffle_uris,
+        interleave_uris: args.interleave_uris,
+        failure_injection: failure_injection_stats
+            .as_ref()
+            .map(|(failure_injection, stats)| failure_injection.report(stats)),
+        mixed_workload: mixed_workload_report,
+        visibility: visibility_report,
+        engine_stats_delta,
+        engine_cpu_seconds,
+        cpu_seconds_per_gb_ingested,
+        cpu_seconds_per_million_docs,
+        input_shard_info,
+    };
+    run_results_file.write(&serde_json::to_value(&results)?)?;
 
     Ok(())
 }
 
+/// The metrics of a run in progress, measured on the client side only.
+fn partial_results(
+    args: &IndexArgs,
+    start_time: DateTime<Utc>,
+    elapsed: Duration,
+    counters: &IngestCounters,
+    num_ingestion_error_bytes: u64,
+    num_billed_bytes: u64,
+    time_slices: Option<TimeSlicesReport>,
+) -> serde_json::Value {
+    let num_ingested_bytes = counters.num_ingested_bytes.load(Ordering::Relaxed);
+    json!({
+        "partial": true,
+        "engine": args.target.engine.to_string(),
+        "host": args.target.host(),
+        "index": args.target.index,
+        "dataset_uri": args.dataset_uri,
+        "tags": args.labels.tags(),
+        "key_value_tags": args.labels.key_value_tags(),
+        "comment": args.labels.comment,
+        "time_range": TimeRange {
+            start: to_utc_timestamp(start_time),
+            end: to_utc_timestamp(Utc::now()),
+        },
+        "elapsed_secs": elapsed.as_secs_f64(),
+        "num_ingested_bytes": num_ingested_bytes,
+        "num_ingested_docs": counters.num_ingested_docs.load(Ordering::Relaxed),
+        "num_ingestion_error_bytes": num_ingestion_error_bytes,
+        "num_failed_batches": counters.num_failed_batches.load(Ordering::Relaxed),
+        "num_retries": counters.num_retries.load(Ordering::Relaxed),
+        "num_timed_out_requests": counters.num_timed_out_requests.load(Ordering::Relaxed),
+        "num_billed_bytes": num_billed_bytes,
+        "megabytes_per_second": num_ingested_bytes as f64 / 1_000_000.0 / elapsed.as_secs_f64(),
+        "time_slices": time_slices,
+    })
+}
+
+fn to_utc_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// The size of a batch handed to `send_with_retry`.
+struct BatchSize {
+    num_bytes: u64,
+    num_docs: u64,
+}
+
+/// A snapshot of the engine stats, None if the engine doesn't have any or if
+/// they could not be fetched.
+async fn engine_stats(sink: &dyn sink::Sink, engine: Engine) -> Option<EngineStats> {
+    if !matches!(
+        engine,
+        Engine::Quickwit | Engine::Elasticsearch | Engine::Opensearch | Engine::Loki
+    ) {
+        return None;
+    }
+    match sink.engine_stats().await {
+        Ok(engine_stats) => Some(engine_stats),
+        Err(error) => {
+            warn!(error=?error, "Failed to fetch the engine stats");
+            None
+        },
+    }
+}
+
 async fn send_with_retry(
-    sink: &Box<dyn sink::Sink>,
+    sink: &dyn sink::Sink,
     doc_batch: DocumentBatch,
     retry: bool,
-) -> Result<u64, u64> {
-    let batch_num_bytes = doc_batch.bytes.len() as u64;
+    counters: &IngestCounters,
+    failed_batch_dumper: Option<&FailedBatchDumper>,
+    adaptive_concurrency: Option<&AdaptiveConcurrency>,
+) -> Result<BatchSize, BatchSize> {
+    let batch_size = BatchSize {
+        num_bytes: doc_batch.bytes.len() as u64,
+        num_docs: doc_batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .count() as u64,
+    };
     loop {
-        match sink.send(&doc_batch).await {
-            Ok(()) => return Ok(batch_num_bytes),
+        let request_start = Instant::now();
+        let send_res = sink
+            .send(&doc_batch)
+            .instrument(debug_span!(
+                "sink_send",
+                num_bytes = batch_size.num_bytes,
+                num_docs = batch_size.num_docs
+            ))
+            .await;
+        counters.record_request_latency(request_start.elapsed());
+        if let Some(adaptive_concurrency) = adaptive_concurrency {
+            adaptive_concurrency.record_request(
+                request_start,
+                request_start.elapsed(),
+                batch_size.num_bytes,
+                send_res.is_err(),
+            );
+        }
+        if let Some(failed_batch_dumper) = failed_batch_dumper {
+            // The whole batch is dumped if the request failed. Taking the
+            // rejected docs then would drop the ones of concurrent requests.
+            if send_res.is_ok() {
+                let rejected_docs = sink.take_rejected_docs();
+                if !rejected_docs.is_empty() {
+                    failed_batch_dumper.dump_rejected_docs(&rejected_docs);
+                }
+            }
+        }
+        match send_res {
+            Ok(()) => return Ok(batch_size),
             Err(err) => {
+                if is_timeout(&err) {
+                    counters
+                        .num_timed_out_requests
+                        .fetch_add(1, Ordering::Relaxed);
+                }
                 error!(err=?err);
+                if let Some(failed_batch_dumper) = failed_batch_dumper {
+                    failed_batch_dumper.dump(&doc_batch.bytes, &err);
+                }
                 if !retry {
-                    return Err(batch_num_bytes);
+                    return Err(batch_size);
                 }
                 tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+                counters.num_retries.fetch_add(1, Ordering::Relaxed);
                 info!("Retrying...");
             },
         }
@@ -260,85 +2666,145 @@ async fn send_with_retry(
 }
 
 fn handle_result(
-    result: Result<u64, u64>,
+    result: Result<BatchSize, BatchSize>,
     num_ingested_bytes: &mut u64,
     num_ingestion_error_bytes: &mut u64,
+    counters: &IngestCounters,
     start: std::time::Instant,
 ) {
     match result {
-        Ok(bytes) => {
-            *num_ingested_bytes += bytes;
+        Ok(batch_size) => {
+            *num_ingested_bytes += batch_size.num_bytes;
+            counters
+                .num_ingested_bytes
+                .fetch_add(batch_size.num_bytes, Ordering::Relaxed);
+            counters
+                .num_ingested_docs
+                .fetch_add(batch_size.num_docs, Ordering::Relaxed);
+            counters
+                .num_ingested_batches
+                .fetch_add(1, Ordering::Relaxed);
             let elapsed_time: f64 = start.elapsed().as_secs_f64();
             let megabytes_per_second =
                 *num_ingested_bytes as f64 / 1_000_000.0 / elapsed_time;
-            info!("Ingest throughput: {:.2} MB/s", megabytes_per_second);
+            info!(
+                target: THROUGHPUT_LOG_TARGET,
+                "Ingest throughput: {:.2} MB/s", megabytes_per_second
+            );
         },
-        Err(bytes) => {
-            *num_ingestion_error_bytes += bytes;
+        Err(batch_size) => {
+            *num_ingestion_error_bytes += batch_size.num_bytes;
+            counters.num_failed_batches.fetch_add(1, Ordering::Relaxed);
         },
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum Engine {
-    Quickwit,
-    Elasticsearch,
-    Opensearch,
-    Loki,
-    Parseable,
-    Signoz,
-    ZincObserve,
-}
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
 
-impl Engine {
-    pub fn default_host(&self) -> &'static str {
-        match self {
-            Engine::Quickwit => "127.0.0.1:7280",
-            Engine::Elasticsearch => "127.0.0.1:9200",
-            Engine::Opensearch => "127.0.0.1:9301",
-            Engine::Loki => "127.0.0.1:3100",
-            Engine::Parseable => "127.0.0.1:8000",
-            Engine::Signoz => "127.0.0.1:3301",
-            Engine::ZincObserve => "127.0.0.1:5080",
-        }
-    }
-}
+    use async_trait::async_trait;
+    use qbench_core::engine_metrics::EngineStats;
+    use qbench_core::sink::{BuildInfo, IndexInfo, RejectedDoc};
+    use tokio::sync::Notify;
+
+    use super::*;
 
-impl Display for Engine {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.as_ref())
+    /// Fails the batch `fail` once the other batch had a document rejected,
+    /// and lets the latter succeed once the failed batch is handled.
+    #[derive(Default)]
+    struct RejectingSink {
+        rejected_docs: Mutex<Vec<RejectedDoc>>,
+        doc_rejected: Notify,
+        failed_batch_handled: Notify,
     }
-}
 
-impl FromStr for Engine {
-    type Err = String;
+    #[async_trait]
+    impl sink::Sink for RejectingSink {
+        async fn send(&self, document_batch: &DocumentBatch) -> anyhow::Result<()> {
+            if document_batch.bytes.as_ref() == b"fail\n" {
+                self.doc_rejected.notified().await;
+                bail!("http error with status code 500");
+            }
+            self.rejected_docs.lock().unwrap().push(RejectedDoc {
+                doc: document_batch.bytes.slice(..4),
+                error: "mapper_parsing_exception".to_string(),
+            });
+            self.doc_rejected.notify_one();
+            self.failed_batch_handled.notified().await;
+            Ok(())
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let engine = match s {
-            "quickwit" => Engine::Quickwit,
-            "elasticsearch" => Engine::Elasticsearch,
-            "opensearch" => Engine::Opensearch,
-            "loki" => Engine::Loki,
-            "parseable" => Engine::Parseable,
-            "signoz" => Engine::Signoz,
-            "zincobserve" => Engine::ZincObserve,
-            _ => return Err(format!("Unknown engine {s:?}")),
-        };
+        fn take_rejected_docs(&self) -> Vec<RejectedDoc> {
+            std::mem::take(&mut *self.rejected_docs.lock().unwrap())
+        }
 
-        Ok(engine)
-    }
-}
+        async fn commit(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
 
-impl AsRef<str> for Engine {
-    fn as_ref(&self) -> &str {
-        match self {
-            Engine::Quickwit => "quickwit",
-            Engine::Elasticsearch => "elasticsearch",
-            Engine::Opensearch => "opensearch",
-            Engine::Loki => "loki",
-            Engine::Parseable => "parseable",
-            Engine::Signoz => "signoz",
-            Engine::ZincObserve => "zincobserve",
+        async fn index_info(&self) -> anyhow::Result<IndexInfo> {
+            Ok(IndexInfo {
+                num_docs: 0,
+                num_splits: 0,
+                num_bytes: 0,
+                split_breakdown: None,
+                engine_specific: EngineStats::new(),
+            })
         }
+
+        async fn build_info(&self) -> anyhow::Result<BuildInfo> {
+            Ok(BuildInfo {
+                version: "rejecting".to_string(),
+                commit_date: String::new(),
+                commit_hash: String::new(),
+                build_target: String::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_keeps_rejected_docs_of_concurrent_requests() {
+        let dir = std::env::temp_dir()
+            .join(format!("qbench-concurrent-sends-{}", std::process::id()));
+        let dumper = FailedBatchDumper::new(&dir, 10).unwrap();
+        let sink = RejectingSink::default();
+        let counters = IngestCounters::default();
+        let failing_send = async {
+            let batch = DocumentBatch {
+                bytes: Bytes::from_static(b"fail\n"),
+                last: false,
+            };
+            let result =
+                send_with_retry(&sink, batch, false, &counters, Some(&dumper), None)
+                    .await;
+            sink.failed_batch_handled.notify_one();
+            result
+        };
+        let rejecting_batch = DocumentBatch {
+            bytes: Bytes::from_static(b"{\"a\"\n{\"b\": 1}\n"),
+            last: false,
+        };
+        let rejecting_send = send_with_retry(
+            &sink,
+            rejecting_batch,
+            false,
+            &counters,
+            Some(&dumper),
+            None,
+        );
+        let (failing_result, rejecting_result) =
+            tokio::join!(failing_send, rejecting_send);
+        assert!(failing_result.is_err());
+        assert!(rejecting_result.is_ok());
+
+        assert_eq!(dumper.num_dumped(), 2);
+        let failed_payload =
+            std::fs::read_to_string(dir.join("failed-batch-000000.ndjson")).unwrap();
+        assert_eq!(failed_payload, "fail\n");
+        let rejected_payload =
+            std::fs::read_to_string(dir.join("failed-batch-000001.ndjson")).unwrap();
+        assert_eq!(rejected_payload, "{\"a\"\n");
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
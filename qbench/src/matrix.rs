@@ -0,0 +1,320 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use clap::{Args, Parser};
+use qbench_core::engine::Engine;
+use qbench_core::results::{read_results, write_results, OutputFormat};
+use qbench_core::run_results::RunResults;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::engine_docker::{engine_down, engine_up, EngineDownArgs, EngineUpArgs};
+use crate::report::print_comparison;
+use crate::{run_indexing, shutdown, CliArgs, Command, IndexArgs};
+
+#[derive(Args, Debug)]
+pub struct MatrixArgs {
+    #[arg(short, long, env)]
+    /// The engine started in Docker: "quickwit", "elasticsearch",
+    /// "opensearch" or "loki".
+    engine: Engine,
+
+    #[arg(long, env, value_delimiter = ',', required = true)]
+    /// The comma-separated tags of the engine's image, benchmarked one after
+    /// the other, e.g. `7.17.22,8.13.4,8.15.0`.
+    versions: Vec<String>,
+
+    #[arg(long, env, alias = "dataset")]
+    /// The dataset sent to every version, see `qbench index --dataset-uri`.
+    dataset_uri: String,
+
+    #[arg(long, env)]
+    /// The index created in every version.
+    index: String,
+
+    #[arg(long, env)]
+    /// The index config the index is created from in every version, once it
+    /// is up, see `qbench setup-index`. Without it, the engine creates the
+    /// index on the fly.
+    index_config: Option<PathBuf>,
+
+    #[arg(long, env, default_value = "4g")]
+    /// The JVM heap size of Elasticsearch and OpenSearch.
+    heap_size: String,
+
+    #[arg(long, env)]
+    /// The memory limit of the containers, e.g. "8g".
+    memory: Option<String>,
+
+    #[arg(long, env)]
+    /// The number of CPUs of the containers, e.g. "4".
+    cpus: Option<String>,
+
+    #[arg(long, env, default_value_t = 120)]
+    /// How long to wait for each version to be ready.
+    wait_for_engine_secs: u64,
+
+    #[arg(long, env, default_value = "matrix_results.json")]
+    /// The file combining the results of all the versions, keyed by version.
+    /// The results of each version are also kept next to it, e.g. in
+    /// `matrix_results-8.13.4.json`.
+    output_path: PathBuf,
+
+    #[arg(long, env, default_value = "json")]
+    /// The format of the results files: "json", "yaml" or "msgpack".
+    output_format: OutputFormat,
+
+    #[arg(last = true)]
+    /// Flags passed to every `qbench index` run, after `--`, e.g.
+    /// `-- --max-docs 1000000 --concurrency 4`.
+    index_args: Vec<String>,
+}
+
+impl MatrixArgs {
+    /// `output_path` with the version appended to its file stem.
+    fn version_path(&self, version: &str, suffix: &str) -> PathBuf {
+        let file_stem = self
+            .output_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        self.output_path
+            .with_file_name(format!("{file_stem}-{version}{suffix}"))
+    }
+
+    fn version_output_path(&self, version: &str) -> PathBuf {
+        let extension = format!(".{}", self.output_format.extension());
+        self.version_path(version, &extension)
+    }
+
+    fn container_config_path(&self, version: &str) -> PathBuf {
+        self.version_path(version, "-container.json")
+    }
+
+    /// The arguments of the indexing run against `version`. The container is
+    /// reached on the engine's default host.
+    fn index_args(&self, version: &str) -> anyhow::Result<IndexArgs> {
+        let container_config_path = self.container_config_path(version);
+        let container_config_path = container_config_path.to_string_lossy();
+        let output_path = self.version_output_path(version);
+        let output_path = output_path.to_string_lossy();
+        let version_tag = format!("engine_version={version}");
+        let mut argv = vec![
+            "qbench",
+            "index",
+            "--engine",
+            self.engine.as_ref(),
+            "--index",
+            &self.index,
+            "--dataset-uri",
+            &self.dataset_uri,
+            "--engine-container",
+            &container_config_path,
+            "--tag",
+            &version_tag,
+            "--output-format",
+            self.output_format.extension(),
+            "--append-output",
+            "--output-path",
+            &output_path,
+        ];
+        // Also recreates the index between the runs of `--runs`.
+        let index_config_path = self
+            .index_config
+            .as_ref()
+            .map(|index_config_path| index_config_path.to_string_lossy());
+        if let Some(index_config_path) = &index_config_path {
+            argv.extend(["--index-config", index_config_path]);
+        }
+        argv.extend(self.index_args.iter().map(String::as_str));
+        let cli = CliArgs::try_parse_from(argv)
+            .with_context(|| format!("Invalid indexing arguments for {version}"))?;
+        let Command::Index(index_args) = cli.command else {
+            unreachable!("the indexing subcommand is set above");
+        };
+        Ok(*index_args)
+    }
+
+    fn engine_up_args(&self, version: &str) -> EngineUpArgs {
+        EngineUpArgs {
+            engine: self.engine,
+            version: version.to_string(),
+            heap_size: self.heap_size.clone(),
+            data_dir: None,
+            memory: self.memory.clone(),
+            cpus: self.cpus.clone(),
+            wait_for_engine_secs: self.wait_for_engine_secs,
+            container_config_path: self.container_config_path(version),
+        }
+    }
+}
+
+/// Starts `version` in Docker, creates the index, sends it the dataset, then
+/// removes its container whether the run succeeded or not.
+async fn run_version(
+    args: &MatrixArgs,
+    version: &str,
+    index_config: Option<&str>,
+    index_args: IndexArgs,
+) -> anyhow::Result<()> {
+    let run = async {
+        engine_up(args.engine_up_args(version)).await?;
+        if let Some(index_config) = index_config {
+            index_args
+                .target
+                .build_sink(None)?
+                .create_index(index_config)
+                .await?;
+            info!(index = index_args.target.index, version, "Index created");
+        }
+        run_indexing(index_args).await
+    };
+    let run_result = run.await;
+    let down_args = EngineDownArgs {
+        engine: args.engine,
+    };
+    if let Err(error) = engine_down(down_args).await {
+        warn!(version, "Failed to remove the engine container: {error:#}");
+    }
+    run_result
+}
+
+fn read_version_runs(path: &Path, output_format: OutputFormat) -> Vec<Value> {
+    read_results(path, output_format).unwrap_or_else(|error| {
+        warn!("Failed to read the results of {path:?}: {error:#}");
+        Vec::new()
+    })
+}
+
+/// Runs the same benchmark against several versions of an engine started in
+/// Docker, and combines their results in one file keyed by version.
+pub async fn matrix(args: MatrixArgs) -> anyhow::Result<()> {
+    // Check all the arguments before starting the first version.
+    let runs_args = args
+        .versions
+        .iter()
+        .map(|version| Ok((version, args.index_args(version)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let index_config = args
+        .index_config
+        .as_ref()
+        .map(|index_config_path| {
+            std::fs::read_to_string(index_config_path).with_context(|| {
+                format!("Failed to read index config {index_config_path:?}")
+            })
+        })
+        .transpose()?;
+    // Installed once for all the versions rather than by every run.
+    shutdown::install_signal_handler()?;
+    let mut version_results = Map::new();
+    let mut version_runs = Vec::new();
+    let mut failed_versions = Vec::new();
+    for (version, index_args) in runs_args {
+        if shutdown::is_requested() {
+            warn!("Skipping the remaining versions");
+            break;
+        }
+        // The runs of `--runs` append their results.
+        let output_path = args.version_output_path(version);
+        std::fs::File::create(&output_path)
+            .with_context(|| format!("Failed to create results file {output_path:?}"))?;
+        info!(engine = %args.engine, version, "Starting the matrix run");
+        if let Err(error) =
+            run_version(&args, version, index_config.as_deref(), index_args).await
+        {
+            error!(version, "The run failed: {error:#}");
+            failed_versions.push(version.clone());
+        }
+        let runs = read_version_runs(&output_path, args.output_format);
+        // A run that can't be compared is still kept in the combined file.
+        for (run_idx, run) in runs.iter().enumerate() {
+            if run["partial"] == true {
+                continue;
+            }
+            match RunResults::deserialize(run) {
+                Ok(mut run) => {
+                    run.engine = format!("{} {version}", run.engine);
+                    version_runs.push(run);
+                },
+                Err(error) => {
+                    warn!(version, run_idx, "Skipping an invalid run: {error}");
+                },
+            }
+        }
+        version_results.insert(version.clone(), Value::Array(runs));
+    }
+    let matrix_results = json!({
+        "engine": args.engine.as_ref(),
+        "dataset_uri": args.dataset_uri,
+        "versions": version_results,
+    });
+    write_results(
+        &args.output_path,
+        args.output_format,
+        false,
+        &matrix_results,
+    )?;
+    if !version_runs.is_empty() {
+        print_comparison(&version_runs);
+    }
+    info!("Matrix results written in `{:?}`", args.output_path);
+    if !failed_versions.is_empty() {
+        bail!("The runs against {} failed", failed_versions.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn test_matrix_index_args() {
+        let cli = CliArgs::try_parse_from([
+            "qbench",
+            "matrix",
+            "--engine",
+            "elasticsearch",
+            "--versions",
+            "7.17.22,8.13.4",
+            "--dataset",
+            "logs.json",
+            "--index",
+            "logs",
+            "--output-path",
+            "out/matrix.json",
+            "--index-config",
+            "index-config.json",
+            "--",
+            "--concurrency",
+            "4",
+        ])
+        .unwrap();
+        let Command::Matrix(matrix_args) = cli.command else {
+            panic!("expected the matrix subcommand");
+        };
+        assert_eq!(matrix_args.versions, ["7.17.22", "8.13.4"]);
+        let index_args = matrix_args.index_args("8.13.4").unwrap();
+        assert_eq!(index_args.target.index, "logs");
+        assert_eq!(index_args.target.host, None);
+        assert_eq!(index_args.concurrency, 4);
+        assert_eq!(
+            index_args.index_config.as_deref(),
+            Some(Path::new("index-config.json"))
+        );
+        assert_eq!(
+            index_args.output_path.as_deref(),
+            Some(Path::new("out/matrix-8.13.4.json"))
+        );
+        assert_eq!(
+            index_args.engine_container.as_deref(),
+            Some(Path::new("out/matrix-8.13.4-container.json"))
+        );
+        assert_eq!(
+            index_args.labels.key_value_tags(),
+            BTreeMap::from([("engine_version".to_string(), "8.13.4".to_string())])
+        );
+    }
+}
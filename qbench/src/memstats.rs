@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use qbench_core::run_results::reports::EngineMemoryReport;
+use tokio::task::JoinHandle;
+
+/// The interval at which the engine's memory is sampled while the run is
+/// ongoing.
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(1);
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+#[derive(Default)]
+struct MemorySamples {
+    rss_bytes: Vec<u64>,
+    cgroup_memory_bytes: Vec<u64>,
+}
+
+/// Periodically samples the memory of a local engine process in the
+/// background. Linux only.
+pub struct EngineMemorySampler {
+    pid: u32,
+    cgroup_path: Option<PathBuf>,
+    samples: Arc<Mutex<MemorySamples>>,
+    handle: JoinHandle<()>,
+}
+
+impl EngineMemorySampler {
+    pub fn start(pid: u32) -> anyhow::Result<Self> {
+        // Fails early if the process does not exist.
+        process_tree_rss_bytes(pid)?;
+        let cgroup_path = std::fs::read_to_string(format!("/proc/{pid}/cgroup"))
+            .ok()
+            .and_then(|cgroup_file| cgroup_v2_path(&cgroup_file))
+            .map(|cgroup| PathBuf::from(format!("{CGROUP_ROOT}{cgroup}")))
+            .filter(|cgroup_path| cgroup_path.join("memory.current").exists());
+        if cgroup_path.is_none() {
+            warn!(pid, "No cgroup v2 memory controller found for the engine");
+        }
+        let samples = Arc::new(Mutex::new(MemorySamples::default()));
+        let handle = tokio::spawn({
+            let samples = samples.clone();
+            let cgroup_path = cgroup_path.clone();
+            async move {
+                let mut interval = tokio::time::interval(SAMPLING_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let rss_bytes = match process_tree_rss_bytes(pid) {
+                        Ok(rss_bytes) => rss_bytes,
+                        Err(err) => {
+                            warn!(err=?err, "Failed to sample the engine memory");
+                            continue;
+                        },
+                    };
+                    let cgroup_memory_bytes =
+                        cgroup_path.as_ref().and_then(|cgroup_path| {
+                            read_u64(&cgroup_path.join("memory.current"))
+                        });
+                    let mut samples = samples.lock().unwrap();
+                    samples.rss_bytes.push(rss_bytes);
+                    samples.cgroup_memory_bytes.extend(cgroup_memory_bytes);
+                }
+            }
+        });
+        Ok(Self {
+            pid,
+            cgroup_path,
+            samples,
+            handle,
+        })
+    }
+
+    pub fn finish(self) -> EngineMemoryReport {
+        self.handle.abort();
+        let samples = self.samples.lock().unwrap();
+        let max_and_avg = |values: &[u64]| {
+            let max = values.iter().copied().max()?;
+            let avg = values.iter().sum::<u64>() / values.len() as u64;
+            Some((max, avg))
+        };
+        let (max_rss_bytes, avg_rss_bytes) =
+            max_and_avg(&samples.rss_bytes).unwrap_or_default();
+        let cgroup_memory = max_and_avg(&samples.cgroup_memory_bytes);
+        EngineMemoryReport {
+            pid: self.pid,
+            num_samples: samples.rss_bytes.len(),
+            max_rss_bytes,
+            avg_rss_bytes,
+            cgroup: self
+                .cgroup_path
+                .as_ref()
+                .map(|cgroup_path| cgroup_path.display().to_string()),
+            max_cgroup_memory_bytes: cgroup_memory.map(|(max, _)| max),
+            avg_cgroup_memory_bytes: cgroup_memory.map(|(_, avg)| avg),
+            cgroup_memory_peak_bytes: self
+                .cgroup_path
+                .as_ref()
+                .and_then(|cgroup_path| read_u64(&cgroup_path.join("memory.peak"))),
+        }
+    }
+}
+
+/// The RSS of the process and of all its descendants.
+fn process_tree_rss_bytes(pid: u32) -> anyhow::Result<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status"))
+        .with_context(|| format!("Failed to read the status of process {pid}"))?;
+    let mut rss_bytes = vm_rss_bytes(&status).unwrap_or_default();
+    for child_pid in child_pids(pid) {
+        // Children may exit in the meantime.
+        rss_bytes += process_tree_rss_bytes(child_pid).unwrap_or_default();
+    }
+    Ok(rss_bytes)
+}
+
+fn child_pids(pid: u32) -> Vec<u32> {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{pid}/task")) else {
+        return Vec::new();
+    };
+    tasks
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .filter_map(|child_pid| child_pid.parse().ok())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// The `VmRSS` line of `/proc/<pid>/status`, absent for kernel threads.
+fn vm_rss_bytes(status: &str) -> Option<u64> {
+    let vm_rss_kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(vm_rss_kb * 1024)
+}
+
+/// The cgroup v2 path in `/proc/<pid>/cgroup`, on its `0::` line.
+fn cgroup_v2_path(cgroup_file: &str) -> Option<String> {
+    cgroup_file
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim().to_string())
+}
+
+fn read_u64(path: &std::path::Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_memory_files() {
+        let status =
+            "Name:\tjava\nVmPeak:\t 9000 kB\nVmRSS:\t    2048 kB\nThreads:\t80\n";
+        assert_eq!(vm_rss_bytes(status), Some(2048 * 1024));
+        assert_eq!(vm_rss_bytes("Name:\tkthreadd\n"), None);
+        assert_eq!(
+            cgroup_v2_path("0::/system.slice/docker-3f4e2a.scope\n").as_deref(),
+            Some("/system.slice/docker-3f4e2a.scope")
+        );
+        assert_eq!(cgroup_v2_path("12:memory:/docker/3f4e2a\n"), None);
+
+        let pid = std::process::id();
+        assert!(process_tree_rss_bytes(pid).unwrap() > 0);
+    }
+}
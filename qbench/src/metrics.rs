@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use chrono::{SecondsFormat, Utc};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// The number of most recent request latencies kept for the live reporters.
+const NUM_RECENT_REQUEST_LATENCIES: usize = 1000;
+
+/// Live counters of the ingestion progress, shared with the reporters running
+/// alongside the ingestion loop.
+#[derive(Debug, Default)]
+pub struct IngestCounters {
+    pub num_ingested_bytes: AtomicU64,
+    pub num_ingested_docs: AtomicU64,
+    pub num_ingested_batches: AtomicU64,
+    pub num_failed_batches: AtomicU64,
+    pub num_retries: AtomicU64,
+    /// The requests failed on a connect or request timeout, retried or not.
+    pub num_timed_out_requests: AtomicU64,
+    pub num_inflight_requests: AtomicU64,
+    recent_request_latencies_ms: Mutex<VecDeque<f64>>,
+}
+
+impl IngestCounters {
+    /// Records the latency of an ingest request, successful or not.
+    pub fn record_request_latency(&self, latency: Duration) {
+        let mut latencies_ms = self.recent_request_latencies_ms.lock().unwrap();
+        if latencies_ms.len() == NUM_RECENT_REQUEST_LATENCIES {
+            latencies_ms.pop_front();
+        }
+        latencies_ms.push_back(latency.as_secs_f64() * 1000.0);
+    }
+
+    /// The latencies of the most recent ingest requests.
+    pub fn recent_request_latencies_ms(&self) -> Vec<f64> {
+        let latencies_ms = self.recent_request_latencies_ms.lock().unwrap();
+        latencies_ms.iter().copied().collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LiveMetricsFormat {
+    /// One tab-separated line per interval, e.g. for `feedgnuplot`.
+    Tsv,
+}
+
+impl FromStr for LiveMetricsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tsv" => Ok(LiveMetricsFormat::Tsv),
+            _ => Err(format!("Unknown live metrics format {s:?}")),
+        }
+    }
+}
+
+/// Prints a line with the throughput over the last interval to stdout every
+/// `interval`, until the returned task is aborted.
+pub fn spawn_live_metrics_printer(
+    counters: Arc<IngestCounters>,
+    format: LiveMetricsFormat,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let LiveMetricsFormat::Tsv = format;
+        println!("# timestamp\tmb_per_sec\tdocs_per_sec\terrors\tinflight");
+        let mut previous_bytes = 0;
+        let mut previous_docs = 0;
+        let mut previous_errors = 0;
+        let mut previous_tick = Instant::now();
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let tick = Instant::now();
+            let elapsed_secs = (tick - previous_tick).as_secs_f64();
+            previous_tick = tick;
+            let num_bytes = counters.num_ingested_bytes.load(Ordering::Relaxed);
+            let num_docs = counters.num_ingested_docs.load(Ordering::Relaxed);
+            let num_errors = counters.num_failed_batches.load(Ordering::Relaxed);
+            let num_inflight = counters.num_inflight_requests.load(Ordering::Relaxed);
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(
+                stdout,
+                "{}\t{:.3}\t{:.1}\t{}\t{}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                (num_bytes - previous_bytes) as f64 / 1_000_000.0 / elapsed_secs,
+                (num_docs - previous_docs) as f64 / elapsed_secs,
+                num_errors - previous_errors,
+                num_inflight,
+            );
+            // Flush right away, stdout is block buffered when piped.
+            let _ = stdout.flush();
+            previous_bytes = num_bytes;
+            previous_docs = num_docs;
+            previous_errors = num_errors;
+        }
+    })
+}
+
+/// Serves the ingestion counters in the Prometheus text format on
+/// `http://0.0.0.0:{port}/metrics`, until the returned task is aborted.
+pub async fn spawn_metrics_server(
+    counters: Arc<IngestCounters>,
+    port: u16,
+    engine: String,
+    index: String,
+) -> anyhow::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to bind the metrics server on port {port}"))?;
+    info!(port, "Serving metrics on /metrics");
+    Ok(tokio::spawn(async move {
+        // The throughput gauge is updated every second, independently of the
+        // scrapes.
+        let mut mb_per_sec = 0.0;
+        let mut previous_bytes = 0;
+        let mut previous_tick = Instant::now();
+        let mut ticker = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    let tick = Instant::now();
+                    let num_bytes = counters.num_ingested_bytes.load(Ordering::Relaxed);
+                    let elapsed_secs = (tick - previous_tick).as_secs_f64();
+                    if elapsed_secs > 0.0 {
+                        mb_per_sec =
+                            (num_bytes - previous_bytes) as f64 / 1_000_000.0 / elapsed_secs;
+                    }
+                    previous_bytes = num_bytes;
+                    previous_tick = tick;
+                },
+                accepted = listener.accept() => {
+                    let stream = match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(error) => {
+                            warn!(%error, "Failed to accept a metrics connection");
+                            continue;
+                        },
+                    };
+                    let body = prometheus_metrics(&counters, mb_per_sec, &engine, &index);
+                    tokio::spawn(async move {
+                        if let Err(error) = serve_metrics(stream, body).await {
+                            debug!(%error, "Failed to serve the metrics");
+                        }
+                    });
+                },
+            }
+        }
+    }))
+}
+
+/// Answers a single HTTP request, with the metrics on `/metrics` and a 404
+/// elsewhere.
+async fn serve_metrics(mut stream: TcpStream, body: String) -> anyhow::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let num_bytes = stream.read(&mut buffer).await?;
+        if num_bytes == 0 || request.len() > 16 * 1024 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..num_bytes]);
+    }
+    let request_line = String::from_utf8_lossy(&request);
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = if path == "/metrics" || path.starts_with("/metrics?") {
+        ("200 OK", body)
+    } else {
+        ("404 Not Found", String::new())
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+         {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn prometheus_metrics(
+    counters: &IngestCounters,
+    mb_per_sec: f64,
+    engine: &str,
+    index: &str,
+) -> String {
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64;
+    let metrics = [
+        (
+            "qbench_ingested_bytes_total",
+            "counter",
+            "Bytes successfully ingested.",
+            load(&counters.num_ingested_bytes),
+        ),
+        (
+            "qbench_ingested_docs_total",
+            "counter",
+            "Documents successfully ingested.",
+            load(&counters.num_ingested_docs),
+        ),
+        (
+            "qbench_ingested_batches_total",
+            "counter",
+            "Batches successfully ingested.",
+            load(&counters.num_ingested_batches),
+        ),
+        (
+            "qbench_failed_batches_total",
+            "counter",
+            "Batches given up on after an ingestion error.",
+            load(&counters.num_failed_batches),
+        ),
+        (
+            "qbench_retries_total",
+            "counter",
+            "Ingest requests retried after an error.",
+            load(&counters.num_retries),
+        ),
+        (
+            "qbench_timed_out_requests_total",
+            "counter",
+            "Ingest requests failed on a connect or request timeout.",
+            load(&counters.num_timed_out_requests),
+        ),
+        (
+            "qbench_inflight_requests",
+            "gauge",
+            "Ingest requests in flight.",
+            load(&counters.num_inflight_requests),
+        ),
+        (
+            "qbench_ingest_megabytes_per_second",
+            "gauge",
+            "Ingestion throughput over the last second.",
+            mb_per_sec,
+        ),
+    ];
+    let mut exposition = String::new();
+    for (name, metric_type, help, value) in metrics {
+        let _ = writeln!(exposition, "# HELP {name} {help}");
+        let _ = writeln!(exposition, "# TYPE {name} {metric_type}");
+        let _ = writeln!(
+            exposition,
+            "{name}{{engine=\"{}\",index=\"{}\"}} {value}",
+            escape_label_value(engine),
+            escape_label_value(index)
+        );
+    }
+    exposition
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_metrics() {
+        let counters = IngestCounters::default();
+        counters.num_ingested_bytes.store(1000, Ordering::Relaxed);
+        counters.num_retries.store(2, Ordering::Relaxed);
+        let exposition = prometheus_metrics(&counters, 1.5, "quickwit", "logs\"1");
+        let lines: Vec<&str> = exposition.lines().collect();
+        assert_eq!(
+            &lines[..3],
+            [
+                "# HELP qbench_ingested_bytes_total Bytes successfully ingested.",
+                "# TYPE qbench_ingested_bytes_total counter",
+                r#"qbench_ingested_bytes_total{engine="quickwit",index="logs\"1"} 1000"#,
+            ]
+        );
+        assert!(lines
+            .contains(&r#"qbench_retries_total{engine="quickwit",index="logs\"1"} 2"#));
+        assert!(lines.contains(
+            &r#"qbench_ingest_megabytes_per_second{engine="quickwit",index="logs\"1"} 1.5"#
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use qbench_core::run_results::reports::{TcpStats, TcpStatsReport};
+use tokio::task::JoinHandle;
+
+/// The interval at which the TCP counters are sampled while the run is ongoing.
+const SAMPLING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Periodically samples the host TCP counters in the background.
+pub struct TcpStatsSampler {
+    start: TcpStats,
+    max_interval_retransmission_rate: Arc<Mutex<f64>>,
+    handle: JoinHandle<()>,
+}
+
+impl TcpStatsSampler {
+    pub fn start() -> anyhow::Result<Self> {
+        let start = TcpStats::read()?;
+        let max_interval_retransmission_rate = Arc::new(Mutex::new(0.0f64));
+        let max_rate = max_interval_retransmission_rate.clone();
+        let handle = tokio::spawn(async move {
+            let mut previous = start;
+            let mut interval = tokio::time::interval(SAMPLING_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match TcpStats::read() {
+                    Ok(current) => {
+                        let rate = current.since(&previous).retransmission_rate();
+                        let mut max_rate = max_rate.lock().unwrap();
+                        *max_rate = max_rate.max(rate);
+                        previous = current;
+                    },
+                    Err(err) => warn!(err=?err, "Failed to sample TCP stats"),
+                }
+            }
+        });
+        Ok(Self {
+            start,
+            max_interval_retransmission_rate,
+            handle,
+        })
+    }
+
+    pub fn finish(self) -> anyhow::Result<TcpStatsReport> {
+        self.handle.abort();
+        let delta = TcpStats::read()?.since(&self.start);
+        let max_interval_retransmission_rate =
+            *self.max_interval_retransmission_rate.lock().unwrap();
+        Ok(TcpStatsReport {
+            delta,
+            retransmission_rate: delta.retransmission_rate(),
+            max_interval_retransmission_rate,
+        })
+    }
+}
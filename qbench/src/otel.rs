@@ -0,0 +1,50 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::Level;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::filter::{Filtered, Targets};
+use tracing_subscriber::Layer;
+
+/// The spans exported, down to the per-batch source reads, batch assembly and
+/// sink sends. Only qbench's own spans are exported: the exporter's HTTP/2
+/// client would otherwise trace its own exports.
+const EXPORTED_SPANS_LEVEL: Level = Level::DEBUG;
+
+/// Starts exporting spans to an OTLP/gRPC collector, e.g.
+/// `http://127.0.0.1:4317`. The returned provider must be shut down to flush
+/// the last spans.
+pub fn init_tracer_provider(endpoint: &str) -> anyhow::Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", "qbench")]))
+        .build())
+}
+
+/// The tracing layer exporting qbench's spans through `tracer_provider`.
+pub fn layer<S>(
+    tracer_provider: &TracerProvider,
+) -> Filtered<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>, Targets, S>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(tracer_provider.tracer("qbench"))
+        .with_filter(Targets::new().with_target("qbench", EXPORTED_SPANS_LEVEL))
+}
+
+/// Flushes the spans not exported yet.
+pub async fn shutdown(tracer_provider: TracerProvider) {
+    // Shutting down blocks until the export task is done.
+    let shutdown_res =
+        tokio::task::spawn_blocking(move || tracer_provider.shutdown()).await;
+    if let Ok(Err(error)) = shutdown_res {
+        warn!(%error, "Failed to export the last spans");
+    }
+}
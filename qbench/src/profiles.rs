@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use qbench_core::run_results::reports::{PacingProfile, PacingReport};
+
+/// Schedules the batches at the rate given by a profile.
+pub struct Pacer {
+    /// The average rate, in bytes per second.
+    rate: f64,
+    profile: PacingProfile,
+    period: Duration,
+    /// When the next batch is due, from the start of the ingestion.
+    next_due: Duration,
+}
+
+impl Pacer {
+    pub fn new(
+        rate_mbps: f64,
+        profile: PacingProfile,
+        period: Option<Duration>,
+    ) -> Self {
+        Self {
+            rate: rate_mbps * 1_000_000.0,
+            profile,
+            period: period.unwrap_or_else(|| profile.default_period()),
+            next_due: Duration::ZERO,
+        }
+    }
+
+    /// When a batch of `num_bytes` is due, from the start of the ingestion.
+    /// The next batch is due once it was sent at the rate of the profile.
+    pub fn schedule(&mut self, num_bytes: usize) -> Duration {
+        let due = self.next_due;
+        let rate = self.rate * self.profile.rate_multiplier(due, self.period);
+        self.next_due += Duration::from_secs_f64(num_bytes as f64 / rate);
+        due
+    }
+
+    pub fn report(&self) -> PacingReport {
+        PacingReport {
+            profile: self.profile,
+            period_secs: self.period.as_secs_f64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer() {
+        let mut pacer = Pacer::new(1.0, PacingProfile::Steady, None);
+        assert_eq!(pacer.schedule(500_000), Duration::ZERO);
+        assert_eq!(pacer.schedule(500_000), Duration::from_millis(500));
+        assert_eq!(pacer.schedule(0), Duration::from_secs(1));
+
+        // Every profile averages the rate over a period.
+        for profile in [
+            PacingProfile::Burst,
+            PacingProfile::Ramp,
+            PacingProfile::Diurnal,
+        ] {
+            let period = profile.default_period();
+            let num_steps = 10_000;
+            let mean_multiplier = (0..num_steps)
+                .map(|step| profile.rate_multiplier(period * step / num_steps, period))
+                .sum::<f64>()
+                / num_steps as f64;
+            assert!((mean_multiplier - 1.0).abs() < 0.01, "{profile:?}");
+        }
+        let period = Duration::from_secs(60);
+        let burst = PacingProfile::Burst;
+        assert!((burst.rate_multiplier(Duration::ZERO, period) - 5.0).abs() < 1e-9);
+        assert_eq!(burst.rate_multiplier(Duration::from_secs(30), period), 0.2);
+        let diurnal = PacingProfile::Diurnal;
+        assert!(
+            (diurnal.rate_multiplier(Duration::from_secs(30), period) - 1.8).abs()
+                < 1e-9
+        );
+    }
+}
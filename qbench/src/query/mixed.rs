@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::bail;
+use qbench_core::engine::Engine;
+use qbench_core::query::LoadReport;
+use reqwest::Client;
+use tokio::task::JoinHandle;
+
+use super::{
+    generate_load,
+    load_report,
+    search_sink,
+    translate_queries,
+    LoadEnd,
+    QuerySuite,
+};
+
+/// Queries the index in the background while it is being fed.
+pub struct MixedWorkload {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<LoadReport>,
+}
+
+impl MixedWorkload {
+    pub fn start(
+        query_suite_path: &Path,
+        num_clients: usize,
+        target_qps: Option<f64>,
+        engine: Engine,
+        host: &str,
+        index: &str,
+        client: Client,
+    ) -> anyhow::Result<Self> {
+        if num_clients == 0 {
+            bail!("--mixed-num-clients must be at least 1");
+        }
+        if target_qps.is_some_and(|target_qps| target_qps <= 0.0) {
+            bail!("--mixed-target-qps must be positive");
+        }
+        let queries =
+            translate_queries(QuerySuite::load(query_suite_path)?.queries, engine)?;
+        let sink = search_sink(engine, host, index, client)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        info!(
+            num_queries = queries.len(),
+            num_clients, target_qps, "Running queries while indexing"
+        );
+        let handle = tokio::spawn({
+            let stop = stop.clone();
+            async move {
+                let (executions, elapsed) = generate_load(
+                    &*sink,
+                    &queries,
+                    LoadEnd::Flag(&stop),
+                    num_clients,
+                    target_qps,
+                )
+                .await;
+                load_report(queries, &[], executions, elapsed, num_clients, target_qps)
+            }
+        });
+        Ok(Self { stop, handle })
+    }
+
+    /// Stops issuing queries, and waits for the ones in flight.
+    pub async fn finish(self) -> anyhow::Result<LoadReport> {
+        self.stop.store(true, Ordering::Relaxed);
+        Ok(self.handle.await?)
+    }
+}
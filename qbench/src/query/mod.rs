@@ -0,0 +1,797 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use clap::Args;
+use futures_util::future::join_all;
+use qbench_core::engine::Engine;
+pub use qbench_core::query::EngineQuery;
+use qbench_core::query::{LatencyStats, LoadReport, QueryResult};
+use qbench_core::results::{write_results, OutputFormat};
+use qbench_core::sink::elasticsearch::{Distribution, ElasticsearchSink};
+use qbench_core::sink::loki::LokiSink;
+use qbench_core::sink::quickwit::QuickwitSink;
+use qbench_core::sink::{SearchResponse, Sink};
+use qbench_core::source::{DatasetFormat, Source, UriSource, DEFAULT_MAX_BODY_SIZE};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub use self::mixed::MixedWorkload;
+pub use self::suite::{Query, QuerySuite};
+pub use self::translate::translate;
+pub use self::visibility::VisibilityProbe;
+use crate::http_client::HttpClientArgs;
+use crate::report::print_statistics;
+use crate::run_results::RunLabelsArgs;
+
+mod mixed;
+mod suite;
+mod translate;
+mod verify;
+mod visibility;
+
+/// Values longer than this are unlikely to be keywords worth querying.
+const MAX_TERM_LEN: usize = 64;
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    #[arg(long, env)]
+    /// The results file written by the indexing run whose index should be
+    /// queried. The engine, host, index and dataset are taken from it. Only
+    /// standalone JSON results can be read.
+    from_run: PathBuf,
+
+    #[arg(long, env)]
+    /// Override the host recorded in the indexing run.
+    host: Option<String>,
+
+    #[command(flatten)]
+    labels: RunLabelsArgs,
+
+    #[command(flatten)]
+    http_client: HttpClientArgs,
+
+    #[arg(long, env, default_value_t = 10)]
+    /// The number of times each query is executed.
+    iterations: usize,
+
+    #[arg(long, env, default_value_t = 1)]
+    /// The number of concurrent clients issuing the queries. The queries are
+    /// interleaved: the clients run a mix of all of them, like the panels of
+    /// a dashboard.
+    num_clients: usize,
+
+    #[arg(long, env)]
+    /// Issue the queries at this rate (open loop), whether the previous ones
+    /// have returned or not, with up to `--num-clients` of them in flight.
+    /// Latencies are then measured from the time a query was due, so that
+    /// the time spent waiting for a free client is accounted for. By default,
+    /// each client issues its next query as soon as the previous one returns
+    /// (closed loop).
+    target_qps: Option<f64>,
+
+    #[arg(long, env, default_value_t = 1)]
+    /// Run the whole benchmark, cold runs included, this many times, and
+    /// report the statistics of the achieved QPS and latencies over the runs.
+    /// The results of every run are collected in the output file.
+    runs: usize,
+
+    #[arg(long, env, default_value_t = 0)]
+    /// The number of times each query is executed after its cold run and
+    /// before the measured (hot) ones, without being measured.
+    warmup_iterations: usize,
+
+    #[arg(long, env)]
+    /// A shell command dropping the engine's caches, run before the cold run
+    /// of each query, e.g. restarting the searchers of an object-storage
+    /// engine. By default, the engine's cache clearing API is used when it
+    /// has one (Elasticsearch, OpenSearch).
+    drop_caches_command: Option<String>,
+
+    #[arg(long, env, default_value_t = 1000)]
+    /// The number of documents sampled from the dataset to generate queries.
+    num_sample_docs: usize,
+
+    #[arg(long, env, default_value_t = 10)]
+    /// The maximum number of term queries generated from the sampled documents.
+    max_generated_queries: usize,
+
+    #[arg(long, env)]
+    /// A pre-obtained GCP access token to sample `gs://` datasets. If not
+    /// provided, application default credentials are used.
+    gcp_access_token: Option<String>,
+
+    #[arg(long, env)]
+    /// Specify output file path.
+    output_path: Option<PathBuf>,
+
+    #[arg(long, env, default_value = "json")]
+    /// The format of the results file: "json", "yaml" or "msgpack".
+    output_format: OutputFormat,
+
+    #[arg(long, env)]
+    /// Append the results to the output file instead of replacing it, e.g. to
+    /// collect the runs of a suite into a single file. JSON results are then
+    /// written one per line and YAML results as separate documents.
+    append_output: bool,
+
+    #[arg(long, env)]
+    /// A YAML or JSON file defining the queries to run, in the dialect of
+    /// each engine, instead of generating them from sampled documents. The
+    /// queries not written in the engine's dialect are skipped.
+    query_suite: Option<PathBuf>,
+
+    #[arg(long, env, default_value_t = 0.0)]
+    /// The share of the measured query executions allowed to fail. Failed
+    /// executions are left out of the QPS and latencies, and the run exits
+    /// with an error past this share, once its results are written.
+    max_error_rate: f64,
+}
+
+/// The subset of the indexing results needed to target the same index.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct IndexingRun {
+    pub engine: String,
+    pub host: String,
+    pub index: String,
+    pub dataset_uri: String,
+    pub dataset_fingerprint: String,
+    /// Absent from the results of the runs before the fingerprint was
+    /// versioned, i.e. version 1.
+    #[serde(default)]
+    pub dataset_fingerprint_version: Option<u32>,
+    /// Absent from the results of older runs.
+    #[serde(default)]
+    pub dataset_format: Option<String>,
+    #[serde(default)]
+    pub csv_infer_types: bool,
+}
+
+/// The first run of a query, after dropping the caches.
+struct ColdRun {
+    latency_ms: Option<f64>,
+    mismatches: Vec<String>,
+}
+
+/// The report of the executions of `queries`. `cold_runs` are the cold runs
+/// of the queries, in order, if any.
+fn load_report(
+    queries: Vec<TranslatedQuery>,
+    cold_runs: &[ColdRun],
+    executions: Vec<QueryExecution>,
+    elapsed: Duration,
+    num_clients: usize,
+    target_qps: Option<f64>,
+) -> LoadReport {
+    let successful_latencies_ms: Vec<f64> = executions
+        .iter()
+        .filter(|execution| execution.response_res.is_ok())
+        .map(|execution| execution.latency_ms)
+        .collect();
+    let num_errors = executions.len() - successful_latencies_ms.len();
+    let error_rate = if executions.is_empty() {
+        0.0
+    } else {
+        num_errors as f64 / executions.len() as f64
+    };
+    let achieved_qps = successful_latencies_ms.len() as f64 / elapsed.as_secs_f64();
+    let latency = LatencyStats::from_latencies_ms(successful_latencies_ms);
+    let mut query_results = Vec::with_capacity(queries.len());
+    let mut num_incorrect_queries = 0;
+    for (query_idx, translated_query) in queries.into_iter().enumerate() {
+        let TranslatedQuery {
+            query,
+            engine_query,
+        } = translated_query;
+        let cold_run = cold_runs.get(query_idx);
+        let cold_latency_ms = cold_run.and_then(|cold_run| cold_run.latency_ms);
+        let mut latencies_ms = Vec::new();
+        let mut num_hits = 0;
+        let mut num_errors = 0;
+        let mut mismatches: BTreeSet<String> = cold_run
+            .map(|cold_run| cold_run.mismatches.iter().cloned().collect())
+            .unwrap_or_default();
+        for execution in &executions {
+            if execution.query_idx != query_idx {
+                continue;
+            }
+            match &execution.response_res {
+                Ok((execution_num_hits, execution_mismatches)) => {
+                    latencies_ms.push(execution.latency_ms);
+                    num_hits = *execution_num_hits;
+                    mismatches.extend(execution_mismatches.iter().cloned());
+                },
+                Err(_) => num_errors += 1,
+            }
+        }
+        let correct = query.expected.is_some().then_some(mismatches.is_empty());
+        if !mismatches.is_empty() {
+            num_incorrect_queries += 1;
+            warn!(query = query.name, mismatches = ?mismatches, "Query returned unexpected results");
+        }
+        let hot_latency = LatencyStats::from_latencies_ms(latencies_ms);
+        info!(query = query.name, num_hits, num_errors, cold_latency_ms, hot_latency = ?hot_latency, "Query done");
+        query_results.push(QueryResult {
+            name: query.name,
+            tags: query.tags,
+            query: engine_query.into_body(),
+            num_hits,
+            num_errors,
+            cold_latency_ms,
+            hot_latency,
+            correct,
+            mismatches,
+        });
+    }
+    info!(achieved_qps, latency = ?latency, num_errors, "Queries done");
+    LoadReport {
+        num_clients,
+        target_qps,
+        achieved_qps,
+        duration_secs: elapsed.as_secs_f64(),
+        latency,
+        num_errors,
+        error_rate,
+        num_incorrect_queries,
+        queries: query_results,
+    }
+}
+
+/// How the caches were dropped before the cold runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CacheDrop {
+    Command,
+    EngineApi,
+    /// The cold runs are merely the first runs of the queries.
+    None,
+}
+
+/// Drops the caches with `drop_caches_command` if set, or the engine's API.
+/// Falls back to `CacheDrop::None` if the engine cannot clear its caches.
+async fn drop_caches(
+    sink: &dyn Sink,
+    drop_caches_command: Option<&str>,
+    cache_drop: CacheDrop,
+) -> anyhow::Result<CacheDrop> {
+    match (drop_caches_command, cache_drop) {
+        (_, CacheDrop::None) => Ok(CacheDrop::None),
+        (Some(drop_caches_command), _) => {
+            let status = tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(drop_caches_command)
+                .status()
+                .await
+                .context("Failed to run the drop caches command")?;
+            if !status.success() {
+                bail!("Drop caches command {drop_caches_command:?} failed: {status}");
+            }
+            Ok(CacheDrop::Command)
+        },
+        (None, _) => match sink.clear_caches().await {
+            Ok(()) => Ok(CacheDrop::EngineApi),
+            Err(error) => {
+                warn!(error = ?error, "Failed to clear the engine caches, cold runs will only be first runs");
+                Ok(CacheDrop::None)
+            },
+        },
+    }
+}
+
+/// A query, along with its translation in the benchmarked engine's dialect.
+#[derive(Clone)]
+struct TranslatedQuery {
+    query: Query,
+    engine_query: EngineQuery,
+}
+
+impl TranslatedQuery {
+    /// Runs the query, checking the response against the expected results.
+    /// Returns the number of hits and the mismatches.
+    async fn run(&self, sink: &dyn Sink) -> anyhow::Result<(u64, Vec<String>)> {
+        let response: SearchResponse = sink.search(&self.engine_query).await?;
+        let mismatches = match &self.query.expected {
+            Some(expected) => expected.check(&response),
+            None => Vec::new(),
+        };
+        Ok((response.num_hits, mismatches))
+    }
+}
+
+/// One execution of a query by the load generator.
+struct QueryExecution {
+    query_idx: usize,
+    latency_ms: f64,
+    /// The number of hits and the differences with the expected results.
+    response_res: anyhow::Result<(u64, Vec<String>)>,
+}
+
+/// When the load generator stops issuing queries.
+#[derive(Debug, Copy, Clone)]
+enum LoadEnd<'a> {
+    /// After executing each query this many times.
+    Iterations(usize),
+    /// Once the flag is set.
+    Flag(&'a AtomicBool),
+}
+
+/// Executes the queries, interleaved, from `num_clients` concurrent clients
+/// until `end`. Returns the executions and the time it took to run them all.
+///
+/// With a target QPS, execution `i` is due `i / target_qps` seconds after the
+/// start, and its latency includes the time it waited past that for a client.
+async fn generate_load(
+    sink: &dyn Sink,
+    queries: &[TranslatedQuery],
+    end: LoadEnd<'_>,
+    num_clients: usize,
+    target_qps: Option<f64>,
+) -> (Vec<QueryExecution>, Duration) {
+    let next_execution = AtomicUsize::new(0);
+    let start = Instant::now();
+    let client = |client_id: usize| {
+        let next_execution = &next_execution;
+        async move {
+            let mut executions = Vec::new();
+            loop {
+                let execution_idx = next_execution.fetch_add(1, Ordering::Relaxed);
+                let ended = match end {
+                    LoadEnd::Iterations(iterations) => {
+                        execution_idx >= queries.len() * iterations
+                    },
+                    LoadEnd::Flag(stop) => stop.load(Ordering::Relaxed),
+                };
+                if ended {
+                    break;
+                }
+                let due = match target_qps {
+                    Some(target_qps) => {
+                        let due = start
+                            + Duration::from_secs_f64(execution_idx as f64 / target_qps);
+                        tokio::time::sleep_until(due.into()).await;
+                        due
+                    },
+                    None => Instant::now(),
+                };
+                let query_idx = execution_idx % queries.len();
+                let query = &queries[query_idx];
+                let response_res = query.run(sink).await;
+                if let Err(error) = &response_res {
+                    warn!(client_id, query = query.query.name, error = ?error, "Query failed");
+                }
+                executions.push(QueryExecution {
+                    query_idx,
+                    latency_ms: due.elapsed().as_secs_f64() * 1000.0,
+                    response_res,
+                });
+            }
+            executions
+        }
+    };
+    let executions = join_all((0..num_clients).map(client))
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    (executions, start.elapsed())
+}
+
+/// Generates a match-all query and term queries on the most frequent keyword-like
+/// value of the top-level fields of the sampled documents.
+fn generate_queries(docs: &[Value], max_generated_queries: usize) -> Vec<Query> {
+    let mut value_counts: BTreeMap<&str, BTreeMap<String, usize>> = BTreeMap::new();
+    for doc in docs {
+        let Some(fields) = doc.as_object() else {
+            continue;
+        };
+        for (field, value) in fields {
+            let term = match value {
+                Value::String(term)
+                    if term.len() <= MAX_TERM_LEN
+                        && !term.is_empty()
+                        && !term.contains(char::is_whitespace) =>
+                {
+                    term.clone()
+                },
+                Value::Number(number) if number.is_i64() || number.is_u64() => {
+                    number.to_string()
+                },
+                _ => continue,
+            };
+            *value_counts
+                .entry(field)
+                .or_default()
+                .entry(term)
+                .or_default() += 1;
+        }
+    }
+    let mut queries = vec![generated_query(
+        "match_all".to_string(),
+        "*".to_string(),
+        json!({ "match_all": {} }),
+    )];
+    for (field, counts) in value_counts.into_iter().take(max_generated_queries) {
+        let (value, _) = counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .expect("fields have at least one value");
+        queries.push(generated_query(
+            format!("term_{field}"),
+            format!("{field}:\"{}\"", value.replace('"', "\\\"")),
+            json!({ "match": { field: value } }),
+        ));
+    }
+    queries
+}
+
+fn generated_query(
+    name: String,
+    quickwit_query: String,
+    elasticsearch_query: Value,
+) -> Query {
+    Query {
+        name,
+        tags: vec!["generated".to_string()],
+        elasticsearch: Some(json!({
+            "query": elasticsearch_query,
+            "size": 10,
+            "track_total_hits": true,
+        })),
+        quickwit: Some(Value::String(quickwit_query)),
+        logql: None,
+        expected: None,
+    }
+}
+
+/// The first `num_sample_docs` documents of the dataset.
+pub async fn sample_docs(
+    dataset_uri: &str,
+    num_sample_docs: usize,
+    gcp_access_token: Option<&str>,
+    dataset_format: DatasetFormat,
+    csv_infer_types: bool,
+) -> anyhow::Result<Vec<Value>> {
+    let mut source = UriSource::new(dataset_uri)?;
+    if let Some(access_token) = gcp_access_token {
+        source = source.with_gcp_access_token(access_token);
+    }
+    source = source.with_dataset_format(dataset_format, csv_infer_types);
+    let mut docs = Vec::with_capacity(num_sample_docs);
+    for batch_res in source.batch_stream(DEFAULT_MAX_BODY_SIZE).await? {
+        let batch = batch_res?;
+        for line in batch.bytes.split(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            docs.push(
+                serde_json::from_slice(line)
+                    .context("Failed to parse document line as JSON")?,
+            );
+            if docs.len() >= num_sample_docs {
+                return Ok(docs);
+            }
+        }
+    }
+    Ok(docs)
+}
+
+/// A sink used to query `index` rather than to feed it.
+pub fn search_sink(
+    engine: Engine,
+    host: &str,
+    index: &str,
+    client: Client,
+) -> anyhow::Result<Box<dyn Sink>> {
+    let sink: Box<dyn Sink> = match engine {
+        Engine::Quickwit => Box::new(QuickwitSink::new(host, index, false, client)),
+        Engine::Elasticsearch => Box::new(ElasticsearchSink::new(
+            host,
+            index,
+            None,
+            Distribution::Elasticsearch,
+            client,
+        )),
+        Engine::Opensearch => Box::new(ElasticsearchSink::new(
+            host,
+            index,
+            None,
+            Distribution::Opensearch,
+            client,
+        )),
+        Engine::Loki => Box::new(LokiSink::new(host, client)),
+        _ => bail!("Queries are not supported for engine {engine}"),
+    };
+    Ok(sink)
+}
+
+/// Translates the queries in the engine's dialect, skipping the ones not
+/// written in it.
+fn translate_queries(
+    queries: Vec<Query>,
+    engine: Engine,
+) -> anyhow::Result<Vec<TranslatedQuery>> {
+    let mut translated_queries = Vec::with_capacity(queries.len());
+    for query in queries {
+        match translate(&query, engine)? {
+            Some(engine_query) => translated_queries.push(TranslatedQuery {
+                query,
+                engine_query,
+            }),
+            None => warn!(
+                query = query.name,
+                "Query not defined for {engine}, skipping it"
+            ),
+        }
+    }
+    if translated_queries.is_empty() {
+        bail!("No query to run on {engine}");
+    }
+    Ok(translated_queries)
+}
+
+/// Runs the queries once cold, then warms up and measures them hot.
+async fn run_query_benchmark(
+    args: &QueryArgs,
+    sink: &dyn Sink,
+    queries: Vec<TranslatedQuery>,
+    mut cache_drop: CacheDrop,
+) -> anyhow::Result<(LoadReport, CacheDrop)> {
+    let mut cold_runs = Vec::with_capacity(queries.len());
+    for translated_query in &queries {
+        let query = &translated_query.query;
+        cache_drop =
+            drop_caches(sink, args.drop_caches_command.as_deref(), cache_drop).await?;
+        let start = Instant::now();
+        let cold_run = match translated_query.run(sink).await {
+            Ok((_, mismatches)) => ColdRun {
+                latency_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+                mismatches,
+            },
+            Err(error) => {
+                warn!(query = query.name, error = ?error, "Cold query failed");
+                ColdRun {
+                    latency_ms: None,
+                    mismatches: Vec::new(),
+                }
+            },
+        };
+        info!(
+            query = query.name,
+            cold_latency_ms = cold_run.latency_ms,
+            "Cold query done"
+        );
+        cold_runs.push(cold_run);
+    }
+    if args.warmup_iterations > 0 {
+        info!(warmup_iterations = args.warmup_iterations, "Warming up");
+        generate_load(
+            sink,
+            &queries,
+            LoadEnd::Iterations(args.warmup_iterations),
+            args.num_clients,
+            None,
+        )
+        .await;
+    }
+
+    info!(
+        num_queries = queries.len(),
+        iterations = args.iterations,
+        num_clients = args.num_clients,
+        target_qps = args.target_qps,
+        "Running queries"
+    );
+    let (executions, elapsed) = generate_load(
+        sink,
+        &queries,
+        LoadEnd::Iterations(args.iterations),
+        args.num_clients,
+        args.target_qps,
+    )
+    .await;
+    let load_report = load_report(
+        queries,
+        &cold_runs,
+        executions,
+        elapsed,
+        args.num_clients,
+        args.target_qps,
+    );
+
+    Ok((load_report, cache_drop))
+}
+
+pub async fn run_queries(args: QueryArgs) -> anyhow::Result<()> {
+    let run_json = std::fs::read_to_string(&args.from_run)
+        .with_context(|| format!("Failed to read indexing run {:?}", args.from_run))?;
+    let run: IndexingRun = serde_json::from_str(&run_json)
+        .with_context(|| format!("Invalid indexing run {:?}", args.from_run))?;
+    let engine: Engine = run
+        .engine
+        .parse()
+        .map_err(|err: String| anyhow::anyhow!(err))?;
+    if args.num_clients == 0 {
+        bail!("--num-clients must be at least 1");
+    }
+    if args.target_qps.is_some_and(|target_qps| target_qps <= 0.0) {
+        bail!("--target-qps must be positive");
+    }
+    if !(0.0..=1.0).contains(&args.max_error_rate) {
+        bail!("--max-error-rate must be between 0 and 1");
+    }
+    let host = args.host.clone().unwrap_or_else(|| run.host.clone());
+    let sink = search_sink(engine, &host, &run.index, args.http_client.build_client()?)?;
+    let output_path = args.output_path.clone().unwrap_or_else(|| {
+        PathBuf::from(format!("query_results.{}", args.output_format.extension()))
+    });
+
+    let queries = match &args.query_suite {
+        Some(query_suite_path) => QuerySuite::load(query_suite_path)?.queries,
+        None => {
+            info!(
+                engine = run.engine,
+                index = run.index,
+                "Sampling documents from `{}` to generate queries",
+                run.dataset_uri
+            );
+            let dataset_format = match &run.dataset_format {
+                Some(dataset_format) => dataset_format
+                    .parse()
+                    .map_err(|err: String| anyhow::anyhow!(err))?,
+                None => DatasetFormat::detect(&run.dataset_uri),
+            };
+            let docs = sample_docs(
+                &run.dataset_uri,
+                args.num_sample_docs,
+                args.gcp_access_token.as_deref(),
+                dataset_format,
+                run.csv_infer_types,
+            )
+            .await?;
+            generate_queries(&docs, args.max_generated_queries)
+        },
+    };
+    let queries = translate_queries(queries, engine)?;
+    let build_info = sink.build_info().await?;
+
+    if args.runs == 0 {
+        bail!("--runs must be at least 1");
+    }
+    if args.runs > 1 && !args.append_output {
+        File::create(&output_path)
+            .with_context(|| format!("Failed to create results file {output_path:?}"))?;
+    }
+    // Each query's cold run follows a cache drop, so that it doesn't benefit
+    // from the caches warmed by the previous queries.
+    let mut cache_drop = if args.drop_caches_command.is_some() {
+        CacheDrop::Command
+    } else {
+        CacheDrop::EngineApi
+    };
+    let mut run_metrics: Vec<(&str, Vec<f64>)> =
+        ["achieved_qps", "p50_ms", "p90_ms", "p99_ms"]
+            .into_iter()
+            .map(|name| (name, Vec::new()))
+            .collect();
+    for run_idx in 0..args.runs {
+        if args.runs > 1 {
+            info!(run = run_idx + 1, runs = args.runs, "Starting run");
+        }
+        let load_report;
+        (load_report, cache_drop) =
+            run_query_benchmark(&args, &*sink, queries.clone(), cache_drop).await?;
+        run_metrics[0].1.push(load_report.achieved_qps);
+        if let Some(latency) = &load_report.latency {
+            run_metrics[1].1.push(latency.p50_ms);
+            run_metrics[2].1.push(latency.p90_ms);
+            run_metrics[3].1.push(latency.p99_ms);
+        }
+        let results = json!({
+            "from_run": run,
+            "tags": args.labels.tags(),
+            "key_value_tags": args.labels.key_value_tags(),
+            "comment": args.labels.comment,
+            "build_info": build_info,
+            "iterations": args.iterations,
+            "warmup_iterations": args.warmup_iterations,
+            "cache_drop": cache_drop,
+            "num_clients": load_report.num_clients,
+            "target_qps": load_report.target_qps,
+            "achieved_qps": load_report.achieved_qps,
+            "duration_secs": load_report.duration_secs,
+            "hot_latency": load_report.latency,
+            "num_errors": load_report.num_errors,
+            "error_rate": load_report.error_rate,
+            "num_incorrect_queries": load_report.num_incorrect_queries,
+            "queries": load_report.queries,
+        });
+        write_results(
+            &output_path,
+            args.output_format,
+            args.append_output || args.runs > 1,
+            &results,
+        )?;
+        if load_report.error_rate > args.max_error_rate {
+            bail!(
+                "{} query executions failed ({:.1}%), more than --max-error-rate allows",
+                load_report.num_errors,
+                load_report.error_rate * 100.0
+            );
+        }
+    }
+    if args.runs > 1 {
+        print_statistics(&run_metrics);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_report_errors() {
+        let query: Query = serde_json::from_value(json!({
+            "name": "match_all",
+            "quickwit": "*",
+        }))
+        .unwrap();
+        let engine_query = translate(&query, Engine::Quickwit).unwrap().unwrap();
+        let execution = |latency_ms: f64, failed: bool| QueryExecution {
+            query_idx: 0,
+            latency_ms,
+            response_res: if failed {
+                Err(anyhow::anyhow!("timeout"))
+            } else {
+                Ok((10, Vec::new()))
+            },
+        };
+        let load_report = load_report(
+            vec![TranslatedQuery {
+                query,
+                engine_query,
+            }],
+            &[],
+            vec![
+                execution(10.0, false),
+                execution(20.0, false),
+                execution(30_000.0, true),
+                execution(30.0, false),
+            ],
+            Duration::from_secs(1),
+            1,
+            None,
+        );
+        assert_eq!(load_report.num_errors, 1);
+        assert_eq!(load_report.error_rate, 0.25);
+        assert_eq!(load_report.achieved_qps, 3.0);
+        assert_eq!(load_report.latency.unwrap().max_ms, 30.0);
+        assert_eq!(load_report.queries[0].num_errors, 1);
+        assert_eq!(
+            load_report.queries[0].hot_latency.as_ref().unwrap().max_ms,
+            30.0
+        );
+    }
+
+    #[test]
+    fn test_generate_queries() {
+        let docs = vec![
+            json!({"level": "info", "status": 200, "message": "hello world"}),
+            json!({"level": "error", "status": 500}),
+            json!({"level": "error", "status": 200, "ratio": 0.5}),
+        ];
+        let queries = generate_queries(&docs, 10);
+        let names: Vec<&str> = queries.iter().map(|query| query.name.as_str()).collect();
+        assert_eq!(names, vec!["match_all", "term_level", "term_status"]);
+        assert_eq!(queries[0].quickwit, Some(json!("*")));
+        assert_eq!(queries[1].quickwit, Some(json!("level:\"error\"")));
+        assert_eq!(
+            queries[2].elasticsearch.as_ref().unwrap()["query"],
+            json!({"match": {"status": "200"}})
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::verify::Expected;
+
+/// A query, written in the dialect of each engine it can run on.
+///
+/// ```yaml
+/// name: term_level
+/// tags: [term]
+/// elasticsearch:
+///   query: {match: {level: error}}
+///   track_total_hits: true
+/// quickwit: "level:error"
+/// logql: '{app="generated-logs"} |= "error"'
+/// expected:
+///   num_hits: 1200
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Query {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// An Elasticsearch search request body, also used for OpenSearch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elasticsearch: Option<Value>,
+    /// A Quickwit query string, or a whole Quickwit search request body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quickwit: Option<Value>,
+    /// A LogQL query, or the whole set of parameters of a Loki `query_range`
+    /// request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logql: Option<Value>,
+    /// The results the query must return on every engine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Expected>,
+}
+
+/// A set of queries, defined once for all the engines.
+#[derive(Debug, Deserialize)]
+pub struct QuerySuite {
+    pub queries: Vec<Query>,
+}
+
+impl QuerySuite {
+    /// Loads a suite from a YAML or JSON file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let suite_yaml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read query suite {path:?}"))?;
+        // JSON documents are valid YAML.
+        serde_yaml::from_str(&suite_yaml)
+            .with_context(|| format!("Invalid query suite {path:?}"))
+    }
+}
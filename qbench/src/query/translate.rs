@@ -0,0 +1,99 @@
+use anyhow::bail;
+use qbench_core::engine::Engine;
+use qbench_core::query::EngineQuery;
+use serde_json::{json, Value};
+
+use super::Query;
+
+/// The number of hits requested when a query doesn't specify it.
+const DEFAULT_MAX_HITS: u64 = 10;
+
+/// Picks the dialect of `engine` among the ones the query is written in.
+/// Returns None if the query is not written in it.
+pub fn translate(query: &Query, engine: Engine) -> anyhow::Result<Option<EngineQuery>> {
+    let engine_query = match engine {
+        Engine::Quickwit => query
+            .quickwit
+            .as_ref()
+            .map(|quickwit_query| {
+                request_body(&query.name, quickwit_query, |query_string| {
+                    json!({ "query": query_string, "max_hits": DEFAULT_MAX_HITS })
+                })
+            })
+            .transpose()?
+            .map(EngineQuery::Quickwit),
+        Engine::Elasticsearch | Engine::Opensearch | Engine::EsCompatible => query
+            .elasticsearch
+            .as_ref()
+            .map(|elasticsearch_query| {
+                if !elasticsearch_query.is_object() {
+                    bail!(
+                        "The Elasticsearch query of {:?} must be a search request body",
+                        query.name
+                    );
+                }
+                Ok(elasticsearch_query.clone())
+            })
+            .transpose()?
+            .map(EngineQuery::Elasticsearch),
+        Engine::Loki => query
+            .logql
+            .as_ref()
+            .map(|logql_query| {
+                request_body(&query.name, logql_query, |logql| {
+                    json!({ "query": logql, "limit": DEFAULT_MAX_HITS })
+                })
+            })
+            .transpose()?
+            .map(EngineQuery::Loki),
+        _ => bail!("Queries are not supported for engine {engine}"),
+    };
+    Ok(engine_query)
+}
+
+/// Dialects accept either a bare query string, wrapped by `from_string`, or a
+/// whole request.
+fn request_body(
+    query_name: &str,
+    query: &Value,
+    from_string: impl FnOnce(&str) -> Value,
+) -> anyhow::Result<Value> {
+    match query {
+        Value::String(query_string) => Ok(from_string(query_string)),
+        Value::Object(_) => Ok(query.clone()),
+        _ => bail!("The query {query_name:?} must be a string or an object"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate() {
+        let query: Query = serde_yaml::from_str(
+            r#"
+            name: term_level
+            tags: [term]
+            elasticsearch:
+              query: {match: {level: error}}
+            quickwit: "level:error"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            translate(&query, Engine::Quickwit).unwrap(),
+            Some(EngineQuery::Quickwit(
+                json!({"query": "level:error", "max_hits": 10})
+            ))
+        );
+        assert_eq!(
+            translate(&query, Engine::Opensearch).unwrap(),
+            Some(EngineQuery::Elasticsearch(
+                json!({"query": {"match": {"level": "error"}}})
+            ))
+        );
+        assert_eq!(translate(&query, Engine::Loki).unwrap(), None);
+        assert!(translate(&query, Engine::Kusto).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use qbench_core::sink::SearchResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The results a query must return, checked on every execution.
+///
+/// ```yaml
+/// expected:
+///   num_hits: 1200
+///   top_k_ids: ["a1", "b7"]
+///   aggregations:
+///     /avg_duration/value: 12.5
+///     /levels/buckets/0/doc_count: 300
+///   tolerance: 0.01
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expected {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_hits: Option<u64>,
+    /// The ids of the first returned documents, in order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k_ids: Option<Vec<String>>,
+    /// The document field holding the ids compared to `top_k_ids`. By
+    /// default, the ids assigned by the engine (Elasticsearch's `_id`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_field: Option<String>,
+    /// Numeric values of the aggregations response, keyed by their JSON
+    /// pointer.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aggregations: BTreeMap<String, f64>,
+    /// The relative difference allowed between the aggregation values and the
+    /// expected ones.
+    #[serde(default)]
+    pub tolerance: f64,
+}
+
+impl Expected {
+    /// Returns a description of each difference between the response and the
+    /// expected results.
+    pub fn check(&self, response: &SearchResponse) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if let Some(num_hits) = self.num_hits {
+            if response.num_hits != num_hits {
+                mismatches.push(format!(
+                    "expected {num_hits} hits, got {}",
+                    response.num_hits
+                ));
+            }
+        }
+        if let Some(top_k_ids) = &self.top_k_ids {
+            let doc_ids: Vec<String> = match &self.id_field {
+                Some(id_field) => response
+                    .docs
+                    .iter()
+                    .map(|doc| match &doc[id_field] {
+                        Value::String(doc_id) => doc_id.clone(),
+                        doc_id => doc_id.to_string(),
+                    })
+                    .collect(),
+                None => response.doc_ids.clone(),
+            };
+            let top_doc_ids = &doc_ids[..top_k_ids.len().min(doc_ids.len())];
+            if top_doc_ids != top_k_ids.as_slice() {
+                mismatches.push(format!(
+                    "expected top ids {top_k_ids:?}, got {top_doc_ids:?}"
+                ));
+            }
+        }
+        for (pointer, expected_value) in &self.aggregations {
+            let value = response
+                .aggregations
+                .as_ref()
+                .and_then(|aggregations| aggregations.pointer(pointer))
+                .and_then(Value::as_f64);
+            match value {
+                Some(value)
+                    if (value - expected_value).abs()
+                        <= self.tolerance * expected_value.abs() => {},
+                Some(value) => mismatches.push(format!(
+                    "expected {expected_value} at {pointer}, got {value}"
+                )),
+                None => {
+                    mismatches.push(format!("missing aggregation value at {pointer}"))
+                },
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_expected_check() {
+        let expected: Expected = serde_yaml::from_str(
+            r#"
+            num_hits: 3
+            top_k_ids: ["a", "b"]
+            id_field: id
+            aggregations:
+              /avg/value: 10.0
+              /levels/buckets/0/doc_count: 2
+            tolerance: 0.05
+            "#,
+        )
+        .unwrap();
+        let response = SearchResponse {
+            num_hits: 3,
+            doc_ids: Vec::new(),
+            docs: vec![json!({"id": "a"}), json!({"id": "b"}), json!({"id": "c"})],
+            aggregations: Some(json!({
+                "avg": {"value": 10.4},
+                "levels": {"buckets": [{"key": "info", "doc_count": 2}]},
+            })),
+        };
+        assert!(expected.check(&response).is_empty());
+
+        let response = SearchResponse {
+            num_hits: 4,
+            docs: vec![json!({"id": "b"})],
+            aggregations: Some(json!({"avg": {"value": 11.0}})),
+            ..Default::default()
+        };
+        assert_eq!(
+            expected.check(&response),
+            vec![
+                "expected 3 hits, got 4",
+                "expected top ids [\"a\", \"b\"], got [\"b\"]",
+                "expected 10 at /avg/value, got 11",
+                "missing aggregation value at /levels/buckets/0/doc_count",
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use chrono::{SecondsFormat, Utc};
+use qbench_core::engine::Engine;
+use qbench_core::query::{LatencyStats, VisibilityReport};
+use qbench_core::sink::Sink;
+use qbench_core::source::DocumentBatch;
+use reqwest::Client;
+use serde_json::{json, Map, Value};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::{search_sink, EngineQuery};
+
+/// How often the sentinel document is searched for.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Measures how long the documents take to become searchable while the index
+/// is being fed: at every interval, a sentinel document is sent, and searched
+/// for until it is visible.
+///
+/// The sentinel is a copy of the first document ingested, so that it fits
+/// the index's schema, with a unique token in `field` and its `timestamp`
+/// field, if any, set to the current time. The sentinels are indexed along
+/// with the dataset.
+pub struct VisibilityProbe {
+    template: Arc<OnceLock<Vec<u8>>>,
+    stop: CancellationToken,
+    handle: JoinHandle<VisibilityReport>,
+}
+
+impl VisibilityProbe {
+    pub fn start(
+        interval: Duration,
+        timeout: Duration,
+        field: &str,
+        engine: Engine,
+        host: &str,
+        index: &str,
+        client: Client,
+    ) -> anyhow::Result<Self> {
+        if interval.is_zero() {
+            bail!("--visibility-probe-interval-secs must be at least 1");
+        }
+        // Fails on the engines that can't be searched.
+        sentinel_query(engine, field, "")?;
+        let sink = search_sink(engine, host, index, client)?;
+        let template: Arc<OnceLock<Vec<u8>>> = Arc::new(OnceLock::new());
+        // Cancels the wait for the next probe too, which can be long.
+        let stop = CancellationToken::new();
+        info!(?interval, field, "Probing the visibility of the documents");
+        let handle = tokio::spawn({
+            let template = template.clone();
+            let stop = stop.clone();
+            let field = field.to_string();
+            async move {
+                let mut latencies_ms = Vec::new();
+                let mut num_timed_out = 0;
+                let mut num_errors = 0;
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    tokio::select! {
+                        _ = ticker.tick() => {},
+                        _ = stop.cancelled() => break,
+                    }
+                    let Some(template) = template.get() else {
+                        continue;
+                    };
+                    match probe(&*sink, engine, template, &field, timeout, &stop).await {
+                        Ok(Some(latency)) => {
+                            latencies_ms.push(latency.as_secs_f64() * 1000.0)
+                        },
+                        Ok(None) if stop.is_cancelled() => {},
+                        Ok(None) => {
+                            warn!("Sentinel document not visible after {timeout:?}");
+                            num_timed_out += 1;
+                        },
+                        Err(error) => {
+                            warn!(error=?error, "Visibility probe failed");
+                            num_errors += 1;
+                        },
+                    }
+                }
+                VisibilityReport {
+                    interval_secs: interval.as_secs(),
+                    timeout_secs: timeout.as_secs(),
+                    field,
+                    num_probes: latencies_ms.len() + num_timed_out + num_errors,
+                    num_timed_out,
+                    num_errors,
+                    visibility_latency: LatencyStats::from_latencies_ms(latencies_ms),
+                }
+            }
+        });
+        Ok(Self {
+            template,
+            stop,
+            handle,
+        })
+    }
+
+    /// Takes the first document of the batch as the template of the
+    /// sentinels, if there is none yet.
+    pub fn observe_batch(&self, batch: &[u8]) {
+        if self.template.get().is_some() {
+            return;
+        }
+        if let Some(first_doc) = batch
+            .split(|byte| *byte == b'\n')
+            .find(|line| !line.trim_ascii().is_empty())
+        {
+            let _ = self.template.set(first_doc.to_vec());
+        }
+    }
+
+    /// Stops probing, abandoning the probe in flight.
+    pub async fn finish(self) -> anyhow::Result<VisibilityReport> {
+        self.stop.cancel();
+        Ok(self.handle.await?)
+    }
+}
+
+/// Sends a sentinel document and returns how long it took to be visible, None
+/// if it wasn't after `timeout` or if the probe was stopped.
+async fn probe(
+    sink: &dyn Sink,
+    engine: Engine,
+    template: &[u8],
+    field: &str,
+    timeout: Duration,
+    stop: &CancellationToken,
+) -> anyhow::Result<Option<Duration>> {
+    let token = format!("qbenchsentinel{:016x}", rand::random::<u64>());
+    let sentinel = sentinel_doc(template, field, &token)?;
+    let query = sentinel_query(engine, field, &token)?;
+    sink.send(&DocumentBatch {
+        bytes: sentinel.into(),
+        last: false,
+    })
+    .await
+    .context("Failed to send the sentinel document")?;
+    let acknowledged = Instant::now();
+    while acknowledged.elapsed() < timeout && !stop.is_cancelled() {
+        if sink.search(&query).await?.num_hits > 0 {
+            return Ok(Some(acknowledged.elapsed()));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Ok(None)
+}
+
+/// The template document with `token` in `field`, and the current time in its
+/// `timestamp` field if it has one.
+fn sentinel_doc(template: &[u8], field: &str, token: &str) -> anyhow::Result<Vec<u8>> {
+    let mut doc: Map<String, Value> = serde_json::from_slice(template)
+        .context("Failed to parse the sentinel template as JSON")?;
+    if doc.get("timestamp").is_some_and(Value::is_string) {
+        doc.insert(
+            "timestamp".to_string(),
+            Value::String(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        );
+    }
+    doc.insert(field.to_string(), Value::String(token.to_string()));
+    let mut sentinel = serde_json::to_vec(&doc)?;
+    sentinel.push(b'\n');
+    Ok(sentinel)
+}
+
+/// The query matching the sentinel document with `token` in `field`.
+fn sentinel_query(
+    engine: Engine,
+    field: &str,
+    token: &str,
+) -> anyhow::Result<EngineQuery> {
+    let engine_query = match engine {
+        Engine::Quickwit => EngineQuery::Quickwit(json!({
+            "query": format!("{field}:{token}"),
+            "max_hits": 0,
+        })),
+        Engine::Elasticsearch | Engine::Opensearch => {
+            EngineQuery::Elasticsearch(json!({
+                "query": {"match": {field: token}},
+                "size": 0,
+                "track_total_hits": true,
+            }))
+        },
+        // The log lines are the documents, whatever the field.
+        Engine::Loki => EngineQuery::Loki(json!({
+            "query": format!(r#"{{label="benchmark"}} |= "{token}""#),
+            "limit": 1,
+        })),
+        _ => bail!("Probing the visibility is not supported for engine {engine}"),
+    };
+    Ok(engine_query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentinel() {
+        let template = br#"{"timestamp": "2024-01-01T00:00:00Z", "message": "hello", "level": "info"}"#;
+        let sentinel = sentinel_doc(template, "message", "qbenchsentinel42").unwrap();
+        assert!(sentinel.ends_with(b"\n"));
+        let sentinel: Value = serde_json::from_slice(&sentinel).unwrap();
+        assert_eq!(sentinel["message"], "qbenchsentinel42");
+        assert_eq!(sentinel["level"], "info");
+        assert_ne!(sentinel["timestamp"], "2024-01-01T00:00:00Z");
+
+        assert_eq!(
+            sentinel_query(Engine::Quickwit, "message", "qbenchsentinel42").unwrap(),
+            EngineQuery::Quickwit(
+                json!({"query": "message:qbenchsentinel42", "max_hits": 0})
+            )
+        );
+        assert_eq!(
+            sentinel_query(Engine::Loki, "message", "qbenchsentinel42")
+                .unwrap()
+                .into_body()["query"],
+            r#"{label="benchmark"} |= "qbenchsentinel42""#
+        );
+        assert!(sentinel_query(Engine::Kusto, "message", "").is_err());
+    }
+}
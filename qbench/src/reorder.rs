@@ -0,0 +1,324 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use bytes::Bytes;
+use chrono::DateTime;
+use qbench_core::run_results::reports::ReorderReport;
+use qbench_core::source::DocumentBatch;
+use serde_json::Value;
+
+/// The timestamp of a document, in seconds since the Unix epoch. Documents
+/// without a timestamp come first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Timestamp(f64);
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Like Quickwit's `unix_timestamp` input format, the precision of integer
+/// timestamps is inferred from their magnitude, and floats are in seconds.
+fn unix_timestamp_secs(number: &serde_json::Number) -> Option<f64> {
+    let Some(timestamp) = number.as_i64() else {
+        return number.as_f64();
+    };
+    let divisor = match timestamp.unsigned_abs() {
+        0..=99_999_999_999 => 1.0,
+        100_000_000_000..=99_999_999_999_999 => 1_000.0,
+        100_000_000_000_000..=99_999_999_999_999_999 => 1_000_000.0,
+        _ => 1_000_000_000.0,
+    };
+    Some(timestamp as f64 / divisor)
+}
+
+fn parse_timestamp(value: &Value) -> Option<Timestamp> {
+    match value {
+        Value::Number(number) => unix_timestamp_secs(number).map(Timestamp),
+        Value::String(text) => DateTime::parse_from_rfc3339(text).ok().map(|datetime| {
+            Timestamp(
+                datetime.timestamp() as f64
+                    + datetime.timestamp_subsec_nanos() as f64 / 1_000_000_000.0,
+            )
+        }),
+        _ => None,
+    }
+}
+
+/// Sorts the documents by timestamp within a sliding window before they are
+/// sent, to compare how engines deal with ordered and unordered ingestion.
+///
+/// The buffer holds up to `window_num_docs` documents and sends the oldest
+/// one whenever it overflows, so a document is only reordered with the
+/// documents read within a window of it. It is drained by the last batch, or
+/// by `flush` when the ingestion stops early.
+pub struct ReorderBuffer {
+    timestamp_field: String,
+    window_num_docs: usize,
+    /// The buffered documents, oldest first. The sequence number keeps the
+    /// read order of the documents with the same timestamp.
+    docs: BinaryHeap<Reverse<(Timestamp, u64, Bytes)>>,
+    num_docs: u64,
+    num_docs_without_timestamp: u64,
+    max_read_timestamp: Option<Timestamp>,
+    num_out_of_order_docs_read: u64,
+    max_sent_timestamp: Option<Timestamp>,
+    num_out_of_order_docs_sent: u64,
+    num_flushed_docs: u64,
+}
+
+impl ReorderBuffer {
+    pub fn new(timestamp_field: String, window_num_docs: usize) -> Self {
+        Self {
+            timestamp_field,
+            window_num_docs,
+            docs: BinaryHeap::with_capacity(window_num_docs + 1),
+            num_docs: 0,
+            num_docs_without_timestamp: 0,
+            max_read_timestamp: None,
+            num_out_of_order_docs_read: 0,
+            max_sent_timestamp: None,
+            num_out_of_order_docs_sent: 0,
+            num_flushed_docs: 0,
+        }
+    }
+
+    pub fn report(&self) -> ReorderReport {
+        ReorderReport {
+            timestamp_field: self.timestamp_field.clone(),
+            window_num_docs: self.window_num_docs,
+            num_docs: self.num_docs,
+            num_docs_without_timestamp: self.num_docs_without_timestamp,
+            num_out_of_order_docs_read: self.num_out_of_order_docs_read,
+            num_out_of_order_docs_sent: self.num_out_of_order_docs_sent,
+            num_flushed_docs: self.num_flushed_docs,
+        }
+    }
+
+    fn timestamp(&self, doc: &[u8]) -> Option<Timestamp> {
+        let doc: Value = serde_json::from_slice(doc).ok()?;
+        doc.get(&self.timestamp_field).and_then(parse_timestamp)
+    }
+
+    fn send_oldest_doc(&mut self, payload: &mut Vec<u8>) {
+        let Some(Reverse((timestamp, _, doc))) = self.docs.pop() else {
+            return;
+        };
+        if self
+            .max_sent_timestamp
+            .is_some_and(|max_sent_timestamp| timestamp < max_sent_timestamp)
+        {
+            self.num_out_of_order_docs_sent += 1;
+        }
+        self.max_sent_timestamp = self.max_sent_timestamp.max(Some(timestamp));
+        payload.extend_from_slice(&doc);
+        payload.push(b'\n');
+    }
+
+    /// Replaces the documents of the batch by the oldest buffered ones, as
+    /// many as it overflowed the window with.
+    pub fn apply(&mut self, document_batch: &mut DocumentBatch) {
+        let mut payload = Vec::with_capacity(document_batch.bytes.len());
+        for line in document_batch.bytes.split(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            let timestamp = match self.timestamp(line) {
+                Some(timestamp) => timestamp,
+                None => {
+                    self.num_docs_without_timestamp += 1;
+                    Timestamp(f64::NEG_INFINITY)
+                },
+            };
+            if self
+                .max_read_timestamp
+                .is_some_and(|max_read_timestamp| timestamp < max_read_timestamp)
+            {
+                self.num_out_of_order_docs_read += 1;
+            }
+            self.max_read_timestamp = self.max_read_timestamp.max(Some(timestamp));
+            self.docs.push(Reverse((
+                timestamp,
+                self.num_docs,
+                document_batch.bytes.slice_ref(line),
+            )));
+            self.num_docs += 1;
+            if self.docs.len() > self.window_num_docs {
+                self.send_oldest_doc(&mut payload);
+            }
+        }
+        if document_batch.last {
+            while !self.docs.is_empty() {
+                self.send_oldest_doc(&mut payload);
+            }
+        }
+        document_batch.bytes = payload.into();
+    }
+
+    /// The batch of the documents not sent when the ingestion stops before
+    /// the last batch, oldest first: `unsent_docs`, taken out of the buffer by
+    /// `apply` but not sent, then the buffered ones. The documents stop at the
+    /// first one for which `fits` is false given the size of the batch. None
+    /// if there are none.
+    pub fn flush(
+        &mut self,
+        unsent_docs: &[u8],
+        fits: impl Fn(u64) -> bool,
+    ) -> Option<DocumentBatch> {
+        let mut payload = Vec::new();
+        let mut num_flushed_docs = 0;
+        let mut unsent_docs = unsent_docs
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.trim_ascii().is_empty());
+        let all_unsent_docs_fit = unsent_docs.all(|doc| {
+            if !fits((payload.len() + doc.len() + 1) as u64) {
+                return false;
+            }
+            payload.extend_from_slice(doc);
+            payload.push(b'\n');
+            num_flushed_docs += 1;
+            true
+        });
+        if all_unsent_docs_fit {
+            while let Some(Reverse((_, _, doc))) = self.docs.peek() {
+                if !fits((payload.len() + doc.len() + 1) as u64) {
+                    break;
+                }
+                self.send_oldest_doc(&mut payload);
+                num_flushed_docs += 1;
+            }
+        }
+        if payload.is_empty() {
+            return None;
+        }
+        self.num_flushed_docs += num_flushed_docs;
+        Some(DocumentBatch {
+            bytes: payload.into(),
+            last: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(
+        reorder_buffer: &mut ReorderBuffer,
+        docs: &[&str],
+        last: bool,
+    ) -> Vec<String> {
+        let mut batch = DocumentBatch {
+            bytes: Bytes::from(docs.join("\n")),
+            last,
+        };
+        reorder_buffer.apply(&mut batch);
+        String::from_utf8(batch.bytes.to_vec())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder_buffer() {
+        let mut reorder_buffer = ReorderBuffer::new("ts".to_string(), 2);
+        let sent_docs = send(
+            &mut reorder_buffer,
+            &[
+                r#"{"ts": 3}"#,
+                r#"{"ts": 1}"#,
+                r#"{"ts": "1970-01-01T00:00:02Z"}"#,
+                r#"{"ts": 5}"#,
+            ],
+            false,
+        );
+        assert_eq!(
+            sent_docs,
+            [r#"{"ts": 1}"#, r#"{"ts": "1970-01-01T00:00:02Z"}"#]
+        );
+        let sent_docs = send(
+            &mut reorder_buffer,
+            &[r#"{"ts": 0}"#, r#"{"msg": "no ts"}"#, r#"{"ts": 4}"#],
+            true,
+        );
+        assert_eq!(
+            sent_docs,
+            [
+                r#"{"ts": 0}"#,
+                r#"{"msg": "no ts"}"#,
+                r#"{"ts": 3}"#,
+                r#"{"ts": 4}"#,
+                r#"{"ts": 5}"#,
+            ]
+        );
+        let report = reorder_buffer.report();
+        assert_eq!(report.num_docs, 7);
+        assert_eq!(report.num_docs_without_timestamp, 1);
+        assert_eq!(report.num_out_of_order_docs_read, 5);
+        // 0 and the document without a timestamp were read too late.
+        assert_eq!(report.num_out_of_order_docs_sent, 2);
+        assert_eq!(report.num_flushed_docs, 0);
+        assert!(reorder_buffer.flush(b"", |_| true).is_none());
+    }
+
+    #[test]
+    fn test_reorder_buffer_flush() {
+        let mut reorder_buffer = ReorderBuffer::new("ts".to_string(), 3);
+        let sent_docs = send(
+            &mut reorder_buffer,
+            &[
+                r#"{"ts": 2}"#,
+                r#"{"ts": 1}"#,
+                r#"{"ts": 3}"#,
+                r#"{"ts": 0}"#,
+            ],
+            false,
+        );
+        assert_eq!(sent_docs, [r#"{"ts": 0}"#]);
+        // The batch of `{"ts": 0}` was not sent, the budget allows 3 documents.
+        let flushed_batch = reorder_buffer
+            .flush(b"{\"ts\": 0}\n", |num_bytes| num_bytes <= 30)
+            .unwrap();
+        assert_eq!(
+            flushed_batch.bytes,
+            "{\"ts\": 0}\n{\"ts\": 1}\n{\"ts\": 2}\n"
+        );
+        assert_eq!(reorder_buffer.report().num_flushed_docs, 3);
+        let flushed_batch = reorder_buffer.flush(b"", |_| true).unwrap();
+        assert_eq!(flushed_batch.bytes, "{\"ts\": 3}\n");
+        assert_eq!(reorder_buffer.report().num_flushed_docs, 4);
+        assert!(reorder_buffer.flush(b"", |_| true).is_none());
+    }
+
+    #[test]
+    fn test_reorder_buffer_timestamp_units() {
+        let mut reorder_buffer = ReorderBuffer::new("ts".to_string(), 3);
+        let sent_docs = send(
+            &mut reorder_buffer,
+            &[
+                r#"{"ts": 1700000003000}"#,
+                r#"{"ts": "2023-11-14T22:13:21Z"}"#,
+                r#"{"ts": 1700000002.5}"#,
+            ],
+            true,
+        );
+        assert_eq!(
+            sent_docs,
+            [
+                r#"{"ts": "2023-11-14T22:13:21Z"}"#,
+                r#"{"ts": 1700000002.5}"#,
+                r#"{"ts": 1700000003000}"#,
+            ]
+        );
+    }
+}
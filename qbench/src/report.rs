@@ -0,0 +1,679 @@
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use clap::Args;
+use qbench_core::results::{read_results, results_format, OutputFormat};
+use qbench_core::run_results::RunResults;
+use serde::Deserialize;
+
+use crate::stats::Summary;
+
+const COLUMNS: [&str; 9] = [
+    "engine",
+    "index",
+    "num_docs",
+    "ingested_mb",
+    "duration_secs",
+    "mb_per_sec",
+    "docs_per_sec",
+    "num_splits",
+    "mixed_p50_ms",
+];
+
+/// How the summary tables are written: aligned text for the terminal, CSV, or
+/// Markdown to paste in GitHub issues and blog posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Text,
+    Csv,
+    Markdown,
+}
+
+impl FromStr for SummaryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(SummaryFormat::Text),
+            "csv" => Ok(SummaryFormat::Csv),
+            "markdown" | "md" => Ok(SummaryFormat::Markdown),
+            _ => Err(format!("Unknown summary format {s:?}")),
+        }
+    }
+}
+
+impl SummaryFormat {
+    /// Guesses the format from the extension of a summary file.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => SummaryFormat::Csv,
+            Some("md" | "markdown") => SummaryFormat::Markdown,
+            _ => SummaryFormat::Text,
+        }
+    }
+
+    /// Formats a table whose first `num_text_columns` columns are text and
+    /// the other ones numbers.
+    pub fn format(
+        &self,
+        header: &[String],
+        rows: &[Vec<String>],
+        num_text_columns: usize,
+    ) -> String {
+        match self {
+            SummaryFormat::Text => format_table(header, rows, num_text_columns),
+            SummaryFormat::Csv => format_csv(header, rows),
+            SummaryFormat::Markdown => format_markdown(header, rows, num_text_columns),
+        }
+    }
+}
+
+/// A regression threshold of `--threshold`, written `metric=pct`.
+#[derive(Debug, Clone)]
+pub struct MetricThreshold {
+    metric: String,
+    max_regression_pct: f64,
+}
+
+impl FromStr for MetricThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((metric, max_regression_pct)) = s.split_once('=') else {
+            return Err(format!("Expected `metric=pct`, got {s:?}"));
+        };
+        if !regression_metrics()
+            .iter()
+            .any(|regression_metric| regression_metric.name == metric)
+        {
+            return Err(format!("Unknown metric {metric:?}"));
+        }
+        Ok(MetricThreshold {
+            metric: metric.to_string(),
+            max_regression_pct: max_regression_pct
+                .parse()
+                .map_err(|_| format!("Invalid threshold {max_regression_pct:?}"))?,
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    #[arg(required_unless_present = "baseline", conflicts_with = "baseline")]
+    /// The indexing results file, possibly collecting several runs.
+    path: Option<PathBuf>,
+
+    #[arg(long)]
+    /// The format of the results file: "json", "yaml" or "msgpack". Guessed
+    /// from the file extension by default.
+    format: Option<OutputFormat>,
+
+    #[arg(long)]
+    /// Print the statistics of each engine and index over their repeated runs,
+    /// instead of one line per run.
+    aggregate: bool,
+
+    #[arg(long, requires = "candidate", conflicts_with = "aggregate")]
+    /// Compare the runs of `--candidate` to the runs of this results file,
+    /// and fail if one of their metrics regressed, e.g. in CI.
+    baseline: Option<PathBuf>,
+
+    #[arg(long, requires = "baseline")]
+    /// The results file compared to `--baseline`.
+    candidate: Option<PathBuf>,
+
+    #[arg(long, default_value_t = 5.0, requires = "baseline")]
+    /// The change of a metric for the worse, in percent, over which the
+    /// candidate regressed.
+    max_regression_pct: f64,
+
+    #[arg(long = "threshold", value_delimiter = ',', requires = "baseline")]
+    /// The comma-separated thresholds of metrics overriding
+    /// `--max-regression-pct`, e.g. `mb_per_sec=10,indexed_mb=2`.
+    thresholds: Vec<MetricThreshold>,
+
+    #[arg(long, default_value = "text")]
+    /// The format of the printed tables: "text", "csv" or "markdown".
+    summary_format: SummaryFormat,
+}
+
+/// Prints a table of the main figures of each run of a results file, or
+/// compares the runs of a candidate results file to a baseline.
+pub fn report(args: ReportArgs) -> anyhow::Result<()> {
+    let (Some(baseline_path), Some(candidate_path)) = (&args.baseline, &args.candidate)
+    else {
+        let path = args.path.as_deref().expect("Required without --baseline");
+        return report_runs(path, args.format, args.aggregate, args.summary_format);
+    };
+    let baseline_runs =
+        read_runs(baseline_path, results_format(baseline_path, args.format)?)?;
+    let candidate_runs =
+        read_runs(candidate_path, results_format(candidate_path, args.format)?)?;
+    let baseline_groups = group_runs(&baseline_runs);
+    let candidate_groups = group_runs(&candidate_runs);
+    if baseline_groups.is_empty() {
+        bail!("No finished run in the baseline results");
+    }
+    let group_keys = |groups: &[RunGroup]| -> Vec<String> {
+        groups
+            .iter()
+            .map(|((engine, index), _)| format!("{engine} {index}"))
+            .collect()
+    };
+    if group_keys(&baseline_groups) != group_keys(&candidate_groups) {
+        bail!(
+            "The baseline runs ({}) and the candidate runs ({}) are not of the same \
+             engines and indexes",
+            group_keys(&baseline_groups).join(", "),
+            group_keys(&candidate_groups).join(", ")
+        );
+    }
+    let mut regressed_metrics = Vec::new();
+    for (((engine, index), baseline_runs), (_, candidate_runs)) in
+        baseline_groups.iter().zip(&candidate_groups)
+    {
+        println!(
+            "{engine} {index}: baseline ({} runs), candidate ({} runs)",
+            baseline_runs.len(),
+            candidate_runs.len()
+        );
+        let metric_deltas = metric_deltas(&args, baseline_runs, candidate_runs);
+        print!(
+            "{}",
+            metric_deltas_table(&metric_deltas, args.summary_format)
+        );
+        regressed_metrics.extend(
+            metric_deltas
+                .iter()
+                .filter(|metric_delta| metric_delta.regressed)
+                .map(|metric_delta| {
+                    let missing = if metric_delta.candidate.is_none() {
+                        ", missing"
+                    } else {
+                        ""
+                    };
+                    format!("{} ({engine} {index}{missing})", metric_delta.name)
+                }),
+        );
+    }
+    if !regressed_metrics.is_empty() {
+        bail!(
+            "The candidate regressed on {}",
+            regressed_metrics.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The runs of an engine and index, in the order of their first run.
+type RunGroup<'a> = ((&'a str, &'a str), Vec<&'a RunResults>);
+
+fn group_runs(runs: &[RunResults]) -> Vec<RunGroup<'_>> {
+    let mut groups: Vec<RunGroup> = Vec::new();
+    for run in runs {
+        let key = (run.engine.as_str(), run.index.as_str());
+        match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+            Some((_, group_runs)) => group_runs.push(run),
+            None => groups.push((key, vec![run])),
+        }
+    }
+    groups
+}
+
+/// Compares the metrics of the candidate runs to the baseline runs.
+fn metric_deltas(
+    args: &ReportArgs,
+    baseline_runs: &[&RunResults],
+    candidate_runs: &[&RunResults],
+) -> Vec<MetricDelta> {
+    regression_metrics()
+        .into_iter()
+        .filter_map(|regression_metric| {
+            let max_regression_pct = args
+                .thresholds
+                .iter()
+                .rev()
+                .find(|threshold| threshold.metric == regression_metric.name)
+                .map_or(args.max_regression_pct, |threshold| {
+                    threshold.max_regression_pct
+                });
+            let values = |runs: &[&RunResults]| -> Vec<f64> {
+                runs.iter()
+                    .filter_map(|run| (regression_metric.value)(run))
+                    .collect()
+            };
+            metric_delta(
+                &regression_metric,
+                &values(baseline_runs),
+                &values(candidate_runs),
+                max_regression_pct,
+            )
+        })
+        .collect()
+}
+
+fn metric_deltas_table(
+    metric_deltas: &[MetricDelta],
+    summary_format: SummaryFormat,
+) -> String {
+    let header: Vec<String> = [
+        "metric",
+        "baseline",
+        "candidate",
+        "change",
+        "threshold",
+        "status",
+    ]
+    .iter()
+    .map(|column| column.to_string())
+    .collect();
+    let rows: Vec<Vec<String>> = metric_deltas
+        .iter()
+        .map(|metric_delta| {
+            vec![
+                metric_delta.name.to_string(),
+                format!("{:.2}", metric_delta.baseline),
+                metric_delta
+                    .candidate
+                    .map(|candidate| format!("{candidate:.2}"))
+                    .unwrap_or_else(|| "-".to_string()),
+                metric_delta
+                    .change_pct
+                    .map(|change_pct| format!("{change_pct:+.1}%"))
+                    .unwrap_or_else(|| "-".to_string()),
+                format!("{:.1}%", metric_delta.max_regression_pct),
+                match (metric_delta.candidate, metric_delta.regressed) {
+                    (None, _) => "MISSING".to_string(),
+                    (Some(_), true) => "REGRESSION".to_string(),
+                    (Some(_), false) => "ok".to_string(),
+                },
+            ]
+        })
+        .collect();
+    summary_format.format(&header, &rows, 1)
+}
+
+/// A metric compared between the baseline and the candidate.
+struct RegressionMetric {
+    name: &'static str,
+    higher_is_better: bool,
+    /// `None` for the runs without the metric.
+    value: fn(&RunResults) -> Option<f64>,
+}
+
+fn regression_metrics() -> Vec<RegressionMetric> {
+    let metric = |name, higher_is_better, value| RegressionMetric {
+        name,
+        higher_is_better,
+        value,
+    };
+    vec![
+        metric("mb_per_sec", true, |run| Some(run.megabytes_per_second)),
+        metric("docs_per_sec", true, |run| Some(run.doc_per_second)),
+        metric("duration_secs", false, |run| {
+            Some(run.indexing_duration_secs)
+        }),
+        metric("indexed_mb", false, |run| {
+            Some(run.num_indexed_bytes as f64 / 1_000_000.0)
+        }),
+        metric("mixed_p99_ms", false, |run| {
+            let latency = run.mixed_workload.as_ref()?.latency.as_ref()?;
+            Some(latency.p99_ms)
+        }),
+        metric("visibility_p99_ms", false, |run| {
+            let latency = run.visibility.as_ref()?.visibility_latency.as_ref()?;
+            Some(latency.p99_ms)
+        }),
+    ]
+}
+
+/// The change of a metric between the means of the baseline and candidate
+/// runs.
+struct MetricDelta {
+    name: &'static str,
+    baseline: f64,
+    /// `None` if the candidate runs lack the metric, which counts as a
+    /// regression.
+    candidate: Option<f64>,
+    change_pct: Option<f64>,
+    max_regression_pct: f64,
+    regressed: bool,
+}
+
+/// `None` if the metric is missing from the baseline, or zero in it.
+fn metric_delta(
+    regression_metric: &RegressionMetric,
+    baseline_values: &[f64],
+    candidate_values: &[f64],
+    max_regression_pct: f64,
+) -> Option<MetricDelta> {
+    let baseline = Summary::from_values(baseline_values)?.mean;
+    if baseline == 0.0 {
+        return None;
+    }
+    let Some(candidate) = Summary::from_values(candidate_values) else {
+        return Some(MetricDelta {
+            name: regression_metric.name,
+            baseline,
+            candidate: None,
+            change_pct: None,
+            max_regression_pct,
+            regressed: true,
+        });
+    };
+    let candidate = candidate.mean;
+    let change_pct = (candidate - baseline) / baseline * 100.0;
+    let regression_pct = if regression_metric.higher_is_better {
+        -change_pct
+    } else {
+        change_pct
+    };
+    Some(MetricDelta {
+        name: regression_metric.name,
+        baseline,
+        candidate: Some(candidate),
+        change_pct: Some(change_pct),
+        max_regression_pct,
+        regressed: regression_pct > max_regression_pct,
+    })
+}
+
+/// Prints a table of the main figures of each run of a results file.
+fn report_runs(
+    path: &Path,
+    format: Option<OutputFormat>,
+    aggregate: bool,
+    summary_format: SummaryFormat,
+) -> anyhow::Result<()> {
+    let format = results_format(path, format)?;
+    let runs = read_runs(path, format)?;
+    if aggregate {
+        for ((engine, index), group_runs) in group_runs(&runs) {
+            println!("{engine} {index} ({} runs)", group_runs.len());
+            let (header, rows) = statistics_table(&run_metrics(&group_runs));
+            print!("{}", summary_format.format(&header, &rows, 1));
+        }
+        return Ok(());
+    }
+    let runs: Vec<&RunResults> = runs.iter().collect();
+    print!("{}", summary(&runs, summary_format));
+    Ok(())
+}
+
+/// The table of the main figures of the runs, one line per run.
+pub fn summary(runs: &[&RunResults], summary_format: SummaryFormat) -> String {
+    let rows: Vec<Vec<String>> = runs.iter().map(|run| row(run)).collect();
+    let header: Vec<String> = COLUMNS.iter().map(|column| column.to_string()).collect();
+    summary_format.format(&header, &rows, 2)
+}
+
+/// The key metrics of indexing runs, with their values over the runs.
+pub fn run_metrics(runs: &[&RunResults]) -> Vec<(&'static str, Vec<f64>)> {
+    let metric = |name, value: fn(&RunResults) -> f64| {
+        (name, runs.iter().map(|run| value(run)).collect())
+    };
+    vec![
+        metric("duration_secs", |run| run.indexing_duration_secs),
+        metric("mb_per_sec", |run| run.megabytes_per_second),
+        metric("docs_per_sec", |run| run.doc_per_second),
+        metric("indexed_mb", |run| {
+            run.num_indexed_bytes as f64 / 1_000_000.0
+        }),
+        metric("num_splits", |run| run.num_splits as f64),
+    ]
+}
+
+/// Prints the mean, median, standard deviation, min and max of each metric
+/// over repeated runs.
+pub fn print_statistics(metrics: &[(&str, Vec<f64>)]) {
+    let (header, rows) = statistics_table(metrics);
+    print!("{}", format_table(&header, &rows, 1));
+}
+
+fn statistics_table(metrics: &[(&str, Vec<f64>)]) -> (Vec<String>, Vec<Vec<String>>) {
+    let header: Vec<String> = ["metric", "mean", "median", "stddev", "min", "max"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    let rows: Vec<Vec<String>> = metrics
+        .iter()
+        .filter_map(|(name, values)| {
+            let summary = Summary::from_values(values)?;
+            let stats = [
+                summary.mean,
+                summary.median,
+                summary.stddev,
+                summary.min,
+                summary.max,
+            ];
+            Some(
+                std::iter::once(name.to_string())
+                    .chain(stats.iter().map(|value| format!("{value:.2}")))
+                    .collect(),
+            )
+        })
+        .collect();
+    (header, rows)
+}
+
+/// Prints the main figures of the runs side by side, one column per run.
+pub fn print_comparison(runs: &[RunResults]) {
+    let run_rows: Vec<Vec<String>> = runs.iter().map(row).collect();
+    let header: Vec<String> = std::iter::once(String::new())
+        .chain(run_rows.iter().map(|run_row| run_row[0].clone()))
+        .collect();
+    let rows: Vec<Vec<String>> = COLUMNS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(column_idx, column)| {
+            std::iter::once(column.to_string())
+                .chain(run_rows.iter().map(|run_row| run_row[column_idx].clone()))
+                .collect()
+        })
+        .collect();
+    print!("{}", format_table(&header, &rows, 1));
+}
+
+/// Reads the runs of an indexing results file, skipping the partial results
+/// of the runs that didn't finish.
+pub fn read_runs(path: &Path, format: OutputFormat) -> anyhow::Result<Vec<RunResults>> {
+    read_results(path, format)?
+        .into_iter()
+        .enumerate()
+        .filter(|(run_idx, run)| {
+            let is_partial = run["partial"] == true;
+            if is_partial {
+                warn!(run_idx, "Skipping the partial results of an unfinished run");
+            }
+            !is_partial
+        })
+        .map(|(run_idx, run)| {
+            RunResults::deserialize(run).with_context(|| {
+                format!("Invalid run {run_idx}, check the file with `qbench validate-results`")
+            })
+        })
+        .collect()
+}
+
+fn row(run: &RunResults) -> Vec<String> {
+    let mixed_p50_ms = run
+        .mixed_workload
+        .as_ref()
+        .and_then(|mixed_workload| mixed_workload.latency.as_ref())
+        .map(|latency| format!("{:.1}", latency.p50_ms))
+        .unwrap_or_else(|| "-".to_string());
+    vec![
+        run.engine.clone(),
+        run.index.clone(),
+        run.num_indexed_docs.to_string(),
+        format!("{:.1}", run.num_ingested_bytes as f64 / 1_000_000.0),
+        format!("{:.1}", run.indexing_duration_secs),
+        format!("{:.2}", run.megabytes_per_second),
+        format!("{:.0}", run.doc_per_second),
+        run.num_splits.to_string(),
+        mixed_p50_ms,
+    ]
+}
+
+/// Aligns the columns, the first `num_text_columns` on the left and the
+/// numbers on the right.
+pub fn format_table(
+    header: &[String],
+    rows: &[Vec<String>],
+    num_text_columns: usize,
+) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|column| column.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut table = String::new();
+    for row in std::iter::once(header).chain(rows.iter().map(Vec::as_slice)) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column_idx, (cell, width))| {
+                if column_idx < num_text_columns {
+                    format!("{cell:<width$}")
+                } else {
+                    format!("{cell:>width$}")
+                }
+            })
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+fn format_csv(header: &[String], rows: &[Vec<String>]) -> String {
+    let mut csv = String::new();
+    for row in std::iter::once(header).chain(rows.iter().map(Vec::as_slice)) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                if cell.contains([',', '"', '\n']) {
+                    format!("\"{}\"", cell.replace('"', "\"\""))
+                } else {
+                    cell.clone()
+                }
+            })
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// A GitHub-flavored Markdown table, with the numbers aligned on the right.
+fn format_markdown(
+    header: &[String],
+    rows: &[Vec<String>],
+    num_text_columns: usize,
+) -> String {
+    let markdown_row =
+        |cells: Vec<String>| -> String { format!("| {} |\n", cells.join(" | ")) };
+    let escape = |row: &[String]| -> Vec<String> {
+        row.iter().map(|cell| cell.replace('|', "\\|")).collect()
+    };
+    let mut markdown = markdown_row(escape(header));
+    markdown.push_str(&markdown_row(
+        (0..header.len())
+            .map(|column_idx| {
+                if column_idx < num_text_columns {
+                    "---".to_string()
+                } else {
+                    "---:".to_string()
+                }
+            })
+            .collect(),
+    ));
+    for row in rows {
+        markdown.push_str(&markdown_row(escape(row)));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_delta() {
+        let metrics = regression_metrics();
+        let mb_per_sec = &metrics[0];
+        let indexed_mb = &metrics[3];
+        let delta = metric_delta(mb_per_sec, &[10.0, 12.0], &[10.0], 5.0).unwrap();
+        assert!((delta.change_pct.unwrap() + 100.0 / 11.0).abs() < 1e-9);
+        assert!(delta.regressed);
+        // A smaller index is an improvement.
+        let delta = metric_delta(indexed_mb, &[100.0], &[80.0], 5.0).unwrap();
+        assert!(!delta.regressed);
+        assert!(metric_delta(indexed_mb, &[100.0], &[104.0], 5.0)
+            .is_some_and(|delta| !delta.regressed));
+        assert!(metric_delta(indexed_mb, &[100.0], &[106.0], 5.0)
+            .is_some_and(|delta| delta.regressed));
+        assert!(metric_delta(mb_per_sec, &[], &[10.0], 5.0).is_none());
+        // A metric the candidate lost fails the comparison.
+        let delta = metric_delta(mb_per_sec, &[10.0], &[], 5.0).unwrap();
+        assert!(delta.candidate.is_none());
+        assert!(delta.regressed);
+
+        assert!("indexed_mb=2".parse::<MetricThreshold>().is_ok());
+        assert!("p42_ms=2".parse::<MetricThreshold>().is_err());
+    }
+
+    #[test]
+    fn test_summary_formats() {
+        let header = vec!["engine".to_string(), "mb_per_sec".to_string()];
+        let rows = vec![vec!["quickwit, 0.8".to_string(), "12.50".to_string()]];
+        assert_eq!(
+            SummaryFormat::Csv.format(&header, &rows, 1),
+            "engine,mb_per_sec\n\"quickwit, 0.8\",12.50\n"
+        );
+        assert_eq!(
+            SummaryFormat::Markdown.format(&header, &rows, 1),
+            "| engine | mb_per_sec |\n| --- | ---: |\n| quickwit, 0.8 | 12.50 |\n"
+        );
+        assert_eq!(
+            SummaryFormat::from_path(Path::new("summary.md")),
+            SummaryFormat::Markdown
+        );
+    }
+
+    #[test]
+    fn test_format_table() {
+        let row = |cells: &[&str]| cells.iter().map(|cell| cell.to_string()).collect();
+        let rows = vec![
+            row(&[
+                "quickwit", "logs", "10", "1.0", "60.0", "0.02", "0", "1", "-",
+            ]),
+            row(&[
+                "elasticsearch",
+                "logs-2",
+                "1000",
+                "12.5",
+                "6.1",
+                "2.05",
+                "164",
+                "12",
+                "3.2",
+            ]),
+        ];
+        let header: Vec<String> =
+            COLUMNS.iter().map(|column| column.to_string()).collect();
+        assert_eq!(
+            format_table(&header, &rows, 2),
+            "engine         index   num_docs  ingested_mb  duration_secs  mb_per_sec  \
+             docs_per_sec  num_splits  mixed_p50_ms\n\
+             quickwit       logs          10          1.0           60.0        0.02             \
+             0           1             -\n\
+             elasticsearch  logs-2      1000         12.5            6.1        2.05           \
+             164          12           3.2\n"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::ffi::OsString;
+
+use anyhow::{bail, Context};
+use clap::CommandFactory;
+use serde_yaml::{Mapping, Value};
+
+use crate::CliArgs;
+
+/// Expands the `--config` file of the command line into the flags it stands
+/// for, appended to the command line.
+///
+/// The file maps the long flags of the subcommands, with underscores or
+/// dashes, to their values:
+///
+/// ```yaml
+/// engine: quickwit
+/// host: 127.0.0.1:7280
+/// index: generated-logs
+/// index_config: tracks/generated-logs/index-config.quickwit.yaml
+/// dataset_uri: https://example.com/generated-logs/documents-{0..9}.ndjson.gz
+/// concurrency: 4
+/// tags: [nightly]
+/// query_suite: tracks/generated-logs/suite.yaml
+/// subcommands:
+///   search:
+///     output_path: search-results.json
+/// ```
+///
+/// The same file serves all the subcommands: the keys a subcommand doesn't
+/// know are ignored, and the `subcommands` section overrides the top-level
+/// keys for a given subcommand. The flags given on the command line override the file,
+/// which overrides the environment variables.
+pub fn expand_config_args(args: Vec<OsString>) -> anyhow::Result<Vec<OsString>> {
+    let Some(config_path) = config_path(&args) else {
+        return Ok(args);
+    };
+    let command = CliArgs::command();
+    // Leave reporting a missing subcommand to clap.
+    let Some(subcommand) = args
+        .iter()
+        .filter_map(|arg| arg.to_str())
+        .find_map(|arg| command.find_subcommand(arg))
+    else {
+        return Ok(args);
+    };
+    let config_yaml = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read run config {config_path:?}"))?;
+    let config: Mapping = serde_yaml::from_str(&config_yaml)
+        .with_context(|| format!("Invalid run config {config_path:?}"))?;
+    let user_args: Vec<&str> = args.iter().filter_map(|arg| arg.to_str()).collect();
+    let config_args = config_args(&config, &command, subcommand, &user_args)
+        .with_context(|| format!("Invalid run config {config_path:?}"))?;
+    Ok(args
+        .into_iter()
+        .chain(config_args.into_iter().map(OsString::from))
+        .collect())
+}
+
+/// The value of `--config`, which has to be known before clap parses the
+/// command line.
+fn config_path(args: &[OsString]) -> Option<OsString> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().cloned();
+        }
+        if let Some(config_path) =
+            arg.to_str().and_then(|arg| arg.strip_prefix("--config="))
+        {
+            return Some(config_path.into());
+        }
+    }
+    None
+}
+
+/// The flags standing for the config entries known to `subcommand`, and not
+/// given in `user_args` already.
+fn config_args(
+    config: &Mapping,
+    command: &clap::Command,
+    subcommand: &clap::Command,
+    user_args: &[&str],
+) -> anyhow::Result<Vec<String>> {
+    let mut entries: Vec<(&str, &Value)> = Vec::new();
+    let mut section_entries: Vec<(&str, &Value)> = Vec::new();
+    for (key, value) in config {
+        let key = key_str(key)?;
+        if key != SUBCOMMANDS_KEY {
+            entries.push((key, value));
+            continue;
+        }
+        let Value::Mapping(sections) = value else {
+            bail!("{SUBCOMMANDS_KEY:?} must map subcommands to their keys");
+        };
+        for (section_key, section) in sections {
+            let section_key = key_str(section_key)?;
+            let Some(section_subcommand) = command.find_subcommand(section_key) else {
+                bail!("Unknown subcommand {section_key:?}");
+            };
+            if section_subcommand.get_name() != subcommand.get_name() {
+                continue;
+            }
+            let Value::Mapping(section) = section else {
+                bail!("The {section_key:?} subcommand section must be a mapping");
+            };
+            for (key, value) in section {
+                section_entries.push((key_str(key)?, value));
+            }
+        }
+    }
+    entries.retain(|(key, _)| {
+        !section_entries
+            .iter()
+            .any(|(section_key, _)| section_key == key)
+    });
+    entries.extend(section_entries);
+    let mut config_args = Vec::new();
+    for (key, value) in entries {
+        let long = key.replace('_', "-");
+        let Some(arg) = subcommand
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+        else {
+            let is_known = command.get_subcommands().any(|other_subcommand| {
+                other_subcommand
+                    .get_arguments()
+                    .any(|arg| arg.get_long() == Some(long.as_str()))
+            });
+            if !is_known {
+                bail!("Unknown key {key:?}");
+            }
+            continue;
+        };
+        let is_user_arg = user_args.iter().any(|user_arg| {
+            user_arg.strip_prefix("--").is_some_and(|user_long| {
+                user_long == long || user_long.starts_with(&format!("{long}="))
+            }) || arg.get_short().is_some_and(|short| {
+                user_arg
+                    .strip_prefix('-')
+                    .is_some_and(|user_short| user_short.starts_with(short))
+            })
+        });
+        if is_user_arg {
+            continue;
+        }
+        let flag = format!("--{long}");
+        match value {
+            Value::Null | Value::Bool(false) => {},
+            Value::Bool(true) => config_args.push(flag),
+            Value::Sequence(values) => {
+                for value in values {
+                    config_args.push(flag.clone());
+                    config_args.push(scalar_arg(key, value)?);
+                }
+            },
+            _ => {
+                config_args.push(flag);
+                config_args.push(scalar_arg(key, value)?);
+            },
+        }
+    }
+    Ok(config_args)
+}
+
+/// The key of the per-subcommand sections.
+const SUBCOMMANDS_KEY: &str = "subcommands";
+
+fn key_str(key: &Value) -> anyhow::Result<&str> {
+    key.as_str()
+        .with_context(|| format!("Keys must be strings, got {key:?}"))
+}
+
+fn scalar_arg(key: &str, value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => bail!("The value of {key:?} must be a string, a number or a list of them"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_args() {
+        let config: Mapping = serde_yaml::from_str(
+            r#"
+            engine: quickwit
+            index: logs
+            index_config: index-config.yaml
+            dataset_uri: data.json
+            concurrency: 4
+            tags: [nightly, ssd]
+            retry_indexing_errors: true
+            merge: false
+            subcommands:
+              setup-index:
+                index: logs-setup
+              index:
+                tags: [index-only]
+            "#,
+        )
+        .unwrap();
+        let command = CliArgs::command();
+        let index = command.find_subcommand("index").unwrap();
+        assert_eq!(
+            config_args(
+                &config,
+                &command,
+                index,
+                &["qbench", "index", "-i", "other"]
+            )
+            .unwrap(),
+            [
+                "--engine",
+                "quickwit",
+                "--index-config",
+                "index-config.yaml",
+                "--dataset-uri",
+                "data.json",
+                "--concurrency",
+                "4",
+                "--retry-indexing-errors",
+                "--tags",
+                "index-only",
+            ]
+        );
+        let setup_index = command.find_subcommand("setup-index").unwrap();
+        assert_eq!(
+            config_args(
+                &config,
+                &command,
+                setup_index,
+                &["qbench", "setup-index", "--engine=elasticsearch"]
+            )
+            .unwrap(),
+            [
+                "--index-config",
+                "index-config.yaml",
+                "--index",
+                "logs-setup"
+            ]
+        );
+
+        let config: Mapping = serde_yaml::from_str("dataset_url: data.json").unwrap();
+        assert!(config_args(&config, &command, index, &[]).is_err());
+    }
+}
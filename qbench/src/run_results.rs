@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::bail;
+use clap::Args;
+use qbench_core::results::{read_results, results_format, OutputFormat};
+use qbench_core::run_results::check_run;
+
+/// A `key=value` tag, e.g. `instance=c6i.2xlarge`.
+#[derive(Debug, Clone)]
+pub struct RunTag {
+    key: String,
+    value: String,
+}
+
+impl FromStr for RunTag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok(RunTag {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("Expected `key=value`, got {s:?}")),
+        }
+    }
+}
+
+/// The metadata recorded in the results of a run, to tell runs apart and
+/// filter them later.
+#[derive(Args, Debug, Clone)]
+pub struct RunLabelsArgs {
+    #[arg(long, env, value_delimiter = ',')]
+    /// Comma-separated labels recorded in the results, e.g. to tell apart the
+    /// runs collected in the same file.
+    tags: Vec<String>,
+
+    #[arg(long = "tag", value_name = "KEY=VALUE")]
+    /// A `key=value` tag recorded in the results, e.g. the instance type,
+    /// the storage class or the engine config variant. Can be repeated.
+    key_value_tags: Vec<RunTag>,
+
+    #[arg(long, env)]
+    /// A free-form comment recorded in the results.
+    pub comment: Option<String>,
+}
+
+impl RunLabelsArgs {
+    /// The labels, as recorded in the `tags` field of the results.
+    pub fn tags(&self) -> Vec<String> {
+        self.tags.clone()
+    }
+
+    /// The `key=value` tags by key, as recorded in the `key_value_tags` field
+    /// of the results. The last value of a repeated key wins.
+    pub fn key_value_tags(&self) -> BTreeMap<String, String> {
+        self.key_value_tags
+            .iter()
+            .map(|tag| (tag.key.clone(), tag.value.clone()))
+            .collect()
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateResultsArgs {
+    /// The results file to check.
+    path: PathBuf,
+
+    #[arg(long)]
+    /// The format of the results file: "json", "yaml" or "msgpack". Guessed
+    /// from the file extension by default.
+    format: Option<OutputFormat>,
+}
+
+/// Checks that every run of a results file matches the current schema.
+pub fn validate_results(args: ValidateResultsArgs) -> anyhow::Result<()> {
+    let format = results_format(&args.path, args.format)?;
+    let runs = read_results(&args.path, format)?;
+    let mut num_invalid_runs = 0;
+    for (run_idx, run) in runs.iter().enumerate() {
+        if let Err(error) = check_run(run) {
+            num_invalid_runs += 1;
+            error!(run_idx, "Invalid run results: {error:#}");
+        }
+    }
+    if num_invalid_runs > 0 {
+        bail!(
+            "{num_invalid_runs} out of {} runs in {:?} are invalid",
+            runs.len(),
+            args.path
+        );
+    }
+    info!(num_runs = runs.len(), "Results are valid");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_run_tags() {
+        #[derive(Parser)]
+        struct TestArgs {
+            #[command(flatten)]
+            labels: RunLabelsArgs,
+        }
+        let args = TestArgs::try_parse_from([
+            "qbench",
+            "--tags",
+            "nightly,ssd",
+            "--tag",
+            "instance=c6i.2xlarge",
+            "--tag",
+            "merge_policy=",
+        ])
+        .unwrap();
+        assert_eq!(args.labels.tags(), ["nightly", "ssd"]);
+        assert_eq!(
+            args.labels.key_value_tags(),
+            BTreeMap::from([
+                ("instance".to_string(), "c6i.2xlarge".to_string()),
+                ("merge_policy".to_string(), String::new()),
+            ])
+        );
+        assert!(TestArgs::try_parse_from(["qbench", "--tag", "instance"]).is_err());
+        assert!(TestArgs::try_parse_from(["qbench", "--tag", "=value"]).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Context;
+use qbench_core::run_results::reports::{
+    PhaseStats,
+    SchemaDriftKind,
+    SchemaDriftReport,
+};
+use qbench_core::source::DocumentBatch;
+use serde_json::Value;
+
+/// The name of the object field holding the fields added by the drift.
+const DRIFT_FIELD_PREFIX: &str = "qbench_drift";
+
+/// Mutates a fraction of the documents once a given amount of input data went
+/// through, simulating the schema drift logs typically go through in real life.
+pub struct SchemaDrift {
+    after_bytes: u64,
+    ratio: f64,
+    kind: SchemaDriftKind,
+    num_seen_bytes: u64,
+    num_candidate_docs: u64,
+    num_drifted_docs: u64,
+}
+
+impl SchemaDrift {
+    pub fn new(after_bytes: u64, ratio: f64, kind: SchemaDriftKind) -> Self {
+        Self {
+            after_bytes,
+            ratio,
+            kind,
+            num_seen_bytes: 0,
+            num_candidate_docs: 0,
+            num_drifted_docs: 0,
+        }
+    }
+
+    /// Returns whether the drift point has been reached.
+    pub fn is_started(&self) -> bool {
+        self.num_seen_bytes >= self.after_bytes
+    }
+
+    pub fn report(&self, before: PhaseStats, after: PhaseStats) -> SchemaDriftReport {
+        SchemaDriftReport {
+            after_bytes: self.after_bytes,
+            ratio: self.ratio,
+            kind: self.kind,
+            num_drifted_docs: self.num_drifted_docs,
+            before,
+            after,
+        }
+    }
+
+    /// Rewrites the drifted documents of the batch in place.
+    pub fn apply(&mut self, document_batch: &mut DocumentBatch) -> anyhow::Result<()> {
+        let started = self.is_started();
+        self.num_seen_bytes += document_batch.bytes.len() as u64;
+        if !started {
+            return Ok(());
+        }
+        let mut payload = Vec::with_capacity(document_batch.bytes.len());
+        for line in document_batch.bytes.split_inclusive(|byte| *byte == b'\n') {
+            if !self.select_next_doc() || line.trim_ascii().is_empty() {
+                payload.extend_from_slice(line);
+                continue;
+            }
+            let mut doc: Value = serde_json::from_slice(line)
+                .context("Failed to parse document line as JSON")?;
+            self.drift(&mut doc);
+            self.num_drifted_docs += 1;
+            serde_json::to_writer(&mut payload, &doc)?;
+            payload.push(b'\n');
+        }
+        document_batch.bytes = payload.into();
+        Ok(())
+    }
+
+    /// Deterministically spreads the drifted documents evenly, so that exactly
+    /// `ratio` of them are selected.
+    fn select_next_doc(&mut self) -> bool {
+        let previous = (self.num_candidate_docs as f64 * self.ratio).floor();
+        self.num_candidate_docs += 1;
+        (self.num_candidate_docs as f64 * self.ratio).floor() > previous
+    }
+
+    fn drift(&self, doc: &mut Value) {
+        let Value::Object(fields) = doc else {
+            return;
+        };
+        if matches!(
+            self.kind,
+            SchemaDriftKind::TypeChanges | SchemaDriftKind::Both
+        ) {
+            for value in fields.values_mut() {
+                *value = match value.take() {
+                    Value::Number(number) => Value::String(number.to_string()),
+                    Value::Bool(boolean) => Value::from(boolean as u8),
+                    Value::String(string) => serde_json::json!({ "value": string }),
+                    other => other,
+                };
+            }
+        }
+        if matches!(
+            self.kind,
+            SchemaDriftKind::NewFields | SchemaDriftKind::Both
+        ) {
+            fields.insert(
+                DRIFT_FIELD_PREFIX.to_string(),
+                serde_json::json!({
+                    "doc_ordinal": self.num_drifted_docs,
+                    "label": format!("drift-{}", self.num_drifted_docs % 100),
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_schema_drift() {
+        let mut drift = SchemaDrift::new(10, 0.5, SchemaDriftKind::Both);
+        let mut first_batch = DocumentBatch {
+            bytes: Bytes::from_static(b"{\"a\":1}\n{\"a\":2}\n"),
+            last: false,
+        };
+        drift.apply(&mut first_batch).unwrap();
+        assert_eq!(first_batch.bytes, &b"{\"a\":1}\n{\"a\":2}\n"[..]);
+        assert!(drift.is_started());
+
+        let mut second_batch = DocumentBatch {
+            bytes: Bytes::from_static(b"{\"a\":1,\"b\":\"x\",\"c\":true}\n{\"a\":2}\n"),
+            last: true,
+        };
+        drift.apply(&mut second_batch).unwrap();
+        let docs: Vec<Value> = second_batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(docs[0], json!({"a": 1, "b": "x", "c": true}));
+        assert_eq!(
+            docs[1],
+            json!({
+                "a": "2",
+                "qbench_drift": {"doc_ordinal": 0, "label": "drift-0"}
+            })
+        );
+        assert_eq!(drift.num_drifted_docs, 1);
+    }
+}
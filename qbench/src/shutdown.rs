@@ -0,0 +1,47 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::signal::unix::{signal, SignalKind};
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SIGNAL_HANDLER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Requests a graceful shutdown on SIGINT and SIGTERM: the ingestion stops
+/// pulling batches and the run ends with the results measured so far. A
+/// second signal exits right away.
+///
+/// Only the first call installs it, e.g. for all the runs of `qbench matrix`,
+/// as every handler would otherwise count the same signal again.
+pub fn install_signal_handler() -> anyhow::Result<()> {
+    if SIGNAL_HANDLER_INSTALLED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = sigint.recv() => {},
+                _ = sigterm.recv() => {},
+            }
+            request();
+        }
+    });
+    SIGNAL_HANDLER_INSTALLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Requests a graceful shutdown, or exits if one was already requested.
+pub fn request() {
+    if SHUTDOWN_REQUESTED.swap(true, Ordering::Relaxed) {
+        warn!("Exiting without writing the results");
+        std::process::exit(130);
+    }
+    warn!(
+        "Stopping the ingestion, waiting for the in-flight requests before \
+         writing the results. Interrupt again to exit right away"
+    );
+}
+
+pub fn is_requested() -> bool {
+    SHUTDOWN_REQUESTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,109 @@
+use qbench_core::run_results::reports::SourceErrorsReport;
+use qbench_core::source::DocumentBatch;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Randomly breaks a fraction of the documents before they are sent, to
+/// compare how engines (and qbench's error accounting) deal with malformed
+/// input.
+///
+/// Broken documents are never valid JSON anymore: they are either truncated or
+/// have one of their bytes replaced by an invalid UTF-8 byte. The selection is
+/// seeded, so that runs are reproducible.
+pub struct SourceErrorInjector {
+    rate: f64,
+    seed: u64,
+    rng: StdRng,
+    num_docs: u64,
+    num_corrupted_docs: u64,
+    num_truncated_docs: u64,
+}
+
+impl SourceErrorInjector {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Self {
+            rate,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            num_docs: 0,
+            num_corrupted_docs: 0,
+            num_truncated_docs: 0,
+        }
+    }
+
+    pub fn report(&self) -> SourceErrorsReport {
+        SourceErrorsReport {
+            rate: self.rate,
+            seed: self.seed,
+            num_docs: self.num_docs,
+            num_corrupted_docs: self.num_corrupted_docs,
+            num_truncated_docs: self.num_truncated_docs,
+        }
+    }
+
+    /// Breaks the selected documents of the batch in place.
+    pub fn apply(&mut self, document_batch: &mut DocumentBatch) {
+        let mut payload = Vec::with_capacity(document_batch.bytes.len());
+        for line in document_batch.bytes.split_inclusive(|byte| *byte == b'\n') {
+            let doc = line.strip_suffix(b"\n").unwrap_or(line);
+            if doc.trim_ascii().is_empty() {
+                payload.extend_from_slice(line);
+                continue;
+            }
+            self.num_docs += 1;
+            if !self.rng.gen_bool(self.rate) {
+                payload.extend_from_slice(line);
+                continue;
+            }
+            if self.rng.gen_bool(0.5) {
+                // Keep at least one byte, an empty line would just be skipped.
+                let truncated_len = self.rng.gen_range(1..doc.len().max(2));
+                payload.extend_from_slice(&doc[..truncated_len]);
+                self.num_truncated_docs += 1;
+            } else {
+                let corrupted_pos = payload.len() + self.rng.gen_range(0..doc.len());
+                payload.extend_from_slice(doc);
+                payload[corrupted_pos] = 0xFF;
+                self.num_corrupted_docs += 1;
+            }
+            payload.push(b'\n');
+        }
+        document_batch.bytes = payload.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_source_error_injector() {
+        let docs = Bytes::from(b"{\"a\":1}\n{\"a\":2}\n\n{\"a\":3}\n".repeat(100));
+        let mut injector = SourceErrorInjector::new(0.5, 42);
+        let mut batch = DocumentBatch {
+            bytes: docs.clone(),
+            last: true,
+        };
+        injector.apply(&mut batch);
+        let report = injector.report();
+        assert_eq!(report.num_docs, 300);
+        let num_broken_docs = report.num_corrupted_docs + report.num_truncated_docs;
+        assert!((100..200).contains(&num_broken_docs));
+        let num_invalid_docs = batch
+            .bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .filter(|line| serde_json::from_slice::<serde_json::Value>(line).is_err())
+            .count() as u64;
+        assert_eq!(num_invalid_docs, num_broken_docs);
+
+        let mut same_seed_batch = DocumentBatch {
+            bytes: docs,
+            last: true,
+        };
+        SourceErrorInjector::new(0.5, 42).apply(&mut same_seed_batch);
+        assert_eq!(batch.bytes, same_seed_batch.bytes);
+    }
+}
@@ -0,0 +1,161 @@
+use std::f64::consts::SQRT_2;
+
+/// The distribution of a metric over repeated runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    /// The sample standard deviation, 0 for a single run.
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// Returns None if there are no values.
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted_values = values.to_vec();
+        sorted_values.sort_by(f64::total_cmp);
+        let num_values = sorted_values.len();
+        let mean = sorted_values.iter().sum::<f64>() / num_values as f64;
+        let median = if num_values.is_multiple_of(2) {
+            (sorted_values[num_values / 2 - 1] + sorted_values[num_values / 2]) / 2.0
+        } else {
+            sorted_values[num_values / 2]
+        };
+        let stddev = if num_values > 1 {
+            let sum_squares: f64 = sorted_values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum();
+            (sum_squares / (num_values - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        Some(Summary {
+            mean,
+            median,
+            stddev,
+            min: sorted_values[0],
+            max: sorted_values[num_values - 1],
+        })
+    }
+}
+
+/// The two-sided p-value of the Mann-Whitney U test that the values of `a`
+/// and `b` come from the same distribution, with the normal approximation
+/// corrected for ties and continuity. It needs a few values on each side to
+/// be meaningful. Returns None if a side is empty or all the values are
+/// equal.
+pub fn mann_whitney_p_value(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let mut values: Vec<(f64, bool)> = a
+        .iter()
+        .map(|value| (*value, true))
+        .chain(b.iter().map(|value| (*value, false)))
+        .collect();
+    values.sort_by(|left, right| left.0.total_cmp(&right.0));
+    let num_values = values.len() as f64;
+    let (num_a_values, num_b_values) = (a.len() as f64, b.len() as f64);
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < values.len() {
+        let mut end = start + 1;
+        while end < values.len() && values[end].0 == values[start].0 {
+            end += 1;
+        }
+        // The tied values share the mean of the ranks start + 1..=end.
+        let rank = (start + 1 + end) as f64 / 2.0;
+        let num_tied_values = (end - start) as f64;
+        tie_correction += num_tied_values.powi(3) - num_tied_values;
+        let num_tied_a_values =
+            values[start..end].iter().filter(|(_, in_a)| *in_a).count();
+        rank_sum_a += rank * num_tied_a_values as f64;
+        start = end;
+    }
+    let u = rank_sum_a - num_a_values * (num_a_values + 1.0) / 2.0;
+    let u_mean = num_a_values * num_b_values / 2.0;
+    let u_variance = num_a_values * num_b_values / 12.0
+        * (num_values + 1.0 - tie_correction / (num_values * (num_values - 1.0)));
+    if u_variance <= 0.0 {
+        return None;
+    }
+    let z = ((u - u_mean).abs() - 0.5).max(0.0) / u_variance.sqrt();
+    Some(erfc(z / SQRT_2))
+}
+
+/// The complementary error function, within 1.2e-7 (Chebyshev fit from
+/// Numerical Recipes).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = [
+        -1.265_512_23,
+        1.000_023_68,
+        0.374_091_96,
+        0.096_784_18,
+        -0.186_288_06,
+        0.278_868_07,
+        -1.135_203_98,
+        1.488_515_87,
+        -0.822_152_23,
+        0.170_872_77,
+    ]
+    .iter()
+    .rev()
+    .fold(0.0, |acc, coefficient| coefficient + t * acc);
+    let erfc = t * (-z * z + polynomial).exp();
+    if x >= 0.0 {
+        erfc
+    } else {
+        2.0 - erfc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(Summary::from_values(&[]), None);
+        assert_eq!(
+            Summary::from_values(&[3.0]),
+            Some(Summary {
+                mean: 3.0,
+                median: 3.0,
+                stddev: 0.0,
+                min: 3.0,
+                max: 3.0,
+            })
+        );
+        let summary = Summary::from_values(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        assert!((summary.stddev - 1.2909944).abs() < 1e-6);
+        assert_eq!((summary.min, summary.max), (1.0, 4.0));
+        assert_eq!(Summary::from_values(&[5.0, 1.0, 2.0]).unwrap().median, 2.0);
+    }
+
+    #[test]
+    fn test_mann_whitney_p_value() {
+        // As computed by scipy's `mannwhitneyu(method="asymptotic")`.
+        let p_value = mann_whitney_p_value(
+            &[1.0, 2.0, 3.0, 4.0, 5.0],
+            &[6.0, 7.0, 8.0, 9.0, 10.0],
+        )
+        .unwrap();
+        assert!((p_value - 0.012186).abs() < 1e-5);
+        let p_value =
+            mann_whitney_p_value(&[1.0, 2.0, 2.0, 5.0], &[2.0, 3.0, 4.0]).unwrap();
+        assert!(p_value > 0.5);
+        assert_eq!(mann_whitney_p_value(&[1.0, 1.0], &[1.0]), None);
+        assert_eq!(mann_whitney_p_value(&[], &[1.0]), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use qbench_core::engine_metrics::EngineMetricsScraper;
+use qbench_core::run_results::reports::{TimeSlice, TimeSlicesReport};
+use tokio::task::JoinHandle;
+
+use crate::metrics::IngestCounters;
+
+/// Counters at the boundary between two slices.
+struct SliceBoundary {
+    instant: Instant,
+    time: DateTime<Utc>,
+    num_ingested_bytes: u64,
+    num_ingested_docs: u64,
+    num_failed_batches: u64,
+    num_timed_out_requests: u64,
+}
+
+impl SliceBoundary {
+    fn now(counters: &IngestCounters) -> Self {
+        Self {
+            instant: Instant::now(),
+            time: Utc::now(),
+            num_ingested_bytes: counters.num_ingested_bytes.load(Ordering::Relaxed),
+            num_ingested_docs: counters.num_ingested_docs.load(Ordering::Relaxed),
+            num_failed_batches: counters.num_failed_batches.load(Ordering::Relaxed),
+            num_timed_out_requests: counters
+                .num_timed_out_requests
+                .load(Ordering::Relaxed),
+        }
+    }
+
+    fn slice_since(
+        &self,
+        previous: &SliceBoundary,
+        engine_metrics: Option<BTreeMap<String, f64>>,
+    ) -> TimeSlice {
+        let elapsed_secs = (self.instant - previous.instant).as_secs_f64();
+        let num_ingested_bytes = self.num_ingested_bytes - previous.num_ingested_bytes;
+        let num_ingested_docs = self.num_ingested_docs - previous.num_ingested_docs;
+        TimeSlice {
+            start: previous.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            end: self.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+            num_ingested_bytes,
+            num_ingested_docs,
+            num_failed_batches: self.num_failed_batches - previous.num_failed_batches,
+            num_timed_out_requests: self.num_timed_out_requests
+                - previous.num_timed_out_requests,
+            megabytes_per_second: num_ingested_bytes as f64 / 1_000_000.0 / elapsed_secs,
+            docs_per_second: num_ingested_docs as f64 / elapsed_secs,
+            engine_metrics,
+        }
+    }
+}
+
+/// Aggregates the ingestion counters, and optionally the engine metrics, on a
+/// common time grid.
+///
+/// Slice boundaries are aligned on the wall clock (e.g. :00, :10, :20... for
+/// 10s slices) like Prometheus scrapes usually are, so that the slices line up
+/// with the engine's own dashboards. The first and last slices are partial.
+pub struct TimeSliceRecorder {
+    interval: Duration,
+    counters: Arc<IngestCounters>,
+    engine_metrics_scraper: Option<Arc<EngineMetricsScraper>>,
+    start_engine_metrics: Option<BTreeMap<String, f64>>,
+    state: Arc<Mutex<(SliceBoundary, Vec<TimeSlice>)>>,
+    handle: JoinHandle<()>,
+}
+
+impl TimeSliceRecorder {
+    pub async fn start(
+        interval: Duration,
+        counters: Arc<IngestCounters>,
+        engine_metrics_scraper: Option<EngineMetricsScraper>,
+    ) -> Self {
+        let engine_metrics_scraper = engine_metrics_scraper.map(Arc::new);
+        let start_engine_metrics = scrape(engine_metrics_scraper.as_deref()).await;
+        let state = Arc::new(Mutex::new((SliceBoundary::now(&counters), Vec::new())));
+        let handle = tokio::spawn({
+            let counters = counters.clone();
+            let engine_metrics_scraper = engine_metrics_scraper.clone();
+            let state = state.clone();
+            async move {
+                let first_boundary =
+                    Instant::now() + until_next_boundary(Utc::now(), interval);
+                let mut ticker =
+                    tokio::time::interval_at(first_boundary.into(), interval);
+                loop {
+                    ticker.tick().await;
+                    let boundary = SliceBoundary::now(&counters);
+                    let engine_metrics = scrape(engine_metrics_scraper.as_deref()).await;
+                    let mut state = state.lock().unwrap();
+                    let slice = boundary.slice_since(&state.0, engine_metrics);
+                    state.1.push(slice);
+                    state.0 = boundary;
+                }
+            }
+        });
+        Self {
+            interval,
+            counters,
+            engine_metrics_scraper,
+            start_engine_metrics,
+            state,
+            handle,
+        }
+    }
+
+    /// The slices closed so far.
+    pub fn report_so_far(&self) -> TimeSlicesReport {
+        TimeSlicesReport {
+            interval_secs: self.interval.as_secs(),
+            start_engine_metrics: self.start_engine_metrics.clone(),
+            slices: self.state.lock().unwrap().1.clone(),
+        }
+    }
+
+    /// Stops recording and closes the last slice.
+    pub async fn finish(self) -> TimeSlicesReport {
+        self.handle.abort();
+        let _ = self.handle.await;
+        let boundary = SliceBoundary::now(&self.counters);
+        let engine_metrics = scrape(self.engine_metrics_scraper.as_deref()).await;
+        let mut state = self.state.lock().unwrap();
+        let slice = boundary.slice_since(&state.0, engine_metrics);
+        state.1.push(slice);
+        TimeSlicesReport {
+            interval_secs: self.interval.as_secs(),
+            start_engine_metrics: self.start_engine_metrics,
+            slices: std::mem::take(&mut state.1),
+        }
+    }
+}
+
+async fn scrape(
+    engine_metrics_scraper: Option<&EngineMetricsScraper>,
+) -> Option<BTreeMap<String, f64>> {
+    match engine_metrics_scraper?.scrape().await {
+        Ok(engine_metrics) => Some(engine_metrics),
+        Err(err) => {
+            warn!(err=?err, "Failed to scrape engine metrics");
+            None
+        },
+    }
+}
+
+/// The time left until the next multiple of `interval` since the epoch.
+fn until_next_boundary(now: DateTime<Utc>, interval: Duration) -> Duration {
+    let interval_micros = interval.as_micros() as i64;
+    let since_boundary_micros = now.timestamp_micros().rem_euclid(interval_micros);
+    Duration::from_micros((interval_micros - since_boundary_micros) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_until_next_boundary() {
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:07.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            until_next_boundary(now, Duration::from_secs(10)),
+            Duration::from_millis(2500)
+        );
+        assert_eq!(
+            until_next_boundary(now, Duration::from_secs(60)),
+            Duration::from_millis(52500)
+        );
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use qbench_core::run_results::reports::{
+    default_max_payload_field_bytes,
+    LogSchema,
+    TransformSpec,
+};
+use qbench_core::source::DocumentBatch;
+use serde_json::{Map, Value};
+
+/// Rewrites a document before it is sent, e.g. to give it the field names the
+/// engine's index config expects.
+pub trait DocTransform: Send + Sync {
+    fn apply(&self, doc: &mut Map<String, Value>);
+
+    /// Whether the document is kept, checked before `apply`. The dropped
+    /// documents are not sent.
+    fn keep(&self, _doc: &Map<String, Value>) -> bool {
+        true
+    }
+}
+
+/// A transform of `--transform`, built in for the datasets every engine needs
+/// it for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinTransform {
+    Gharchive,
+}
+
+impl FromStr for BuiltinTransform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gharchive" => Ok(BuiltinTransform::Gharchive),
+            _ => Err(format!("Unknown built-in transform {s:?}")),
+        }
+    }
+}
+
+impl BuiltinTransform {
+    /// The spec of the transform, keeping only the `event_types` if any.
+    pub fn spec(&self, event_types: &[String]) -> TransformSpec {
+        match self {
+            BuiltinTransform::Gharchive => TransformSpec::Gharchive {
+                event_types: event_types.to_vec(),
+                max_payload_field_bytes: default_max_payload_field_bytes(),
+            },
+        }
+    }
+}
+
+const TIMESTAMP_FIELDS: &[&str] = &["@timestamp", "timestamp", "ts", "time"];
+const MESSAGE_FIELDS: &[&str] = &["message", "msg", "body", "log"];
+const SEVERITY_FIELDS: &[&str] = &["level", "severity", "severity_text", "log.level"];
+
+struct RenameFields(BTreeMap<String, String>);
+
+impl DocTransform for RenameFields {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        for (from, to) in &self.0 {
+            if let Some(value) = doc.remove(from) {
+                doc.insert(to.clone(), value);
+            }
+        }
+    }
+}
+
+struct DropFields(Vec<String>);
+
+impl DocTransform for DropFields {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        for field in &self.0 {
+            doc.remove(field);
+        }
+    }
+}
+
+struct AddFields(Map<String, Value>);
+
+impl DocTransform for AddFields {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        for (field, value) in &self.0 {
+            doc.insert(field.clone(), value.clone());
+        }
+    }
+}
+
+struct LowercaseKeys;
+
+impl LowercaseKeys {
+    /// Lowercases the keys of the objects nested in `value`, including the
+    /// objects in arrays.
+    fn apply_nested(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => self.apply(object),
+            Value::Array(values) => {
+                for value in values {
+                    self.apply_nested(value);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl DocTransform for LowercaseKeys {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        *doc = std::mem::take(doc)
+            .into_iter()
+            .map(|(key, mut value)| {
+                self.apply_nested(&mut value);
+                (key.to_lowercase(), value)
+            })
+            .collect();
+    }
+}
+
+struct LogSchemaRemap {
+    schema: LogSchema,
+    timestamp_fields: Vec<String>,
+    message_fields: Vec<String>,
+    severity_fields: Vec<String>,
+    resource_fields: BTreeMap<String, String>,
+}
+
+/// Removes the first of `fields` present in the document.
+fn take_first(doc: &mut Map<String, Value>, fields: &[String]) -> Option<Value> {
+    fields.iter().find_map(|field| doc.remove(field))
+}
+
+/// Inserts a dotted path as nested objects, e.g. `log.level` as
+/// `{"log": {"level": ...}}`, or as a flat key if a parent is not an object.
+fn insert_dotted(doc: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        Some((parent, child)) => {
+            match doc
+                .entry(parent)
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(object) => insert_dotted(object, child, value),
+                _ => {
+                    doc.insert(path.to_string(), value);
+                },
+            }
+        },
+        None => {
+            doc.insert(path.to_string(), value);
+        },
+    }
+}
+
+/// The OpenTelemetry severity number of the first severity of its range.
+fn severity_number(severity_text: &str) -> Option<u64> {
+    match severity_text.to_ascii_lowercase().as_str() {
+        "trace" => Some(1),
+        "debug" => Some(5),
+        "info" | "information" => Some(9),
+        "warn" | "warning" => Some(13),
+        "error" | "err" => Some(17),
+        "fatal" | "critical" | "crit" => Some(21),
+        _ => None,
+    }
+}
+
+impl DocTransform for LogSchemaRemap {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        let timestamp = take_first(doc, &self.timestamp_fields);
+        let message = take_first(doc, &self.message_fields);
+        let severity = take_first(doc, &self.severity_fields);
+        let resource: Vec<(String, Value)> = self
+            .resource_fields
+            .iter()
+            .filter_map(|(field, attribute)| {
+                doc.remove(field).map(|value| (attribute.clone(), value))
+            })
+            .collect();
+        match self.schema {
+            LogSchema::Ecs => {
+                let fields = [
+                    ("@timestamp", timestamp),
+                    ("message", message),
+                    ("log.level", severity),
+                ];
+                for (path, value) in fields {
+                    if let Some(value) = value {
+                        insert_dotted(doc, path, value);
+                    }
+                }
+                for (attribute, value) in resource {
+                    insert_dotted(doc, &attribute, value);
+                }
+            },
+            LogSchema::Otel => {
+                let attributes = std::mem::take(doc);
+                if let Some(timestamp) = timestamp {
+                    doc.insert("timestamp".to_string(), timestamp);
+                }
+                match severity {
+                    Some(Value::String(severity_text)) => {
+                        if let Some(number) = severity_number(&severity_text) {
+                            doc.insert("severity_number".to_string(), number.into());
+                        }
+                        doc.insert("severity_text".to_string(), severity_text.into());
+                    },
+                    Some(severity @ Value::Number(_)) => {
+                        doc.insert("severity_number".to_string(), severity);
+                    },
+                    Some(severity) => {
+                        doc.insert(
+                            "severity_text".to_string(),
+                            severity.to_string().into(),
+                        );
+                    },
+                    None => {},
+                }
+                if let Some(message) = message {
+                    doc.insert("body".to_string(), message);
+                }
+                doc.insert("attributes".to_string(), Value::Object(attributes));
+                doc.insert(
+                    "resource_attributes".to_string(),
+                    Value::Object(resource.into_iter().collect()),
+                );
+            },
+        }
+    }
+}
+
+struct FlattenGharchiveEvent {
+    event_types: Vec<String>,
+    max_payload_field_bytes: usize,
+}
+
+impl DocTransform for FlattenGharchiveEvent {
+    fn apply(&self, doc: &mut Map<String, Value>) {
+        for object_field in ["actor", "repo", "org"] {
+            let Some(Value::Object(object)) = doc.get_mut(object_field) else {
+                continue;
+            };
+            let name_field = if object_field == "repo" {
+                "name"
+            } else {
+                "login"
+            };
+            let id = object.remove("id");
+            let name = object.remove(name_field);
+            doc.remove(object_field);
+            for (field, value) in [("id", id), (name_field, name)] {
+                if let Some(value) = value {
+                    doc.insert(format!("{object_field}_{field}"), value);
+                }
+            }
+        }
+        if let Some(Value::Object(payload)) = doc.get_mut("payload") {
+            payload.retain(|_, value| {
+                serde_json::to_string(value)
+                    .map(|value_json| value_json.len() <= self.max_payload_field_bytes)
+                    .unwrap_or(false)
+            });
+        }
+    }
+
+    fn keep(&self, doc: &Map<String, Value>) -> bool {
+        self.event_types.is_empty()
+            || doc
+                .get("type")
+                .and_then(Value::as_str)
+                .is_some_and(|event_type| {
+                    self.event_types.iter().any(|kept| kept == event_type)
+                })
+    }
+}
+
+/// The transform described by `spec`.
+fn build_transform(spec: &TransformSpec) -> Box<dyn DocTransform> {
+    match spec {
+        TransformSpec::Rename { fields } => Box::new(RenameFields(fields.clone())),
+        TransformSpec::Drop { fields } => Box::new(DropFields(fields.clone())),
+        TransformSpec::Add { fields } => Box::new(AddFields(fields.clone())),
+        TransformSpec::LowercaseKeys => Box::new(LowercaseKeys),
+        TransformSpec::LogSchema {
+            schema,
+            timestamp_field,
+            message_field,
+            severity_field,
+            resource_fields,
+        } => {
+            let fields = |field: &Option<String>, defaults: &[&str]| match field {
+                Some(field) => vec![field.clone()],
+                None => defaults.iter().map(|field| field.to_string()).collect(),
+            };
+            Box::new(LogSchemaRemap {
+                schema: *schema,
+                timestamp_fields: fields(timestamp_field, TIMESTAMP_FIELDS),
+                message_fields: fields(message_field, MESSAGE_FIELDS),
+                severity_fields: fields(severity_field, SEVERITY_FIELDS),
+                resource_fields: resource_fields.clone(),
+            })
+        },
+        TransformSpec::Gharchive {
+            event_types,
+            max_payload_field_bytes,
+        } => Box::new(FlattenGharchiveEvent {
+            event_types: event_types.clone(),
+            max_payload_field_bytes: *max_payload_field_bytes,
+        }),
+    }
+}
+
+/// The transforms applied in order to every document, between the source and
+/// the sink.
+pub struct TransformPipeline {
+    specs: Vec<TransformSpec>,
+    transforms: Vec<Box<dyn DocTransform>>,
+}
+
+impl TransformPipeline {
+    pub fn new(specs: Vec<TransformSpec>) -> Self {
+        let transforms = specs.iter().map(build_transform).collect();
+        Self { specs, transforms }
+    }
+
+    /// Loads the transform specs of a JSON array.
+    pub fn load_specs(path: &Path) -> anyhow::Result<Vec<TransformSpec>> {
+        let specs_json = std::fs::read(path)
+            .with_context(|| format!("Failed to read transforms {path:?}"))?;
+        serde_json::from_slice(&specs_json)
+            .with_context(|| format!("Invalid transforms {path:?}"))
+    }
+
+    pub fn specs(&self) -> &[TransformSpec] {
+        &self.specs
+    }
+
+    /// Rewrites the documents of the batch, removing the dropped ones.
+    pub fn apply(&self, document_batch: &mut DocumentBatch) -> anyhow::Result<()> {
+        let mut payload = Vec::with_capacity(document_batch.bytes.len());
+        for line in document_batch.bytes.split_inclusive(|byte| *byte == b'\n') {
+            if line.trim_ascii().is_empty() {
+                payload.extend_from_slice(line);
+                continue;
+            }
+            let mut doc: Map<String, Value> = serde_json::from_slice(line)
+                .context("Failed to parse document line as JSON")?;
+            let mut kept = true;
+            for transform in &self.transforms {
+                kept = transform.keep(&doc);
+                if !kept {
+                    break;
+                }
+                transform.apply(&mut doc);
+            }
+            if !kept {
+                continue;
+            }
+            serde_json::to_writer(&mut payload, &doc)?;
+            payload.push(b'\n');
+        }
+        document_batch.bytes = payload.into();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use qbench_core::run_results::reports::default_resource_fields;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_transform_pipeline() {
+        let specs: Vec<TransformSpec> = serde_json::from_value(json!([
+            {"type": "rename", "fields": {"ts": "Timestamp"}},
+            {"type": "drop", "fields": ["tenant_id", "missing"]},
+            {"type": "add", "fields": {"source": "qbench"}},
+            {"type": "lowercase_keys"},
+        ]))
+        .unwrap();
+        let pipeline = TransformPipeline::new(specs);
+        let mut batch = DocumentBatch {
+            bytes: Bytes::from_static(
+                b"{\"ts\": 1, \"tenant_id\": 2, \"Attributes\": {\"Host\": \"a\"}, \
+                  \"Spans\": [{\"Name\": \"s\"}, [{\"Kind\": 1}], 3]}\n\n",
+            ),
+            last: false,
+        };
+        pipeline.apply(&mut batch).unwrap();
+        let mut lines = batch.bytes.split(|byte| *byte == b'\n');
+        let doc: Value = serde_json::from_slice(lines.next().unwrap()).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "timestamp": 1,
+                "attributes": {"host": "a"},
+                "spans": [{"name": "s"}, [{"kind": 1}], 3],
+                "source": "qbench",
+            })
+        );
+        assert!(batch.bytes.ends_with(b"\n\n"));
+
+        assert!(serde_json::from_value::<Vec<TransformSpec>>(json!([
+            {"type": "uppercase_keys"}
+        ]))
+        .is_err());
+        assert!(serde_json::from_value::<Vec<TransformSpec>>(json!([
+            {"type": "rename", "fields": {"a": 1}}
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_log_schema_transform() {
+        let line = b"{\"ts\": 1, \"level\": \"WARN\", \"msg\": \"m\", \"service\": \"s\", \"user\": \"u\"}\n";
+        let otel = TransformPipeline::new(vec![TransformSpec::LogSchema {
+            schema: LogSchema::Otel,
+            timestamp_field: None,
+            message_field: None,
+            severity_field: None,
+            resource_fields: default_resource_fields(),
+        }]);
+        let mut batch = DocumentBatch {
+            bytes: Bytes::from_static(line),
+            last: false,
+        };
+        otel.apply(&mut batch).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&batch.bytes).unwrap(),
+            json!({
+                "timestamp": 1,
+                "severity_text": "WARN",
+                "severity_number": 13,
+                "body": "m",
+                "attributes": {"user": "u"},
+                "resource_attributes": {"service.name": "s"},
+            })
+        );
+        let ecs_specs: Vec<TransformSpec> = serde_json::from_value(json!([
+            {"type": "log_schema", "schema": "ecs", "severity_field": "level"}
+        ]))
+        .unwrap();
+        let mut batch = DocumentBatch {
+            bytes: Bytes::from_static(line),
+            last: false,
+        };
+        TransformPipeline::new(ecs_specs).apply(&mut batch).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&batch.bytes).unwrap(),
+            json!({
+                "@timestamp": 1,
+                "message": "m",
+                "log": {"level": "WARN"},
+                "service": {"name": "s"},
+                "user": "u",
+            })
+        );
+    }
+
+    #[test]
+    fn test_gharchive_transform() {
+        let gharchive =
+            TransformPipeline::new(vec![BuiltinTransform::from_str("gharchive")
+                .unwrap()
+                .spec(&["PushEvent".to_string()])]);
+        let mut batch = DocumentBatch {
+            bytes: Bytes::from(format!(
+                "{}\n{}\n",
+                json!({
+                    "type": "PushEvent",
+                    "actor": {"id": 1, "login": "a", "avatar_url": "https://x"},
+                    "repo": {"id": 2, "name": "a/b", "url": "https://y"},
+                    "payload": {"size": 1, "commits": [{"message": "m".repeat(2000)}]},
+                }),
+                json!({"type": "WatchEvent", "actor": {"id": 1, "login": "a"}}),
+            )),
+            last: false,
+        };
+        gharchive.apply(&mut batch).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&batch.bytes).unwrap(),
+            json!({
+                "type": "PushEvent",
+                "actor_id": 1,
+                "actor_login": "a",
+                "repo_id": 2,
+                "repo_name": "a/b",
+                "payload": {"size": 1},
+            })
+        );
+        assert!(BuiltinTransform::from_str("wikipedia").is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use anyhow::bail;
+use clap::Args;
+use qbench_core::results::{results_format, OutputFormat};
+use qbench_core::run_results::RunResults;
+use qbench_core::sink::BuildInfo;
+
+use crate::report::{format_table, read_runs};
+use crate::stats::{mann_whitney_p_value, Summary};
+
+#[derive(Args, Debug)]
+pub struct TrendArgs {
+    #[arg(required = true)]
+    /// The results files of the stored runs, e.g. collected with
+    /// `--append-output`.
+    paths: Vec<PathBuf>,
+
+    #[arg(long)]
+    /// The format of the results files: "json", "yaml" or "msgpack". Guessed
+    /// from the file extensions by default.
+    format: Option<OutputFormat>,
+
+    #[arg(long, default_value = "megabytes_per_second")]
+    /// The followed metric, a number in the results. Nested fields are
+    /// separated by dots, e.g. `mixed_workload.latency.p99_ms`.
+    metric: String,
+
+    #[arg(long)]
+    /// Only follow the runs against this engine.
+    engine: Option<String>,
+
+    #[arg(long)]
+    /// Only follow the runs against this index.
+    index: Option<String>,
+
+    #[arg(long, default_value_t = 10)]
+    /// The number of most recent engine versions printed.
+    num_versions: usize,
+
+    #[arg(long, default_value_t = 0.05)]
+    /// The p-value of the Mann-Whitney U test under which the change of the
+    /// metric from the previous version is flagged.
+    significance: f64,
+}
+
+/// The values of the metric over the runs of an engine build.
+struct VersionRuns {
+    version: String,
+    commit_hash: String,
+    commit_date: String,
+    /// The start of the first run, ordering the builds of the same commit
+    /// date.
+    first_run_start: String,
+    values: Vec<f64>,
+}
+
+/// The value of a metric of a run, `None` if it is absent or not a number.
+fn metric_value(run: &RunResults, metric: &str) -> anyhow::Result<Option<f64>> {
+    let pointer = format!("/{}", metric.replace('.', "/"));
+    Ok(serde_json::to_value(run)?
+        .pointer(&pointer)
+        .and_then(serde_json::Value::as_f64))
+}
+
+/// Groups the values of the metric, with the engine build and start of their
+/// runs, per engine build in the order of their commits.
+fn version_runs<'a>(
+    values: impl IntoIterator<Item = (&'a BuildInfo, &'a String, f64)>,
+) -> Vec<VersionRuns> {
+    let mut versions: Vec<VersionRuns> = Vec::new();
+    for (build_info, run_start, value) in values {
+        match versions.iter_mut().find(|version| {
+            version.version == build_info.version
+                && version.commit_hash == build_info.commit_hash
+        }) {
+            Some(version) => {
+                version.values.push(value);
+                if *run_start < version.first_run_start {
+                    version.first_run_start = run_start.clone();
+                }
+            },
+            None => versions.push(VersionRuns {
+                version: build_info.version.clone(),
+                commit_hash: build_info.commit_hash.clone(),
+                commit_date: build_info.commit_date.clone(),
+                first_run_start: run_start.clone(),
+                values: vec![value],
+            }),
+        }
+    }
+    versions.sort_by(|left, right| {
+        (&left.commit_date, &left.first_run_start)
+            .cmp(&(&right.commit_date, &right.first_run_start))
+    });
+    versions
+}
+
+/// Prints the mean of a metric for each engine version of the stored runs,
+/// flagging the significant changes from one version to the next.
+pub fn trend(args: TrendArgs) -> anyhow::Result<()> {
+    let mut runs = Vec::new();
+    for path in &args.paths {
+        runs.extend(read_runs(path, results_format(path, args.format)?)?);
+    }
+    runs.retain(|run| {
+        args.engine
+            .as_ref()
+            .is_none_or(|engine| run.engine == *engine)
+            && args.index.as_ref().is_none_or(|index| run.index == *index)
+    });
+    let mut values = Vec::new();
+    for run in &runs {
+        if let Some(value) = metric_value(run, &args.metric)? {
+            values.push((&run.build_info, &run.time_range.start, value));
+        }
+    }
+    let versions = version_runs(values);
+    if versions.is_empty() {
+        bail!("No run with the metric {:?}", args.metric);
+    }
+    let header: Vec<String> = [
+        "version",
+        "commit",
+        "commit_date",
+        "runs",
+        "mean",
+        "stddev",
+        "change",
+        "p_value",
+        "",
+    ]
+    .iter()
+    .map(|column| column.to_string())
+    .collect();
+    let mut rows = Vec::new();
+    let mut num_significant_changes = 0;
+    let first_version_idx = versions.len().saturating_sub(args.num_versions);
+    for (version_idx, version) in versions.iter().enumerate().skip(first_version_idx) {
+        let summary = Summary::from_values(&version.values).expect("At least one value");
+        let previous_version = version_idx
+            .checked_sub(1)
+            .map(|previous_version_idx| &versions[previous_version_idx]);
+        let change_pct = previous_version.map(|previous_version| {
+            let previous_mean = Summary::from_values(&previous_version.values)
+                .expect("At least one value")
+                .mean;
+            (summary.mean - previous_mean) / previous_mean * 100.0
+        });
+        let p_value = previous_version.and_then(|previous_version| {
+            mann_whitney_p_value(&previous_version.values, &version.values)
+        });
+        let is_significant = p_value.is_some_and(|p_value| p_value < args.significance);
+        num_significant_changes += is_significant as usize;
+        rows.push(vec![
+            version.version.clone(),
+            version.commit_hash.chars().take(8).collect(),
+            version.commit_date.clone(),
+            version.values.len().to_string(),
+            format!("{:.2}", summary.mean),
+            format!("{:.2}", summary.stddev),
+            change_pct
+                .map_or("-".to_string(), |change_pct| format!("{change_pct:+.1}%")),
+            p_value.map_or("-".to_string(), |p_value| format!("{p_value:.3}")),
+            if is_significant { "*" } else { "" }.to_string(),
+        ]);
+    }
+    println!("{} over {} runs", args.metric, runs.len());
+    print!("{}", format_table(&header, &rows, 3));
+    if num_significant_changes > 0 {
+        println!(
+            "* significant change from the previous version (p < {})",
+            args.significance
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_runs() {
+        let build_info = |version: &str, commit_date: &str| BuildInfo {
+            version: version.to_string(),
+            commit_date: commit_date.to_string(),
+            commit_hash: format!("{version}-hash"),
+            build_target: String::new(),
+        };
+        let (v2, v1) = (
+            build_info("0.9", "2024-06-01"),
+            build_info("0.8", "2024-01-01"),
+        );
+        let starts: Vec<String> = (0..3)
+            .map(|day| format!("2024-07-0{}T00:00:00Z", day + 1))
+            .collect();
+        let versions = version_runs([
+            (&v2, &starts[1], 12.0),
+            (&v1, &starts[2], 9.0),
+            (&v2, &starts[0], 11.0),
+        ]);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version, "0.8");
+        assert_eq!(versions[0].values, [9.0]);
+        assert_eq!(versions[1].values, [12.0, 11.0]);
+        assert_eq!(versions[1].first_run_start, starts[0]);
+    }
+}
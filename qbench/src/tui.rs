@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use qbench_core::query::LatencyStats;
+use qbench_core::source::{SourceProgress, UriState};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::metrics::IngestCounters;
+use crate::shutdown;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+/// Throughput samples are taken every second, whatever the refresh interval.
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const NUM_THROUGHPUT_SAMPLES: usize = 600;
+
+/// A full-screen terminal dashboard of the ingestion progress, redrawn until
+/// `stop` is called.
+///
+/// Pressing `q` or Ctrl-C stops the ingestion gracefully, as the terminal
+/// doesn't turn Ctrl-C into a SIGINT while the dashboard is shown. Pressing it
+/// again restores the terminal and aborts the run.
+///
+/// Dropping it without calling `stop`, when the run fails, restores the
+/// terminal too.
+pub struct Dashboard {
+    stop_tx: Option<oneshot::Sender<()>>,
+    task: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl Dashboard {
+    pub fn start(
+        title: String,
+        counters: Arc<IngestCounters>,
+        progress: Arc<SourceProgress>,
+    ) -> anyhow::Result<Self> {
+        let terminal = ratatui::try_init()?;
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let state = DashboardState {
+            title,
+            counters,
+            progress,
+            start: Instant::now(),
+            throughput_samples: VecDeque::new(),
+            previous_sample: (Instant::now(), 0, 0),
+            bytes_per_sec: 0.0,
+            docs_per_sec: 0.0,
+        };
+        let task = tokio::spawn(run_dashboard(terminal, state, stop_rx));
+        Ok(Self {
+            stop_tx: Some(stop_tx),
+            task: Some(task),
+        })
+    }
+
+    /// Stops redrawing and restores the terminal.
+    pub async fn stop(mut self) -> anyhow::Result<()> {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+        match self.task.take() {
+            Some(task) => task.await?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+            ratatui::restore();
+        }
+    }
+}
+
+async fn run_dashboard(
+    mut terminal: DefaultTerminal,
+    mut state: DashboardState,
+    mut stop_rx: oneshot::Receiver<()>,
+) -> anyhow::Result<()> {
+    let mut ticker = tokio::time::interval(REFRESH_INTERVAL);
+    let draw_res = loop {
+        tokio::select! {
+            _ = &mut stop_rx => break Ok(()),
+            _ = ticker.tick() => {},
+        }
+        if let Err(error) = handle_key_events() {
+            break Err(error);
+        }
+        state.sample_throughput();
+        if let Err(error) = terminal.draw(|frame| state.draw(frame)) {
+            break Err(error.into());
+        }
+    };
+    ratatui::restore();
+    draw_res
+}
+
+fn handle_key_events() -> anyhow::Result<()> {
+    while event::poll(Duration::ZERO)? {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        let is_ctrl_c = key.code == KeyCode::Char('c')
+            && key.modifiers.contains(KeyModifiers::CONTROL);
+        if key.kind == KeyEventKind::Press
+            && (key.code == KeyCode::Char('q') || is_ctrl_c)
+        {
+            if shutdown::is_requested() {
+                ratatui::restore();
+                warn!("Run aborted from the dashboard");
+                std::process::exit(130);
+            }
+            shutdown::request();
+        }
+    }
+    Ok(())
+}
+
+struct DashboardState {
+    title: String,
+    counters: Arc<IngestCounters>,
+    progress: Arc<SourceProgress>,
+    start: Instant,
+    /// Ingested bytes per second, oldest first.
+    throughput_samples: VecDeque<u64>,
+    /// Time, ingested bytes and docs of the previous throughput sample.
+    previous_sample: (Instant, u64, u64),
+    bytes_per_sec: f64,
+    docs_per_sec: f64,
+}
+
+impl DashboardState {
+    fn sample_throughput(&mut self) {
+        let (previous_tick, previous_bytes, previous_docs) = self.previous_sample;
+        let tick = Instant::now();
+        let elapsed = tick - previous_tick;
+        if elapsed < THROUGHPUT_SAMPLE_INTERVAL {
+            return;
+        }
+        let num_bytes = self.counters.num_ingested_bytes.load(Ordering::Relaxed);
+        let num_docs = self.counters.num_ingested_docs.load(Ordering::Relaxed);
+        self.bytes_per_sec = (num_bytes - previous_bytes) as f64 / elapsed.as_secs_f64();
+        self.docs_per_sec = (num_docs - previous_docs) as f64 / elapsed.as_secs_f64();
+        if self.throughput_samples.len() == NUM_THROUGHPUT_SAMPLES {
+            self.throughput_samples.pop_front();
+        }
+        self.throughput_samples.push_back(self.bytes_per_sec as u64);
+        self.previous_sample = (tick, num_bytes, num_docs);
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [summary_area, throughput_area, requests_area, uris_area] =
+            Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(8),
+                Constraint::Length(4),
+                Constraint::Min(3),
+            ])
+            .areas(frame.area());
+
+        let elapsed = self.start.elapsed();
+        let num_bytes = self.counters.num_ingested_bytes.load(Ordering::Relaxed);
+        let num_docs = self.counters.num_ingested_docs.load(Ordering::Relaxed);
+        let summary = Paragraph::new(Line::from(format!(
+            "elapsed {:.0}s | {:.2} MB ingested | {} docs | avg {:.2} MB/s",
+            elapsed.as_secs_f64(),
+            num_bytes as f64 / 1_000_000.0,
+            num_docs,
+            num_bytes as f64 / 1_000_000.0 / elapsed.as_secs_f64(),
+        )))
+        .block(Block::bordered().title(format!(
+            " qbench: {} (q to stop, twice to abort) ",
+            self.title
+        )));
+        frame.render_widget(summary, summary_area);
+
+        // Only the most recent samples fitting in the widget are shown.
+        let num_shown_samples = throughput_area.width.saturating_sub(2) as usize;
+        let samples: Vec<u64> = self
+            .throughput_samples
+            .iter()
+            .skip(
+                self.throughput_samples
+                    .len()
+                    .saturating_sub(num_shown_samples),
+            )
+            .copied()
+            .collect();
+        let sparkline = Sparkline::default()
+            .data(&samples)
+            .style(Style::default().fg(Color::Green))
+            .block(Block::bordered().title(format!(
+                " Throughput: {:.2} MB/s, {:.0} docs/s ",
+                self.bytes_per_sec / 1_000_000.0,
+                self.docs_per_sec,
+            )));
+        frame.render_widget(sparkline, throughput_area);
+
+        let latency_line = match LatencyStats::from_latencies_ms(
+            self.counters.recent_request_latencies_ms(),
+        ) {
+            Some(latency) => format!(
+                "latency p50 {:.0}ms | p90 {:.0}ms | max {:.0}ms",
+                latency.p50_ms, latency.p90_ms, latency.max_ms
+            ),
+            None => "latency -".to_string(),
+        };
+        let num_failed_batches =
+            self.counters.num_failed_batches.load(Ordering::Relaxed);
+        let errors_line = Line::from(format!(
+            "failed batches {} | in-flight requests {}",
+            num_failed_batches,
+            self.counters.num_inflight_requests.load(Ordering::Relaxed),
+        ));
+        let requests = Paragraph::new(vec![
+            Line::from(latency_line),
+            if num_failed_batches > 0 {
+                errors_line.red()
+            } else {
+                errors_line
+            },
+        ])
+        .block(Block::bordered().title(" Requests (recent) "));
+        frame.render_widget(requests, requests_area);
+
+        let uris = &self.progress.uris;
+        let num_done = uris
+            .iter()
+            .filter(|uri| uri.state() == UriState::Done)
+            .count();
+        // The most recently started URIs first, pending ones are not shown.
+        let rows = uris
+            .iter()
+            .rev()
+            .filter(|uri| uri.state() != UriState::Pending)
+            .map(|uri| {
+                let state = uri.state();
+                let row = Row::new(vec![
+                    state.as_ref().to_string(),
+                    format!("{:.2} MB", uri.num_read_bytes() as f64 / 1_000_000.0),
+                    uri.uri.clone(),
+                ]);
+                match state {
+                    UriState::Failed | UriState::Skipped => row.red(),
+                    UriState::Reading => row.green(),
+                    _ => row,
+                }
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(12),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(vec!["state", "read", "uri"]).bold())
+        .block(Block::bordered().title(format!(
+            " URIs: {}/{} done ",
+            num_done,
+            uris.len()
+        )));
+        frame.render_widget(table, uris_area);
+    }
+}
@@ -15,6 +15,7 @@ import pprint
 import random
 import re
 import statistics
+import string
 import subprocess
 import sys
 import time
@@ -1073,20 +1074,47 @@ def run_search_benchmark(search_client: SearchClient, engine: str, index: str, n
     return results
 
 
-def prepare_index(engine: str, track: str, index: str, overwrite_index: bool):
+def render_index_config_template(template_path: str, template_vars: dict[str, Any]) -> str:
+    """Renders an index config template, substituting the `${var}` placeholders.
+
+    Non-string values (e.g. the list of default search fields) are rendered as
+    JSON, which is valid in both YAML and JSON configs.
+    """
+    rendered_vars = {
+        name: value if isinstance(value, str) else json.dumps(value)
+        for name, value in template_vars.items()
+    }
+    with open(template_path) as template_file:
+        return string.Template(template_file.read()).substitute(rendered_vars)
+
+
+def prepare_index(engine: str, track: str, index: str, overwrite_index: bool,
+                  track_config: dict[str, Any]):
+    # Tracks can share an index config template per engine instead of
+    # providing their own config files.
+    template_path = track_config.get("index_config_templates", {}).get(engine)
+    if template_path:
+        template_vars = track_config.get("template_vars", {}) | {"index_id": index}
+        print(f"Rendering index config template {template_path} with {template_vars}")
+        index_config = render_index_config_template(template_path, template_vars)
+    else:
+        index_config = None
+
     if engine == "quickwit":
         client = QuickwitClient()
-        index_config = open(f"tracks/{track}/index-config.quickwit.yaml").read()
+        index_config_filename = "index-config.quickwit.yaml"
     elif engine == "opensearch":
         client = ElasticClient(endpoint="http://127.0.0.1:9301")
-        index_config = open(f"tracks/{track}/index-config.opensearch.json").read()
+        index_config_filename = "index-config.opensearch.json"
     elif engine == "loki":
         client = LokiClient(endpoint="http://127.0.0.1:9301")
-        index_config = open(f"tracks/{track}/index-config.quickwit.yaml").read()
+        index_config_filename = "index-config.quickwit.yaml"
     else:
         assert engine == "elasticsearch", f"Unknown engine {engine}"
         client = ElasticClient()
-        index_config = open(f"tracks/{track}/index-config.elasticsearch.json").read()
+        index_config_filename = "index-config.elasticsearch.json"
+    if index_config is None:
+        index_config = open(f"tracks/{track}/{index_config_filename}").read()
         
     if client.check_index_exists(index):
         if overwrite_index:
@@ -1257,6 +1285,7 @@ def run_indexing_benchmark(
     print("Run indexing...")
     qbench_command = [
         "./qbench/target/release/qbench",
+        "index",
         "--engine",
         engine,
         "--index",
@@ -1318,7 +1347,7 @@ def run_benchmark(benchs_to_run: list[BenchType],
 
     if BenchType.INDEXING in benchs_to_run:
         # TODO: use 'engine_client'.
-        prepare_index(args.engine, args.track, index, args.overwrite_index)
+        prepare_index(args.engine, args.track, index, args.overwrite_index, track_config)
 
     if BenchType.INDEXING in benchs_to_run:
         output_path = f'{results_dir}/indexing-results.json'
@@ -0,0 +1,33 @@
+#!/usr/bin/env python3
+"""A minimal sink command for `qbench index --engine exec --exec-command ...`.
+
+It speaks the exec sink's line protocol on stdin/stdout and only counts the
+documents it receives: replace `send` with the calls to your engine.
+"""
+
+import json
+import os
+import sys
+
+num_docs = 0
+num_bytes = 0
+stdin = sys.stdin.buffer
+
+for request_line in stdin:
+    request = json.loads(request_line)
+    op = request["op"]
+    if op == "send":
+        batch = stdin.read(request["num_bytes"])
+        num_docs += sum(1 for line in batch.splitlines() if line.strip())
+        num_bytes += len(batch)
+        response = {"num_rejected_docs": 0}
+    elif op == "commit":
+        response = {}
+    elif op == "index_info":
+        response = {"num_docs": num_docs, "num_splits": 1, "num_bytes": num_bytes}
+    elif op == "build_info":
+        response = {"version": "example-" + os.environ.get("QBENCH_INDEX", "")}
+    else:
+        response = {"error": f"unknown op {op}"}
+    sys.stdout.write(json.dumps(response) + "\n")
+    sys.stdout.flush()